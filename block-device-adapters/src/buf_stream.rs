@@ -6,6 +6,9 @@ use embedded_io_async::{ErrorKind, Read, Seek, SeekFrom, Write};
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 pub enum BufStreamError<T> {
+    /// A seek would move before the start or past the end of the device, or a read or write was attempted at an
+    /// offset past the last block that can be addressed with a `u32` block address. Holds the requested offset.
+    InvalidSeek(i64),
     Io(T),
 }
 
@@ -17,17 +20,26 @@ impl<T> From<T> for BufStreamError<T> {
 
 impl<T: core::fmt::Debug> embedded_io_async::Error for BufStreamError<T> {
     fn kind(&self) -> ErrorKind {
-        ErrorKind::Other
+        match self {
+            BufStreamError::InvalidSeek(_) => ErrorKind::InvalidInput,
+            BufStreamError::Io(_) => ErrorKind::Other,
+        }
     }
 }
 
 /// A Stream wrapper for accessing a stream in block sized chunks.
 ///
-/// [`BufStream<T, const SIZE: usize, const ALIGN: usize`](BufStream) can be initialized with the following parameters.
+/// [`BufStream<T, const SIZE: usize, const BLOCKS: usize = 1>`](BufStream) can be initialized with the following parameters.
 ///
 /// - `T`: The inner stream.
 /// - `SIZE`: The size of the block, this dictates the size of the internal buffer.
-/// - `ALIGN`: The alignment of the internal buffer.
+/// - `BLOCKS`: The number of blocks held by the internal buffer, defaults to one.
+///
+/// The alignment of the internal buffer is dictated by [`BlockDevice::Align`].
+///
+/// On a cache miss the internal buffer is filled with up to `BLOCKS` consecutive blocks in a single
+/// [`BlockDevice::read`] call, which greatly reduces the number of device transactions for sequential access.
+/// Modified blocks are tracked and written back in a single [`BlockDevice::write`] call before the buffer is refilled.
 ///
 /// If the `buf` provided to either [`Read::read`] or [`Write::write`] meets the following conditions the `buf`
 /// will be used directly instead of the intermediate buffer to avoid unnecessary copies:
@@ -36,79 +48,199 @@ impl<T: core::fmt::Debug> embedded_io_async::Error for BufStreamError<T> {
 /// - `buf` has the same alignment as the internal buffer
 /// - The byte address of the inner device is aligned to a block size.
 ///
-/// [`BufStream<T, const SIZE: usize, const ALIGN: usize`](BufStream) implements the [`embedded_io_async`] traits, and implicitly
+/// [`Write::write`] relaxes the first condition: all whole blocks at the start of `buf` are written in a single
+/// [`BlockDevice::write`] call and only the remaining partial block goes through the internal buffer. Whole blocks of
+/// a misaligned `buf` are copied into the internal buffer, up to `BLOCKS` at a time, and written without reading them
+/// from the device first.
+///
+/// [`BufStream<T, const SIZE: usize, const BLOCKS: usize = 1>`](BufStream) implements the [`embedded_io_async`] traits, and implicitly
 /// handles the RMW (Read, Modify, Write) cycle for you.
-pub struct BufStream<T: BlockDevice<SIZE>, const SIZE: usize> {
+///
+/// The size of the device is queried with [`BlockDevice::size`] on first use and cached, seeks are checked against it
+/// and fail with [`BufStreamError::InvalidSeek`] if they would end up before the start or past the end of the device.
+/// Call [`BufStream::invalidate`] if the size of the device changes.
+///
+/// Modified blocks are only written back when the buffer is refilled or on [`Write::flush`]. `Drop` cannot run async
+/// code, so dropping a [`BufStream`] (or calling [`BufStream::into_inner`]) silently discards unflushed data.
+/// Use [`BufStream::close`] to flush the buffer and get the inner device back, or create the stream with
+/// [`BufStream::new_write_through`] to write every modification to the device immediately.
+#[must_use = "unflushed data is lost when a `BufStream` is dropped, use `BufStream::close`"]
+pub struct BufStream<T: BlockDevice<SIZE>, const SIZE: usize, const BLOCKS: usize = 1> {
     inner: T,
-    buffer: Aligned<T::Align, [u8; SIZE]>,
+    buffer: [Aligned<T::Align, [u8; SIZE]>; BLOCKS],
+    // first block held in the buffer
     current_block: u32,
+    // number of valid blocks in the buffer
+    cached_blocks: u32,
     current_offset: u64,
-    dirty: bool,
+    // range of modified blocks in the buffer (inclusive, relative to current_block)
+    dirty: Option<(usize, usize)>,
+    // write modified blocks back immediately instead of on refill or flush
+    write_through: bool,
+    // size of the device, queried on first use
+    size: Option<u64>,
 }
 
-impl<T: BlockDevice<SIZE>, const SIZE: usize> BufStream<T, SIZE> {
+impl<T: BlockDevice<SIZE>, const SIZE: usize, const BLOCKS: usize> BufStream<T, SIZE, BLOCKS> {
     const ALIGN: usize = core::mem::align_of::<Aligned<T::Align, [u8; SIZE]>>();
     /// Create a new [`BufStream`] around a hardware block device.
     pub fn new(inner: T) -> Self {
+        assert!(BLOCKS > 0, "BufStream must hold at least one block");
         Self {
             inner,
             current_block: u32::MAX,
+            cached_blocks: 0,
             current_offset: 0,
-            buffer: Aligned([0; SIZE]),
-            dirty: false,
+            buffer: [Aligned([0; SIZE]); BLOCKS],
+            dirty: None,
+            write_through: false,
+            size: None,
+        }
+    }
+
+    /// Create a new [`BufStream`] in write-through mode around a hardware block device.
+    ///
+    /// Every block modified through the internal buffer is written back to the device before [`Write::write`]
+    /// returns, so the device always reflects the latest write and an unexpected power loss or a dropped stream
+    /// cannot lose buffered data. Reads still use the internal buffer.
+    ///
+    /// This costs one device write per partially written block, so small sequential writes (e.g. appending log
+    /// lines) rewrite the same block many times. This is slower and increases wear on flash media compared to the
+    /// default mode.
+    pub fn new_write_through(inner: T) -> Self {
+        Self {
+            write_through: true,
+            ..Self::new(inner)
         }
     }
 
     /// Returns inner object.
+    ///
+    /// Unflushed data in the internal buffer is discarded, use [`BufStream::close`] to write it back first.
     pub fn into_inner(self) -> T {
         self.inner
     }
 
+    /// Flushes the internal buffer and returns inner object.
+    pub async fn close(mut self) -> Result<T, BufStreamError<T::Error>> {
+        self.flush().await?;
+        Ok(self.inner)
+    }
+
+    /// Flushes and drops all cached blocks, so the next access reads them from the device again.
+    ///
+    /// Use this after the content or the size of the device was changed behind the stream's back, e.g. by
+    /// repartitioning or reformatting it through another handle. The size of the device is queried again on the next
+    /// access. The current position is kept.
+    pub async fn invalidate(&mut self) -> Result<(), BufStreamError<T::Error>> {
+        self.flush().await?;
+        self.current_block = u32::MAX;
+        self.cached_blocks = 0;
+        self.size = None;
+        Ok(())
+    }
+
+    /// Returns the size of the device, which is only queried once and then kept until [`BufStream::invalidate`].
+    async fn device_size(&mut self) -> Result<u64, T::Error> {
+        match self.size {
+            Some(size) => Ok(size),
+            None => {
+                let size = self.inner.size().await?;
+                self.size = Some(size);
+                Ok(size)
+            }
+        }
+    }
+
+    #[inline]
+    fn pointer_block_start_addr(&self) -> Result<u64, BufStreamError<T::Error>> {
+        Ok(u64::from(self.pointer_block_start()?) * SIZE as u64)
+    }
+
+    /// Returns the address of the block at the current offset.
+    ///
+    /// Block addresses are `u32`, so with 512 byte blocks nothing past 2TB can be accessed.
     #[inline]
-    fn pointer_block_start_addr(&self) -> u64 {
-        self.pointer_block_start() as u64 * SIZE as u64
+    fn pointer_block_start(&self) -> Result<u32, BufStreamError<T::Error>> {
+        u32::try_from(self.current_offset / SIZE as u64).map_err(|_| {
+            BufStreamError::InvalidSeek(i64::try_from(self.current_offset).unwrap_or(i64::MAX))
+        })
     }
 
+    /// Returns the index of `block` in the internal buffer if it is cached.
     #[inline]
-    fn pointer_block_start(&self) -> u32 {
-        (self.current_offset / SIZE as u64)
-            .try_into()
-            .expect("Block larger than 2TB")
+    fn cached_index(&self, block: u32) -> Option<usize> {
+        let index = block.wrapping_sub(self.current_block);
+        (index < self.cached_blocks).then_some(index as usize)
     }
 
     async fn flush(&mut self) -> Result<(), T::Error> {
         // flush the internal buffer if we have modified the buffer
-        if self.dirty {
-            self.dirty = false;
+        if let Some((first, last)) = self.dirty.take() {
+            // only addressable blocks are cached, see `check_cache`
+            debug_assert!(
+                u64::from(self.current_block) + last as u64 <= u64::from(u32::MAX),
+                "Block larger than 2TB"
+            );
             // Note, alignment of internal buffer is guarenteed at compile time so we don't have to check it here
             self.inner
-                .write(self.current_block, slice_to_blocks(&self.buffer[..]))
+                .write(
+                    self.current_block + first as u32,
+                    &self.buffer[first..=last],
+                )
                 .await?;
         }
         Ok(())
     }
 
-    async fn check_cache(&mut self) -> Result<(), T::Error> {
-        let block_start = self.pointer_block_start();
-        if block_start != self.current_block {
-            // we may have modified data in old block, flush it to disk
+    /// Flushes and drops any cached blocks overlapping `count` blocks starting at `block`.
+    async fn invalidate_blocks(&mut self, block: u32, count: usize) -> Result<(), T::Error> {
+        let end = u64::from(block) + count as u64;
+        let cache_end = u64::from(self.current_block) + u64::from(self.cached_blocks);
+        if u64::from(block) < cache_end && end > u64::from(self.current_block) {
             self.flush().await?;
-            // We have seeked to a new block, read it
-            let buf = &mut self.buffer[..];
-            self.inner
-                .read(block_start, slice_to_blocks_mut(buf))
-                .await?;
-            self.current_block = block_start;
+            self.current_block = u32::MAX;
+            self.cached_blocks = 0;
         }
         Ok(())
     }
+
+    /// Makes sure the block at the current offset is cached and returns its index in the internal buffer.
+    async fn check_cache(&mut self) -> Result<usize, BufStreamError<T::Error>> {
+        let block_start = self.pointer_block_start()?;
+        if let Some(index) = self.cached_index(block_start) {
+            return Ok(index);
+        }
+        // we may have modified data in old blocks, flush them to disk
+        self.flush().await?;
+        // We have seeked to a new block, read it (and the following blocks if they fit in the buffer)
+        let count = if BLOCKS == 1 {
+            1
+        } else {
+            // never cache blocks past the last addressable block
+            let device_blocks =
+                (self.device_size().await? / SIZE as u64).min(u64::from(u32::MAX) + 1);
+            let remaining = device_blocks.saturating_sub(u64::from(block_start)).max(1);
+            remaining.min(BLOCKS as u64) as usize
+        };
+        self.inner
+            .read(block_start, &mut self.buffer[..count])
+            .await?;
+        self.current_block = block_start;
+        self.cached_blocks = count as u32;
+        Ok(0)
+    }
 }
 
-impl<T: BlockDevice<SIZE>, const SIZE: usize> embedded_io_async::ErrorType for BufStream<T, SIZE> {
+impl<T: BlockDevice<SIZE>, const SIZE: usize, const BLOCKS: usize> embedded_io_async::ErrorType
+    for BufStream<T, SIZE, BLOCKS>
+{
     type Error = BufStreamError<T::Error>;
 }
 
-impl<T: BlockDevice<SIZE>, const SIZE: usize> Read for BufStream<T, SIZE> {
+impl<T: BlockDevice<SIZE>, const SIZE: usize, const BLOCKS: usize> Read
+    for BufStream<T, SIZE, BLOCKS>
+{
     async fn read(&mut self, mut buf: &mut [u8]) -> Result<usize, Self::Error> {
         let mut total = 0;
         let target = buf.len();
@@ -118,12 +250,14 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> Read for BufStream<T, SIZE> {
                 && self.current_offset % SIZE as u64 == 0
             {
                 // If the provided buffer has a suitable length and alignment _and_ the read head is on a block boundary, use it directly
-                let block = self.pointer_block_start();
+                let block = self.pointer_block_start()?;
+                // make sure modified blocks in the cache are visible to the read
+                self.invalidate_blocks(block, buf.len() / SIZE).await?;
                 self.inner.read(block, slice_to_blocks_mut(buf)).await?;
 
                 buf.len()
             } else {
-                let block_start = self.pointer_block_start_addr();
+                let block_start = self.pointer_block_start_addr()?;
                 let block_end = block_start + SIZE as u64;
                 trace!(
                     "offset {}, block_start {}, block_end {}",
@@ -132,7 +266,7 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> Read for BufStream<T, SIZE> {
                     block_end
                 );
 
-                self.check_cache().await?;
+                let index = self.check_cache().await?;
 
                 // copy as much as possible, up to the block boundary
                 let buffer_offset = (self.current_offset - block_start) as usize;
@@ -141,7 +275,7 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> Read for BufStream<T, SIZE> {
                 let end = core::cmp::min(buffer_offset + bytes_to_read, SIZE);
                 trace!("buffer_offset {}, end {}", buffer_offset, end);
                 let bytes_read = end - buffer_offset;
-                buf[..bytes_read].copy_from_slice(&self.buffer[buffer_offset..end]);
+                buf[..bytes_read].copy_from_slice(&self.buffer[index][buffer_offset..end]);
                 buf = &mut buf[bytes_read..]; // move the buffer along
 
                 bytes_read
@@ -157,22 +291,59 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> Read for BufStream<T, SIZE> {
     }
 }
 
-impl<T: BlockDevice<SIZE>, const SIZE: usize> Write for BufStream<T, SIZE> {
+impl<T: BlockDevice<SIZE>, const SIZE: usize, const BLOCKS: usize> Write
+    for BufStream<T, SIZE, BLOCKS>
+{
     async fn write(&mut self, mut buf: &[u8]) -> Result<usize, Self::Error> {
         let mut total = 0;
         let target = buf.len();
         loop {
-            let bytes_written = if buf.len() % SIZE == 0
+            // whole blocks can be written without reading them first, unless the write can be merged into modified
+            // blocks in the internal buffer
+            let full_blocks = if self.current_offset % SIZE as u64 == 0 {
+                buf.len() / SIZE
+            } else {
+                0
+            };
+            let cached = self.cached_index(self.pointer_block_start()?).is_some();
+            let bytes_written = if full_blocks > 0
+                && (buf.len() % SIZE == 0 || !cached)
                 && buf.as_ptr().cast::<u8>() as usize % Self::ALIGN == 0
-                && self.current_offset % SIZE as u64 == 0
             {
-                // If the provided buffer has a suitable length and alignment _and_ the write head is on a block boundary, use it directly
-                let block = self.pointer_block_start();
-                self.inner.write(block, slice_to_blocks(buf)).await?;
+                // If the provided buffer has a suitable alignment _and_ the write head is on a block boundary, write
+                // all whole blocks directly in a single call
+                let block = self.pointer_block_start()?;
+                let len = full_blocks * SIZE;
+                // cached copies of the written blocks would become stale
+                self.invalidate_blocks(block, full_blocks).await?;
+                self.inner
+                    .write(block, slice_to_blocks(&buf[..len]))
+                    .await?;
+                buf = &buf[len..];
+
+                len
+            } else if full_blocks > 0 && !cached {
+                // The provided buffer is misaligned, copy as many whole blocks as fit into the internal buffer and
+                // write them in a single call. The internal buffer then caches the written blocks.
+                let block = self.pointer_block_start()?;
+                let count = full_blocks.min(BLOCKS);
+                let len = count * SIZE;
+                // we may have modified data in old blocks, flush them to disk
+                self.flush().await?;
+                for (dst, src) in self.buffer[..count]
+                    .iter_mut()
+                    .zip(buf[..len].chunks_exact(SIZE))
+                {
+                    dst.copy_from_slice(src);
+                }
+                self.current_block = block;
+                self.cached_blocks = count as u32;
+                self.inner.write(block, &self.buffer[..count]).await?;
+                buf = &buf[len..];
 
-                buf.len()
+                len
             } else {
-                let block_start = self.pointer_block_start_addr();
+                let block_start = self.pointer_block_start_addr()?;
                 let block_end = block_start + SIZE as u64;
                 trace!(
                     "offset {}, block_start {}, block_end {}",
@@ -182,7 +353,7 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> Write for BufStream<T, SIZE> {
                 );
 
                 // reload the cache if we need to
-                self.check_cache().await?;
+                let index = self.check_cache().await?;
 
                 // copy as much as possible, up to the block boundary
                 let buffer_offset = (self.current_offset - block_start) as usize;
@@ -191,16 +362,22 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> Write for BufStream<T, SIZE> {
                 let end = core::cmp::min(buffer_offset + bytes_to_write, SIZE);
                 trace!("buffer_offset {}, end {}", buffer_offset, end);
                 let bytes_written = end - buffer_offset;
-                self.buffer[buffer_offset..buffer_offset + bytes_written]
+                self.buffer[index][buffer_offset..buffer_offset + bytes_written]
                     .copy_from_slice(&buf[..bytes_written]);
                 buf = &buf[bytes_written..]; // move the buffer along
 
                 // If we haven't written directly, we will use the cache, which will may need to flush later
                 // so we mark it as dirty
-                self.dirty = true;
-
-                // write out the whole block with the modified data
-                if block_start + end as u64 == block_end {
+                self.dirty = Some(match self.dirty {
+                    Some((first, last)) => (first.min(index), last.max(index)),
+                    None => (index, index),
+                });
+
+                // write out the cached blocks with the modified data once the end of the buffer is reached
+                if self.write_through
+                    || (block_start + end as u64 == block_end
+                        && index + 1 == self.cached_blocks as usize)
+                {
                     trace!("Flushing sector cache");
                     self.flush().await?;
                 }
@@ -223,14 +400,26 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> Write for BufStream<T, SIZE> {
     }
 }
 
-impl<T: BlockDevice<SIZE>, const SIZE: usize> Seek for BufStream<T, SIZE> {
+impl<T: BlockDevice<SIZE>, const SIZE: usize, const BLOCKS: usize> Seek
+    for BufStream<T, SIZE, BLOCKS>
+{
     async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
-        self.current_offset = match pos {
-            SeekFrom::Start(x) => x,
-            SeekFrom::End(x) => (self.inner.size().await? as i64 - x) as u64,
-            SeekFrom::Current(x) => (self.current_offset as i64 + x) as u64,
+        let size = self.device_size().await?;
+        let (base, x) = match pos {
+            SeekFrom::Start(x) => (x, 0),
+            SeekFrom::End(x) => (size, x),
+            SeekFrom::Current(x) => (self.current_offset, x),
         };
-        Ok(self.current_offset)
+        match base.checked_add_signed(x) {
+            Some(new_offset) if new_offset <= size => {
+                self.current_offset = new_offset;
+                Ok(self.current_offset)
+            }
+            // the base may not fit into an i64 if the device reports a huge size
+            _ => Err(BufStreamError::InvalidSeek(
+                i64::try_from(base).unwrap_or(i64::MAX).saturating_add(x),
+            )),
+        }
     }
 }
 
@@ -304,6 +493,274 @@ mod tests {
         }
     }
 
+    struct CountingBlockDevice<T> {
+        inner: T,
+        reads: usize,
+        writes: usize,
+        size_queries: usize,
+    }
+
+    impl<T: BlockDevice<512>> BlockDevice<512> for CountingBlockDevice<T> {
+        type Error = T::Error;
+        type Align = T::Align;
+
+        async fn read(
+            &mut self,
+            block_address: u32,
+            data: &mut [Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            self.reads += 1;
+            self.inner.read(block_address, data).await
+        }
+
+        async fn write(
+            &mut self,
+            block_address: u32,
+            data: &[Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            self.writes += 1;
+            self.inner.write(block_address, data).await
+        }
+
+        async fn size(&mut self) -> Result<u64, Self::Error> {
+            self.size_queries += 1;
+            self.inner.size().await
+        }
+    }
+
+    /// Reports the real length of a cursor backed [`TestBlockDevice`] as its size.
+    struct SizedBlockDevice<T: Read + Write + Seek>(TestBlockDevice<T>);
+
+    impl<T: Read + Write + Seek> BlockDevice<512> for SizedBlockDevice<T> {
+        type Error = T::Error;
+        type Align = aligned::A4;
+
+        async fn read(
+            &mut self,
+            block_address: u32,
+            data: &mut [Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            BlockDevice::read(&mut self.0, block_address, data).await
+        }
+
+        async fn write(
+            &mut self,
+            block_address: u32,
+            data: &[Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            BlockDevice::write(&mut self.0, block_address, data).await
+        }
+
+        async fn size(&mut self) -> Result<u64, Self::Error> {
+            let size = self.0 .0.seek(SeekFrom::End(0)).await?;
+            Ok(size)
+        }
+    }
+
+    /// A device whose content and size can be changed while it is owned by a [`BufStream`].
+    struct SharedBlockDevice(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl BlockDevice<512> for SharedBlockDevice {
+        type Error = core::convert::Infallible;
+        type Align = aligned::A4;
+
+        async fn read(
+            &mut self,
+            block_address: u32,
+            data: &mut [Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            let disk = self.0.borrow();
+            let start = block_address as usize * 512;
+            for (i, b) in data.iter_mut().enumerate() {
+                b.copy_from_slice(&disk[start + i * 512..start + (i + 1) * 512]);
+            }
+            Ok(())
+        }
+
+        async fn write(
+            &mut self,
+            block_address: u32,
+            data: &[Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            let mut disk = self.0.borrow_mut();
+            let start = block_address as usize * 512;
+            for (i, b) in data.iter().enumerate() {
+                disk[start + i * 512..start + (i + 1) * 512].copy_from_slice(&b[..]);
+            }
+            Ok(())
+        }
+
+        async fn size(&mut self) -> Result<u64, Self::Error> {
+            Ok(self.0.borrow().len() as u64)
+        }
+    }
+
+    #[tokio::test]
+    async fn invalidate_after_device_change() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let disk = std::rc::Rc::new(std::cell::RefCell::new(vec![b'A'; 2048]));
+        let mut block: BufStream<_, 512> = BufStream::new(SharedBlockDevice(disk.clone()));
+        assert_eq!(block.seek(SeekFrom::End(0)).await.unwrap(), 2048);
+
+        // cache the first block and modify it
+        block.seek(SeekFrom::Start(0)).await.unwrap();
+        block.write_all(b"B").await.unwrap();
+
+        // the device grows and its content changes behind the stream's back, the cached size is still used
+        disk.borrow_mut().resize(4096, b'C');
+        disk.borrow_mut()[100] = b'D';
+        assert_eq!(block.seek(SeekFrom::End(0)).await.unwrap(), 2048);
+        let mut buf = [0u8; 1];
+        block.seek(SeekFrom::Start(100)).await.unwrap();
+        block.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf[0], b'A');
+
+        // the modified block is written back before it is dropped, which also restores the stale byte 100
+        block.invalidate().await.unwrap();
+        assert_eq!(disk.borrow()[0], b'B');
+        assert_eq!(disk.borrow()[100], b'A');
+        assert_eq!(block.stream_position().await.unwrap(), 101);
+        // the size is queried again
+        assert_eq!(block.seek(SeekFrom::End(0)).await.unwrap(), 4096);
+        disk.borrow_mut()[100] = b'D';
+        block.seek(SeekFrom::Start(100)).await.unwrap();
+        block.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf[0], b'D');
+
+        // the new end of the device is readable
+        block.seek(SeekFrom::End(-1)).await.unwrap();
+        block.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf[0], b'C');
+    }
+
+    async fn read_1mb<const BLOCKS: usize>(data: Vec<u8>) -> (Vec<u8>, usize) {
+        let cur = std::io::Cursor::new(data);
+        let mut block: BufStream<_, 512, BLOCKS> = BufStream::new(CountingBlockDevice {
+            inner: TestBlockDevice(embedded_io_adapters::tokio_1::FromTokio::new(cur)),
+            reads: 0,
+            writes: 0,
+            size_queries: 0,
+        });
+        let mut out = vec![0; 1024 * 1024];
+        // odd sized chunks never hit the direct block path
+        for chunk in out.chunks_mut(100) {
+            block.read_exact(chunk).await.unwrap();
+        }
+        let reads = block.into_inner().reads;
+        (out, reads)
+    }
+
+    #[tokio::test]
+    async fn multi_block_read_ahead() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let data: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        let (single, single_reads) = read_1mb::<1>(data.clone()).await;
+        let (multi, multi_reads) = read_1mb::<8>(data.clone()).await;
+        assert_eq!(single, data);
+        assert_eq!(multi, data);
+        assert_eq!(single_reads, 2048);
+        assert_eq!(multi_reads, 256);
+    }
+
+    #[tokio::test]
+    async fn multi_block_write_batched() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let buf = "A".repeat(4096).into_bytes();
+        let cur = std::io::Cursor::new(buf);
+        let mut block: BufStream<_, 512, 4> = BufStream::new(CountingBlockDevice {
+            inner: TestBlockDevice(embedded_io_adapters::tokio_1::FromTokio::new(cur)),
+            reads: 0,
+            writes: 0,
+            size_queries: 0,
+        });
+
+        block.seek(SeekFrom::Start(100)).await.unwrap();
+        block
+            .write_all(&"B".repeat(1000).into_bytes())
+            .await
+            .unwrap();
+        // nothing written until flush, the modified blocks are written back in one go
+        assert_eq!(block.inner.writes, 0);
+        block.flush().await.unwrap();
+        assert_eq!(block.inner.writes, 1);
+        assert_eq!(block.inner.reads, 1);
+
+        // moving past the cached window flushes modified blocks first
+        block.seek(SeekFrom::Start(600)).await.unwrap();
+        block.write_all(b"C").await.unwrap();
+        block.seek(SeekFrom::Start(3000)).await.unwrap();
+        let mut tmp = [0u8; 10];
+        block.read_exact(&mut tmp).await.unwrap();
+        assert_eq!(tmp, [b'A'; 10]);
+        assert_eq!(block.inner.writes, 2);
+
+        let buf = block.into_inner().inner.0.into_inner().into_inner();
+        assert_eq!(
+            buf,
+            ("A".repeat(100) + &"B".repeat(500) + "C" + &"B".repeat(499) + &"A".repeat(2996))
+                .into_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn write_spanning_blocks_batched() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let cur = std::io::Cursor::new(vec![0; 4096]);
+        let mut block: BufStream<_, 512> = BufStream::new(CountingBlockDevice {
+            inner: TestBlockDevice(embedded_io_adapters::tokio_1::FromTokio::new(cur)),
+            reads: 0,
+            writes: 0,
+            size_queries: 0,
+        });
+
+        // the data following the 511 byte head is aligned
+        let mut data: Aligned<A4, [u8; 2052]> = Aligned([0; 2052]);
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 251) as u8;
+        }
+        let data = &data[1..2050];
+        block.seek(SeekFrom::Start(1)).await.unwrap();
+        block.write_all(data).await.unwrap();
+        block.flush().await.unwrap();
+        // head and tail blocks are read and written through the cache, the middle blocks are written in one call
+        assert_eq!(block.inner.reads, 2);
+        assert_eq!(block.inner.writes, 3);
+
+        let buf = block.into_inner().inner.0.into_inner().into_inner();
+        assert_eq!(buf[0], 0);
+        assert_eq!(&buf[1..2050], data);
+        assert!(buf[2050..].iter().all(|&b| b == 0));
+    }
+
+    #[tokio::test]
+    async fn misaligned_write_spanning_blocks_batched() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let cur = std::io::Cursor::new(vec![0; 8192]);
+        let mut block: BufStream<_, 512, 4> = BufStream::new(CountingBlockDevice {
+            inner: TestBlockDevice(embedded_io_adapters::tokio_1::FromTokio::new(cur)),
+            reads: 0,
+            writes: 0,
+            size_queries: 0,
+        });
+
+        // the data is misaligned on a block boundary so it is copied through the internal buffer
+        let mut data: Aligned<A4, [u8; 4097]> = Aligned([0; 4097]);
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 251) as u8;
+        }
+        let data = &data[1..];
+        block.write_all(data).await.unwrap();
+        block.flush().await.unwrap();
+        // whole blocks are not read and up to 4 blocks are written at a time
+        assert_eq!(block.inner.reads, 0);
+        assert_eq!(block.inner.writes, 2);
+
+        let buf = block.into_inner().inner.0.into_inner().into_inner();
+        assert_eq!(&buf[..4096], data);
+        assert!(buf[4096..].iter().all(|&b| b == 0));
+    }
+
     #[tokio::test]
     async fn block_512_read_test() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -378,6 +835,47 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn close_flushes_pending_block() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let buf = vec![0; 2048];
+        let cur = std::io::Cursor::new(buf);
+        let mut block: BufStream<_, 512> = BufStream::new(TestBlockDevice(
+            embedded_io_adapters::tokio_1::FromTokio::new(cur),
+        ));
+
+        // partial block write stays in the internal buffer until flushed
+        block.seek(SeekFrom::Start(600)).await.unwrap();
+        block.write_all(b"pending").await.unwrap();
+        let inner = block.close().await.unwrap();
+        let data = inner.0.into_inner().into_inner();
+        assert_eq!(&data[600..607], b"pending");
+        assert!(data[..600].iter().all(|&b| b == 0));
+    }
+
+    #[tokio::test]
+    async fn write_through_writes_before_flush() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let buf = vec![0; 2048];
+        let cur = std::io::Cursor::new(buf);
+        let mut block: BufStream<_, 512> = BufStream::new_write_through(TestBlockDevice(
+            embedded_io_adapters::tokio_1::FromTokio::new(cur),
+        ));
+
+        block.seek(SeekFrom::Start(600)).await.unwrap();
+        block.write_all(b"written").await.unwrap();
+        // the cached block is still used for reads
+        block.seek(SeekFrom::Start(600)).await.unwrap();
+        let mut read_back = [0; 7];
+        block.read_exact(&mut read_back).await.unwrap();
+        assert_eq!(&read_back, b"written");
+
+        // `into_inner` discards unflushed data, so the write must already be on the device
+        let data = block.into_inner().0.into_inner().into_inner();
+        assert_eq!(&data[600..607], b"written");
+        assert!(data[..600].iter().all(|&b| b == 0));
+    }
+
     #[tokio::test]
     async fn block_512_write_across_sectors() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -414,7 +912,7 @@ mod tests {
         block.write_all(&aligned_buffer[..]).await.unwrap();
 
         // if we wrote directly, the block buffer will be empty
-        assert_eq!(&block.buffer[..], [0u8; 512]);
+        assert_eq!(&block.buffer[0][..], [0u8; 512]);
         // ensure that the current offset is still updated
         assert_eq!(block.current_offset, 512);
         // the write suceeded
@@ -443,7 +941,7 @@ mod tests {
         block.flush().await.unwrap();
 
         // because the addr was not block aligned, we will have used the cache
-        assert_ne!(&block.buffer[..], [0u8; 512]);
+        assert_ne!(&block.buffer[0][..], [0u8; 512]);
         // the write suceeded
         assert_eq!(
             &block.into_inner().0.into_inner().into_inner()[3..515],
@@ -465,7 +963,7 @@ mod tests {
         block.read_exact(&mut aligned_buffer[..]).await.unwrap();
 
         // if we read directly, the block buffer will be empty
-        assert_eq!(&block.buffer[..], [0u8; 512]);
+        assert_eq!(&block.buffer[0][..], [0u8; 512]);
         // ensure that the current offset is still updated
         assert_eq!(block.current_offset, 512);
         // the write suceeded
@@ -492,7 +990,7 @@ mod tests {
 
         // now, we must seek back and read the entire block
         // meaning our block cache will be written to:
-        assert_ne!(&block.buffer[..], [0u8; 512]);
+        assert_ne!(&block.buffer[0][..], [0u8; 512]);
 
         // the read suceeded
         assert_eq!(
@@ -501,6 +999,138 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn seek_out_of_bounds() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let cur = std::io::Cursor::new(vec![0; 2048]);
+        let mut block: BufStream<_, 512> = BufStream::new(SizedBlockDevice(TestBlockDevice(
+            embedded_io_adapters::tokio_1::FromTokio::new(cur),
+        )));
+
+        assert_eq!(block.seek(SeekFrom::End(0)).await.unwrap(), 2048);
+        assert_eq!(block.seek(SeekFrom::End(-48)).await.unwrap(), 2000);
+        assert_eq!(block.seek(SeekFrom::Current(-2000)).await.unwrap(), 0);
+
+        // before the start
+        assert!(matches!(
+            block.seek(SeekFrom::Current(-1)).await,
+            Err(BufStreamError::InvalidSeek(-1))
+        ));
+        assert!(matches!(
+            block.seek(SeekFrom::End(-2049)).await,
+            Err(BufStreamError::InvalidSeek(-1))
+        ));
+        // past the end
+        assert!(matches!(
+            block.seek(SeekFrom::Start(2049)).await,
+            Err(BufStreamError::InvalidSeek(2049))
+        ));
+        assert!(matches!(
+            block.seek(SeekFrom::End(1)).await,
+            Err(BufStreamError::InvalidSeek(2049))
+        ));
+        // a failed seek keeps the current position
+        assert_eq!(block.seek(SeekFrom::Current(0)).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn seek_end_uses_cached_size() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let cur = std::io::Cursor::new(vec![0; 2048]);
+        let mut block: BufStream<_, 512> = BufStream::new(CountingBlockDevice {
+            inner: SizedBlockDevice(TestBlockDevice(
+                embedded_io_adapters::tokio_1::FromTokio::new(cur),
+            )),
+            reads: 0,
+            writes: 0,
+            size_queries: 0,
+        });
+
+        assert_eq!(block.seek(SeekFrom::End(0)).await.unwrap(), 2048);
+        assert!(matches!(
+            block.seek(SeekFrom::End(2049)).await,
+            Err(BufStreamError::InvalidSeek(4097))
+        ));
+        assert!(matches!(
+            block.seek(SeekFrom::End(-2049)).await,
+            Err(BufStreamError::InvalidSeek(-1))
+        ));
+        assert_eq!(block.seek(SeekFrom::End(-2048)).await.unwrap(), 0);
+        assert_eq!(block.into_inner().size_queries, 1);
+
+        // a device of unknown size reports u64::MAX, seeking past it must not wrap around
+        let cur = std::io::Cursor::new(vec![0; 2048]);
+        let mut block: BufStream<_, 512> = BufStream::new(TestBlockDevice(
+            embedded_io_adapters::tokio_1::FromTokio::new(cur),
+        ));
+        assert_eq!(block.seek(SeekFrom::End(0)).await.unwrap(), u64::MAX);
+        assert!(matches!(
+            block.seek(SeekFrom::End(1)).await,
+            Err(BufStreamError::InvalidSeek(i64::MAX))
+        ));
+        assert_eq!(block.seek(SeekFrom::Current(0)).await.unwrap(), u64::MAX);
+    }
+
+    #[tokio::test]
+    async fn seek_near_boundaries() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let cur = std::io::Cursor::new(vec![0; 2048]);
+        let mut block: BufStream<_, 512> = BufStream::new(SizedBlockDevice(TestBlockDevice(
+            embedded_io_adapters::tokio_1::FromTokio::new(cur),
+        )));
+        assert_eq!(block.seek(SeekFrom::Start(1000)).await.unwrap(), 1000);
+        assert!(matches!(
+            block.seek(SeekFrom::Current(i64::MIN)).await,
+            Err(BufStreamError::InvalidSeek(offset)) if offset == i64::MIN + 1000
+        ));
+        assert!(matches!(
+            block.seek(SeekFrom::Current(i64::MAX)).await,
+            Err(BufStreamError::InvalidSeek(i64::MAX))
+        ));
+        assert!(matches!(
+            block.seek(SeekFrom::Start(u64::MAX)).await,
+            Err(BufStreamError::InvalidSeek(i64::MAX))
+        ));
+        assert!(matches!(
+            block.seek(SeekFrom::End(i64::MIN)).await,
+            Err(BufStreamError::InvalidSeek(_))
+        ));
+        assert_eq!(block.seek(SeekFrom::Current(0)).await.unwrap(), 1000);
+    }
+
+    #[tokio::test]
+    async fn access_past_last_addressable_block() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        // a device of unknown size allows seeking past the blocks a u32 block address can reach
+        let cur = std::io::Cursor::new(vec![0; 2048]);
+        let mut block: BufStream<_, 512, 4> = BufStream::new(TestBlockDevice(
+            embedded_io_adapters::tokio_1::FromTokio::new(cur),
+        ));
+        let end = (u64::from(u32::MAX) + 1) * 512;
+        assert_eq!(block.seek(SeekFrom::Start(end)).await.unwrap(), end);
+        let expected = i64::try_from(end).unwrap();
+        assert!(matches!(
+            block.read(&mut [0; 10]).await,
+            Err(BufStreamError::InvalidSeek(offset)) if offset == expected
+        ));
+        assert!(matches!(
+            block.write(&[0; 10]).await,
+            Err(BufStreamError::InvalidSeek(offset)) if offset == expected
+        ));
+        assert!(matches!(
+            block.write(&[0; 512]).await,
+            Err(BufStreamError::InvalidSeek(offset)) if offset == expected
+        ));
+        // the position is kept and the stream stays usable
+        assert_eq!(block.seek(SeekFrom::Current(0)).await.unwrap(), end);
+        block.seek(SeekFrom::Start(0)).await.unwrap();
+        block.write_all(&[1; 10]).await.unwrap();
+        block.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut buf = [0; 10];
+        block.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, [1; 10]);
+    }
+
     #[tokio::test]
     async fn write_seek_read_write() {
         let _ = env_logger::builder().is_test(true).try_init();
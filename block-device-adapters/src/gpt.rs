@@ -0,0 +1,535 @@
+use embedded_io_async::{Read, ReadExactError, Seek, SeekFrom};
+
+use crate::mbr::{self, MbrError, MBR_SECTOR_SIZE};
+
+const HEADER_SIGNATURE: &[u8; 8] = b"EFI PART";
+const MIN_HEADER_SIZE: usize = 92;
+const MIN_ENTRY_SIZE: u32 = 128;
+const PRIMARY_HEADER_LBA: u64 = 1;
+
+/// Type GUID of a Microsoft basic data partition (used for FAT volumes), in on-disk byte order.
+pub const BASIC_DATA_PARTITION_GUID: [u8; 16] = [
+    0xA2, 0xA0, 0xD0, 0xEB, 0xE5, 0xB9, 0x33, 0x44, 0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99, 0xC7,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum GptError<T> {
+    /// The first sector does not contain a protective MBR.
+    InvalidProtectiveMbr,
+    /// Neither the primary nor the backup GPT header is valid.
+    InvalidHeader,
+    /// The requested partition entry does not exist or is unused.
+    PartitionNotFound,
+    /// A partition entry describes a sector range that is empty or cannot be addressed in bytes.
+    InvalidPartition,
+    /// The device ended before a full sector could be read.
+    UnexpectedEof,
+    Io(T),
+}
+
+impl<T> From<T> for GptError<T> {
+    fn from(t: T) -> Self {
+        GptError::Io(t)
+    }
+}
+
+impl<T> From<ReadExactError<T>> for GptError<T> {
+    fn from(e: ReadExactError<T>) -> Self {
+        match e {
+            ReadExactError::UnexpectedEof => GptError::UnexpectedEof,
+            ReadExactError::Other(e) => GptError::Io(e),
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> embedded_io_async::Error for GptError<T> {
+    fn kind(&self) -> embedded_io_async::ErrorKind {
+        match self {
+            GptError::InvalidProtectiveMbr
+            | GptError::InvalidHeader
+            | GptError::InvalidPartition => embedded_io_async::ErrorKind::InvalidData,
+            GptError::PartitionNotFound => embedded_io_async::ErrorKind::NotFound,
+            GptError::UnexpectedEof | GptError::Io(_) => embedded_io_async::ErrorKind::Other,
+        }
+    }
+}
+
+/// A validated GUID Partition Table header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GptHeader {
+    /// The sector containing this header.
+    pub header_lba: u64,
+    /// The sector containing the other copy of the header.
+    pub alternate_lba: u64,
+    /// The first sector usable by partitions.
+    pub first_usable_lba: u64,
+    /// The last sector usable by partitions (inclusive).
+    pub last_usable_lba: u64,
+    /// The GUID of the disk, in on-disk byte order.
+    pub disk_guid: [u8; 16],
+    /// The first sector of the partition entry array.
+    pub partition_entry_lba: u64,
+    /// The number of entries in the partition entry array.
+    pub num_partition_entries: u32,
+    /// The size of a single partition entry in bytes.
+    pub partition_entry_size: u32,
+}
+
+impl GptHeader {
+    fn deserialize(raw: &[u8; MBR_SECTOR_SIZE as usize], lba: u64) -> Option<Self> {
+        if &raw[0..8] != HEADER_SIGNATURE {
+            return None;
+        }
+        let header_size = u32::from_le_bytes(raw[12..16].try_into().unwrap()) as usize;
+        if !(MIN_HEADER_SIZE..=raw.len()).contains(&header_size) {
+            return None;
+        }
+        // the CRC is calculated with the CRC field itself zeroed
+        let header_crc = u32::from_le_bytes(raw[16..20].try_into().unwrap());
+        let mut crc = Crc32::new();
+        crc.update(&raw[0..16]);
+        crc.update(&[0; 4]);
+        crc.update(&raw[20..header_size]);
+        if crc.finish() != header_crc {
+            return None;
+        }
+        let header = Self {
+            header_lba: u64::from_le_bytes(raw[24..32].try_into().unwrap()),
+            alternate_lba: u64::from_le_bytes(raw[32..40].try_into().unwrap()),
+            first_usable_lba: u64::from_le_bytes(raw[40..48].try_into().unwrap()),
+            last_usable_lba: u64::from_le_bytes(raw[48..56].try_into().unwrap()),
+            disk_guid: raw[56..72].try_into().unwrap(),
+            partition_entry_lba: u64::from_le_bytes(raw[72..80].try_into().unwrap()),
+            num_partition_entries: u32::from_le_bytes(raw[80..84].try_into().unwrap()),
+            partition_entry_size: u32::from_le_bytes(raw[84..88].try_into().unwrap()),
+        };
+        let entry_size = header.partition_entry_size;
+        if header.header_lba != lba || entry_size < MIN_ENTRY_SIZE || !entry_size.is_power_of_two()
+        {
+            return None;
+        }
+        // the whole partition entry array has to be addressable, see `entry_offset`
+        header
+            .partition_entry_lba
+            .checked_mul(MBR_SECTOR_SIZE)?
+            .checked_add(u64::from(header.num_partition_entries) * u64::from(entry_size))?;
+        Some(header)
+    }
+
+    fn entry_offset(&self, index: u32) -> u64 {
+        // cannot overflow for valid indices, checked in `deserialize`
+        self.partition_entry_lba * MBR_SECTOR_SIZE
+            + u64::from(index) * u64::from(self.partition_entry_size)
+    }
+}
+
+/// A partition entry of a GUID Partition Table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GptPartition {
+    /// The partition type GUID, in on-disk byte order. See [`BASIC_DATA_PARTITION_GUID`].
+    pub type_guid: [u8; 16],
+    /// The unique GUID of the partition, in on-disk byte order.
+    pub unique_guid: [u8; 16],
+    /// The first sector of the partition.
+    pub first_lba: u64,
+    /// The last sector of the partition (inclusive).
+    pub last_lba: u64,
+    /// The partition attribute flags.
+    pub attributes: u64,
+}
+
+impl GptPartition {
+    fn deserialize(raw: &[u8; MIN_ENTRY_SIZE as usize]) -> Option<Self> {
+        let type_guid: [u8; 16] = raw[0..16].try_into().unwrap();
+        // an unused entry has an all-zero type GUID
+        if type_guid == [0; 16] {
+            return None;
+        }
+        Some(Self {
+            type_guid,
+            unique_guid: raw[16..32].try_into().unwrap(),
+            first_lba: u64::from_le_bytes(raw[32..40].try_into().unwrap()),
+            last_lba: u64::from_le_bytes(raw[40..48].try_into().unwrap()),
+            attributes: u64::from_le_bytes(raw[48..56].try_into().unwrap()),
+        })
+    }
+
+    // checks that the partition is not empty and its byte offsets fit in u64
+    fn is_valid(&self) -> bool {
+        self.first_lba <= self.last_lba
+            && self
+                .last_lba
+                .checked_add(1)
+                .and_then(|n| n.checked_mul(MBR_SECTOR_SIZE))
+                .is_some()
+    }
+
+    /// Returns `true` if the partition type is [`BASIC_DATA_PARTITION_GUID`], which is used for FAT volumes.
+    pub fn is_basic_data(&self) -> bool {
+        self.type_guid == BASIC_DATA_PARTITION_GUID
+    }
+
+    /// Byte offset of the first byte of the partition.
+    ///
+    /// Entries returned by [`read_partition`] always have addressable offsets, the offsets of other entries
+    /// saturate instead of overflowing.
+    pub fn start_offset(&self) -> u64 {
+        self.first_lba.saturating_mul(MBR_SECTOR_SIZE)
+    }
+
+    /// Byte offset of the first byte after the partition.
+    pub fn end_offset(&self) -> u64 {
+        self.last_lba
+            .saturating_add(1)
+            .saturating_mul(MBR_SECTOR_SIZE)
+    }
+
+    /// Size of the partition in bytes.
+    pub fn size(&self) -> u64 {
+        self.end_offset().saturating_sub(self.start_offset())
+    }
+}
+
+/// Reads and validates the GUID Partition Table header of `inner`.
+///
+/// The protective MBR in the first sector is checked first. If the primary header at LBA 1 or its partition
+/// entry array is corrupt, the backup header in the last sector of the device is used instead.
+///
+/// Sectors are assumed to be 512 bytes long.
+pub async fn read_header<T: Read + Seek>(inner: &mut T) -> Result<GptHeader, GptError<T::Error>> {
+    let partitions = mbr::read_partitions(inner).await.map_err(|e| match e {
+        MbrError::InvalidSignature => GptError::InvalidProtectiveMbr,
+        MbrError::UnexpectedEof => GptError::UnexpectedEof,
+        MbrError::Io(e) => GptError::Io(e),
+    })?;
+    if !partitions.iter().flatten().any(|p| p.is_protective()) {
+        return Err(GptError::InvalidProtectiveMbr);
+    }
+
+    if let Some(header) = read_header_at(inner, PRIMARY_HEADER_LBA).await? {
+        return Ok(header);
+    }
+    warn!("Primary GPT header is corrupt, trying backup header");
+    let last_lba = (inner.seek(SeekFrom::End(0)).await? / MBR_SECTOR_SIZE)
+        .checked_sub(1)
+        .ok_or(GptError::UnexpectedEof)?;
+    if let Some(header) = read_header_at(inner, last_lba).await? {
+        return Ok(header);
+    }
+    error!("No valid GPT header found");
+    Err(GptError::InvalidHeader)
+}
+
+async fn read_header_at<T: Read + Seek>(
+    inner: &mut T,
+    lba: u64,
+) -> Result<Option<GptHeader>, GptError<T::Error>> {
+    let mut sector = [0u8; MBR_SECTOR_SIZE as usize];
+    inner.seek(SeekFrom::Start(lba * MBR_SECTOR_SIZE)).await?;
+    inner.read_exact(&mut sector).await?;
+    let header = match GptHeader::deserialize(&sector, lba) {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    // validate the partition entry array
+    let entries_crc = u32::from_le_bytes(sector[88..92].try_into().unwrap());
+    let mut remaining =
+        u64::from(header.num_partition_entries) * u64::from(header.partition_entry_size);
+    let mut crc = Crc32::new();
+    inner.seek(SeekFrom::Start(header.entry_offset(0))).await?;
+    while remaining > 0 {
+        let len = remaining.min(sector.len() as u64) as usize;
+        inner.read_exact(&mut sector[..len]).await?;
+        crc.update(&sector[..len]);
+        remaining -= len as u64;
+    }
+    if crc.finish() != entries_crc {
+        return Ok(None);
+    }
+    trace!("GPT header at LBA {}: {:?}", lba, header);
+    Ok(Some(header))
+}
+
+/// Reads the partition entry at `index` of the partition entry array described by `header`.
+///
+/// Unused entries and indices past the end of the array are returned as `None`. Returns
+/// `GptError::InvalidPartition` if the last sector of the entry is before its first sector or the partition
+/// cannot be addressed in bytes.
+pub async fn read_partition<T: Read + Seek>(
+    inner: &mut T,
+    header: &GptHeader,
+    index: u32,
+) -> Result<Option<GptPartition>, GptError<T::Error>> {
+    if index >= header.num_partition_entries {
+        return Ok(None);
+    }
+    let mut raw = [0u8; MIN_ENTRY_SIZE as usize];
+    inner
+        .seek(SeekFrom::Start(header.entry_offset(index)))
+        .await?;
+    inner.read_exact(&mut raw).await?;
+    let partition = GptPartition::deserialize(&raw);
+    trace!("partition {}: {:?}", index, partition);
+    match partition {
+        Some(p) if !p.is_valid() => {
+            error!("GPT partition {} has an invalid sector range", index);
+            Err(GptError::InvalidPartition)
+        }
+        p => Ok(p),
+    }
+}
+
+/// Returns an iterator over the used partition entries described by `header`.
+pub fn partitions<'a, T: Read + Seek>(
+    inner: &'a mut T,
+    header: &GptHeader,
+) -> GptPartitionIter<'a, T> {
+    GptPartitionIter {
+        inner,
+        header: *header,
+        index: 0,
+    }
+}
+
+/// An iterator over the used entries of a GUID Partition Table.
+pub struct GptPartitionIter<'a, T: Read + Seek> {
+    inner: &'a mut T,
+    header: GptHeader,
+    index: u32,
+}
+
+impl<T: Read + Seek> GptPartitionIter<'_, T> {
+    /// Returns the next used partition entry together with its index in the partition entry array.
+    pub async fn next(&mut self) -> Option<Result<(u32, GptPartition), GptError<T::Error>>> {
+        while self.index < self.header.num_partition_entries {
+            let index = self.index;
+            self.index += 1;
+            match read_partition(self.inner, &self.header, index).await {
+                Ok(Some(partition)) => return Some(Ok((index, partition))),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+/// CRC-32 (IEEE 802.3) as used by the GPT header and partition entry array.
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 ^= u32::from(byte);
+            for _bit in 0..8 {
+                let mask = (self.0 & 1).wrapping_neg();
+                self.0 = (self.0 >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mbr::PROTECTIVE_PARTITION_TYPE;
+    use crate::StreamSlice;
+
+    const DISK_SECTORS: u64 = 64;
+    const ENTRIES: u32 = 4;
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.finish()
+    }
+
+    fn write_header(image: &mut [u8], lba: u64, alternate_lba: u64, entry_lba: u64) {
+        let entries_start = (entry_lba * MBR_SECTOR_SIZE) as usize;
+        let entries_crc = crc32(&image[entries_start..entries_start + ENTRIES as usize * 128]);
+        let start = (lba * MBR_SECTOR_SIZE) as usize;
+        let header = &mut image[start..start + 92];
+        header[0..8].copy_from_slice(HEADER_SIGNATURE);
+        header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        header[12..16].copy_from_slice(&92u32.to_le_bytes());
+        header[24..32].copy_from_slice(&lba.to_le_bytes());
+        header[32..40].copy_from_slice(&alternate_lba.to_le_bytes());
+        header[40..48].copy_from_slice(&3u64.to_le_bytes());
+        header[48..56].copy_from_slice(&(DISK_SECTORS - 3).to_le_bytes());
+        header[56..72].copy_from_slice(&[0x11; 16]);
+        header[72..80].copy_from_slice(&entry_lba.to_le_bytes());
+        header[80..84].copy_from_slice(&ENTRIES.to_le_bytes());
+        header[84..88].copy_from_slice(&128u32.to_le_bytes());
+        header[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+        header[16..20].fill(0);
+        let header_crc = crc32(header);
+        header[16..20].copy_from_slice(&header_crc.to_le_bytes());
+    }
+
+    fn disk_image() -> Vec<u8> {
+        // 64 sector disk with a single basic data partition spanning sectors 10..=19 as the second entry
+        let mut image = vec![0u8; (DISK_SECTORS * MBR_SECTOR_SIZE) as usize];
+        let entry = &mut image[0x1BE..0x1BE + 16];
+        entry[4] = PROTECTIVE_PARTITION_TYPE;
+        entry[8..12].copy_from_slice(&1u32.to_le_bytes());
+        entry[12..16].copy_from_slice(&(DISK_SECTORS as u32 - 1).to_le_bytes());
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+        for entry_lba in [2, DISK_SECTORS - 2] {
+            let start = (entry_lba * MBR_SECTOR_SIZE) as usize + 128;
+            let entry = &mut image[start..start + 128];
+            entry[0..16].copy_from_slice(&BASIC_DATA_PARTITION_GUID);
+            entry[16..32].copy_from_slice(&[0x22; 16]);
+            entry[32..40].copy_from_slice(&10u64.to_le_bytes());
+            entry[40..48].copy_from_slice(&19u64.to_le_bytes());
+        }
+        write_header(&mut image, 1, DISK_SECTORS - 1, 2);
+        write_header(&mut image, DISK_SECTORS - 1, 1, DISK_SECTORS - 2);
+
+        // fake FAT boot sector at the start of the partition
+        image[10 * 512 + 3..10 * 512 + 11].copy_from_slice(b"MSWIN4.1");
+        image
+    }
+
+    fn expected_partition() -> GptPartition {
+        GptPartition {
+            type_guid: BASIC_DATA_PARTITION_GUID,
+            unique_guid: [0x22; 16],
+            first_lba: 10,
+            last_lba: 19,
+            attributes: 0,
+        }
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[tokio::test]
+    async fn read_single_partition() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut io =
+            embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(disk_image()));
+        let header = read_header(&mut io).await.unwrap();
+        assert_eq!(header.header_lba, 1);
+        assert_eq!(header.disk_guid, [0x11; 16]);
+
+        let mut iter = partitions(&mut io, &header);
+        let (index, partition) = iter.next().await.unwrap().unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(partition, expected_partition());
+        assert_eq!(partition.size(), 10 * 512);
+        assert!(partition.is_basic_data());
+        assert!(iter.next().await.is_none());
+        assert_eq!(read_partition(&mut io, &header, 0).await.unwrap(), None);
+
+        let mut slice = StreamSlice::from_gpt_partition(io, 1).await.unwrap();
+        let mut boot_sector = [0u8; 512];
+        slice.read_exact(&mut boot_sector).await.unwrap();
+        assert_eq!(&boot_sector[3..11], b"MSWIN4.1");
+        assert_eq!(slice.seek(SeekFrom::End(0)).await.unwrap(), 10 * 512);
+    }
+
+    #[tokio::test]
+    async fn corrupt_primary_header_uses_backup() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut image = disk_image();
+        // corrupt the primary partition entry array
+        image[2 * 512 + 128 + 32] = 0xFF;
+        let mut io = embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(image));
+        let header = read_header(&mut io).await.unwrap();
+        assert_eq!(header.header_lba, DISK_SECTORS - 1);
+        assert_eq!(
+            read_partition(&mut io, &header, 1).await.unwrap(),
+            Some(expected_partition())
+        );
+
+        // corrupt both headers
+        let mut image = disk_image();
+        image[512 + 40] ^= 1;
+        image[(DISK_SECTORS as usize - 1) * 512 + 40] ^= 1;
+        let mut io = embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(image));
+        assert!(matches!(
+            read_header(&mut io).await,
+            Err(GptError::InvalidHeader)
+        ));
+    }
+
+    fn set_partition_range(image: &mut [u8], first_lba: u64, last_lba: u64) {
+        for entry_lba in [2, DISK_SECTORS - 2] {
+            let start = (entry_lba * MBR_SECTOR_SIZE) as usize + 128;
+            image[start + 32..start + 40].copy_from_slice(&first_lba.to_le_bytes());
+            image[start + 40..start + 48].copy_from_slice(&last_lba.to_le_bytes());
+        }
+        write_header(image, 1, DISK_SECTORS - 1, 2);
+        write_header(image, DISK_SECTORS - 1, 1, DISK_SECTORS - 2);
+    }
+
+    #[tokio::test]
+    async fn invalid_partition_range() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        for (first_lba, last_lba) in [(20, 19), (10, u64::MAX), (10, u64::MAX / MBR_SECTOR_SIZE)] {
+            let mut image = disk_image();
+            set_partition_range(&mut image, first_lba, last_lba);
+            let mut io = embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(image));
+            let header = read_header(&mut io).await.unwrap();
+            assert!(matches!(
+                read_partition(&mut io, &header, 1).await,
+                Err(GptError::InvalidPartition)
+            ));
+            assert!(matches!(
+                StreamSlice::from_gpt_partition(io, 1).await,
+                Err(GptError::InvalidPartition)
+            ));
+        }
+
+        // a single sector partition is valid
+        let mut image = disk_image();
+        set_partition_range(&mut image, 10, 10);
+        let mut io = embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(image));
+        let header = read_header(&mut io).await.unwrap();
+        let partition = read_partition(&mut io, &header, 1).await.unwrap().unwrap();
+        assert_eq!(partition.size(), 512);
+    }
+
+    #[test]
+    fn offsets_saturate() {
+        let partition = GptPartition {
+            first_lba: 20,
+            last_lba: u64::MAX,
+            ..expected_partition()
+        };
+        assert_eq!(partition.end_offset(), u64::MAX);
+        let partition = GptPartition {
+            last_lba: 10,
+            ..partition
+        };
+        assert_eq!(partition.size(), 0);
+    }
+
+    #[tokio::test]
+    async fn missing_protective_mbr() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut image = disk_image();
+        image[0x1BE + 4] = 0x0C;
+        let io = embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(image));
+        assert!(matches!(
+            StreamSlice::from_gpt_partition(io, 1).await,
+            Err(GptError::InvalidProtectiveMbr)
+        ));
+    }
+}
@@ -6,7 +6,11 @@
 mod fmt;
 
 mod buf_stream;
+pub mod gpt;
+pub mod mbr;
+mod stream_device;
 mod stream_slice;
 
 pub use buf_stream::{BufStream, BufStreamError};
+pub use stream_device::{StreamDevice, StreamDeviceError};
 pub use stream_slice::{StreamSlice, StreamSliceError};
@@ -0,0 +1,184 @@
+use embedded_io_async::{Read, ReadExactError, Seek, SeekFrom};
+
+/// Size of a sector as used by the LBA fields of the partition table.
+pub const MBR_SECTOR_SIZE: u64 = 512;
+
+/// Partition types of FAT12, FAT16 and FAT32 volumes (with CHS or LBA addressing).
+pub const FAT_PARTITION_TYPES: [u8; 6] = [0x01, 0x04, 0x06, 0x0B, 0x0C, 0x0E];
+/// Partition type of the protective MBR entry covering a GPT disk.
+pub const PROTECTIVE_PARTITION_TYPE: u8 = 0xEE;
+
+const PARTITION_TABLE_OFFSET: usize = 0x1BE;
+const PARTITION_ENTRY_SIZE: usize = 16;
+const BOOT_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum MbrError<T> {
+    /// The sector does not end with the `0x55AA` boot signature.
+    InvalidSignature,
+    /// The device ended before a full sector could be read.
+    UnexpectedEof,
+    Io(T),
+}
+
+impl<T> From<T> for MbrError<T> {
+    fn from(t: T) -> Self {
+        MbrError::Io(t)
+    }
+}
+
+impl<T: core::fmt::Debug> embedded_io_async::Error for MbrError<T> {
+    fn kind(&self) -> embedded_io_async::ErrorKind {
+        match self {
+            MbrError::InvalidSignature => embedded_io_async::ErrorKind::InvalidData,
+            MbrError::UnexpectedEof | MbrError::Io(_) => embedded_io_async::ErrorKind::Other,
+        }
+    }
+}
+
+/// A primary partition entry of a Master Boot Record.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartitionEntry {
+    /// Whether the partition is marked as active (bootable).
+    pub bootable: bool,
+    /// The partition type, e.g. `0x0C` for FAT32 with LBA addressing.
+    pub partition_type: u8,
+    /// The first sector of the partition.
+    pub lba_start: u32,
+    /// The number of sectors in the partition.
+    pub sector_count: u32,
+}
+
+impl PartitionEntry {
+    fn deserialize(raw: &[u8]) -> Option<Self> {
+        let partition_type = raw[4];
+        let lba_start = u32::from_le_bytes([raw[8], raw[9], raw[10], raw[11]]);
+        let sector_count = u32::from_le_bytes([raw[12], raw[13], raw[14], raw[15]]);
+        // an unused entry has a zero type or no sectors
+        if partition_type == 0 || sector_count == 0 {
+            return None;
+        }
+        Some(Self {
+            bootable: raw[0] == 0x80,
+            partition_type,
+            lba_start,
+            sector_count,
+        })
+    }
+
+    /// Returns `true` if the partition type is one of [`FAT_PARTITION_TYPES`].
+    pub fn is_fat(&self) -> bool {
+        FAT_PARTITION_TYPES.contains(&self.partition_type)
+    }
+
+    /// Returns `true` if this is the protective entry of a disk partitioned with a GUID Partition Table.
+    pub fn is_protective(&self) -> bool {
+        self.partition_type == PROTECTIVE_PARTITION_TYPE
+    }
+
+    /// Byte offset of the first byte of the partition.
+    pub fn start_offset(&self) -> u64 {
+        u64::from(self.lba_start) * MBR_SECTOR_SIZE
+    }
+
+    /// Byte offset of the first byte after the partition.
+    pub fn end_offset(&self) -> u64 {
+        self.start_offset() + self.size()
+    }
+
+    /// Size of the partition in bytes.
+    pub fn size(&self) -> u64 {
+        u64::from(self.sector_count) * MBR_SECTOR_SIZE
+    }
+}
+
+/// Reads the four primary partition entries from the Master Boot Record at the start of `inner`.
+///
+/// Unused entries are returned as `None`.
+pub async fn read_partitions<T: Read + Seek>(
+    inner: &mut T,
+) -> Result<[Option<PartitionEntry>; 4], MbrError<T::Error>> {
+    let mut sector = [0u8; MBR_SECTOR_SIZE as usize];
+    inner.seek(SeekFrom::Start(0)).await?;
+    inner.read_exact(&mut sector).await.map_err(|e| match e {
+        ReadExactError::UnexpectedEof => MbrError::UnexpectedEof,
+        ReadExactError::Other(e) => MbrError::Io(e),
+    })?;
+    if sector[510..] != BOOT_SIGNATURE {
+        return Err(MbrError::InvalidSignature);
+    }
+
+    let mut partitions = [None; 4];
+    for (i, partition) in partitions.iter_mut().enumerate() {
+        let start = PARTITION_TABLE_OFFSET + i * PARTITION_ENTRY_SIZE;
+        *partition = PartitionEntry::deserialize(&sector[start..start + PARTITION_ENTRY_SIZE]);
+        trace!("partition {}: {:?}", i, partition);
+    }
+    Ok(partitions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StreamSlice;
+
+    fn disk_image() -> Vec<u8> {
+        // 8 sector disk with a single FAT16 partition spanning sectors 2..6
+        let mut image = vec![0u8; 8 * MBR_SECTOR_SIZE as usize];
+        let entry = &mut image[PARTITION_TABLE_OFFSET..PARTITION_TABLE_OFFSET + 16];
+        entry[0] = 0x80;
+        entry[4] = 0x06;
+        entry[8..12].copy_from_slice(&2u32.to_le_bytes());
+        entry[12..16].copy_from_slice(&4u32.to_le_bytes());
+        image[510..512].copy_from_slice(&BOOT_SIGNATURE);
+        // fake FAT boot sector at the start of the partition
+        image[1024..1027].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        image[1027..1035].copy_from_slice(b"MSWIN4.1");
+        image[1024 + 510..1024 + 512].copy_from_slice(&BOOT_SIGNATURE);
+        image
+    }
+
+    #[tokio::test]
+    async fn read_single_partition() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut io =
+            embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(disk_image()));
+        let partitions = read_partitions(&mut io).await.unwrap();
+        let entry = partitions[0].unwrap();
+        assert_eq!(
+            entry,
+            PartitionEntry {
+                bootable: true,
+                partition_type: 0x06,
+                lba_start: 2,
+                sector_count: 4,
+            }
+        );
+        assert_eq!(partitions[1..], [None; 3]);
+
+        assert!(entry.is_fat());
+        assert!(!entry.is_protective());
+
+        let mut slice = StreamSlice::from_partition(io, &entry).await.unwrap();
+        let mut boot_sector = [0u8; 512];
+        slice.read_exact(&mut boot_sector).await.unwrap();
+        assert_eq!(&boot_sector[3..11], b"MSWIN4.1");
+        assert_eq!(boot_sector[510..], BOOT_SIGNATURE);
+        assert_eq!(slice.seek(SeekFrom::End(0)).await.unwrap(), 4 * 512);
+    }
+
+    #[tokio::test]
+    async fn invalid_signature() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut image = disk_image();
+        image[511] = 0;
+        let mut io = embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(image));
+        assert!(matches!(
+            read_partitions(&mut io).await,
+            Err(MbrError::InvalidSignature)
+        ));
+    }
+}
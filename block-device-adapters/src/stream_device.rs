@@ -0,0 +1,146 @@
+use core::marker::PhantomData;
+
+use aligned::Aligned;
+use block_device_driver::{blocks_to_slice, blocks_to_slice_mut, BlockDevice};
+use embedded_io_async::{Read, ReadExactError, Seek, SeekFrom, Write};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum StreamDeviceError<T> {
+    /// The stream ended before all requested blocks could be read.
+    UnexpectedEof,
+    Io(T),
+}
+
+impl<T> From<T> for StreamDeviceError<T> {
+    fn from(t: T) -> Self {
+        StreamDeviceError::Io(t)
+    }
+}
+
+impl<T> From<ReadExactError<T>> for StreamDeviceError<T> {
+    fn from(e: ReadExactError<T>) -> Self {
+        match e {
+            ReadExactError::UnexpectedEof => StreamDeviceError::UnexpectedEof,
+            ReadExactError::Other(e) => StreamDeviceError::Io(e),
+        }
+    }
+}
+
+/// A [`BlockDevice`] backed by a byte stream, e.g. a disk image file or an in-memory buffer.
+///
+/// [`StreamDevice<T, const SIZE: usize, ALIGN = aligned::A1>`](StreamDevice) can be initialized with the following
+/// parameters.
+///
+/// - `T`: The inner stream implementing [`Read`], [`Write`] and [`Seek`].
+/// - `SIZE`: The size of the block.
+/// - `ALIGN`: The alignment of the block buffers, defaults to no alignment requirement.
+///
+/// Block `n` is stored at byte offset `n * SIZE` of the stream. A trailing partial block of the stream is not
+/// accessible.
+pub struct StreamDevice<T, const SIZE: usize, ALIGN = aligned::A1> {
+    inner: T,
+    _align: PhantomData<ALIGN>,
+}
+
+impl<T: Read + Write + Seek, const SIZE: usize, ALIGN: aligned::Alignment>
+    StreamDevice<T, SIZE, ALIGN>
+{
+    /// Creates a new block device from the inner stream.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            _align: PhantomData,
+        }
+    }
+
+    /// Returns the inner stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    async fn seek_to_block(&mut self, block_address: u32) -> Result<(), T::Error> {
+        let offset = u64::from(block_address) * SIZE as u64;
+        self.inner.seek(SeekFrom::Start(offset)).await?;
+        Ok(())
+    }
+}
+
+impl<T: Read + Write + Seek, const SIZE: usize, ALIGN: aligned::Alignment> BlockDevice<SIZE>
+    for StreamDevice<T, SIZE, ALIGN>
+{
+    type Error = StreamDeviceError<T::Error>;
+    type Align = ALIGN;
+
+    async fn read(
+        &mut self,
+        block_address: u32,
+        data: &mut [Aligned<Self::Align, [u8; SIZE]>],
+    ) -> Result<(), Self::Error> {
+        self.seek_to_block(block_address).await?;
+        self.inner.read_exact(blocks_to_slice_mut(data)).await?;
+        Ok(())
+    }
+
+    async fn write(
+        &mut self,
+        block_address: u32,
+        data: &[Aligned<Self::Align, [u8; SIZE]>],
+    ) -> Result<(), Self::Error> {
+        self.seek_to_block(block_address).await?;
+        self.inner.write_all(blocks_to_slice(data)).await?;
+        Ok(())
+    }
+
+    async fn size(&mut self) -> Result<u64, Self::Error> {
+        let len = self.inner.seek(SeekFrom::End(0)).await?;
+        Ok(len - len % SIZE as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(len: usize) -> StreamDevice<impl Read + Write + Seek<Error = std::io::Error>, 512> {
+        StreamDevice::new(embedded_io_adapters::tokio_1::FromTokio::new(
+            std::io::Cursor::new(vec![0u8; len]),
+        ))
+    }
+
+    #[tokio::test]
+    async fn read_write_blocks() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut device = device(4 * 512 + 100);
+        assert_eq!(device.size().await.unwrap(), 4 * 512);
+
+        let mut blocks = [Aligned([0u8; 512]); 2];
+        blocks[0].fill(b'A');
+        blocks[1].fill(b'B');
+        device.write(0, &blocks[..1]).await.unwrap();
+        device.write(3, &blocks[1..]).await.unwrap();
+
+        let mut read = [Aligned([0u8; 512]); 4];
+        device.read(0, &mut read).await.unwrap();
+        assert!(read[0].iter().all(|b| *b == b'A'));
+        assert!(read[1].iter().chain(read[2].iter()).all(|b| *b == 0));
+        assert!(read[3].iter().all(|b| *b == b'B'));
+
+        // multiple blocks are written at consecutive offsets
+        device.write(1, &blocks).await.unwrap();
+        device.read(1, &mut read[..2]).await.unwrap();
+        assert_eq!(read[..2], blocks);
+    }
+
+    #[tokio::test]
+    async fn read_past_end() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut device = device(4 * 512);
+        let mut read = [Aligned([0u8; 512]); 2];
+        assert!(matches!(
+            device.read(3, &mut read).await,
+            Err(StreamDeviceError::UnexpectedEof)
+        ));
+    }
+}
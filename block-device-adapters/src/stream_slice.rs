@@ -2,12 +2,19 @@ use core::cmp;
 use core::fmt::Debug;
 use embedded_io_async::{Read, Seek, SeekFrom, Write};
 
+use crate::buf_stream::BufStreamError;
+use crate::gpt::{self, GptError};
+use crate::mbr::PartitionEntry;
+
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum StreamSliceError<T: Debug> {
     InvalidSeek(i64),
+    /// The inner stream accepted no bytes of a non-empty write.
     WriteZero,
+    /// A non-empty write was attempted at the end of the slice.
+    EndOfSlice,
     Other(T),
 }
 
@@ -18,6 +25,10 @@ impl<E: Debug> From<E> for StreamSliceError<E> {
 }
 
 /// Stream wrapper for accessing limited segment of data from underlying file or device.
+///
+/// Reads and writes never cross the end of the slice. A write that does not fit is shortened to the bytes left in
+/// the slice and a non-empty write at the end of the slice returns [`StreamSliceError::EndOfSlice`], so `write_all`
+/// fails instead of looping. Reads at the end of the slice return `Ok(0)`.
 pub struct StreamSlice<T: Read + Write + Seek> {
     inner: T,
     start_offset: u64,
@@ -25,10 +36,35 @@ pub struct StreamSlice<T: Read + Write + Seek> {
     size: u64,
 }
 
+/// Converts the error of a [`BufStream`](crate::BufStream) into the error of a slice over it, an invalid seek stays
+/// an invalid seek and the error of the device becomes [`StreamSliceError::Other`].
+impl<E: Debug> From<BufStreamError<E>> for StreamSliceError<E> {
+    fn from(e: BufStreamError<E>) -> Self {
+        match e {
+            BufStreamError::InvalidSeek(offset) => Self::InvalidSeek(offset),
+            BufStreamError::Io(e) => Self::Other(e),
+        }
+    }
+}
+
+impl<E: Debug> StreamSliceError<BufStreamError<E>> {
+    /// Removes the [`BufStreamError`] layer of the error of a [`StreamSlice`] over a
+    /// [`BufStream`](crate::BufStream), so the error of the device is directly held by [`StreamSliceError::Other`].
+    pub fn flatten(self) -> StreamSliceError<E> {
+        match self {
+            Self::InvalidSeek(offset) => StreamSliceError::InvalidSeek(offset),
+            Self::WriteZero => StreamSliceError::WriteZero,
+            Self::EndOfSlice => StreamSliceError::EndOfSlice,
+            Self::Other(e) => e.into(),
+        }
+    }
+}
+
 impl<E: Debug> embedded_io_async::Error for StreamSliceError<E> {
     fn kind(&self) -> embedded_io_async::ErrorKind {
         match self {
             StreamSliceError::InvalidSeek(_) => embedded_io_async::ErrorKind::InvalidInput,
+            StreamSliceError::EndOfSlice => embedded_io_async::ErrorKind::WriteZero,
             StreamSliceError::Other(_) | StreamSliceError::WriteZero => {
                 embedded_io_async::ErrorKind::Other
             }
@@ -62,15 +98,74 @@ impl<T: Read + Write + Seek> StreamSlice<T> {
         })
     }
 
+    /// Creates new `StreamSlice` from inner stream, start offset and length.
+    ///
+    /// `start_offset` is inclusive offset of the first accessible byte.
+    /// `len` is the number of accessible bytes.
+    pub async fn new_from_len(
+        inner: T,
+        start_offset: u64,
+        len: u64,
+    ) -> Result<Self, StreamSliceError<T::Error>> {
+        Self::new(inner, start_offset, start_offset + len).await
+    }
+
+    /// Creates new `StreamSlice` covering the partition described by a Master Boot Record entry.
+    pub async fn from_partition(
+        inner: T,
+        entry: &PartitionEntry,
+    ) -> Result<Self, StreamSliceError<T::Error>> {
+        Self::new(inner, entry.start_offset(), entry.end_offset()).await
+    }
+
+    /// Creates new `StreamSlice` covering the partition at `index` of the GUID Partition Table of `inner`.
+    ///
+    /// Returns `GptError::PartitionNotFound` if the entry at `index` is unused or out of range.
+    pub async fn from_gpt_partition(mut inner: T, index: u32) -> Result<Self, GptError<T::Error>> {
+        let header = gpt::read_header(&mut inner).await?;
+        let partition = gpt::read_partition(&mut inner, &header, index)
+            .await?
+            .ok_or(GptError::PartitionNotFound)?;
+        inner
+            .seek(SeekFrom::Start(partition.start_offset()))
+            .await?;
+        Ok(StreamSlice {
+            start_offset: partition.start_offset(),
+            size: partition.size(),
+            inner,
+            current_offset: 0,
+        })
+    }
+
+    /// Returns the offset of the first accessible byte in the inner stream.
+    pub fn start_offset(&self) -> u64 {
+        self.start_offset
+    }
+
+    /// Returns the number of accessible bytes.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns `true` if no bytes are accessible.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
     /// Returns inner object
     pub fn into_inner(self) -> T {
         self.inner
     }
+
+    /// Returns the number of bytes of a `len` bytes long access that fit before the end of the slice.
+    fn remaining(&self, len: usize) -> usize {
+        cmp::min(self.size - self.current_offset, len as u64) as usize
+    }
 }
 
 impl<T: Read + Write + Seek> Read for StreamSlice<T> {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, StreamSliceError<T::Error>> {
-        let max_read_size = cmp::min((self.size - self.current_offset) as usize, buf.len());
+        let max_read_size = self.remaining(buf.len());
         let bytes_read = self.inner.read(&mut buf[..max_read_size]).await?;
         self.current_offset += bytes_read as u64;
         Ok(bytes_read)
@@ -79,7 +174,13 @@ impl<T: Read + Write + Seek> Read for StreamSlice<T> {
 
 impl<T: Read + Write + Seek> Write for StreamSlice<T> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, StreamSliceError<T::Error>> {
-        let max_write_size = cmp::min((self.size - self.current_offset) as usize, buf.len());
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let max_write_size = self.remaining(buf.len());
+        if max_write_size == 0 {
+            return Err(StreamSliceError::EndOfSlice);
+        }
         let bytes_written = self.inner.write(&buf[..max_write_size]).await?;
         if bytes_written == 0 {
             return Err(StreamSliceError::WriteZero);
@@ -117,6 +218,25 @@ impl<T: Read + Write + Seek> Seek for StreamSlice<T> {
 mod test {
     use super::*;
 
+    #[test]
+    fn flatten_buf_stream_error() {
+        let e: StreamSliceError<u8> = BufStreamError::Io(7).into();
+        assert!(matches!(e, StreamSliceError::Other(7)));
+        let e: StreamSliceError<u8> = BufStreamError::InvalidSeek(-1).into();
+        assert!(matches!(e, StreamSliceError::InvalidSeek(-1)));
+
+        let e = StreamSliceError::Other(BufStreamError::Io(7_u8)).flatten();
+        assert!(matches!(e, StreamSliceError::Other(7)));
+        let e = StreamSliceError::Other(BufStreamError::<u8>::InvalidSeek(-1)).flatten();
+        assert!(matches!(e, StreamSliceError::InvalidSeek(-1)));
+        let e = StreamSliceError::<BufStreamError<u8>>::InvalidSeek(100).flatten();
+        assert!(matches!(e, StreamSliceError::InvalidSeek(100)));
+        let e = StreamSliceError::<BufStreamError<u8>>::EndOfSlice.flatten();
+        assert!(matches!(e, StreamSliceError::EndOfSlice));
+        let e = StreamSliceError::<BufStreamError<u8>>::WriteZero.flatten();
+        assert!(matches!(e, StreamSliceError::WriteZero));
+    }
+
     #[tokio::test]
     async fn stream_test() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -142,6 +262,65 @@ mod test {
         assert_eq!(data, "Test Rust");
     }
 
+    #[tokio::test]
+    async fn new_from_len_matches_new() {
+        let buf = "BeforeTest dataAfter".to_string().into_bytes();
+        let mut by_end = StreamSlice::new(
+            embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(buf.clone())),
+            6,
+            6 + 9,
+        )
+        .await
+        .unwrap();
+        let mut by_len = StreamSlice::new_from_len(
+            embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(buf)),
+            6,
+            9,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(by_len.start_offset(), by_end.start_offset());
+        assert_eq!(by_len.len(), by_end.len());
+        assert_eq!(by_len.start_offset(), 6);
+        assert_eq!(by_len.len(), 9);
+        assert!(!by_len.is_empty());
+        assert_eq!(
+            read_to_string(&mut by_len).await.unwrap(),
+            read_to_string(&mut by_end).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn write_across_end() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let buf = "BeforeTest dataAfter".to_string().into_bytes();
+        let cur = std::io::Cursor::new(buf);
+        let mut stream =
+            StreamSlice::new(embedded_io_adapters::tokio_1::FromTokio::new(cur), 6, 6 + 9)
+                .await
+                .unwrap();
+
+        // a write crossing the end is shortened to the bytes left in the slice
+        stream.seek(SeekFrom::Start(7)).await.unwrap();
+        assert_eq!(stream.write(b"XYZ").await.unwrap(), 2);
+        // further writes report the end of the slice instead of writing nothing
+        assert!(matches!(
+            stream.write(b"Z").await,
+            Err(StreamSliceError::EndOfSlice)
+        ));
+        assert_eq!(stream.write(b"").await.unwrap(), 0);
+        stream.seek(SeekFrom::Start(7)).await.unwrap();
+        assert!(matches!(
+            stream.write_all(b"XYZ").await,
+            Err(StreamSliceError::EndOfSlice)
+        ));
+
+        // the bytes after the slice are untouched
+        let cur = stream.into_inner().into_inner();
+        assert_eq!(cur.into_inner(), b"BeforeTest daXYAfter");
+    }
+
     async fn read_to_string<IO: embedded_io_async::Read>(io: &mut IO) -> Result<String, IO::Error> {
         let mut buf = Vec::new();
         loop {
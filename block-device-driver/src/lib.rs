@@ -4,8 +4,19 @@
 #![warn(missing_docs)]
 #![allow(async_fn_in_trait)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+mod ram_disk;
+mod slice_device;
+
 use aligned::Aligned;
 
+#[cfg(feature = "alloc")]
+pub use ram_disk::{RamDisk, RamDiskError};
+pub use slice_device::{SliceBlockDevice, SliceBlockDeviceError};
+
 /// A trait for a block devices
 ///
 /// [`BlockDevice<const SIZE: usize>`](BlockDevice) can be initialized with the following parameters.
@@ -47,6 +58,77 @@ pub trait BlockDevice<const SIZE: usize> {
 
     /// Report the size of the block device in bytes.
     async fn size(&mut self) -> Result<u64, Self::Error>;
+
+    /// Erase the blocks from `start_block` to `end_block` (inclusive).
+    ///
+    /// Erasing informs the device that the contents of the blocks are no longer needed, allowing it to reclaim
+    /// the underlying storage and speed up later writes. The contents of erased blocks are undefined until they
+    /// are written again.
+    ///
+    /// The default implementation returns [`EraseError::NotSupported`].
+    async fn erase(
+        &mut self,
+        start_block: u32,
+        end_block: u32,
+    ) -> Result<(), EraseError<Self::Error>> {
+        let _ = (start_block, end_block);
+        Err(EraseError::NotSupported)
+    }
+
+    /// Read `buf.len()` bytes starting at the byte `offset`, which does not need to be block aligned.
+    ///
+    /// The default implementation reads one block at a time into a buffer on the stack, use a buffered stream for
+    /// larger or repeated accesses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range reaches past the largest block address.
+    async fn read_bytes(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let mut block = [Aligned([0; SIZE])];
+        let mut pos = offset;
+        let mut buf = buf;
+        while !buf.is_empty() {
+            let (block_address, offset_in_block) = byte_to_block::<SIZE>(pos);
+            let len = buf.len().min(SIZE - offset_in_block);
+            self.read(block_address, &mut block).await?;
+            buf[..len].copy_from_slice(&block[0][offset_in_block..offset_in_block + len]);
+            buf = &mut buf[len..];
+            pos += len as u64;
+        }
+        Ok(())
+    }
+
+    /// Write `buf` starting at the byte `offset`, which does not need to be block aligned.
+    ///
+    /// The default implementation writes one block at a time from a buffer on the stack. Blocks that are only
+    /// partially covered by `buf` are read first and written back with the new bytes (read-modify-write).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range reaches past the largest block address.
+    async fn write_bytes(&mut self, offset: u64, buf: &[u8]) -> Result<(), Self::Error> {
+        let mut block = [Aligned([0; SIZE])];
+        let mut pos = offset;
+        let mut buf = buf;
+        while !buf.is_empty() {
+            let (block_address, offset_in_block) = byte_to_block::<SIZE>(pos);
+            let len = buf.len().min(SIZE - offset_in_block);
+            if len < SIZE {
+                self.read(block_address, &mut block).await?;
+            }
+            block[0][offset_in_block..offset_in_block + len].copy_from_slice(&buf[..len]);
+            self.write(block_address, &block).await?;
+            buf = &buf[len..];
+            pos += len as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a byte offset into a block address and an offset within the block.
+fn byte_to_block<const SIZE: usize>(offset: u64) -> (u32, usize) {
+    let block_address = u32::try_from(offset / SIZE as u64).expect("block address out of range");
+    (block_address, (offset % SIZE as u64) as usize)
 }
 
 impl<T: BlockDevice<SIZE>, const SIZE: usize> BlockDevice<SIZE> for &mut T {
@@ -72,6 +154,37 @@ impl<T: BlockDevice<SIZE>, const SIZE: usize> BlockDevice<SIZE> for &mut T {
     async fn size(&mut self) -> Result<u64, Self::Error> {
         (*self).size().await
     }
+
+    async fn erase(
+        &mut self,
+        start_block: u32,
+        end_block: u32,
+    ) -> Result<(), EraseError<Self::Error>> {
+        (*self).erase(start_block, end_block).await
+    }
+
+    async fn read_bytes(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), Self::Error> {
+        (*self).read_bytes(offset, buf).await
+    }
+
+    async fn write_bytes(&mut self, offset: u64, buf: &[u8]) -> Result<(), Self::Error> {
+        (*self).write_bytes(offset, buf).await
+    }
+}
+
+/// The error returned by [`BlockDevice::erase`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EraseError<E> {
+    /// The block device does not support erasing blocks.
+    NotSupported,
+    /// The block device failed to erase the blocks.
+    Device(E),
+}
+
+impl<E> From<E> for EraseError<E> {
+    fn from(e: E) -> Self {
+        EraseError::Device(e)
+    }
 }
 
 /// Cast a byte slice to an aligned slice of blocks.
@@ -175,4 +288,120 @@ mod test {
         let blocks: &mut [Aligned<aligned::A4, [u8; 512]>] = slice_to_blocks_mut(slice);
         assert!(blocks.len() == 2);
     }
+
+    struct NoEraseDevice;
+
+    impl BlockDevice<512> for NoEraseDevice {
+        type Error = ();
+        type Align = aligned::A4;
+
+        async fn read(
+            &mut self,
+            _block_address: u32,
+            _data: &mut [Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn write(
+            &mut self,
+            _block_address: u32,
+            _data: &[Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn size(&mut self) -> Result<u64, Self::Error> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_erase_default_not_supported() {
+        let mut device = NoEraseDevice;
+        let r = embassy_futures::block_on(BlockDevice::<512>::erase(&mut &mut device, 0, 7));
+        assert_eq!(r, Err(EraseError::NotSupported));
+    }
+
+    /// A device of four blocks counting the block transfers.
+    struct MemDevice {
+        blocks: [Aligned<aligned::A4, [u8; 512]>; 4],
+        reads: usize,
+        writes: usize,
+    }
+
+    impl MemDevice {
+        fn new() -> Self {
+            let mut blocks = [Aligned([0; 512]); 4];
+            for (i, b) in blocks.iter_mut().enumerate() {
+                b.fill(b'0' + i as u8);
+            }
+            Self {
+                blocks,
+                reads: 0,
+                writes: 0,
+            }
+        }
+    }
+
+    impl BlockDevice<512> for MemDevice {
+        type Error = ();
+        type Align = aligned::A4;
+
+        async fn read(
+            &mut self,
+            block_address: u32,
+            data: &mut [Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            self.reads += data.len();
+            let start = block_address as usize;
+            data.copy_from_slice(&self.blocks[start..start + data.len()]);
+            Ok(())
+        }
+
+        async fn write(
+            &mut self,
+            block_address: u32,
+            data: &[Aligned<Self::Align, [u8; 512]>],
+        ) -> Result<(), Self::Error> {
+            self.writes += data.len();
+            let start = block_address as usize;
+            self.blocks[start..start + data.len()].copy_from_slice(data);
+            Ok(())
+        }
+
+        async fn size(&mut self) -> Result<u64, Self::Error> {
+            Ok(4 * 512)
+        }
+    }
+
+    #[test]
+    fn test_read_bytes_spanning_blocks() {
+        let mut device = MemDevice::new();
+        let mut buf = [0; 8];
+        embassy_futures::block_on(device.read_bytes(508, &mut buf)).unwrap();
+        assert_eq!(&buf, b"00001111");
+        assert_eq!(device.reads, 2);
+
+        // an empty read does not touch the device
+        embassy_futures::block_on(device.read_bytes(100, &mut [])).unwrap();
+        assert_eq!(device.reads, 2);
+    }
+
+    #[test]
+    fn test_write_bytes_partial_block() {
+        let mut device = MemDevice::new();
+        embassy_futures::block_on(device.write_bytes(510, b"abcd")).unwrap();
+        assert_eq!(device.reads, 2);
+        assert_eq!(device.writes, 2);
+        assert_eq!(&device.blocks[0][508..], b"00ab");
+        assert_eq!(&device.blocks[1][..4], b"cd11");
+
+        // whole blocks are written without reading them first
+        device.reads = 0;
+        embassy_futures::block_on(device.write_bytes(1024, &[b'x'; 512])).unwrap();
+        assert_eq!(device.reads, 0);
+        assert!(device.blocks[2].iter().all(|b| *b == b'x'));
+        assert!(device.blocks[3].iter().all(|b| *b == b'3'));
+    }
 }
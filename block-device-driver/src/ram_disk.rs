@@ -0,0 +1,147 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use aligned::Aligned;
+
+use crate::{blocks_to_slice, BlockDevice, EraseError};
+
+/// The error returned by [`RamDisk`] operations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RamDiskError {
+    /// The accessed blocks are not within the bounds of the disk.
+    OutOfBounds,
+}
+
+/// An in-memory [`BlockDevice`] backed by a heap allocated buffer.
+///
+/// [`RamDisk<const SIZE: usize>`](RamDisk) stores `SIZE` byte blocks aligned to 4 bytes. It is intended for tests and
+/// examples, e.g. to format and mount a filesystem entirely in RAM. Accessing blocks past the end of the disk returns
+/// [`RamDiskError::OutOfBounds`].
+///
+/// Enabled by the `alloc` feature.
+#[derive(Clone)]
+pub struct RamDisk<const SIZE: usize> {
+    blocks: Vec<Aligned<aligned::A4, [u8; SIZE]>>,
+}
+
+impl<const SIZE: usize> RamDisk<SIZE> {
+    /// Creates a zero-filled disk with `num_blocks` blocks.
+    pub fn new(num_blocks: usize) -> Self {
+        Self {
+            blocks: vec![Aligned([0; SIZE]); num_blocks],
+        }
+    }
+
+    /// Returns the number of blocks of the disk.
+    pub fn num_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns the contents of the disk.
+    pub fn as_bytes(&self) -> &[u8] {
+        blocks_to_slice(&self.blocks)
+    }
+
+    fn range(
+        &self,
+        block_address: u32,
+        len: usize,
+    ) -> Result<core::ops::Range<usize>, RamDiskError> {
+        let start = block_address as usize;
+        match start.checked_add(len) {
+            Some(end) if end <= self.blocks.len() => Ok(start..end),
+            _ => Err(RamDiskError::OutOfBounds),
+        }
+    }
+}
+
+impl<const SIZE: usize> BlockDevice<SIZE> for RamDisk<SIZE> {
+    type Error = RamDiskError;
+    type Align = aligned::A4;
+
+    async fn read(
+        &mut self,
+        block_address: u32,
+        data: &mut [Aligned<Self::Align, [u8; SIZE]>],
+    ) -> Result<(), Self::Error> {
+        let range = self.range(block_address, data.len())?;
+        data.copy_from_slice(&self.blocks[range]);
+        Ok(())
+    }
+
+    async fn write(
+        &mut self,
+        block_address: u32,
+        data: &[Aligned<Self::Align, [u8; SIZE]>],
+    ) -> Result<(), Self::Error> {
+        let range = self.range(block_address, data.len())?;
+        self.blocks[range].copy_from_slice(data);
+        Ok(())
+    }
+
+    async fn size(&mut self) -> Result<u64, Self::Error> {
+        Ok(self.blocks.len() as u64 * SIZE as u64)
+    }
+
+    async fn erase(
+        &mut self,
+        start_block: u32,
+        end_block: u32,
+    ) -> Result<(), EraseError<Self::Error>> {
+        let len = end_block
+            .checked_sub(start_block)
+            .ok_or(RamDiskError::OutOfBounds)? as usize
+            + 1;
+        let range = self.range(start_block, len)?;
+        self.blocks[range].fill(Aligned([0; SIZE]));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_write_in_bounds() {
+        let mut disk = RamDisk::<512>::new(4);
+        let block = [Aligned([0xAB; 512])];
+        embassy_futures::block_on(disk.write(3, &block)).unwrap();
+        let mut read = [Aligned([0; 512]); 2];
+        embassy_futures::block_on(disk.read(2, &mut read)).unwrap();
+        assert!(read[0].iter().all(|b| *b == 0));
+        assert_eq!(read[1], block[0]);
+        assert_eq!(embassy_futures::block_on(disk.size()), Ok(4 * 512));
+        assert_eq!(disk.as_bytes().len(), 4 * 512);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut disk = RamDisk::<512>::new(4);
+        let mut read = [Aligned([0; 512]); 2];
+        assert_eq!(
+            embassy_futures::block_on(disk.read(3, &mut read)),
+            Err(RamDiskError::OutOfBounds)
+        );
+        assert_eq!(
+            embassy_futures::block_on(disk.write(u32::MAX, &read)),
+            Err(RamDiskError::OutOfBounds)
+        );
+        assert_eq!(
+            embassy_futures::block_on(disk.erase(2, 4)),
+            Err(EraseError::Device(RamDiskError::OutOfBounds))
+        );
+    }
+
+    #[test]
+    fn erase_zero_fills() {
+        let mut disk = RamDisk::<512>::new(4);
+        let blocks = [Aligned([0xAB; 512]); 4];
+        embassy_futures::block_on(disk.write(0, &blocks)).unwrap();
+        embassy_futures::block_on(disk.erase(1, 2)).unwrap();
+        let bytes = disk.as_bytes();
+        assert!(bytes[..512].iter().all(|b| *b == 0xAB));
+        assert!(bytes[512..3 * 512].iter().all(|b| *b == 0));
+        assert!(bytes[3 * 512..].iter().all(|b| *b == 0xAB));
+    }
+}
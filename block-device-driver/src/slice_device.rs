@@ -0,0 +1,184 @@
+use aligned::Aligned;
+
+use crate::{blocks_to_slice, blocks_to_slice_mut, BlockDevice, EraseError};
+
+/// The error returned by [`SliceBlockDevice`] operations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SliceBlockDeviceError {
+    /// The length of the slice is not a multiple of the block size.
+    UnalignedLength,
+    /// The accessed blocks or bytes are not within the bounds of the slice.
+    OutOfBounds,
+}
+
+/// A [`BlockDevice`] over a borrowed byte slice.
+///
+/// [`SliceBlockDevice<const SIZE: usize>`](SliceBlockDevice) reads and writes the slice directly, so an image that is
+/// already in memory (e.g. loaded from a file or memory-mapped by an emulator) can be mounted without copying it.
+/// Byte accesses through [`BlockDevice::read_bytes`] and [`BlockDevice::write_bytes`] are not split into blocks.
+/// Accessing data past the end of the slice returns [`SliceBlockDeviceError::OutOfBounds`].
+pub struct SliceBlockDevice<'a, const SIZE: usize> {
+    buf: &'a mut [u8],
+}
+
+impl<'a, const SIZE: usize> SliceBlockDevice<'a, SIZE> {
+    /// Creates a device over `buf`.
+    ///
+    /// Returns [`SliceBlockDeviceError::UnalignedLength`] if the length of `buf` is not a multiple of `SIZE`.
+    pub fn new(buf: &'a mut [u8]) -> Result<Self, SliceBlockDeviceError> {
+        if buf.len() % SIZE != 0 {
+            return Err(SliceBlockDeviceError::UnalignedLength);
+        }
+        Ok(Self { buf })
+    }
+
+    /// Returns the number of blocks of the device.
+    pub fn num_blocks(&self) -> usize {
+        self.buf.len() / SIZE
+    }
+
+    /// Returns the contents of the device.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf
+    }
+
+    /// Returns the borrowed slice.
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.buf
+    }
+
+    fn range(
+        &self,
+        offset: u64,
+        len: usize,
+    ) -> Result<core::ops::Range<usize>, SliceBlockDeviceError> {
+        let start = usize::try_from(offset).map_err(|_| SliceBlockDeviceError::OutOfBounds)?;
+        match start.checked_add(len) {
+            Some(end) if end <= self.buf.len() => Ok(start..end),
+            _ => Err(SliceBlockDeviceError::OutOfBounds),
+        }
+    }
+
+    fn block_range(
+        &self,
+        block_address: u32,
+        num_blocks: usize,
+    ) -> Result<core::ops::Range<usize>, SliceBlockDeviceError> {
+        let len = num_blocks
+            .checked_mul(SIZE)
+            .ok_or(SliceBlockDeviceError::OutOfBounds)?;
+        self.range(u64::from(block_address) * SIZE as u64, len)
+    }
+}
+
+impl<const SIZE: usize> BlockDevice<SIZE> for SliceBlockDevice<'_, SIZE> {
+    type Error = SliceBlockDeviceError;
+    type Align = aligned::A1;
+
+    async fn read(
+        &mut self,
+        block_address: u32,
+        data: &mut [Aligned<Self::Align, [u8; SIZE]>],
+    ) -> Result<(), Self::Error> {
+        let range = self.block_range(block_address, data.len())?;
+        blocks_to_slice_mut(data).copy_from_slice(&self.buf[range]);
+        Ok(())
+    }
+
+    async fn write(
+        &mut self,
+        block_address: u32,
+        data: &[Aligned<Self::Align, [u8; SIZE]>],
+    ) -> Result<(), Self::Error> {
+        let range = self.block_range(block_address, data.len())?;
+        self.buf[range].copy_from_slice(blocks_to_slice(data));
+        Ok(())
+    }
+
+    async fn size(&mut self) -> Result<u64, Self::Error> {
+        Ok(self.buf.len() as u64)
+    }
+
+    async fn erase(
+        &mut self,
+        start_block: u32,
+        end_block: u32,
+    ) -> Result<(), EraseError<Self::Error>> {
+        let num_blocks = end_block
+            .checked_sub(start_block)
+            .ok_or(SliceBlockDeviceError::OutOfBounds)? as usize
+            + 1;
+        let range = self.block_range(start_block, num_blocks)?;
+        self.buf[range].fill(0);
+        Ok(())
+    }
+
+    async fn read_bytes(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let range = self.range(offset, buf.len())?;
+        buf.copy_from_slice(&self.buf[range]);
+        Ok(())
+    }
+
+    async fn write_bytes(&mut self, offset: u64, buf: &[u8]) -> Result<(), Self::Error> {
+        let range = self.range(offset, buf.len())?;
+        self.buf[range].copy_from_slice(buf);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unaligned_length() {
+        let mut buf = [0; 1000];
+        assert!(matches!(
+            SliceBlockDevice::<512>::new(&mut buf),
+            Err(SliceBlockDeviceError::UnalignedLength)
+        ));
+    }
+
+    #[test]
+    fn read_write_in_bounds() {
+        let mut buf = [0; 4 * 512];
+        let mut disk = SliceBlockDevice::<512>::new(&mut buf).unwrap();
+        let block = [Aligned([0xAB; 512])];
+        embassy_futures::block_on(disk.write(3, &block)).unwrap();
+        let mut read = [Aligned([0; 512]); 2];
+        embassy_futures::block_on(disk.read(2, &mut read)).unwrap();
+        assert!(read[0].iter().all(|b| *b == 0));
+        assert_eq!(read[1], block[0]);
+        assert_eq!(embassy_futures::block_on(disk.size()), Ok(4 * 512));
+        assert_eq!(disk.num_blocks(), 4);
+
+        embassy_futures::block_on(disk.write_bytes(510, &[1, 2, 3, 4])).unwrap();
+        let mut bytes = [0; 6];
+        embassy_futures::block_on(disk.read_bytes(509, &mut bytes)).unwrap();
+        assert_eq!(bytes, [0, 1, 2, 3, 4, 0]);
+        assert_eq!(&disk.into_inner()[510..514], &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut buf = [0; 4 * 512];
+        let mut disk = SliceBlockDevice::<512>::new(&mut buf).unwrap();
+        let mut read = [Aligned([0; 512]); 2];
+        assert_eq!(
+            embassy_futures::block_on(disk.read(3, &mut read)),
+            Err(SliceBlockDeviceError::OutOfBounds)
+        );
+        assert_eq!(
+            embassy_futures::block_on(disk.write(u32::MAX, &read)),
+            Err(SliceBlockDeviceError::OutOfBounds)
+        );
+        assert_eq!(
+            embassy_futures::block_on(disk.read_bytes(4 * 512 - 1, &mut [0; 2])),
+            Err(SliceBlockDeviceError::OutOfBounds)
+        );
+        assert_eq!(
+            embassy_futures::block_on(disk.erase(2, 4)),
+            Err(EraseError::Device(SliceBlockDeviceError::OutOfBounds))
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Blocking façade over the async filesystem API.
+//!
+//! The types in this module wrap `FileSystem`, `Dir` and `File` and drive their async methods to completion by
+//! polling each future exactly once with a no-op waker. A blocking `File` implements the `embedded_io` `Read`,
+//! `Write` and `Seek` traits, so it can be passed to code that is not async.
+//!
+//! This only works if the underlying storage is actually synchronous, i.e. every future returned by its
+//! `embedded_io_async` methods completes on the first poll. This is the case for in-memory images and for block
+//! device drivers that do their IO by busy-waiting. Storage that really waits for an interrupt or another task
+//! (e.g. DMA driven SPI with an async executor) must not be used here: if any future returns `Poll::Pending` the
+//! blocking wrapper panics instead of spinning forever.
+//!
+//! With the `std` feature a blocking `File` also implements the `std::io` `Read`, `Write` and `Seek` traits, so it
+//! can be used with `std` utilities on host tooling, e.g. `std::io::copy`. The filesystem errors are converted to
+//! `std::io::Error`, which requires the storage error type to implement `std::error::Error`.
+//!
+//! Enabled by the `blocking` feature.
+
+use core::future::Future;
+use core::pin::pin;
+use core::ptr;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::dir_entry::DirEntry;
+use crate::error::Error;
+use crate::fs::{FileSystemStats, FsOptions, IntoStorage, OemCpConverter, ReadWriteSeek};
+use crate::io::{Read as _, Seek as _, Write as _};
+use crate::time::TimeProvider;
+
+const NOOP_WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(noop_clone, noop, noop, noop);
+
+fn noop_clone(_: *const ()) -> RawWaker {
+    RawWaker::new(ptr::null(), &NOOP_WAKER_VTABLE)
+}
+
+fn noop(_: *const ()) {}
+
+/// Runs a future to completion by polling it once.
+///
+/// Can be used to call async functions of this crate that have no blocking counterpart, e.g. `format_volume`.
+///
+/// # Panics
+///
+/// Panics if the future returns `Poll::Pending`. This means the underlying storage is not synchronous and cannot
+/// be used with the blocking API.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    // SAFETY: all vtable functions are no-ops that never dereference the data pointer
+    let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &NOOP_WAKER_VTABLE)) };
+    let mut cx = Context::from_waker(&waker);
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future is pending - blocking API requires synchronous storage"),
+    }
+}
+
+/// A blocking wrapper of `FileSystem`.
+pub struct FileSystem<IO: ReadWriteSeek, TP, OCC> {
+    inner: crate::FileSystem<IO, TP, OCC>,
+}
+
+impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
+    /// Creates a new filesystem object instance.
+    ///
+    /// See `FileSystem::new`.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::CorruptedFileSystem` will be returned if the boot sector and/or the file system information sector
+    ///   contains invalid values.
+    /// * `Error::Io` will be returned if the provided storage object returned an I/O error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying storage is not synchronous.
+    pub fn new<T: IntoStorage<IO>>(storage: T, options: FsOptions<TP, OCC>) -> Result<Self, Error<IO::Error>> {
+        Ok(Self {
+            inner: block_on(crate::FileSystem::new(storage, options))?,
+        })
+    }
+
+    /// Returns a reference to the wrapped async filesystem.
+    #[must_use]
+    pub fn as_async(&self) -> &crate::FileSystem<IO, TP, OCC> {
+        &self.inner
+    }
+
+    /// Unwraps the async filesystem.
+    #[must_use]
+    pub fn into_async(self) -> crate::FileSystem<IO, TP, OCC> {
+        self.inner
+    }
+
+    /// Returns a root directory object allowing for futher penetration of a filesystem structure.
+    #[must_use]
+    pub fn root_dir(&self) -> Dir<'_, IO, TP, OCC> {
+        Dir::from(self.inner.root_dir())
+    }
+
+    /// Returns status of the filesystem: free clusters count, total clusters count, etc.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub fn stats(&self) -> Result<FileSystemStats, Error<IO::Error>> {
+        block_on(self.inner.stats())
+    }
+
+    /// Flushes all filesystem metadata to the underlying storage.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub fn flush(&self) -> Result<(), Error<IO::Error>> {
+        block_on(self.inner.flush())
+    }
+
+    /// Unmounts the filesystem.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub fn unmount(self) -> Result<(), Error<IO::Error>> {
+        block_on(self.inner.unmount())
+    }
+}
+
+/// A blocking wrapper of `Dir`.
+pub struct Dir<'a, IO: ReadWriteSeek, TP, OCC> {
+    inner: crate::Dir<'a, IO, TP, OCC>,
+}
+
+impl<'a, IO: ReadWriteSeek, TP, OCC> From<crate::Dir<'a, IO, TP, OCC>> for Dir<'a, IO, TP, OCC> {
+    fn from(inner: crate::Dir<'a, IO, TP, OCC>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, IO: ReadWriteSeek, TP, OCC> Dir<'a, IO, TP, OCC> {
+    /// Returns a reference to the wrapped async directory.
+    #[must_use]
+    pub fn as_async(&self) -> &crate::Dir<'a, IO, TP, OCC> {
+        &self.inner
+    }
+
+    /// Unwraps the async directory.
+    #[must_use]
+    pub fn into_async(self) -> crate::Dir<'a, IO, TP, OCC> {
+        self.inner
+    }
+}
+
+impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, TP, OCC> {
+    /// Creates directory entries iterator.
+    #[must_use]
+    pub fn iter(&self) -> DirIter<'a, IO, TP, OCC> {
+        DirIter {
+            inner: self.inner.iter(),
+        }
+    }
+    /// Opens existing subdirectory. See `Dir::open_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Dir::open_dir`.
+    pub fn open_dir(&self, path: &str) -> Result<Self, Error<IO::Error>> {
+        block_on(self.inner.open_dir(path)).map(Self::from)
+    }
+
+    /// Opens existing file. See `Dir::open_file`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Dir::open_file`.
+    pub fn open_file(&self, path: &str) -> Result<File<'a, IO, TP, OCC>, Error<IO::Error>> {
+        block_on(self.inner.open_file(path)).map(File::from)
+    }
+
+    /// Creates new or opens existing file. See `Dir::create_file`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Dir::create_file`.
+    pub fn create_file(&self, path: &str) -> Result<File<'a, IO, TP, OCC>, Error<IO::Error>> {
+        block_on(self.inner.create_file(path)).map(File::from)
+    }
+
+    /// Creates new directory or opens existing. See `Dir::create_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Dir::create_dir`.
+    pub fn create_dir(&self, path: &str) -> Result<Self, Error<IO::Error>> {
+        block_on(self.inner.create_dir(path)).map(Self::from)
+    }
+
+    /// Removes existing file or directory. See `Dir::remove`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Dir::remove`.
+    pub fn remove(&self, path: &str) -> Result<(), Error<IO::Error>> {
+        block_on(self.inner.remove(path))
+    }
+
+    /// Renames or moves existing file or directory. See `Dir::rename`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Dir::rename`.
+    pub fn rename(&self, src_path: &str, dst_dir: &Dir<IO, TP, OCC>, dst_path: &str) -> Result<(), Error<IO::Error>> {
+        block_on(self.inner.rename(src_path, &dst_dir.inner, dst_path))
+    }
+
+    /// Checks whether a file or directory exists at `path`. See `Dir::exists`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Dir::exists`.
+    pub fn exists(&self, path: &str) -> Result<bool, Error<IO::Error>> {
+        block_on(self.inner.exists(path))
+    }
+}
+
+impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> IntoIterator for &Dir<'a, IO, TP, OCC> {
+    type Item = Result<DirEntry<'a, IO, TP, OCC>, Error<IO::Error>>;
+    type IntoIter = DirIter<'a, IO, TP, OCC>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A blocking wrapper of `DirIter`.
+pub struct DirIter<'a, IO: ReadWriteSeek, TP, OCC> {
+    inner: crate::DirIter<'a, IO, TP, OCC>,
+}
+
+impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Iterator for DirIter<'a, IO, TP, OCC> {
+    type Item = Result<DirEntry<'a, IO, TP, OCC>, Error<IO::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(self.inner.next())
+    }
+}
+
+/// A blocking wrapper of `File` implementing the `embedded_io` traits.
+pub struct File<'a, IO: ReadWriteSeek, TP, OCC> {
+    inner: crate::File<'a, IO, TP, OCC>,
+}
+
+impl<'a, IO: ReadWriteSeek, TP, OCC> From<crate::File<'a, IO, TP, OCC>> for File<'a, IO, TP, OCC> {
+    fn from(inner: crate::File<'a, IO, TP, OCC>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
+    /// Returns a reference to the wrapped async file.
+    #[must_use]
+    pub fn as_async(&self) -> &crate::File<'a, IO, TP, OCC> {
+        &self.inner
+    }
+
+    /// Unwraps the async file.
+    #[must_use]
+    pub fn into_async(self) -> crate::File<'a, IO, TP, OCC> {
+        self.inner
+    }
+
+    /// Truncate file in current position. See `File::truncate`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `File::truncate`.
+    pub fn truncate(&mut self) -> Result<(), Error<IO::Error>> {
+        block_on(self.inner.truncate())
+    }
+}
+
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> File<'_, IO, TP, OCC> {
+    /// Truncate file to `len` bytes. See `File::truncate_to`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `File::truncate_to`.
+    pub fn truncate_to(&mut self, len: u64) -> Result<(), Error<IO::Error>> {
+        block_on(self.inner.truncate_to(len))
+    }
+}
+
+impl<IO: ReadWriteSeek, TP, OCC> embedded_io::ErrorType for File<'_, IO, TP, OCC> {
+    type Error = Error<IO::Error>;
+}
+
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> embedded_io::Read for File<'_, IO, TP, OCC> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        block_on(self.inner.read(buf))
+    }
+}
+
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> embedded_io::Write for File<'_, IO, TP, OCC> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        block_on(self.inner.write(buf))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        block_on(self.inner.flush())
+    }
+}
+
+impl<IO: ReadWriteSeek, TP, OCC> embedded_io::Seek for File<'_, IO, TP, OCC> {
+    fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Self::Error> {
+        block_on(self.inner.seek(pos))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> std::io::Read for File<'_, IO, TP, OCC>
+where
+    IO::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(block_on(self.inner.read(buf))?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> std::io::Write for File<'_, IO, TP, OCC>
+where
+    IO::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(block_on(self.inner.write(buf))?)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(block_on(self.inner.flush())?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO: ReadWriteSeek, TP, OCC> std::io::Seek for File<'_, IO, TP, OCC>
+where
+    IO::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            std::io::SeekFrom::Start(n) => embedded_io::SeekFrom::Start(n),
+            std::io::SeekFrom::End(n) => embedded_io::SeekFrom::End(n),
+            std::io::SeekFrom::Current(n) => embedded_io::SeekFrom::Current(n),
+        };
+        Ok(block_on(self.inner.seek(pos))?)
+    }
+}
@@ -3,7 +3,7 @@ use core::u16;
 use core::u8;
 
 use crate::dir_entry::DIR_ENTRY_SIZE;
-use crate::error::{Error, IoError};
+use crate::error::{Error, FileSystemKind, IoError};
 use crate::fs::{FatType, FormatVolumeOptions, FsStatusFlags};
 use crate::io::{Read, ReadLeExt, Write, WriteLeExt};
 use crate::table::RESERVED_FAT_ENTRIES;
@@ -413,15 +413,36 @@ impl BiosParameterBlock {
     }
 }
 
-pub(crate) struct BootSector {
+/// A parsed boot sector of a FAT volume.
+///
+/// It can be read once with `BootSector::read` and passed to `FileSystem::from_existing_parts` to mount the volume
+/// without reading the boot sector again, e.g. when a bootloader hands over an already validated volume.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Debug)]
+pub struct BootSector {
     bootjmp: [u8; 3],
-    oem_name: [u8; 8],
+    pub(crate) oem_name: [u8; 8],
     pub(crate) bpb: BiosParameterBlock,
     boot_code: [u8; 448],
     boot_sig: [u8; 2],
 }
 
 impl BootSector {
+    /// Reads and validates a boot sector from the current position of `rdr`.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::CorruptedFileSystem` will be returned if the boot sector contains invalid values.
+    /// * `Error::UnsupportedFileSystem` will be returned if the boot sector belongs to an exFAT or NTFS volume.
+    /// * `Error::Io` will be returned if the provided reader returned an I/O error.
+    pub async fn read<R: Read>(rdr: &mut R) -> Result<Self, Error<R::Error>> {
+        let boot = Self::deserialize(rdr).await?;
+        boot.validate()?;
+        Ok(boot)
+    }
+
     pub(crate) async fn deserialize<R: Read>(rdr: &mut R) -> Result<Self, Error<R::Error>> {
         let mut boot = Self::default();
         rdr.read_exact(&mut boot.bootjmp).await?;
@@ -453,6 +474,18 @@ impl BootSector {
     }
 
     pub(crate) fn validate<E: IoError>(&self) -> Result<(), Error<E>> {
+        // exFAT and NTFS store their filesystem name where FAT stores the OEM name
+        match &self.oem_name {
+            b"EXFAT   " => {
+                error!("Unsupported filesystem: exFAT");
+                return Err(Error::UnsupportedFileSystem(FileSystemKind::ExFat));
+            }
+            b"NTFS    " => {
+                error!("Unsupported filesystem: NTFS");
+                return Err(Error::UnsupportedFileSystem(FileSystemKind::Ntfs));
+            }
+            _ => {}
+        }
         if self.boot_sig != [0x55, 0xAA] {
             error!(
                 "Invalid boot sector signature: expected [0x55, 0xAA] but got {:?}",
@@ -587,11 +620,18 @@ fn try_fs_geometry(
     fat_type: FatType,
     root_dir_sectors: u32,
     fats: u8,
+    reserved_sectors: Option<u16>,
 ) -> Result<(u16, u32), Error<()>> {
     // Note: most of implementations use 32 reserved sectors for FAT32 but it's wasting of space
-    // This implementation uses only 8. This is enough to fit in two boot sectors (main and backup) with additional
-    // bootstrap code and one FSInfo sector. It also makes FAT alligned to 4096 which is a nice number.
-    let reserved_sectors: u16 = if fat_type == FatType::Fat32 { 8 } else { 1 };
+    // This implementation uses only 8 by default. This is enough to fit in two boot sectors (main and backup) with
+    // additional bootstrap code and one FSInfo sector. It also makes FAT alligned to 4096 which is a nice number.
+    let reserved_sectors: u16 = reserved_sectors.unwrap_or(if fat_type == FatType::Fat32 { 8 } else { 1 });
+    // FAT32 keeps the backup boot sector in sector 6
+    let min_reserved_sectors = if fat_type == FatType::Fat32 { 7 } else { 1 };
+    if reserved_sectors < min_reserved_sectors {
+        error!("Too few reserved sectors for {:?}: {}", fat_type, reserved_sectors);
+        return Err(Error::InvalidInput);
+    }
 
     // Check if volume has enough space to accomodate reserved sectors, FAT, root directory and some data space
     // Having less than 8 sectors for FAT and data would make a little sense
@@ -643,8 +683,14 @@ fn determine_fs_geometry<E: IoError>(
     sectors_per_cluster: u8,
     root_dir_entries: u16,
     fats: u8,
+    reserved_sectors: Option<u16>,
+    fat_type: Option<FatType>,
 ) -> Result<(FatType, u16, u32), Error<E>> {
-    for &fat_type in &[FatType::Fat32, FatType::Fat16, FatType::Fat12] {
+    let fat_types: &[FatType] = match &fat_type {
+        Some(fat_type) => core::slice::from_ref(fat_type),
+        None => &[FatType::Fat32, FatType::Fat16, FatType::Fat12],
+    };
+    for &fat_type in fat_types {
         let root_dir_sectors = determine_root_dir_sectors(root_dir_entries, bytes_per_sector, fat_type);
         let result = try_fs_geometry(
             total_sectors,
@@ -653,6 +699,7 @@ fn determine_fs_geometry<E: IoError>(
             fat_type,
             root_dir_sectors,
             fats,
+            reserved_sectors,
         );
         if let Ok((reserved_sectors, sectors_per_fat)) = result {
             return Ok((fat_type, reserved_sectors, sectors_per_fat));
@@ -663,29 +710,65 @@ fn determine_fs_geometry<E: IoError>(
     Err(Error::InvalidInput)
 }
 
+fn validate_root_dir_entries<E: IoError>(
+    options: &FormatVolumeOptions,
+    root_dir_entries: u16,
+    bytes_per_sector: u16,
+    fat_type: FatType,
+) -> Result<(), Error<E>> {
+    if fat_type == FatType::Fat32 {
+        if options.root_dir_entries.is_some() {
+            warn!("root_dir_entries is ignored on FAT32 volumes");
+        }
+    } else if root_dir_entries == 0 || (u32::from(root_dir_entries) * DIR_ENTRY_SIZE) % u32::from(bytes_per_sector) != 0
+    {
+        error!(
+            "Invalid root_dir_entries: {} entries do not fill whole sectors",
+            root_dir_entries
+        );
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
 fn format_bpb<E: IoError>(
     options: &FormatVolumeOptions,
     total_sectors: u32,
     bytes_per_sector: u16,
 ) -> Result<(BiosParameterBlock, FatType), Error<E>> {
-    let bytes_per_cluster = options.bytes_per_cluster.unwrap_or_else(|| {
-        let total_bytes = u64::from(total_sectors) * u64::from(bytes_per_sector);
-        determine_bytes_per_cluster(total_bytes, bytes_per_sector, options.fat_type)
-    });
-
-    let sectors_per_cluster = bytes_per_cluster / u32::from(bytes_per_sector);
-    assert!(sectors_per_cluster <= u32::from(u8::MAX));
-    let sectors_per_cluster = sectors_per_cluster as u8;
+    let sectors_per_cluster = if let Some(sectors_per_cluster) = options.sectors_per_cluster {
+        if !sectors_per_cluster.is_power_of_two() {
+            error!("Invalid sectors_per_cluster: {}", sectors_per_cluster);
+            return Err(Error::InvalidInput);
+        }
+        let bytes_per_cluster = u32::from(sectors_per_cluster) * u32::from(bytes_per_sector);
+        if options.bytes_per_cluster.is_some_and(|n| n != bytes_per_cluster) {
+            error!("sectors_per_cluster does not match bytes_per_cluster");
+            return Err(Error::InvalidInput);
+        }
+        sectors_per_cluster
+    } else {
+        let bytes_per_cluster = options.bytes_per_cluster.unwrap_or_else(|| {
+            let total_bytes = u64::from(total_sectors) * u64::from(bytes_per_sector);
+            determine_bytes_per_cluster(total_bytes, bytes_per_sector, options.fat_type)
+        });
+        let sectors_per_cluster = bytes_per_cluster / u32::from(bytes_per_sector);
+        assert!(sectors_per_cluster <= u32::from(u8::MAX));
+        sectors_per_cluster as u8
+    };
 
     let fats = options.fats.unwrap_or(2_u8);
-    let root_dir_entries = options.max_root_dir_entries.unwrap_or(512);
+    let root_dir_entries = options.root_dir_entries.unwrap_or(512);
     let (fat_type, reserved_sectors, sectors_per_fat) = determine_fs_geometry(
         total_sectors,
         bytes_per_sector,
         sectors_per_cluster,
         root_dir_entries,
         fats,
+        options.reserved_sectors,
+        options.fat_type,
     )?;
+    validate_root_dir_entries(options, root_dir_entries, bytes_per_sector, fat_type)?;
 
     // drive_num should be 0 for floppy disks and 0x80 for hard disks - determine it using FAT type
     let drive_num = options
@@ -771,7 +854,7 @@ pub(crate) fn format_boot_sector<E: IoError>(
     let mut boot = BootSector::default();
     let (bpb, fat_type) = format_bpb(options, total_sectors, bytes_per_sector)?;
     boot.bpb = bpb;
-    boot.oem_name.copy_from_slice(b"MSWIN4.1");
+    boot.oem_name = options.oem_name.unwrap_or(*b"MSWIN4.1");
     // Boot code copied from FAT32 boot sector initialized by mkfs.fat
     boot.bootjmp = [0xEB, 0x58, 0x90];
     let boot_code: [u8; 129] = [
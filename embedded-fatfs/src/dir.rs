@@ -1,5 +1,5 @@
-#[cfg(all(not(feature = "std"), feature = "alloc", feature = "lfn"))]
-use alloc::vec::Vec;
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{string::String, vec, vec::Vec};
 
 use core::char;
 use core::cmp;
@@ -9,12 +9,12 @@ use core::str;
 use core::{iter, slice};
 
 use crate::dir_entry::{
-    DirEntry, DirEntryData, DirFileEntryData, DirLfnEntryData, FileAttributes, ShortName, DIR_ENTRY_SIZE,
+    DirEntry, DirEntryData, DirFileEntryData, DirLfnEntryData, FileAttributes, RawDirEntry, ShortName, DIR_ENTRY_SIZE,
 };
 #[cfg(feature = "lfn")]
 use crate::dir_entry::{LFN_ENTRY_LAST_FLAG, LFN_PART_LEN};
 use crate::dir_entry::{SFN_PADDING, SFN_SIZE};
-use crate::error::{Error, IoError};
+use crate::error::{Error, IoError, ReadExactError};
 use crate::file::File;
 use crate::fs::{DiskSlice, FileSystem, FsIoAdapter, OemCpConverter, ReadWriteSeek};
 use crate::io::{self, IoBase, Read, Seek, SeekFrom, Write};
@@ -102,6 +102,45 @@ enum DirEntryOrShortName<'a, IO: ReadWriteSeek, TP, OCC> {
     ShortName([u8; SFN_SIZE]),
 }
 
+/// Metadata of an open directory.
+///
+/// Returned by `Dir::metadata`.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct DirMetadata {
+    attributes: FileAttributes,
+    first_cluster: Option<u32>,
+    is_root: bool,
+}
+
+impl DirMetadata {
+    /// Returns the attributes of the directory entry. The root directory has no entry and reports only
+    /// `FileAttributes::DIRECTORY`.
+    #[must_use]
+    pub fn attributes(&self) -> FileAttributes {
+        self.attributes
+    }
+
+    /// Returns the first cluster of the directory, `None` for the root directory of FAT12/16 filesystems.
+    #[must_use]
+    pub fn first_cluster(&self) -> Option<u32> {
+        self.first_cluster
+    }
+
+    /// Checks if this is the root directory.
+    #[must_use]
+    pub fn is_root(&self) -> bool {
+        self.is_root
+    }
+
+    /// Checks if this is the root directory stored in the fixed region between the FATs and the data area
+    /// (FAT12/16). The FAT32 root directory is a cluster chain like any other directory.
+    #[must_use]
+    pub fn is_fixed_root(&self) -> bool {
+        self.is_root && self.first_cluster.is_none()
+    }
+}
+
 /// A FAT filesystem directory.
 ///
 /// This struct is created by the `open_dir` or `create_dir` methods on `Dir`.
@@ -116,12 +155,101 @@ impl<'a, IO: ReadWriteSeek, TP, OCC> Dir<'a, IO, TP, OCC> {
         Dir { stream, fs }
     }
 
+    /// Sets the read-only, hidden, system and archive attributes of this directory.
+    ///
+    /// Other attributes are ignored and left unchanged. The directory entry is written immediately.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if this is the root directory, which has no directory entry.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn set_attributes(&mut self, attrs: FileAttributes) -> Result<(), Error<IO::Error>> {
+        self.fs.check_writable()?;
+        match self.stream {
+            DirRawStream::File(ref mut file) if file.has_dir_entry() => {
+                file.set_attributes(attrs);
+                file.flush_dir_entry().await?;
+                FsIoAdapter { fs: self.fs }.flush().await.map_err(Error::Io)
+            }
+            _ => {
+                error!("Root directory has no attributes");
+                Err(Error::InvalidInput)
+            }
+        }
+    }
+
+    /// Returns metadata of this directory: its attributes, first cluster and whether it is the root directory.
+    #[must_use]
+    pub fn metadata(&self) -> DirMetadata {
+        let attributes = match self.stream {
+            DirRawStream::File(ref file) => file.attributes(),
+            DirRawStream::Root(_) => None,
+        };
+        DirMetadata {
+            attributes: attributes.unwrap_or(FileAttributes::DIRECTORY),
+            first_cluster: self.stream.first_cluster(),
+            is_root: self.is_root_dir(),
+        }
+    }
+
+    fn is_root_dir(&self) -> bool {
+        match self.stream {
+            DirRawStream::File(ref file) => !file.has_dir_entry(),
+            DirRawStream::Root(_) => true,
+        }
+    }
+
     /// Creates directory entries iterator.
     #[must_use]
     #[allow(clippy::iter_not_returning_iterator)]
     pub fn iter(&self) -> DirIter<'a, IO, TP, OCC> {
         DirIter::new(self.stream.clone(), self.fs, true)
     }
+
+    /// Creates an iterator over all 32-byte slots of this directory.
+    ///
+    /// Unlike `iter` it also returns deleted entries, long file name parts and the volume label, see `RawDirEntry`.
+    /// The iteration stops at the end-of-directory marker.
+    #[must_use]
+    pub fn iter_raw(&self) -> RawDirIter<'a, IO, TP, OCC> {
+        RawDirIter {
+            stream: self.stream.clone(),
+            fs: self.fs,
+            err: false,
+        }
+    }
+
+    /// Creates an iterator over the raw bytes of all 32-byte slots of this directory.
+    ///
+    /// Slots are returned in on-disk order like in `iter_raw`, including deleted entries, long file name parts and
+    /// the volume label, and the iteration stops at the end-of-directory marker. Names are not decoded, so hashing
+    /// the returned bytes is a cheap way to detect changes of the directory layout.
+    #[must_use]
+    pub fn raw_bytes(&self) -> RawDirBytes<'a, IO, TP, OCC> {
+        RawDirBytes { inner: self.iter_raw() }
+    }
+
+    /// Creates an iterator recursively walking this directory and all of its subdirectories.
+    ///
+    /// Entries are returned in depth-first order together with their path relative to this directory. Each
+    /// directory is returned before its content. `.` and `..` entries are skipped.
+    ///
+    /// The walker holds one open directory iterator and its path for every level of nesting below this
+    /// directory, so memory usage grows with the depth of the directory tree, not with the number of entries.
+    ///
+    /// A subdirectory sharing its first cluster with one of the directories it is nested in (which only happens on
+    /// a corrupted volume) is returned as `Error::CorruptedFileSystem` and not descended into, so the walk always
+    /// terminates.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn walk(&self) -> Walk<'a, IO, TP, OCC> {
+        Walk {
+            stack: vec![(self.iter(), String::new(), self.stream.first_cluster())],
+        }
+    }
 }
 
 impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, TP, OCC> {
@@ -167,6 +295,32 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         Ok(None)
     }
 
+    // Updates the name of the volume label entry or creates it if it does not exist. A label consisting of padding
+    // only removes the entry.
+    pub(crate) async fn write_volume_entry(&self, label: [u8; SFN_SIZE]) -> Result<(), Error<IO::Error>> {
+        let is_empty = label.iter().all(|b| *b == SFN_PADDING);
+        if let Some(e) = self.find_volume_entry().await? {
+            let mut raw_entry = e.data.renamed(label);
+            if is_empty {
+                raw_entry.set_deleted();
+            } else {
+                raw_entry.set_modified(self.fs.options.time_provider.get_current_date_time());
+            }
+            // set the dirty flag upfront so the adapter does not move the disk position between writes
+            self.fs.set_dirty_flag(true).await?;
+            let mut disk = FsIoAdapter { fs: self.fs };
+            disk.seek(SeekFrom::Start(e.entry_pos)).await?;
+            raw_entry.serialize(&mut disk).await?;
+            disk.flush().await?;
+        } else if !is_empty {
+            let mut stream = self.find_free_entries(1).await?;
+            let raw_entry = self.create_sfn_entry(label, FileAttributes::VOLUME_ID, None);
+            raw_entry.serialize(&mut stream).await?;
+            stream.flush().await?;
+        }
+        Ok(())
+    }
+
     async fn check_for_existence(
         &self,
         name: &str,
@@ -194,22 +348,54 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         }
     }
 
+    /// Returns the total size in bytes of the files directly contained in this directory.
+    ///
+    /// Subdirectories are not descended into. The whole directory is read to compute the result.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn content_len(&self) -> Result<u64, Error<IO::Error>> {
+        let mut len = 0;
+        let mut iter = self.iter();
+        while let Some(r) = iter.next().await {
+            let e = r?;
+            if e.is_file() {
+                len += e.len();
+            }
+        }
+        Ok(len)
+    }
+
     /// Check to see if a file or directory with the given name exists
     pub async fn exists(&self, path: &str) -> Result<bool, Error<IO::Error>> {
-        self.exists_internal(path, None).await
+        Ok(self.exists_entry(path).await?.is_some())
     }
 
     /// Check to see if a file with the given name exists
     pub async fn file_exists(&self, path: &str) -> Result<bool, Error<IO::Error>> {
-        self.exists_internal(path, Some(false)).await
+        Ok(self.exists_entry(path).await?.is_some_and(|e| !e.is_dir()))
     }
 
     /// Check to see if a directory with the given name exists
     pub async fn dir_exists(&self, path: &str) -> Result<bool, Error<IO::Error>> {
-        self.exists_internal(path, Some(true)).await
+        Ok(self.exists_entry(path).await?.is_some_and(|e| e.is_dir()))
     }
 
-    async fn exists_internal(&self, mut path: &str, is_dir: Option<bool>) -> Result<bool, Error<IO::Error>> {
+    /// Looks up a file or directory and returns its entry if it exists.
+    ///
+    /// `path` is a '/' separated path relative to self directory. Unlike `open_meta` a missing entry is not an error,
+    /// so the existence check and the metadata (kind, size, timestamps) are obtained in a single lookup.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::NotFound` will be returned if a directory in `path` does not exist.
+    /// * `Error::InvalidInput` will be returned if a directory in `path` is a file.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn exists_entry(&self, mut path: &str) -> Result<Option<DirEntry<'a, IO, TP, OCC>>, Error<IO::Error>> {
+        trace!("Dir::exists_entry {}", path);
         let mut dir = self.clone();
         // traverse path
         while let (name, Some(rest)) = split_path(path) {
@@ -217,17 +403,10 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
             path = rest;
         }
         let (name, _rest) = split_path(path);
-
-        // check if name exists and matches the is_dir flag
         match dir.find_entry(name, None, None).await {
-            Ok(e) => match is_dir {
-                None => Ok(true),
-                Some(is_dir) => Ok(e.is_dir() == is_dir),
-            },
-            Err(e) => match e {
-                Error::NotFound => Ok(false),
-                _ => Err(e),
-            },
+            Ok(e) => Ok(Some(e)),
+            Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
         }
     }
 
@@ -294,7 +473,8 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
 
     /// Opens existing file.
     ///
-    /// `path` is a '/' separated file path relative to self directory.
+    /// `path` is a '/' separated file path relative to self directory. Every path component is matched against both
+    /// the long name and the short 8.3 alias of the entries (e.g. `NEW-FI~1.TXT`).
     ///
     /// # Errors
     ///
@@ -302,6 +482,7 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
     ///
     /// * `Error::NotFound` will be returned if `path` points to a non-existing directory entry.
     /// * `Error::InvalidInput` will be returned if `path` points to a file that is a directory.
+    /// * `Error::Busy` will be returned if the file is already open and `FsOptions::lock_open_files` is enabled.
     /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn open_file(&self, path: &str) -> Result<File<'a, IO, TP, OCC>, Error<IO::Error>> {
         trace!("Dir::open_file {}", path);
@@ -315,7 +496,7 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
                     e = e.find_entry(name, Some(true), None).await?.to_dir();
                 }
                 None => {
-                    return Ok(e.find_entry(name, Some(false), None).await?.to_file());
+                    return e.find_entry(name, Some(false), None).await?.to_file().lock();
                 }
             }
         }
@@ -333,10 +514,96 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
     /// * `Error::InvalidInput` will be returned if `path` points to an existing file that is a directory.
     /// * `Error::InvalidFileNameLength` will be returned if the file name is empty or if it is too long.
     /// * `Error::UnsupportedFileNameCharacter` will be returned if the file name contains an invalid character.
-    /// * `Error::NotEnoughSpace` will be returned if there is not enough free space to create a new file.
+    /// * `Error::NotEnoughSpace` will be returned if there is not enough free space to create a new file or the
+    ///   fixed-size root directory of a FAT12/FAT16 volume is full.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Busy` will be returned if the file is already open and `FsOptions::lock_open_files` is enabled.
     /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn create_file(&self, path: &str) -> Result<File<'a, IO, TP, OCC>, Error<IO::Error>> {
         trace!("Dir::create_file {}", path);
+        self.create_file_impl(path, false).await
+    }
+
+    /// Creates new or opens existing files, flushing the underlying storage only once.
+    ///
+    /// Each path in `names` is handled like in `Dir::create_file` and the file is closed right away. The underlying
+    /// storage is flushed and the modification time of a directory is written once after its files are created
+    /// instead of once per file. This makes creating many files in a directory considerably faster on devices with a
+    /// write cache (e.g. `BufStream`).
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * All errors of `Dir::create_file`. Creation stops at the first error, files created before it are kept
+    ///   and the underlying storage is flushed.
+    pub async fn create_files(&self, names: &[&str]) -> Result<(), Error<IO::Error>> {
+        trace!("Dir::create_files {}", names.len());
+        self.fs.check_writable()?;
+        // directory written to whose modification time has not been updated yet
+        let mut modified_dir = None;
+        let mut result = Ok(());
+        for name in names {
+            result = self.create_file_in_batch(name, &mut modified_dir).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        let touch_result = match modified_dir {
+            Some(dir) => dir.touch_modified().await,
+            None => Ok(()),
+        };
+        let flush_result = FsIoAdapter { fs: self.fs }.flush().await.map_err(Error::Io);
+        result?;
+        touch_result?;
+        flush_result
+    }
+
+    // creates a file like `create_file_impl` without flushing the storage - see `Dir::create_files`
+    async fn create_file_in_batch(&self, path: &str, modified_dir: &mut Option<Self>) -> Result<(), Error<IO::Error>> {
+        let (parent, name) = self.open_parent(path).await?;
+        // access the directory through a stream without its directory entry, so nothing is flushed and the
+        // modification time of the directory is updated once per run of files created in it instead of once per file
+        let detached = Self::new(parent.detached_stream(), self.fs);
+        match detached.check_for_existence(name, Some(false)).await? {
+            DirEntryOrShortName::ShortName(short_name) => {
+                let sfn_entry = parent.create_sfn_entry(short_name, FileAttributes::from_bits_truncate(0), None);
+                detached.write_entry_unflushed(name, sfn_entry).await?;
+                let same_dir = modified_dir
+                    .as_ref()
+                    .is_some_and(|dir| dir.stream.first_cluster() == parent.stream.first_cluster());
+                if !same_dir {
+                    if let Some(dir) = modified_dir.replace(parent) {
+                        dir.touch_modified().await?;
+                    }
+                }
+                Ok(())
+            }
+            // file already exists - it is only opened
+            DirEntryOrShortName::DirEntry(e) => e.to_file().lock().map(drop),
+        }
+    }
+
+    // updates the modification time of the directory entry like writing to the directory does
+    async fn touch_modified(&self) -> Result<(), Error<IO::Error>> {
+        if let DirRawStream::File(file) = &self.stream {
+            let mut file = file.clone();
+            file.touch_modified();
+            file.flush_dir_entry().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns a builder for opening the file at a path with custom options.
+    ///
+    /// By default the builder has all options disabled. See [`OpenOptions`].
+    #[must_use]
+    pub fn open_file_with_options(&self) -> OpenOptions<'_, 'a, IO, TP, OCC> {
+        OpenOptions::new(self)
+    }
+
+    async fn create_file_impl(&self, path: &str, create_new: bool) -> Result<File<'a, IO, TP, OCC>, Error<IO::Error>> {
+        self.fs.check_writable()?;
         let mut split = split_path(path);
         let mut e = self.clone();
         loop {
@@ -360,10 +627,11 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
             // file does not exist - create it
             DirEntryOrShortName::ShortName(short_name) => {
                 let sfn_entry = parent.create_sfn_entry(short_name, FileAttributes::from_bits_truncate(0), None);
-                Ok(parent.write_entry(name, sfn_entry).await?.to_file())
+                parent.write_entry(name, sfn_entry).await?.to_file().lock()
             }
+            DirEntryOrShortName::DirEntry(_) if create_new => Err(Error::AlreadyExists),
             // file already exists - return it
-            DirEntryOrShortName::DirEntry(e) => Ok(e.to_file()),
+            DirEntryOrShortName::DirEntry(e) => e.to_file().lock(),
         }
     }
 
@@ -378,10 +646,13 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
     /// * `Error::InvalidInput` will be returned if `path` points to an existing file that is not a directory.
     /// * `Error::InvalidFileNameLength` will be returned if the file name is empty or if it is too long.
     /// * `Error::UnsupportedFileNameCharacter` will be returned if the file name contains an invalid character.
-    /// * `Error::NotEnoughSpace` will be returned if there is not enough free space to create a new directory.
+    /// * `Error::NotEnoughSpace` will be returned if there is not enough free space to create a new directory or the
+    ///   fixed-size root directory of a FAT12/FAT16 volume is full.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
     /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn create_dir(&self, path: &str) -> Result<Self, Error<IO::Error>> {
         trace!("Dir::create_dir {}", path);
+        self.fs.check_writable()?;
         let mut split = split_path(path);
         let mut e = self.clone();
         loop {
@@ -407,7 +678,14 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
                 let cluster = self.fs.alloc_cluster(None, true).await?;
                 // create entry in parent directory
                 let sfn_entry = e.create_sfn_entry(short_name, FileAttributes::DIRECTORY, Some(cluster));
-                let entry = e.write_entry(name, sfn_entry).await?;
+                let entry = match e.write_entry(name, sfn_entry).await {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        // do not leak the directory cluster if the parent has no room for the entry
+                        self.fs.free_cluster_chain(cluster).await?;
+                        return Err(err);
+                    }
+                };
                 let dir = entry.to_dir();
                 // create special entries "." and ".."
                 let dot_sfn = ShortNameGenerator::generate_dot();
@@ -423,19 +701,101 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         }
     }
 
+    /// Recursively creates a directory and all of its missing parent directories.
+    ///
+    /// `path` is a '/' separated path relative to self directory. Existing directories are reused, so calling this
+    /// method for a path that already exists simply opens it.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if any component of `path` points to an existing file that is not a
+    ///   directory.
+    /// * `Error::NotFound` will be returned if a `.` or `..` component does not exist (e.g. in the root directory).
+    /// * `Error::InvalidFileNameLength` will be returned if a file name is empty or if it is too long.
+    /// * `Error::UnsupportedFileNameCharacter` will be returned if a file name contains an invalid character.
+    /// * `Error::NotEnoughSpace` will be returned if there is not enough free space to create a new directory or the
+    ///   fixed-size root directory of a FAT12/FAT16 volume is full.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn create_dir_all(&self, path: &str) -> Result<Self, Error<IO::Error>> {
+        trace!("Dir::create_dir_all {}", path);
+        let mut split = split_path(path);
+        let mut e = self.clone();
+        loop {
+            let (name, rest_opt) = split;
+            e = if name == "." || name == ".." {
+                // special entries are never created - follow them like `open_dir` does
+                e.find_entry(name, Some(true), None).await?.to_dir()
+            } else {
+                e.create_dir(name).await?
+            };
+            match rest_opt {
+                Some(rest) => split = split_path(rest),
+                None => break,
+            }
+        }
+
+        Ok(e)
+    }
+
+    /// Checks if the directory contains no entries other than the special `.` and `..` entries.
+    ///
+    /// Raw directory entries are scanned without building `DirEntry` objects and the scan stops at the first entry
+    /// that is found, so only the beginning of a non-empty directory is read.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn is_empty(&self) -> Result<bool, Error<IO::Error>> {
         trace!("Dir::is_empty");
-        // check if directory contains no files
-        let mut iter = self.iter();
-        while let Some(r) = iter.next().await {
-            let e = r?;
-            let name = e.short_file_name_as_bytes();
-            // ignore special entries "." and ".."
-            if name != b"." && name != b".." {
-                return Ok(false);
+        Ok(self.count_entries(1).await? == 0)
+    }
+
+    /// Returns the number of entries in the directory.
+    ///
+    /// Special `.` and `..` entries, deleted entries and the volume label are not counted.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn entry_count(&self) -> Result<usize, Error<IO::Error>> {
+        trace!("Dir::entry_count");
+        self.count_entries(usize::MAX).await
+    }
+
+    // returns a stream that does not update the directory entry when it is read or written
+    fn detached_stream(&self) -> DirRawStream<'a, IO, TP, OCC> {
+        match &self.stream {
+            DirRawStream::File(file) => DirRawStream::File(File::new(file.first_cluster(), None, self.fs)),
+            DirRawStream::Root(raw) => DirRawStream::Root(raw.clone()),
+        }
+    }
+
+    async fn count_entries(&self, limit: usize) -> Result<usize, Error<IO::Error>> {
+        // counting does not update the access date
+        let mut stream = self.detached_stream();
+        stream.seek(SeekFrom::Start(0)).await?;
+        let mut count = 0;
+        while count < limit {
+            let raw_entry = DirEntryData::deserialize(&mut stream).await?;
+            if raw_entry.is_end() {
+                break;
+            }
+            // long name entries are followed by the short name entry they belong to
+            if let DirEntryData::File(data) = raw_entry {
+                if data.is_deleted() || data.is_volume() {
+                    continue;
+                }
+                // ignore special entries "." and ".."
+                let name = data.name();
+                if *name != ShortNameGenerator::generate_dot() && *name != ShortNameGenerator::generate_dotdot() {
+                    count += 1;
+                }
             }
         }
-        Ok(true)
+        Ok(count)
     }
 
     /// Removes existing file or directory.
@@ -451,9 +811,12 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
     /// * `Error::NotFound` will be returned if `path` points to a non-existing directory entry.
     /// * `Error::InvalidInput` will be returned if `path` points to a file that is not a directory.
     /// * `Error::DirectoryIsNotEmpty` will be returned if the specified directory is not empty.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Busy` will be returned if the file is open and `FsOptions::lock_open_files` is enabled.
     /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn remove(&self, path: &str) -> Result<(), Error<IO::Error>> {
         trace!("Dir::remove {}", path);
+        self.fs.check_writable()?;
 
         // traverse path
         let mut split = split_path(path);
@@ -480,12 +843,18 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         if e.is_dir() && !e.to_dir().is_empty().await? {
             return Err(Error::DirectoryIsNotEmpty);
         }
+        parent.remove_entry(&e).await
+    }
+
+    async fn remove_entry(&self, e: &DirEntry<'a, IO, TP, OCC>) -> Result<(), Error<IO::Error>> {
+        #[cfg(feature = "alloc")]
+        self.fs.check_file_closed(e.entry_pos)?;
         // free data
         if let Some(n) = e.first_cluster() {
             self.fs.free_cluster_chain(n).await?;
         }
         // free long and short name entries
-        let mut stream = parent.stream.clone();
+        let mut stream = self.stream.clone();
         stream.seek(SeekFrom::Start(e.offset_range.0)).await?;
         let num = ((e.offset_range.1 - e.offset_range.0) / u64::from(DIR_ENTRY_SIZE)) as usize;
         for _ in 0..num {
@@ -500,11 +869,72 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         Ok(())
     }
 
+    /// Recursively removes a directory together with all files and subdirectories it contains.
+    ///
+    /// `path` is a '/' separated directory path relative to self directory.
+    /// Make sure there is no reference to any file inside of the removed tree (no File instance) or filesystem
+    /// corruption can happen.
+    ///
+    /// The tree is removed depth-first. The first error stops the operation and is returned, leaving entries that
+    /// were not removed yet in place. A subdirectory sharing its first cluster with one of the directories it is
+    /// nested in (which only happens on a corrupted volume) stops the operation with `Error::CorruptedFileSystem`.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::NotFound` will be returned if `path` does not point to any existing directory entry.
+    /// * `Error::InvalidInput` will be returned if `path` points to a file that is not a directory.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Busy` will be returned if a file inside of the tree is open and `FsOptions::lock_open_files` is
+    ///   enabled.
+    /// * `Error::CorruptedFileSystem` will be returned if a subdirectory links back to a directory it is nested in.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    #[cfg(feature = "alloc")]
+    pub async fn remove_dir_all(&self, path: &str) -> Result<(), Error<IO::Error>> {
+        trace!("Dir::remove_dir_all {}", path);
+        self.fs.check_writable()?;
+        let dir = self.open_dir(path).await?;
+        // directories being emptied together with their iterators, first clusters and entries in the parent directory
+        let mut stack = vec![(dir.iter(), dir.stream.first_cluster(), dir, None)];
+        while let Some((iter, _, parent, _)) = stack.last_mut() {
+            let Some(r) = iter.next().await else {
+                // the directory is empty now - remove its entry from the parent directory
+                if let Some((_, _, _, Some(e))) = stack.pop() {
+                    if let Some((_, _, parent, _)) = stack.last() {
+                        parent.remove_entry(&e).await?;
+                    }
+                }
+                continue;
+            };
+            let e = r?;
+            let name = e.short_file_name_as_bytes();
+            // ignore special entries "." and ".."
+            if name == b"." || name == b".." {
+                continue;
+            }
+            if e.is_dir() {
+                let cluster = e.first_cluster();
+                if stack.iter().any(|(_, c, _, _)| *c == cluster) {
+                    error!("Directory links back to a parent directory");
+                    return Err(Error::CorruptedFileSystem);
+                }
+                let sub_dir = e.to_dir();
+                stack.push((sub_dir.iter(), cluster, sub_dir, Some(e)));
+            } else {
+                parent.remove_entry(&e).await?;
+            }
+        }
+        self.remove(path).await
+    }
+
     /// Renames or moves existing file or directory.
     ///
     /// `src_path` is a '/' separated source file path relative to self directory.
     /// `dst_path` is a '/' separated destination file path relative to `dst_dir`.
     /// `dst_dir` can be set to self directory if rename operation without moving is needed.
+    /// Only the directory entry is rewritten: the first cluster, size, attributes and timestamps of the file or
+    /// directory are preserved. See `rename_in_place` for renaming without moving.
     /// Make sure there is no reference to this file (no File instance) or filesystem corruption
     /// can happen.
     ///
@@ -515,6 +945,8 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
     /// * `Error::NotFound` will be returned if `src_path` points to a non-existing directory entry or if `dst_path`
     ///   stripped from the last component does not point to an existing directory.
     /// * `Error::AlreadyExists` will be returned if `dst_path` points to an existing directory entry.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Busy` will be returned if the file is open and `FsOptions::lock_open_files` is enabled.
     /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn rename(
         &self,
@@ -523,6 +955,7 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         dst_path: &str,
     ) -> Result<(), Error<IO::Error>> {
         trace!("Dir::rename {} {}", src_path, dst_path);
+        self.fs.check_writable()?;
         // traverse source path
         let mut split_src = split_path(src_path);
         let mut e_src = self.clone();
@@ -558,6 +991,220 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         e_src.rename_internal(split_src.0, &dst_dir, split_dst.0).await
     }
 
+    /// Renames existing file or directory without moving it to another directory.
+    ///
+    /// `src_path` is a '/' separated file path relative to self directory and `new_name` is the new name of its last
+    /// component. Only the long name and the short name alias change: the first cluster, size, attributes and
+    /// timestamps are preserved and the contents are not touched. If the new name needs as many directory entries as
+    /// the old one the entries are overwritten in place, otherwise they are moved to free entries of the same
+    /// directory, which may have to grow by a cluster.
+    /// Make sure there is no reference to this file (no File instance) or filesystem corruption
+    /// can happen.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::NotFound` will be returned if `src_path` points to a non-existing directory entry.
+    /// * `Error::AlreadyExists` will be returned if an entry named `new_name` already exists in the directory.
+    /// * `Error::InvalidFileNameLength` will be returned if `new_name` is empty or too long.
+    /// * `Error::UnsupportedFileNameCharacter` will be returned if `new_name` contains an invalid character.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Busy` will be returned if the file is open and `FsOptions::lock_open_files` is enabled.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn rename_in_place(&self, src_path: &str, new_name: &str) -> Result<(), Error<IO::Error>> {
+        trace!("Dir::rename_in_place {} {}", src_path, new_name);
+        self.fs.check_writable()?;
+        let (dir, src_name) = self.open_parent(src_path).await?;
+        dir.rename_internal(src_name, &dir, new_name).await
+    }
+
+    /// Moves existing file or directory to a new path.
+    ///
+    /// Both `src_path` and `dst_path` are '/' separated paths relative to self directory. Unlike `rename` the
+    /// destination parent directory is opened by this method. When a directory is moved to a different parent its
+    /// `..` entry is updated to point to the new parent.
+    /// Make sure there is no reference to this file (no File instance) or filesystem corruption
+    /// can happen.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::NotFound` will be returned if `src_path` points to a non-existing directory entry or if `dst_path`
+    ///   stripped from the last component does not point to an existing directory.
+    /// * `Error::AlreadyExists` will be returned if `dst_path` points to an existing directory entry.
+    /// * `Error::InvalidInput` will be returned if `src_path` points to a directory and `dst_path` is inside of it.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Busy` will be returned if the file is open and `FsOptions::lock_open_files` is enabled.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn move_path(&self, src_path: &str, dst_path: &str) -> Result<(), Error<IO::Error>> {
+        trace!("Dir::move_path {} {}", src_path, dst_path);
+        self.fs.check_writable()?;
+        let (src_dir, src_name) = self.open_parent(src_path).await?;
+        let (dst_dir, dst_name) = self.open_parent(dst_path).await?;
+        let e = src_dir.find_entry(src_name, None, None).await?;
+
+        let new_parent_cluster = if dst_dir.is_root_dir() {
+            None
+        } else {
+            dst_dir.stream.first_cluster()
+        };
+        let moves_dir = e.is_dir() && src_dir.stream.first_cluster() != dst_dir.stream.first_cluster();
+        if moves_dir {
+            // walk up from the destination to the root - the moved directory must not be one of the ancestors
+            let mut ancestor = dst_dir.clone();
+            loop {
+                if ancestor.stream.first_cluster() == e.first_cluster() {
+                    error!("Cannot move a directory into itself");
+                    return Err(Error::InvalidInput);
+                }
+                if ancestor.is_root_dir() {
+                    break;
+                }
+                ancestor = ancestor.find_entry("..", Some(true), None).await?.to_dir();
+            }
+        }
+
+        src_dir.rename_internal(src_name, &dst_dir, dst_name).await?;
+
+        if moves_dir {
+            let dotdot = e.to_dir().find_entry("..", Some(true), None).await?;
+            let mut editor = dotdot.editor();
+            editor.set_first_cluster(new_parent_cluster, self.fs.fat_type());
+            editor.flush(self.fs).await?;
+            FsIoAdapter { fs: self.fs }.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Copies existing file to a new file.
+    ///
+    /// `src_path` is a '/' separated source file path relative to self directory.
+    /// `dst_name` is the name of the new file created in `dst_dir`.
+    /// The cluster chain of the copy is allocated up front and the data is copied cluster by cluster from the source
+    /// chain, so no buffer for the whole file is needed. The copy gets the attributes and the modification time of
+    /// the source file.
+    /// If copying fails the partially written destination file is removed.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::NotFound` will be returned if `src_path` points to a non-existing directory entry.
+    /// * `Error::InvalidInput` will be returned if `src_path` points to a directory.
+    /// * `Error::AlreadyExists` will be returned if `dst_name` points to an existing directory entry.
+    /// * `Error::InvalidFileNameLength` will be returned if `dst_name` is empty or if it is too long.
+    /// * `Error::UnsupportedFileNameCharacter` will be returned if `dst_name` contains an invalid character.
+    /// * `Error::NotEnoughSpace` will be returned if there is not enough free space to copy the file.
+    /// * `Error::CorruptedFileSystem` will be returned if the cluster chain of the source is shorter than its size.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn copy_file(
+        &self,
+        src_path: &str,
+        dst_dir: &Dir<'_, IO, TP, OCC>,
+        dst_name: &str,
+    ) -> Result<(), Error<IO::Error>> {
+        trace!("Dir::copy_file {} {}", src_path, dst_name);
+        self.fs.check_writable()?;
+        let (src_dir, src_name) = self.open_parent(src_path).await?;
+        let src_entry = src_dir.find_entry(src_name, Some(false), None).await?;
+        let short_name = match dst_dir.check_for_existence(dst_name, None).await? {
+            DirEntryOrShortName::DirEntry(_) => return Err(Error::AlreadyExists),
+            DirEntryOrShortName::ShortName(short_name) => short_name,
+        };
+        let sfn_entry = dst_dir.create_sfn_entry(short_name, src_entry.attributes(), None);
+        let dst_entry = dst_dir.write_entry(dst_name, sfn_entry).await?;
+
+        let len = src_entry.len();
+        let mut dst = dst_entry.to_file();
+        let result = async {
+            dst.allocate_contiguous(len).await?;
+            self.copy_clusters(src_entry.first_cluster(), dst.first_cluster(), len)
+                .await
+        }
+        .await;
+        if let Err(err) = result {
+            error!("Copying file failed, removing the copy");
+            // the error that stopped the copy is returned even if removing the copy fails too
+            let cleanup = async {
+                // free the partially allocated cluster chain before removing the entry
+                dst.truncate().await?;
+                dst.flush().await?;
+                dst_dir.remove_entry(&dst_entry).await
+            }
+            .await;
+            if cleanup.is_err() {
+                error!("Removing the partial copy failed");
+            }
+            return Err(err);
+        }
+        // safe cast: the size of the source fits in a directory entry
+        #[allow(clippy::cast_possible_truncation)]
+        dst.set_size(len as u32);
+        dst.set_modified(src_entry.modified());
+        dst.flush().await
+    }
+
+    // copies the first `len` bytes of the chain starting at `src_cluster` to the chain starting at `dst_cluster`
+    async fn copy_clusters(
+        &self,
+        src_cluster: Option<u32>,
+        dst_cluster: Option<u32>,
+        len: u64,
+    ) -> Result<(), Error<IO::Error>> {
+        if len == 0 {
+            return Ok(());
+        }
+        let (Some(mut src_cluster), Some(mut dst_cluster)) = (src_cluster, dst_cluster) else {
+            error!("File has no cluster chain");
+            return Err(Error::CorruptedFileSystem);
+        };
+        let cluster_size = u64::from(self.fs.cluster_size());
+        let mut src_iter = self.fs.cluster_iter(src_cluster);
+        let mut dst_iter = self.fs.cluster_iter(dst_cluster);
+        let mut disk = FsIoAdapter { fs: self.fs };
+        let mut buf = [0_u8; 512];
+        let mut remaining = len;
+        loop {
+            let src_offset = self.fs.offset_from_cluster(src_cluster);
+            let dst_offset = self.fs.offset_from_cluster(dst_cluster);
+            let cluster_len = cmp::min(cluster_size, remaining);
+            let mut offset = 0;
+            while offset < cluster_len {
+                // safe cast: the length is limited by the buffer size
+                #[allow(clippy::cast_possible_truncation)]
+                let n = cmp::min(buf.len() as u64, cluster_len - offset) as usize;
+                disk.seek(SeekFrom::Start(src_offset + offset)).await?;
+                disk.read_exact(&mut buf[..n]).await?;
+                disk.seek(SeekFrom::Start(dst_offset + offset)).await?;
+                disk.write_all(&buf[..n]).await?;
+                offset += n as u64;
+            }
+            remaining -= cluster_len;
+            if remaining == 0 {
+                return Ok(());
+            }
+            let (Some(src_next), Some(dst_next)) = (src_iter.next().await, dst_iter.next().await) else {
+                error!("Cluster chain is shorter than the file size");
+                return Err(Error::CorruptedFileSystem);
+            };
+            src_cluster = src_next?;
+            dst_cluster = dst_next?;
+        }
+    }
+
+    async fn open_parent<'p>(&self, path: &'p str) -> Result<(Self, &'p str), Error<IO::Error>> {
+        let mut split = split_path(path);
+        let mut e = self.clone();
+        while let (name, Some(rest)) = split {
+            split = split_path(rest);
+            e = e.find_entry(name, Some(true), None).await?.to_dir();
+        }
+        Ok((e, split.0))
+    }
+
     async fn rename_internal(
         &self,
         src_name: &str,
@@ -565,8 +1212,12 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         dst_name: &str,
     ) -> Result<(), Error<IO::Error>> {
         trace!("Dir::rename_internal {} {}", src_name, dst_name);
+        // check the new name before anything is modified
+        validate_long_name(dst_name)?;
         // find existing file
         let e = self.find_entry(src_name, None, None).await?;
+        #[cfg(feature = "alloc")]
+        self.fs.check_file_closed(e.entry_pos)?;
         // check if destionation filename is unused
         let r = dst_dir.check_for_existence(dst_name, None).await?;
         let short_name = match r {
@@ -583,6 +1234,24 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
             // destionation file does not exist, short name has been generated
             DirEntryOrShortName::ShortName(short_name) => short_name,
         };
+        let sfn_entry = e.data.renamed(short_name);
+        if self.stream.first_cluster() == dst_dir.stream.first_cluster() {
+            // renaming inside of a directory: overwrite the old entries if the new name needs as many of them
+            let lfn_utf16 = Self::encode_lfn_utf16(dst_name);
+            let lfn_iter = LfnEntriesGenerator::new(lfn_utf16.as_ucs2_units(), lfn_checksum(&short_name));
+            let num = (e.offset_range.1 - e.offset_range.0) / u64::from(DIR_ENTRY_SIZE);
+            if lfn_iter.len() as u64 + 1 == num {
+                trace!("renaming in place");
+                let mut stream = self.stream.clone();
+                stream.seek(SeekFrom::Start(e.offset_range.0)).await?;
+                for lfn_entry in lfn_iter {
+                    lfn_entry.serialize(&mut stream).await?;
+                }
+                sfn_entry.serialize(&mut stream).await?;
+                stream.flush().await?;
+                return Ok(());
+            }
+        }
         // free long and short name entries
         let mut stream = self.stream.clone();
         stream.seek(SeekFrom::Start(e.offset_range.0)).await?;
@@ -595,7 +1264,6 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
             data.serialize(&mut stream).await?;
         }
         // save new directory entry
-        let sfn_entry = e.data.renamed(short_name);
         dst_dir.write_entry(dst_name, sfn_entry).await?;
 
         // rename requires stream flush (no async drop :()
@@ -615,6 +1283,13 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
                 if num_free == 0 {
                     first_free = i;
                 }
+                if let DirRawStream::Root(_) = stream {
+                    // root directory of FAT12/FAT16 has a fixed size and cannot grow
+                    if first_free + num_entries > self.fs.root_dir_entries() {
+                        error!("Root directory is full");
+                        return Err(Error::NotEnoughSpace);
+                    }
+                }
                 let pos = u64::from(first_free * DIR_ENTRY_SIZE);
                 stream.seek(io::SeekFrom::Start(pos)).await?;
                 return Ok(stream);
@@ -688,6 +1363,17 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         name: &str,
         raw_entry: DirFileEntryData,
     ) -> Result<DirEntry<'a, IO, TP, OCC>, Error<IO::Error>> {
+        let (entry, mut stream) = self.write_entry_unflushed(name, raw_entry).await?;
+        // explicit flush call because async drop doesn't exist
+        stream.flush().await?;
+        Ok(entry)
+    }
+
+    async fn write_entry_unflushed(
+        &self,
+        name: &str,
+        raw_entry: DirFileEntryData,
+    ) -> Result<(DirEntry<'a, IO, TP, OCC>, DirRawStream<'a, IO, TP, OCC>), Error<IO::Error>> {
         trace!("Dir::write_entry {}", name);
         // check if name doesn't contain unsupported characters
         validate_long_name(name)?;
@@ -709,10 +1395,7 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
         let start_abs_pos = end_abs_pos - u64::from(DIR_ENTRY_SIZE);
         // return new logical entry descriptor
         let short_name = ShortName::new(raw_entry.name());
-
-        // explicit flush call because async drop doesn't exist
-        stream.flush().await?;
-        Ok(DirEntry {
+        let entry = DirEntry {
             data: raw_entry,
             short_name,
             #[cfg(feature = "lfn")]
@@ -720,7 +1403,8 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Dir<'a, IO, T
             fs: self.fs,
             entry_pos: start_abs_pos,
             offset_range: (start_pos, end_pos),
-        })
+        };
+        Ok((entry, stream))
     }
 }
 
@@ -734,6 +1418,138 @@ impl<IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Clone for Dir<'_,
     }
 }
 
+/// Options and flags which can be used to configure how a file is opened.
+///
+/// This builder is created by the `open_file_with_options` method on `Dir`. All options are initially disabled.
+/// It mirrors `std::fs::OpenOptions`.
+#[allow(clippy::struct_excessive_bools)]
+pub struct OpenOptions<'d, 'a, IO: ReadWriteSeek, TP, OCC> {
+    dir: &'d Dir<'a, IO, TP, OCC>,
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl<'d, 'a, IO: ReadWriteSeek, TP, OCC> OpenOptions<'d, 'a, IO, TP, OCC> {
+    fn new(dir: &'d Dir<'a, IO, TP, OCC>) -> Self {
+        Self {
+            dir,
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+        }
+    }
+
+    /// Sets the option for read access.
+    ///
+    /// Reading from a file opened without read access returns `Error::InvalidInput`.
+    #[must_use]
+    pub fn read(mut self, read: bool) -> Self {
+        self.read = read;
+        self
+    }
+
+    /// Sets the option for write access.
+    ///
+    /// Writing to a file opened without write access returns `Error::ReadOnly`.
+    #[must_use]
+    pub fn write(mut self, write: bool) -> Self {
+        self.write = write;
+        self
+    }
+
+    /// Sets the option for append mode.
+    ///
+    /// Before each write the position is moved to the end of the file, no matter where it was moved by a previous
+    /// seek. This applies to `File::write_at` too. Implies write access.
+    #[must_use]
+    pub fn append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Sets the option for truncating an existing file to zero length when opening it.
+    ///
+    /// Requires write access.
+    #[must_use]
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Sets the option to create a new file if it does not exist, or open it if it does.
+    ///
+    /// Requires write or append access.
+    #[must_use]
+    pub fn create(mut self, create: bool) -> Self {
+        self.create = create;
+        self
+    }
+
+    /// Sets the option to create a new file, failing if it already exists.
+    ///
+    /// If set, `create` and `truncate` are ignored. Requires write or append access.
+    #[must_use]
+    pub fn create_new(mut self, create_new: bool) -> Self {
+        self.create_new = create_new;
+        self
+    }
+}
+
+impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> OpenOptions<'_, 'a, IO, TP, OCC> {
+    /// Opens the file at `path` with the options specified by `self`.
+    ///
+    /// `path` is a '/' separated file path relative to the directory this builder was created from.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if the combination of options is invalid (e.g. neither read, write
+    ///   nor append access was requested, or `truncate`, `create` or `create_new` was requested without write
+    ///   access) or if `path` points to a directory.
+    /// * `Error::NotFound` will be returned if `path` points to a non-existing file and neither `create` nor
+    ///   `create_new` was requested.
+    /// * `Error::AlreadyExists` will be returned if `create_new` was requested and the file already exists.
+    /// * `Error::InvalidFileNameLength` will be returned if the file name is empty or if it is too long.
+    /// * `Error::UnsupportedFileNameCharacter` will be returned if the file name contains an invalid character.
+    /// * `Error::NotEnoughSpace` will be returned if there is not enough free space to create a new file.
+    /// * `Error::ReadOnly` will be returned if write access was requested and the filesystem is mounted read-only.
+    /// * `Error::Busy` will be returned if the file is already open and `FsOptions::lock_open_files` is enabled.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn open(&self, path: &str) -> Result<File<'a, IO, TP, OCC>, Error<IO::Error>> {
+        trace!("OpenOptions::open {}", path);
+        let writable = self.write || self.append;
+        if !self.read && !writable {
+            return Err(Error::InvalidInput);
+        }
+        if !writable && (self.truncate || self.create || self.create_new) {
+            return Err(Error::InvalidInput);
+        }
+        if writable {
+            self.dir.fs.check_writable()?;
+        }
+        let mut file = if self.create_new {
+            self.dir.create_file_impl(path, true).await?
+        } else if self.create {
+            self.dir.create_file_impl(path, false).await?
+        } else {
+            self.dir.open_file(path).await?
+        };
+        file.set_access(self.read, writable, self.append);
+        if self.truncate && !self.create_new {
+            file.truncate().await?;
+        }
+        Ok(file)
+    }
+}
+
 /// An iterator over the directory entries.
 ///
 /// This struct is created by the `iter` method on `Dir`.
@@ -774,8 +1590,12 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC> DirIter<'a, IO, TP, OCC> {
         let mut begin_offset = offset;
         loop {
             let raw_entry = DirEntryData::deserialize(&mut self.stream).await?;
-            // access time has changed
-            self.stream.flush().await?;
+            // access time has changed - streams without a directory entry have no access time to update
+            if let DirRawStream::File(file) = &mut self.stream {
+                if file.has_dir_entry() {
+                    file.flush().await?;
+                }
+            }
             offset += u64::from(DIR_ENTRY_SIZE);
             // Check if this is end of dir
             if raw_entry.is_end() {
@@ -847,6 +1667,82 @@ impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC> DirIter<'a, IO, TP, OCC> {
     }
 }
 
+/// An iterator over all 32-byte slots of a directory.
+///
+/// This struct is created by the `iter_raw` method on `Dir`.
+pub struct RawDirIter<'a, IO: ReadWriteSeek, TP, OCC> {
+    stream: DirRawStream<'a, IO, TP, OCC>,
+    fs: &'a FileSystem<IO, TP, OCC>,
+    err: bool,
+}
+
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> RawDirIter<'_, IO, TP, OCC> {
+    async fn read_raw_entry(&mut self) -> Result<Option<RawDirEntry>, Error<IO::Error>> {
+        let mut data = [0; DIR_ENTRY_SIZE as usize];
+        match self.stream.read_exact(&mut data).await {
+            // entries can occupy all clusters of directory so there is no zero entry at the end
+            Err(ReadExactError::UnexpectedEof) => return Ok(None),
+            Err(ReadExactError::Other(err)) => return Err(err),
+            Ok(()) => {}
+        }
+        if data[0] == 0 {
+            return Ok(None);
+        }
+        // Unwrapping is safe because abs_pos() returns None only if stream is at position 0
+        let entry_pos = self.stream.abs_pos().unwrap() - u64::from(DIR_ENTRY_SIZE);
+        Ok(Some(RawDirEntry {
+            data,
+            fat_type: self.fs.fat_type(),
+            entry_pos,
+        }))
+    }
+
+    pub async fn next(&mut self) -> Option<Result<RawDirEntry, Error<IO::Error>>> {
+        if self.err {
+            return None;
+        }
+        match self.read_raw_entry().await {
+            Ok(Some(e)) => Some(Ok(e)),
+            Ok(None) => None,
+            Err(err) => {
+                self.err = true;
+                Some(Err(err))
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub async fn collect(&mut self) -> Vec<Result<RawDirEntry, Error<IO::Error>>> {
+        let mut v = Vec::new();
+        while let Some(i) = self.next().await {
+            v.push(i);
+        }
+        v
+    }
+}
+
+/// An iterator over the raw bytes of all 32-byte slots of a directory.
+///
+/// This struct is created by the `raw_bytes` method on `Dir`.
+pub struct RawDirBytes<'a, IO: ReadWriteSeek, TP, OCC> {
+    inner: RawDirIter<'a, IO, TP, OCC>,
+}
+
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> RawDirBytes<'_, IO, TP, OCC> {
+    pub async fn next(&mut self) -> Option<Result<[u8; DIR_ENTRY_SIZE as usize], Error<IO::Error>>> {
+        Some(self.inner.next().await?.map(|e| e.data))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub async fn collect(&mut self) -> Vec<Result<[u8; DIR_ENTRY_SIZE as usize], Error<IO::Error>>> {
+        let mut v = Vec::new();
+        while let Some(i) = self.next().await {
+            v.push(i);
+        }
+        v
+    }
+}
+
 // Note: derive cannot be used because of invalid bounds. See: https://github.com/rust-lang/rust/issues/26925
 impl<IO: ReadWriteSeek, TP, OCC> Clone for DirIter<'_, IO, TP, OCC> {
     fn clone(&self) -> Self {
@@ -859,6 +1755,64 @@ impl<IO: ReadWriteSeek, TP, OCC> Clone for DirIter<'_, IO, TP, OCC> {
     }
 }
 
+/// An iterator recursively walking a directory tree.
+///
+/// This struct is created by the `walk` method on `Dir`.
+#[cfg(feature = "alloc")]
+pub struct Walk<'a, IO: ReadWriteSeek, TP, OCC> {
+    // iterators of the directories being walked together with their paths and first clusters
+    #[allow(clippy::type_complexity)]
+    stack: Vec<(DirIter<'a, IO, TP, OCC>, String, Option<u32>)>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> Walk<'a, IO, TP, OCC> {
+    #[allow(clippy::type_complexity)]
+    pub async fn next(&mut self) -> Option<Result<(String, DirEntry<'a, IO, TP, OCC>), Error<IO::Error>>> {
+        loop {
+            let (iter, dir_path, _) = self.stack.last_mut()?;
+            let entry = match iter.next().await {
+                Some(Ok(e)) => e,
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let name = entry.file_name();
+            if name == "." || name == ".." {
+                continue;
+            }
+            let path = if dir_path.is_empty() {
+                name
+            } else {
+                let mut path = dir_path.clone();
+                path.push('/');
+                path.push_str(&name);
+                path
+            };
+            if entry.is_dir() {
+                let cluster = entry.first_cluster();
+                if self.stack.iter().any(|(_, _, c)| *c == cluster) {
+                    error!("Directory {} links back to a parent directory", path.as_str());
+                    return Some(Err(Error::CorruptedFileSystem));
+                }
+                self.stack.push((entry.to_dir().iter(), path.clone(), cluster));
+            }
+            return Some(Ok((path, entry)));
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub async fn collect(&mut self) -> Vec<Result<(String, DirEntry<'a, IO, TP, OCC>), Error<IO::Error>>> {
+        let mut v = Vec::new();
+        while let Some(i) = self.next().await {
+            v.push(i);
+        }
+        v
+    }
+}
+
 #[rustfmt::skip]
 fn validate_long_name<E: IoError>(name: &str) -> Result<(), Error<E>> {
     // check if length is valid
@@ -1,5 +1,6 @@
 use bitflags::bitflags;
 use core::char;
+use core::cmp;
 use core::fmt;
 #[cfg(not(feature = "unicode"))]
 use core::iter;
@@ -127,6 +128,11 @@ impl ShortName {
         let uppercase_char_iter = char_iter.flat_map(char_to_uppercase);
         uppercase_char_iter.eq(name.chars().flat_map(char_to_uppercase))
     }
+
+    fn eq_exact<OCC: OemCpConverter>(&self, name: &str, oem_cp_converter: &OCC) -> bool {
+        let byte_iter = self.as_bytes().iter().copied();
+        byte_iter.map(|c| oem_cp_converter.decode(c)).eq(name.chars())
+    }
 }
 
 #[allow(dead_code)]
@@ -166,7 +172,6 @@ impl DirFileEntryData {
         &self.name
     }
 
-    #[cfg(feature = "alloc")]
     fn lowercase_name(&self) -> ShortName {
         let mut name_copy: [u8; SFN_SIZE] = self.name;
         if self.lowercase_basename() {
@@ -212,6 +217,10 @@ impl DirFileEntryData {
         self.size = size;
     }
 
+    pub(crate) fn attributes(&self) -> FileAttributes {
+        self.attrs
+    }
+
     pub(crate) fn is_dir(&self) -> bool {
         self.attrs.contains(FileAttributes::DIRECTORY)
     }
@@ -269,7 +278,6 @@ impl DirFileEntryData {
         wrt.write_u16_le(self.modify_date).await?;
         wrt.write_u16_le(self.first_cluster_lo).await?;
         wrt.write_u32_le(self.size).await?;
-        wrt.flush().await?;
         Ok(())
     }
 
@@ -342,7 +350,6 @@ impl DirLfnEntryData {
         for ch in &self.name_2 {
             wrt.write_u16_le(*ch).await?;
         }
-        wrt.flush().await?;
         Ok(())
     }
 
@@ -469,6 +476,8 @@ pub(crate) struct DirEntryEditor {
     data: DirFileEntryData,
     pos: u64,
     dirty: bool,
+    // size or first cluster changed - the entry is needed to read back the file data
+    layout_dirty: bool,
 }
 
 impl DirEntryEditor {
@@ -477,6 +486,7 @@ impl DirEntryEditor {
             data,
             pos,
             dirty: false,
+            layout_dirty: false,
         }
     }
 
@@ -484,6 +494,11 @@ impl DirEntryEditor {
         &self.data
     }
 
+    #[cfg(feature = "alloc")]
+    pub(crate) fn pos(&self) -> u64 {
+        self.pos
+    }
+
     pub(crate) fn dirty(&self) -> bool {
         self.dirty
     }
@@ -492,6 +507,7 @@ impl DirEntryEditor {
         if first_cluster != self.data.first_cluster(fat_type) {
             self.data.set_first_cluster(first_cluster, fat_type);
             self.dirty = true;
+            self.layout_dirty = true;
         }
     }
 
@@ -500,11 +516,24 @@ impl DirEntryEditor {
             Some(n) if size != n => {
                 self.data.set_size(size);
                 self.dirty = true;
+                self.layout_dirty = true;
             }
             _ => {}
         }
     }
 
+    pub(crate) fn set_attributes(&mut self, attrs: FileAttributes) {
+        // directory and volume label bits describe the entry type and cannot be changed
+        let fixed = FileAttributes::DIRECTORY | FileAttributes::VOLUME_ID;
+        let settable =
+            FileAttributes::READ_ONLY | FileAttributes::HIDDEN | FileAttributes::SYSTEM | FileAttributes::ARCHIVE;
+        let new_attrs = (self.data.attrs & fixed) | (attrs & settable);
+        if new_attrs != self.data.attrs {
+            self.data.attrs = new_attrs;
+            self.dirty = true;
+        }
+    }
+
     pub(crate) fn set_created(&mut self, date_time: DateTime) {
         if date_time != self.data.created() {
             self.data.set_created(date_time);
@@ -533,6 +562,17 @@ impl DirEntryEditor {
         if self.dirty {
             self.write(fs).await?;
             self.dirty = false;
+            self.layout_dirty = false;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn flush_layout<IO: ReadWriteSeek, TP, OCC>(
+        &mut self,
+        fs: &FileSystem<IO, TP, OCC>,
+    ) -> Result<(), IO::Error> {
+        if self.layout_dirty {
+            self.flush(fs).await?;
         }
         Ok(())
     }
@@ -544,6 +584,82 @@ impl DirEntryEditor {
     }
 }
 
+/// A raw 32-byte directory entry slot.
+///
+/// `RawDirEntry` is returned by `RawDirIter` for every slot of a directory, including deleted entries, long file
+/// name parts and the volume label, which `DirIter` hides. It is meant for recovery tools: the short name, size and
+/// first cluster of a deleted entry are kept until the slot is reused, only the first byte of the name is replaced by
+/// the deletion marker.
+#[derive(Clone, Debug)]
+pub struct RawDirEntry {
+    pub(crate) data: [u8; DIR_ENTRY_SIZE as usize],
+    pub(crate) fat_type: FatType,
+    pub(crate) entry_pos: u64,
+}
+
+impl RawDirEntry {
+    /// Returns the raw content of the slot.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; DIR_ENTRY_SIZE as usize] {
+        &self.data
+    }
+
+    /// Returns the absolute position of the slot on the storage in bytes.
+    #[must_use]
+    pub fn entry_pos(&self) -> u64 {
+        self.entry_pos
+    }
+
+    /// Checks if the slot belongs to a deleted entry.
+    #[must_use]
+    pub fn is_deleted(&self) -> bool {
+        self.data[0] == DIR_ENTRY_DELETED_FLAG
+    }
+
+    /// Checks if the slot holds a part of a long file name.
+    #[must_use]
+    pub fn is_lfn(&self) -> bool {
+        self.attributes() & FileAttributes::LFN == FileAttributes::LFN
+    }
+
+    /// Returns the attributes byte of the slot.
+    #[must_use]
+    pub fn attributes(&self) -> FileAttributes {
+        FileAttributes::from_bits_truncate(self.data[11])
+    }
+
+    /// Returns the 8.3 short name as stored in the slot, without the separating dot.
+    ///
+    /// The first byte is `0xE5` for deleted entries. The result is meaningless for long file name slots.
+    #[must_use]
+    pub fn short_name_as_bytes(&self) -> [u8; SFN_SIZE] {
+        let mut name = [0; SFN_SIZE];
+        name.copy_from_slice(&self.data[..SFN_SIZE]);
+        name
+    }
+
+    /// Returns the first cluster of a short name slot, `None` for long file name slots and empty files.
+    #[must_use]
+    pub fn first_cluster(&self) -> Option<u32> {
+        if self.is_lfn() {
+            return None;
+        }
+        let hi = if self.fat_type == FatType::Fat32 {
+            u32::from(u16::from_le_bytes([self.data[20], self.data[21]]))
+        } else {
+            0
+        };
+        let n = (hi << 16) | u32::from(u16::from_le_bytes([self.data[26], self.data[27]]));
+        (n != 0).then_some(n)
+    }
+
+    /// Returns the file size stored in a short name slot.
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        u32::from_le_bytes([self.data[28], self.data[29], self.data[30], self.data[31]])
+    }
+}
+
 /// A FAT directory entry.
 ///
 /// `DirEntry` is returned by `DirIter` when reading a directory.
@@ -562,6 +678,9 @@ pub struct DirEntry<'a, IO: ReadWriteSeek, TP, OCC> {
 impl<'a, IO: ReadWriteSeek, TP, OCC: OemCpConverter> DirEntry<'a, IO, TP, OCC> {
     /// Returns short file name.
     ///
+    /// Every entry has a short name, including entries created with a long name. It can be used to open the entry
+    /// instead of the long name.
+    ///
     /// Non-ASCII characters are replaced by the replacement character (U+FFFD).
     #[cfg(feature = "alloc")]
     #[must_use]
@@ -623,11 +742,37 @@ impl<'a, IO: ReadWriteSeek, TP, OCC: OemCpConverter> DirEntry<'a, IO, TP, OCC> {
         self.data.is_file()
     }
 
-    pub(crate) fn first_cluster(&self) -> Option<u32> {
+    /// Checks if the read-only attribute is set.
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.data.attrs.contains(FileAttributes::READ_ONLY)
+    }
+
+    /// Checks if the hidden attribute is set.
+    #[must_use]
+    pub fn is_hidden(&self) -> bool {
+        self.data.attrs.contains(FileAttributes::HIDDEN)
+    }
+
+    /// Checks if the system attribute is set.
+    #[must_use]
+    pub fn is_system(&self) -> bool {
+        self.data.attrs.contains(FileAttributes::SYSTEM)
+    }
+
+    /// Checks if the archive attribute is set.
+    #[must_use]
+    pub fn is_archive(&self) -> bool {
+        self.data.attrs.contains(FileAttributes::ARCHIVE)
+    }
+
+    /// Returns the first cluster of the file or directory, `None` for an empty file.
+    #[must_use]
+    pub fn first_cluster(&self) -> Option<u32> {
         self.data.first_cluster(self.fs.fat_type())
     }
 
-    fn editor(&self) -> DirEntryEditor {
+    pub(crate) fn editor(&self) -> DirEntryEditor {
         DirEntryEditor::new(self.data.clone(), self.entry_pos)
     }
 
@@ -699,15 +844,46 @@ impl<'a, IO: ReadWriteSeek, TP, OCC: OemCpConverter> DirEntry<'a, IO, TP, OCC> {
         u64::from(self.data.size)
     }
 
+    /// Returns the number of bytes occupied on disk, including the slack at the end of the last cluster.
+    ///
+    /// For files the size is rounded up to whole clusters. An empty file that still owns a cluster occupies one
+    /// cluster. Directories do not store their size, so their cluster chain is followed instead. The fixed root
+    /// directory of FAT12 and FAT16 volumes is not stored in clusters and reports 0.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error while reading the cluster
+    /// chain of a directory.
+    pub async fn len_on_disk(&self) -> Result<u64, Error<IO::Error>> {
+        let Some(first_cluster) = self.first_cluster() else {
+            return Ok(0);
+        };
+        let clusters = if self.is_dir() {
+            let mut clusters = 1;
+            let mut iter = self.fs.cluster_iter(first_cluster);
+            while let Some(r) = iter.next().await {
+                r?;
+                clusters += 1;
+            }
+            clusters
+        } else {
+            cmp::max(self.fs.clusters_from_bytes(self.len()), 1)
+        };
+        Ok(self.fs.bytes_from_clusters(clusters))
+    }
+
     /// Returns file creation date and time.
     ///
     /// Resolution of the time field is 1/100s.
+    /// Creation time is optional in FAT - if it is not set 1980-01-01 00:00:00 is returned.
     #[must_use]
     pub fn created(&self) -> DateTime {
         self.data.created()
     }
 
     /// Returns file last access date.
+    ///
+    /// Access date is optional in FAT - if it is not set 1980-01-01 is returned.
     #[must_use]
     pub fn accessed(&self) -> Date {
         self.data.accessed()
@@ -729,6 +905,10 @@ impl<'a, IO: ReadWriteSeek, TP, OCC: OemCpConverter> DirEntry<'a, IO, TP, OCC> {
     fn eq_name_lfn(&self, name: &str) -> bool {
         if let Some(lfn) = self.long_file_name_as_ucs2_units() {
             let self_decode_iter = char::decode_utf16(lfn.iter().copied());
+            if self.fs.options.case_sensitive {
+                // decoding errors never match
+                return self_decode_iter.map(Result::ok).eq(name.chars().map(Some));
+            }
             let mut other_uppercase_iter = name.chars().flat_map(char_to_uppercase);
             for decode_result in self_decode_iter {
                 if let Ok(self_char) = decode_result {
@@ -759,7 +939,13 @@ impl<'a, IO: ReadWriteSeek, TP, OCC: OemCpConverter> DirEntry<'a, IO, TP, OCC> {
             }
         }
 
-        self.short_name.eq_ignore_case(name, &self.fs.options.oem_cp_converter)
+        let oem_cp_converter = &self.fs.options.oem_cp_converter;
+        if self.fs.options.case_sensitive {
+            self.short_name.eq_exact(name, oem_cp_converter)
+                || self.data.lowercase_name().eq_exact(name, oem_cp_converter)
+        } else {
+            self.short_name.eq_ignore_case(name, oem_cp_converter)
+        }
     }
 }
 
@@ -772,7 +958,14 @@ impl<IO: ReadWriteSeek, TP, OCC> fmt::Debug for DirEntry<'_, IO, TP, OCC> {
 #[cfg(feature = "defmt")]
 impl<IO: ReadWriteSeek, TP, OCC> defmt::Format for DirEntry<'_, IO, TP, OCC> {
     fn format(&self, fmt: defmt::Formatter) {
-        defmt::write!(fmt, "{}", self.data);
+        // formats the short name only - the long name would require decoding UTF-16 into a buffer
+        defmt::write!(
+            fmt,
+            "DirEntry {{ name: {=[u8]:a}, size: {}, attrs: {=u8:#04x} }}",
+            self.short_name.as_bytes(),
+            self.data.size(),
+            self.data.attrs.bits()
+        );
     }
 }
 
@@ -793,6 +986,14 @@ mod tests {
         assert!(ShortName::new(b"\x99OOK AT M \x99").eq_ignore_case("\u{FFFD}OOK AT.M \u{FFFD}", &oem_cp_conv));
     }
 
+    #[test]
+    fn short_name_cp1252() {
+        let oem_cp_conv = crate::oem::Cp1252OemCpConverter::new();
+        let short_name = ShortName::new(b"CAF\xE9    TXT");
+        assert_eq!(short_name.to_string(&oem_cp_conv), "CAF\u{E9}.TXT");
+        assert_eq!(oem_cp_conv.encode('\u{E9}'), Some(0xE9));
+    }
+
     #[test]
     fn short_name_without_ext() {
         let oem_cp_conv = LossyOemCpConverter::new();
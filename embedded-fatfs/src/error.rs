@@ -30,11 +30,99 @@ pub enum Error<T> {
     InvalidFileNameLength,
     /// The provided file name contains an invalid character.
     UnsupportedFileNameCharacter,
+    /// A mutating operation was attempted on a filesystem mounted read-only or on a file opened without write access.
+    ///
+    /// Errors reported by a write-protected storage device are still returned as `Error::Io` so the original error
+    /// is preserved.
+    ReadOnly,
+    /// The volume contains a filesystem other than FAT that is not supported by this crate.
+    UnsupportedFileSystem(FileSystemKind),
+    /// The file is already open through another handle and `FsOptions::lock_open_files` is enabled.
+    Busy,
+}
+
+impl<T> Error<T> {
+    /// Converts the error returned by the storage with `f`, keeping all other variants unchanged.
+    ///
+    /// It can be used to remove layers from the error of stacked storage adapters, e.g. with
+    /// `StreamSliceError::flatten` from the `block-device-adapters` crate, or to convert it to an application error
+    /// type.
+    pub fn map_io<U>(self, f: impl FnOnce(T) -> U) -> Error<U> {
+        match self {
+            Error::Io(io_error) => Error::Io(f(io_error)),
+            Error::UnexpectedEof => Error::UnexpectedEof,
+            Error::WriteZero => Error::WriteZero,
+            Error::InvalidInput => Error::InvalidInput,
+            Error::NotFound => Error::NotFound,
+            Error::AlreadyExists => Error::AlreadyExists,
+            Error::DirectoryIsNotEmpty => Error::DirectoryIsNotEmpty,
+            Error::CorruptedFileSystem => Error::CorruptedFileSystem,
+            Error::NotEnoughSpace => Error::NotEnoughSpace,
+            Error::InvalidFileNameLength => Error::InvalidFileNameLength,
+            Error::UnsupportedFileNameCharacter => Error::UnsupportedFileNameCharacter,
+            Error::ReadOnly => Error::ReadOnly,
+            Error::UnsupportedFileSystem(kind) => Error::UnsupportedFileSystem(kind),
+            Error::Busy => Error::Busy,
+        }
+    }
+}
+
+/// Error returned by `FileSystem::export_image`.
+///
+/// Generic parameters `S` and `D` are the error types of the storage of the filesystem and of the destination writer.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug)]
+pub enum ExportError<S, D> {
+    /// Reading or flushing the filesystem failed.
+    Source(Error<S>),
+    /// The destination writer returned an error.
+    Sink(D),
+}
+
+impl<S, D> From<Error<S>> for ExportError<S, D> {
+    fn from(error: Error<S>) -> Self {
+        ExportError::Source(error)
+    }
+}
+
+impl<S: core::fmt::Display, D: core::fmt::Display> core::fmt::Display for ExportError<S, D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExportError::Source(error) => write!(f, "Source error: {}", error),
+            ExportError::Sink(error) => write!(f, "Sink error: {}", error),
+        }
+    }
+}
+
+/// A filesystem recognized in a boot sector but not supported by this crate.
+///
+/// Returned in `Error::UnsupportedFileSystem`.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum FileSystemKind {
+    /// exFAT, a successor of FAT32 commonly used on SDXC cards.
+    ExFat,
+    /// NTFS.
+    Ntfs,
+}
+
+impl core::fmt::Display for FileSystemKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FileSystemKind::ExFat => write!(f, "exFAT"),
+            FileSystemKind::Ntfs => write!(f, "NTFS"),
+        }
+    }
 }
 
 impl<T: Debug> IoError for Error<T> {
     fn kind(&self) -> ErrorKind {
-        ErrorKind::Other
+        match self {
+            Error::ReadOnly => ErrorKind::PermissionDenied,
+            Error::UnsupportedFileSystem(_) => ErrorKind::Unsupported,
+            _ => ErrorKind::Other,
+        }
     }
 }
 
@@ -76,10 +164,32 @@ impl<T: core::fmt::Display> core::fmt::Display for Error<T> {
             Error::NotFound => write!(f, "No such file or directory"),
             Error::AlreadyExists => write!(f, "File or directory already exists"),
             Error::CorruptedFileSystem => write!(f, "Corrupted file system"),
+            Error::ReadOnly => write!(f, "Read-only file system"),
+            Error::UnsupportedFileSystem(kind) => write!(f, "Unsupported file system: {}", kind),
+            Error::Busy => write!(f, "File is already open"),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl<T: std::error::Error + Send + Sync + 'static> From<Error<T>> for std::io::Error {
+    fn from(error: Error<T>) -> Self {
+        let kind = match error {
+            Error::NotFound => std::io::ErrorKind::NotFound,
+            Error::AlreadyExists => std::io::ErrorKind::AlreadyExists,
+            Error::InvalidInput | Error::InvalidFileNameLength | Error::UnsupportedFileNameCharacter => {
+                std::io::ErrorKind::InvalidInput
+            }
+            Error::UnexpectedEof => std::io::ErrorKind::UnexpectedEof,
+            Error::WriteZero => std::io::ErrorKind::WriteZero,
+            Error::ReadOnly => std::io::ErrorKind::PermissionDenied,
+            Error::UnsupportedFileSystem(_) => std::io::ErrorKind::Unsupported,
+            _ => std::io::ErrorKind::Other,
+        };
+        Self::new(kind, error)
+    }
+}
+
 #[cfg(feature = "std")]
 impl<T: std::error::Error + 'static> std::error::Error for Error<T> {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
@@ -90,3 +200,13 @@ impl<T: std::error::Error + 'static> std::error::Error for Error<T> {
         }
     }
 }
+
+#[cfg(feature = "std")]
+impl<S: std::error::Error + 'static, D: std::error::Error + 'static> std::error::Error for ExportError<S, D> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExportError::Source(error) => Some(error),
+            ExportError::Sink(error) => Some(error),
+        }
+    }
+}
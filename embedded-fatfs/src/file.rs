@@ -1,9 +1,11 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
 use core::cmp;
 
-use crate::dir_entry::DirEntryEditor;
+use crate::dir_entry::{DirEntryEditor, FileAttributes};
 use crate::error::Error;
 use crate::fs::{FileSystem, ReadWriteSeek};
-use crate::io::{IoBase, Read, Seek, SeekFrom, Write};
+use crate::io::{BufRead, IoBase, Read, Seek, SeekFrom, Write};
 use crate::time::{Date, DateTime, TimeProvider};
 
 const MAX_FILE_SIZE: u32 = core::u32::MAX;
@@ -11,10 +13,29 @@ const MAX_FILE_SIZE: u32 = core::u32::MAX;
 /// A FAT filesystem file object used for reading and writing data.
 ///
 /// This struct is created by the `open_file` or `create_file` methods on `Dir`.
+///
+/// Seeking beyond the end of the file is allowed. Reads at such a position return no data and the next write
+/// zero-fills the gap between the old end of the file and the position before writing the data.
+#[allow(clippy::struct_excessive_bools)]
 pub struct File<'a, IO: ReadWriteSeek, TP, OCC> {
     context: FileContext,
+    // position requested by a seek beyond the end of the file - the gap is zero-filled by the next write
+    seek_past_end: Option<u32>,
+    // access mode the file was opened with - see `OpenOptions`
+    readable: bool,
+    writable: bool,
+    append: bool,
+    // timestamps set explicitly by the user - they take precedence over the `TimeProvider`
+    modified: Option<DateTime>,
+    accessed: Option<Date>,
     // file-system reference
     fs: &'a FileSystem<IO, TP, OCC>,
+    // the file is registered as open - see `FsOptions::lock_open_files`
+    #[cfg(feature = "alloc")]
+    locked: bool,
+    // cluster runs resolved when the forward-only mode was enabled - see `File::set_forward_only`
+    #[cfg(feature = "alloc")]
+    forward_runs: Option<Vec<(u32, u32)>>,
 }
 
 /// A context of an existing [`File`].
@@ -48,6 +69,96 @@ pub struct Extent {
     pub size: u32,
 }
 
+/// An iterator over contiguous runs of clusters occupied by a file.
+///
+/// This struct is created by the `cluster_chain` method on `File`. Each item is a tuple of the first cluster of the
+/// run and the number of consecutive clusters in it.
+pub struct ClusterChain<'a, IO: ReadWriteSeek, TP, OCC> {
+    fs: &'a FileSystem<IO, TP, OCC>,
+    // first cluster of the next run
+    next_run: Option<u32>,
+}
+
+impl<IO: ReadWriteSeek, TP, OCC> ClusterChain<'_, IO, TP, OCC> {
+    /// Returns the next run of contiguous clusters as a `(first cluster, cluster count)` tuple.
+    pub async fn next(&mut self) -> Option<Result<(u32, u32), Error<IO::Error>>> {
+        let start = self.next_run?;
+        let mut last = start;
+        let mut count = 1;
+        loop {
+            match self.fs.cluster_iter(last).next().await {
+                Some(Ok(n)) if n == last + 1 => {
+                    last = n;
+                    count += 1;
+                }
+                Some(Ok(n)) => {
+                    self.next_run = Some(n);
+                    break;
+                }
+                Some(Err(err)) => {
+                    self.next_run = None;
+                    return Some(Err(err));
+                }
+                None => {
+                    self.next_run = None;
+                    break;
+                }
+            }
+        }
+        Some(Ok((start, count)))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub async fn collect(&mut self) -> Vec<Result<(u32, u32), Error<IO::Error>>> {
+        let mut v = Vec::new();
+        while let Some(i) = self.next().await {
+            v.push(i);
+        }
+        v
+    }
+}
+
+/// An iterator over the sector ranges occupied by the data of a file.
+///
+/// This struct is created by the `extents` method on `File`. Each item is a tuple of the first sector of the range,
+/// relative to the start of the volume, and the number of sectors in it.
+pub struct Extents<'a, IO: ReadWriteSeek, TP, OCC> {
+    chain: ClusterChain<'a, IO, TP, OCC>,
+    // file data not covered by the returned ranges yet
+    bytes_left: u64,
+}
+
+impl<IO: ReadWriteSeek, TP, OCC> Extents<'_, IO, TP, OCC> {
+    /// Returns the next range of contiguous sectors as a `(first sector, sector count)` tuple.
+    pub async fn next(&mut self) -> Option<Result<(u64, u32), Error<IO::Error>>> {
+        if self.bytes_left == 0 {
+            return None;
+        }
+        let (cluster, count) = match self.chain.next().await? {
+            Ok(run) => run,
+            Err(err) => {
+                self.bytes_left = 0;
+                return Some(Err(err));
+            }
+        };
+        let fs = self.chain.fs;
+        let bytes = cmp::min(fs.bytes_from_clusters(count), self.bytes_left);
+        self.bytes_left -= bytes;
+        let sectors = bytes.div_ceil(u64::from(fs.sector_size()));
+        // a run never exceeds the volume so its sector count fits in u32
+        Some(Ok((u64::from(fs.sector_from_cluster(cluster)), sectors as u32)))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub async fn collect(&mut self) -> Vec<Result<(u64, u32), Error<IO::Error>>> {
+        let mut v = Vec::new();
+        while let Some(i) = self.next().await {
+            v.push(i);
+        }
+        v
+    }
+}
+
 impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
     pub(crate) fn new(
         first_cluster: Option<u32>,
@@ -61,7 +172,17 @@ impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
                 current_cluster: None, // cluster before first one
                 offset: 0,
             },
+            seek_past_end: None,
+            readable: true,
+            writable: true,
+            append: false,
+            modified: None,
+            accessed: None,
             fs,
+            #[cfg(feature = "alloc")]
+            locked: false,
+            #[cfg(feature = "alloc")]
+            forward_runs: None,
         }
     }
 
@@ -75,20 +196,71 @@ impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
     /// Prefer using [`DirEntry::try_to_file_with_context`](crate::dir_entry::DirEntry::try_to_file_with_context) where possible because
     /// it does some basic checks to avoid file corruption.
     pub(crate) fn new_from_context(context: FileContext, fs: &'a FileSystem<IO, TP, OCC>) -> Self {
-        File { context, fs }
+        File {
+            context,
+            seek_past_end: None,
+            readable: true,
+            writable: true,
+            append: false,
+            modified: None,
+            accessed: None,
+            fs,
+            #[cfg(feature = "alloc")]
+            locked: false,
+            #[cfg(feature = "alloc")]
+            forward_runs: None,
+        }
+    }
+
+    /// Registers the file as open if `FsOptions::lock_open_files` is enabled.
+    ///
+    /// Returns `Error::Busy` if the file is already open.
+    pub(crate) fn lock(self) -> Result<Self, Error<IO::Error>> {
+        #[cfg(feature = "alloc")]
+        {
+            let mut file = self;
+            if let (true, Some(e)) = (file.fs.options.lock_open_files, &file.context.entry) {
+                file.fs.lock_file(e.pos())?;
+                file.locked = true;
+            }
+            Ok(file)
+        }
+        #[cfg(not(feature = "alloc"))]
+        Ok(self)
+    }
+
+    pub(crate) fn set_access(&mut self, readable: bool, writable: bool, append: bool) {
+        self.readable = readable;
+        self.writable = writable;
+        self.append = append;
+    }
+
+    fn check_writable(&self) -> Result<(), Error<IO::Error>> {
+        self.fs.check_writable()?;
+        if self.writable && self.forward_runs().is_none() {
+            Ok(())
+        } else {
+            Err(Error::ReadOnly)
+        }
     }
 
     /// Truncate file in current position.
     ///
+    /// See `truncate_to` for truncating the file to an absolute length independent of the current position.
+    ///
     /// # Errors
     ///
-    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    /// Errors that can be returned:
+    ///
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
     ///
     /// # Panics
     ///
     /// Will panic if this is the root directory.
     pub async fn truncate(&mut self) -> Result<(), Error<IO::Error>> {
         trace!("File::truncate");
+        self.check_writable()?;
         if let Some(ref mut e) = self.context.entry {
             e.set_size(self.context.offset);
             if self.context.offset == 0 {
@@ -112,41 +284,136 @@ impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
         }
     }
 
-    /// Get the extents of a file on disk.
-    ///
-    /// This returns an iterator over the byte ranges on-disk occupied by
-    /// this file.
-    // pub fn extents(&mut self) -> impl Iterator<Item = Result<Extent, Error<IO::Error>>> + 'a {
-    // let fs = self.fs;
-    // let cluster_size = fs.cluster_size();
-    // let mut bytes_left = match self.size() {
-    //     Some(s) => s,
-    //     None => return None.into_iter().flatten(),
-    // };
-    // let first = match self.context.first_cluster {
-    //     Some(f) => f,
-    //     None => return None.into_iter().flatten(),
-    // };
-
-    // Some(
-    //     core::iter::once(Ok(first))
-    //         .chain(fs.cluster_iter(first))
-    //         .map(move |cluster_err| match cluster_err {
-    //             Ok(cluster) => {
-    //                 let size = cluster_size.min(bytes_left);
-    //                 bytes_left -= size;
-    //                 Ok(Extent {
-    //                     offset: fs.offset_from_cluster(cluster),
-    //                     size,
-    //                 })
-    //             }
-    //             Err(e) => Err(e),
-    //         }),
-    // )
-    // .into_iter()
-    // .flatten()
-    // todo!("extents needs to be implemented using AsyncIterator");
-    // }
+    /// Returns an iterator over the sector ranges occupied by the data of this file.
+    ///
+    /// Each item is a `(first sector, sector count)` tuple. Sectors are numbered from the start of the volume (LBA
+    /// relative to the volume) in units of the volume sector size, so a partition start offset has to be added to
+    /// get absolute device addresses. The ranges cover the file data only: clusters past the end of the file are
+    /// skipped and the last range is shortened to the sectors holding data. This allows transferring the file data
+    /// directly from the storage device, e.g. with DMA.
+    #[must_use]
+    pub fn extents(&self) -> Extents<'a, IO, TP, OCC> {
+        Extents {
+            chain: self.cluster_chain(),
+            bytes_left: self.size().map_or(u64::MAX, u64::from),
+        }
+    }
+
+    /// Returns an iterator over the physical cluster chain of this file.
+    ///
+    /// Consecutive clusters are coalesced into `(first cluster, cluster count)` runs. Note that the chain may
+    /// contain clusters past the end of the file data if the file was preallocated.
+    #[must_use]
+    pub fn cluster_chain(&self) -> ClusterChain<'a, IO, TP, OCC> {
+        ClusterChain {
+            fs: self.fs,
+            next_run: self.context.first_cluster,
+        }
+    }
+
+    /// Returns the number of contiguous runs of clusters the file data is split into.
+    ///
+    /// An empty file has no fragments. Only the FAT is read, see `cluster_chain`.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn fragment_count(&self) -> Result<u32, Error<IO::Error>> {
+        let mut chain = self.cluster_chain();
+        let mut count = 0;
+        while let Some(r) = chain.next().await {
+            r?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Checks if all clusters of the file are stored one after another on the disk.
+    ///
+    /// The data of a contiguous file can be read with a single transfer starting at the first cluster. Empty files
+    /// are contiguous. Unlike `fragment_count` this method stops at the first gap in the cluster chain.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn is_contiguous(&self) -> Result<bool, Error<IO::Error>> {
+        let mut chain = self.cluster_chain();
+        if let Some(r) = chain.next().await {
+            r?;
+        }
+        // the first run ends either at the end of the chain or at a gap
+        Ok(chain.next_run.is_none())
+    }
+
+    /// Returns the current position in the file.
+    ///
+    /// Unlike `Seek::stream_position` this does not access the storage. A position set by a seek beyond the end of
+    /// the file is returned as is.
+    #[must_use]
+    pub fn position(&self) -> u64 {
+        u64::from(self.seek_past_end.unwrap_or(self.context.offset))
+    }
+
+    /// Moves the position by `delta` bytes relative to the current position, clamped to the start and the end of the
+    /// file.
+    ///
+    /// Unlike `Seek::seek` with `SeekFrom::Current` this never fails if the new position would be before the start
+    /// or beyond the end of the file: it saturates at `0` and at the file size respectively. Returns the new
+    /// position.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn seek_relative(&mut self, delta: i64) -> Result<u64, Error<IO::Error>> {
+        let len = i64::from(self.size().unwrap_or(MAX_FILE_SIZE));
+        let pos = i64::try_from(self.position()).unwrap_or(i64::MAX);
+        // the clamped position is not negative
+        let new_pos = pos.saturating_add(delta).clamp(0, len).unsigned_abs();
+        self.seek(SeekFrom::Start(new_pos)).await
+    }
+
+    /// Enables or disables the forward-only read mode.
+    ///
+    /// The mode allows reading the file sequentially from storage that cannot seek backward cheaply, e.g. a network
+    /// block source or a pipe. The cluster chain of the file is read from the FAT when the mode is enabled, so later
+    /// reads never look up the FAT and only move the storage position forward, provided the clusters of the file
+    /// are stored in ascending order (which is always the case for non-fragmented files).
+    ///
+    /// While the mode is enabled:
+    ///
+    /// * seeking backward returns `Error::InvalidInput`, seeking forward is allowed,
+    /// * the file cannot be modified - writes and other modifying operations return `Error::ReadOnly`,
+    /// * flushing the file writes its directory entry if it was modified (e.g. the accessed date), which requires
+    ///   seeking back to the directory.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    #[cfg(feature = "alloc")]
+    pub async fn set_forward_only(&mut self, forward_only: bool) -> Result<(), Error<IO::Error>> {
+        if !forward_only {
+            self.forward_runs = None;
+            return Ok(());
+        }
+        let mut runs = Vec::new();
+        let mut chain = self.cluster_chain();
+        while let Some(r) = chain.next().await {
+            runs.push(r?);
+        }
+        self.forward_runs = Some(runs);
+        Ok(())
+    }
+
+    #[cfg(feature = "alloc")]
+    fn forward_runs(&self) -> Option<&[(u32, u32)]> {
+        self.forward_runs.as_deref()
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[allow(clippy::unused_self)]
+    fn forward_runs(&self) -> Option<&[(u32, u32)]> {
+        None
+    }
 
     pub(crate) fn abs_pos(&self) -> Option<u64> {
         // Returns current position relative to filesystem start
@@ -169,44 +436,61 @@ impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
         }
     }
 
-    async fn flush_dir_entry(&mut self) -> Result<(), Error<IO::Error>> {
+    pub(crate) async fn flush_dir_entry(&mut self) -> Result<(), Error<IO::Error>> {
         if let Some(ref mut e) = self.context.entry {
+            if e.dirty() {
+                self.fs.check_writable()?;
+            }
             e.flush(self.fs).await?;
         }
         Ok(())
     }
 
+    /// Sets the read-only, hidden, system and archive attributes of this file.
+    ///
+    /// Other attributes are ignored and left unchanged. The directory entry is updated on the next flush.
+    pub fn set_attributes(&mut self, attrs: FileAttributes) {
+        if let Some(ref mut e) = self.context.entry {
+            e.set_attributes(attrs);
+        }
+    }
+
     /// Sets date and time of creation for this file.
     ///
-    /// Note: it is set to a value from the `TimeProvider` when creating a file.
-    /// Deprecated: if needed implement a custom `TimeProvider`.
-    #[deprecated]
+    /// Note: it is set to a value from the `TimeProvider` when creating a file. The directory entry is updated on
+    /// the next flush. A value out of the range supported by FAT (e.g. before 1980) is clamped to the closest
+    /// supported value.
     pub fn set_created(&mut self, date_time: DateTime) {
         if let Some(ref mut e) = self.context.entry {
-            e.set_created(date_time);
+            e.set_created(date_time.clamped());
         }
     }
 
     /// Sets date of last access for this file.
     ///
-    /// Note: it is overwritten by a value from the `TimeProvider` on every file read operation.
-    /// Deprecated: if needed implement a custom `TimeProvider`.
-    #[deprecated]
+    /// The date is kept when the file is read afterwards, even if updating accessed dates is enabled in
+    /// `FsOptions`. The directory entry is updated on the next flush. A date out of the range supported by FAT
+    /// (e.g. before 1980) is clamped to the closest supported date.
     pub fn set_accessed(&mut self, date: Date) {
+        let date = date.clamped();
         if let Some(ref mut e) = self.context.entry {
             e.set_accessed(date);
         }
+        self.accessed = Some(date);
     }
 
     /// Sets date and time of last modification for this file.
     ///
-    /// Note: it is overwritten by a value from the `TimeProvider` on every file write operation.
-    /// Deprecated: if needed implement a custom `TimeProvider`.
-    #[deprecated]
+    /// The time is kept when the file is written afterwards instead of using a value from the `TimeProvider`, so
+    /// it can be called before or after restoring the file content. The directory entry is updated on the next
+    /// flush. A value out of the range supported by FAT (e.g. before 1980) is clamped to the closest supported
+    /// value.
     pub fn set_modified(&mut self, date_time: DateTime) {
+        let date_time = date_time.clamped();
         if let Some(ref mut e) = self.context.entry {
             e.set_modified(date_time);
         }
+        self.modified = Some(date_time);
     }
 
     fn size(&self) -> Option<u32> {
@@ -224,7 +508,7 @@ impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
     }
 
     fn bytes_left_in_file(&self) -> Option<usize> {
-        // Note: seeking beyond end of file is not allowed so overflow is impossible
+        // Note: seeking beyond end of file only records the requested position so overflow is impossible
         self.size().map(|s| (s - self.context.offset) as usize)
     }
 
@@ -235,23 +519,108 @@ impl<'a, IO: ReadWriteSeek, TP, OCC> File<'a, IO, TP, OCC> {
         }
     }
 
+    pub(crate) fn has_dir_entry(&self) -> bool {
+        self.context.entry.is_some()
+    }
+
     pub(crate) fn first_cluster(&self) -> Option<u32> {
         self.context.first_cluster
     }
 
+    // sets the size of a file whose data was written directly to its clusters - see `Dir::copy_file`
+    pub(crate) fn set_size(&mut self, size: u32) {
+        if let Some(ref mut e) = self.context.entry {
+            e.set_size(size);
+        }
+    }
+
+    pub(crate) fn attributes(&self) -> Option<FileAttributes> {
+        self.context.entry.as_ref().map(|e| e.inner().attributes())
+    }
+
     async fn flush(&mut self) -> Result<(), Error<IO::Error>> {
         self.flush_dir_entry().await?;
         let mut disk = self.fs.disk.borrow_mut();
         disk.flush().await?;
         Ok(())
     }
+
+    /// Flushes file data and all metadata (size, first cluster and timestamps) to the storage device.
+    ///
+    /// This is equivalent to `Write::flush`.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn sync_all(&mut self) -> Result<(), Error<IO::Error>> {
+        self.flush().await
+    }
+
+    /// Flushes file data to the storage device.
+    ///
+    /// The directory entry is only written if metadata required to read the data back (size or first cluster)
+    /// has changed. In that case the whole entry is written, including pending timestamp updates, otherwise
+    /// timestamp updates stay pending. Use `sync_all` or `Write::flush` before dropping the file to make sure they
+    /// are written.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn sync_data(&mut self) -> Result<(), Error<IO::Error>> {
+        if let Some(ref mut e) = self.context.entry {
+            e.flush_layout(self.fs).await?;
+        }
+        let mut disk = self.fs.disk.borrow_mut();
+        disk.flush().await?;
+        Ok(())
+    }
 }
 
 impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> File<'_, IO, TP, OCC> {
+    fn modified_now(&self) -> DateTime {
+        self.modified
+            .unwrap_or_else(|| self.fs.options.time_provider.get_current_date_time())
+    }
+
+    // returns the cluster holding the byte at the current position, moving to the next cluster of the chain when
+    // the position is at a cluster boundary
+    async fn cluster_for_read(&self) -> Result<Option<u32>, Error<IO::Error>> {
+        if self.context.offset % self.fs.cluster_size() != 0 {
+            return Ok(self.context.current_cluster);
+        }
+        match self.context.current_cluster {
+            None => Ok(self.context.first_cluster),
+            Some(n) => {
+                if let Some(runs) = self.forward_runs() {
+                    Ok(next_cluster_in_runs(runs, n))
+                } else {
+                    self.fs.cluster_iter(n).next().await.transpose()
+                }
+            }
+        }
+    }
+
+    fn update_accessed_date(&mut self) {
+        if let Some(ref mut e) = self.context.entry {
+            if self.fs.options.update_accessed_date && !self.fs.options.read_only && self.accessed.is_none() {
+                let now = self.fs.options.time_provider.get_current_date();
+                e.set_accessed(now);
+            }
+        }
+    }
+
+    // sets the modification time of the directory entry like a write does
+    pub(crate) fn touch_modified(&mut self) {
+        let now = self.modified_now();
+        if let Some(ref mut e) = self.context.entry {
+            e.set_modified(now);
+        }
+    }
+
     fn update_dir_entry_after_write(&mut self) {
         let offset = self.context.offset;
+        let now = self.modified_now();
         if let Some(ref mut e) = self.context.entry {
-            let now = self.fs.options.time_provider.get_current_date_time();
             e.set_modified(now);
             if e.inner().size().map_or(false, |s| offset > s) {
                 e.set_size(offset);
@@ -259,6 +628,445 @@ impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> File<'_, IO, TP, OCC> {
         }
     }
 
+    /// Truncate the file to `len` bytes regardless of the current position.
+    ///
+    /// All clusters past the new end of the file are freed and the directory entry size is updated. Unlike
+    /// `set_len` this never grows the file. The current position is preserved unless it is beyond the new end of the
+    /// file, in which case it is moved to the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if `len` is greater than the file size or this is a directory.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn truncate_to(&mut self, len: u64) -> Result<(), Error<IO::Error>> {
+        trace!("File::truncate_to {}", len);
+        self.check_writable()?;
+        let Some(size) = self.size() else {
+            error!("Cannot truncate a directory");
+            return Err(Error::InvalidInput);
+        };
+        if len > u64::from(size) {
+            error!("Cannot truncate a file of {} bytes to {} bytes", size, len);
+            return Err(Error::InvalidInput);
+        }
+        self.set_len(len).await
+    }
+
+    /// Set the length of the file to exactly `new_len` bytes.
+    ///
+    /// If the file is shrunk all clusters past the new end of the file are freed. If the file is grown clusters
+    /// are allocated as needed and the new region is filled with zeros, so subsequent writes up to `new_len` do not
+    /// need to allocate clusters. The current position is preserved unless it is beyond the new end of the file, in
+    /// which case it is moved to the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if `new_len` is greater than the maximal file size or this is a
+    ///   directory.
+    /// * `Error::NotEnoughSpace` will be returned if there are not enough free clusters to grow the file.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if this is the root directory.
+    pub async fn set_len(&mut self, new_len: u64) -> Result<(), Error<IO::Error>> {
+        trace!("File::set_len {}", new_len);
+        self.check_writable()?;
+        if self.context.entry.is_none() {
+            // Note: we cannot handle this case because there is no size field
+            panic!("Trying to set length of a file without an entry");
+        }
+        let Some(size) = self.size() else {
+            error!("Cannot set length of a directory");
+            return Err(Error::InvalidInput);
+        };
+        let Ok(new_size) = u32::try_from(new_len) else {
+            error!("New file length exceeds the maximal file size");
+            return Err(Error::InvalidInput);
+        };
+        if new_size == size {
+            return Ok(());
+        }
+        let pending_offset = self.seek_past_end.take();
+        let old_offset = self.context.offset;
+        let old_cluster = self.context.current_cluster;
+        if new_size < size {
+            self.seek(SeekFrom::Start(u64::from(new_size))).await?;
+            self.truncate().await?;
+            if old_offset < new_size {
+                self.seek(SeekFrom::Start(u64::from(old_offset))).await?;
+            }
+        } else {
+            self.fs.set_dirty_flag(true).await?;
+            self.seek(SeekFrom::End(0)).await?;
+            self.grow_to(new_size).await?;
+            if let Some(ref mut e) = self.context.entry {
+                e.set_size(new_size);
+            }
+            self.context.offset = old_offset;
+            self.context.current_cluster = old_cluster;
+        }
+        if let Some(offset) = pending_offset {
+            self.seek(SeekFrom::Start(u64::from(offset))).await?;
+        }
+        let now = self.modified_now();
+        if let Some(ref mut e) = self.context.entry {
+            e.set_modified(now);
+        }
+        Ok(())
+    }
+
+    /// Allocates clusters for the first `len` bytes of the file up front, trying to keep them contiguous.
+    ///
+    /// Clusters missing from the chain are allocated as a single run of consecutive free clusters if one exists.
+    /// Otherwise they are allocated one by one like during a normal write, which may fragment the file. The file
+    /// size is not changed - the clusters are used by subsequent writes. Call `flush` to save the updated directory
+    /// entry.
+    ///
+    /// Returns `true` if no allocation was needed or the new clusters are contiguous, `false` if the fallback
+    /// allocation was used.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if `len` is greater than the maximal file size or this is a
+    ///   directory.
+    /// * `Error::NotEnoughSpace` will be returned if there are not enough free clusters.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn allocate_contiguous(&mut self, len: u64) -> Result<bool, Error<IO::Error>> {
+        trace!("File::allocate_contiguous {}", len);
+        self.check_writable()?;
+        if self.size().is_none() {
+            error!("Cannot preallocate a directory");
+            return Err(Error::InvalidInput);
+        }
+        if u32::try_from(len).is_err() {
+            error!("Length exceeds the maximal file size");
+            return Err(Error::InvalidInput);
+        }
+        let needed = self.fs.clusters_from_bytes(len);
+        let mut allocated = 0;
+        let mut last_cluster = None;
+        if let Some(first_cluster) = self.context.first_cluster {
+            allocated = 1;
+            last_cluster = Some(first_cluster);
+            let mut iter = self.fs.cluster_iter(first_cluster);
+            while let Some(r) = iter.next().await {
+                allocated += 1;
+                last_cluster = Some(r?);
+            }
+        }
+        if allocated >= needed {
+            return Ok(true);
+        }
+        let count = needed - allocated;
+        self.fs.set_dirty_flag(true).await?;
+        if let Some(n) = self.fs.alloc_contiguous_clusters(last_cluster, count).await? {
+            if self.context.first_cluster.is_none() {
+                self.set_first_cluster(n);
+            }
+            return Ok(true);
+        }
+        warn!("No contiguous run of {} free clusters", count);
+        for _ in 0..count {
+            let n = self.fs.alloc_cluster(last_cluster, false).await?;
+            if self.context.first_cluster.is_none() {
+                self.set_first_cluster(n);
+            }
+            last_cluster = Some(n);
+        }
+        Ok(false)
+    }
+
+    /// Reads bytes starting at the absolute `offset` without changing the current position.
+    ///
+    /// Returns the number of bytes read. Like `Read::read` it may return fewer bytes than requested, e.g. when the
+    /// read reaches the end of a cluster or the end of the file - use `read_exact_at` to fill the whole buffer.
+    /// Reading at or past the end of the file returns 0.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, Error<IO::Error>> {
+        trace!("File::read_at {}", offset);
+        let position = self.save_position();
+        let result = async {
+            self.seek(SeekFrom::Start(offset)).await?;
+            self.read(buf).await
+        }
+        .await;
+        self.restore_position(position).await?;
+        result
+    }
+
+    /// Writes bytes starting at the absolute `offset` without changing the current position.
+    ///
+    /// Writing past the end of the file grows it and zero-fills the gap, like a write after seeking past the end.
+    /// Returns the number of bytes written, which may be fewer than requested - use `write_all_at` to write the
+    /// whole buffer.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::NotEnoughSpace` will be returned if there are not enough free clusters to grow the file.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<usize, Error<IO::Error>> {
+        trace!("File::write_at {}", offset);
+        self.check_writable()?;
+        let position = self.save_position();
+        let result = async {
+            self.seek(SeekFrom::Start(offset)).await?;
+            self.write(buf).await
+        }
+        .await;
+        self.restore_position(position).await?;
+        result
+    }
+
+    /// Reads exactly `buf.len()` bytes starting at the absolute `offset` without changing the current position.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::UnexpectedEof` will be returned if the end of the file is reached before `buf` is filled.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn read_exact_at(&mut self, mut offset: u64, mut buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+        while !buf.is_empty() {
+            match self.read_at(offset, buf).await? {
+                0 => return Err(Error::UnexpectedEof),
+                n => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes all of `buf` starting at the absolute `offset` without changing the current position.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::WriteZero` will be returned if the file cannot accept more data.
+    /// * `Error::NotEnoughSpace` will be returned if there are not enough free clusters to grow the file.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn write_all_at(&mut self, mut offset: u64, mut buf: &[u8]) -> Result<(), Error<IO::Error>> {
+        while !buf.is_empty() {
+            match self.write_at(offset, buf).await? {
+                0 => return Err(Error::WriteZero),
+                n => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads data from the current position into the buffers of `bufs` in order.
+    ///
+    /// Each buffer is filled completely before the next one is used, so the buffers receive the same data as a
+    /// sequence of `read_exact` calls. The cluster chain is walked once: every cluster is looked up and seeked to a
+    /// single time and its data is split across as many buffers as it covers. Reading stops at the end of the file.
+    /// Returns the total number of bytes read, which is smaller than the total length of `bufs` only if the end of
+    /// the file was reached.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if the file was not opened for reading.
+    /// * `Error::UnexpectedEof` will be returned if the underlying storage ends before the cluster chain does.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize, Error<IO::Error>> {
+        trace!("File::read_vectored {}", bufs.len());
+        if !self.readable {
+            return Err(Error::InvalidInput);
+        }
+        let cluster_size = self.fs.cluster_size();
+        let mut total = 0;
+        let mut buf_index = 0;
+        let mut buf_offset = 0;
+        loop {
+            while buf_index < bufs.len() && buf_offset == bufs[buf_index].len() {
+                buf_index += 1;
+                buf_offset = 0;
+            }
+            if buf_index == bufs.len() {
+                break;
+            }
+            let Some(current_cluster) = self.cluster_for_read().await? else {
+                break;
+            };
+            let offset_in_cluster = self.context.offset % cluster_size;
+            let bytes_left_in_cluster = (cluster_size - offset_in_cluster) as usize;
+            let mut span = cmp::min(
+                bytes_left_in_cluster,
+                self.bytes_left_in_file().unwrap_or(bytes_left_in_cluster),
+            );
+            if span == 0 {
+                break;
+            }
+            trace!("read {} bytes in cluster {}", span, current_cluster);
+            let offset_in_fs = self.fs.offset_from_cluster(current_cluster) + u64::from(offset_in_cluster);
+            {
+                let mut disk = self.fs.disk.borrow_mut();
+                disk.seek(SeekFrom::Start(offset_in_fs)).await?;
+                // the rest of the cluster is contiguous on the storage, so consecutive buffers need no further seek
+                while span > 0 && buf_index < bufs.len() {
+                    let buf = &mut bufs[buf_index][buf_offset..];
+                    let n = cmp::min(buf.len(), span);
+                    disk.read_exact(&mut buf[..n]).await?;
+                    span -= n;
+                    total += n;
+                    buf_offset += n;
+                    self.context.offset += n as u32;
+                    if buf_offset == bufs[buf_index].len() {
+                        buf_index += 1;
+                        buf_offset = 0;
+                    }
+                }
+            }
+            self.context.current_cluster = Some(current_cluster);
+        }
+        if total > 0 {
+            self.update_accessed_date();
+        }
+        Ok(total)
+    }
+
+    /// Overwrites `len` bytes starting at the absolute `offset` with zeros.
+    ///
+    /// Neither the file length nor the current position are changed. Clusters fully covered by the range are
+    /// zeroed directly on the storage, partially covered clusters at the start and the end of the range only have
+    /// the covered bytes overwritten.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if the range extends past the end of the file or this is a directory.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::CorruptedFileSystem` will be returned if the cluster chain is shorter than the file size.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn zero_range(&mut self, offset: u64, len: u64) -> Result<(), Error<IO::Error>> {
+        trace!("File::zero_range {} {}", offset, len);
+        self.check_writable()?;
+        let Some(size) = self.size() else {
+            return Err(Error::InvalidInput);
+        };
+        let end = offset.checked_add(len).ok_or(Error::InvalidInput)?;
+        if end > u64::from(size) {
+            return Err(Error::InvalidInput);
+        }
+        if len == 0 {
+            return Ok(());
+        }
+        self.fs.set_dirty_flag(true).await?;
+        let cluster_size = u64::from(self.fs.cluster_size());
+        let mut cluster = self.context.first_cluster;
+        let mut cluster_start = 0;
+        // skip clusters before the range
+        while cluster_start + cluster_size <= offset {
+            cluster = match cluster {
+                Some(n) => self.fs.cluster_iter(n).next().await.transpose()?,
+                None => None,
+            };
+            cluster_start += cluster_size;
+        }
+        while cluster_start < end {
+            let Some(n) = cluster else {
+                error!("cluster chain ends before the end of the file");
+                return Err(Error::CorruptedFileSystem);
+            };
+            let zero_start = cmp::max(offset, cluster_start) - cluster_start;
+            let zero_end = cmp::min(end, cluster_start + cluster_size) - cluster_start;
+            self.fs
+                .write_zeros_at(self.fs.offset_from_cluster(n) + zero_start, zero_end - zero_start)
+                .await?;
+            cluster_start += cluster_size;
+            if cluster_start < end {
+                cluster = self.fs.cluster_iter(n).next().await.transpose()?;
+            }
+        }
+        let now = self.modified_now();
+        if let Some(ref mut e) = self.context.entry {
+            e.set_modified(now);
+        }
+        Ok(())
+    }
+
+    fn save_position(&self) -> (u32, Option<u32>, Option<u32>) {
+        (self.context.offset, self.context.current_cluster, self.seek_past_end)
+    }
+
+    async fn restore_position(
+        &mut self,
+        (offset, current_cluster, seek_past_end): (u32, Option<u32>, Option<u32>),
+    ) -> Result<(), Error<IO::Error>> {
+        // clusters are never freed by reads and writes so the saved cluster is still part of the chain
+        self.context.offset = offset;
+        self.context.current_cluster = current_cluster;
+        self.seek_past_end = None;
+        if let Some(pending_offset) = seek_past_end {
+            // the file may have grown past the pending offset in the meantime
+            self.seek(SeekFrom::Start(u64::from(pending_offset))).await?;
+        }
+        Ok(())
+    }
+
+    async fn grow_to(&mut self, new_size: u32) -> Result<(), Error<IO::Error>> {
+        // Zero-fill everything between the current position (end of file) and the new size, reusing clusters that
+        // are already in the chain and allocating (zeroed) clusters when the chain ends
+        let cluster_size = self.fs.cluster_size();
+        while self.context.offset < new_size {
+            let offset_in_cluster = self.context.offset % cluster_size;
+            let (cluster, needs_zeroing) = if offset_in_cluster == 0 {
+                let next_cluster = match self.context.current_cluster {
+                    None => self.context.first_cluster,
+                    Some(n) => self.fs.cluster_iter(n).next().await.transpose()?,
+                };
+                if let Some(n) = next_cluster {
+                    (n, true)
+                } else {
+                    let new_cluster = self.fs.alloc_cluster(self.context.current_cluster, true).await?;
+                    trace!("allocated cluster {}", new_cluster);
+                    if self.context.first_cluster.is_none() {
+                        self.set_first_cluster(new_cluster);
+                    }
+                    (new_cluster, false)
+                }
+            } else {
+                match self.context.current_cluster {
+                    Some(n) => (n, true),
+                    None => panic!("Offset inside cluster but no cluster allocated"),
+                }
+            };
+            let len = cmp::min(cluster_size - offset_in_cluster, new_size - self.context.offset);
+            if needs_zeroing {
+                let offset_in_fs = self.fs.offset_from_cluster(cluster) + u64::from(offset_in_cluster);
+                self.fs.write_zeros_at(offset_in_fs, u64::from(len)).await?;
+            }
+            self.context.offset += len;
+            self.context.current_cluster = Some(cluster);
+        }
+        Ok(())
+    }
+
     /// Manually close the file
     ///
     /// A [`FileContext`] is returned, which can be used in conjunction with the
@@ -276,6 +1084,10 @@ impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> File<'_, IO, TP, OCC> {
 impl<IO: ReadWriteSeek, TP, OCC> Drop for File<'_, IO, TP, OCC> {
     fn drop(&mut self) {
         if let Some(e) = &self.context.entry {
+            #[cfg(feature = "alloc")]
+            if self.locked {
+                self.fs.unlock_file(e.pos());
+            }
             if e.dirty() {
                 warn!("Dropping dirty file before flushing");
                 #[cfg(feature = "dirty-file-panic")]
@@ -290,9 +1102,23 @@ impl<IO: ReadWriteSeek, TP, OCC> Drop for File<'_, IO, TP, OCC> {
 // Note: derive cannot be used because of invalid bounds. See: https://github.com/rust-lang/rust/issues/26925
 impl<IO: ReadWriteSeek, TP, OCC> Clone for File<'_, IO, TP, OCC> {
     fn clone(&self) -> Self {
+        #[cfg(feature = "alloc")]
+        if let (true, Some(e)) = (self.locked, &self.context.entry) {
+            self.fs.retain_file(e.pos());
+        }
         File {
             context: self.context.clone(),
+            seek_past_end: self.seek_past_end,
+            readable: self.readable,
+            writable: self.writable,
+            append: self.append,
+            modified: self.modified,
+            accessed: self.accessed,
             fs: self.fs,
+            #[cfg(feature = "alloc")]
+            locked: self.locked,
+            #[cfg(feature = "alloc")]
+            forward_runs: self.forward_runs.clone(),
         }
     }
 }
@@ -304,24 +1130,11 @@ impl<IO: ReadWriteSeek, TP, OCC> IoBase for File<'_, IO, TP, OCC> {
 impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> Read for File<'_, IO, TP, OCC> {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
         trace!("File::read");
+        if !self.readable {
+            return Err(Error::InvalidInput);
+        }
         let cluster_size = self.fs.cluster_size();
-        let current_cluster_opt = if self.context.offset % cluster_size == 0 {
-            // next cluster
-            match self.context.current_cluster {
-                None => self.context.first_cluster,
-                Some(n) => {
-                    let r = self.fs.cluster_iter(n).next().await;
-                    match r {
-                        Some(Err(err)) => return Err(err),
-                        Some(Ok(n)) => Some(n),
-                        None => None,
-                    }
-                }
-            }
-        } else {
-            self.context.current_cluster
-        };
-        let current_cluster = match current_cluster_opt {
+        let current_cluster = match self.cluster_for_read().await? {
             Some(n) => n,
             None => return Ok(0),
         };
@@ -344,13 +1157,7 @@ impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> Read for File<'_, IO, TP, OCC> {
         }
         self.context.offset += read_bytes as u32;
         self.context.current_cluster = Some(current_cluster);
-
-        if let Some(ref mut e) = self.context.entry {
-            if self.fs.options.update_accessed_date {
-                let now = self.fs.options.time_provider.get_current_date();
-                e.set_accessed(now);
-            }
-        }
+        self.update_accessed_date();
         Ok(read_bytes)
     }
 }
@@ -358,6 +1165,21 @@ impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> Read for File<'_, IO, TP, OCC> {
 impl<IO: ReadWriteSeek, TP: TimeProvider, OCC> Write for File<'_, IO, TP, OCC> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         trace!("File::write");
+        self.check_writable()?;
+        if self.append && (self.seek_past_end.is_some() || Some(self.context.offset) != self.size()) {
+            // in append mode every write goes to the end of the file
+            self.seek(SeekFrom::End(0)).await?;
+        }
+        if let Some(offset) = self.seek_past_end {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            // Previous seek went beyond the end of the file - zero-fill the gap before writing
+            self.fs.set_dirty_flag(true).await?;
+            self.grow_to(offset).await?;
+            self.seek_past_end = None;
+            self.update_dir_entry_after_write();
+        }
         let cluster_size = self.fs.cluster_size();
         let offset_in_cluster = self.context.offset % cluster_size;
         let bytes_left_in_cluster = (cluster_size - offset_in_cluster) as usize;
@@ -432,7 +1254,7 @@ impl<IO: ReadWriteSeek, TP, OCC> Seek for File<'_, IO, TP, OCC> {
         trace!("File::seek");
         let size_opt = self.size();
         let new_offset_opt: Option<u32> = match pos {
-            SeekFrom::Current(x) => i64::from(self.context.offset)
+            SeekFrom::Current(x) => i64::from(self.seek_past_end.unwrap_or(self.context.offset))
                 .checked_add(x)
                 .and_then(|n| u32::try_from(n).ok()),
             SeekFrom::Start(x) => u32::try_from(x).ok(),
@@ -446,12 +1268,21 @@ impl<IO: ReadWriteSeek, TP, OCC> Seek for File<'_, IO, TP, OCC> {
             error!("Invalid seek offset");
             return Err(Error::InvalidInput);
         };
+        // Seeking beyond the end of the file is allowed - the position is remembered and the gap is zero-filled
+        // when the file is written
+        let mut past_end = None;
         if let Some(size) = size_opt {
             if new_offset > size {
-                warn!("Seek beyond the end of the file");
+                trace!("Seek beyond the end of the file");
+                past_end = Some(new_offset);
                 new_offset = size;
             }
         }
+        if self.forward_runs().is_some() && new_offset < self.context.offset {
+            error!("Backward seek in forward-only mode");
+            return Err(Error::InvalidInput);
+        }
+        self.seek_past_end = past_end;
         trace!(
             "file seek {} -> {} - entry {:?}",
             self.context.offset,
@@ -460,7 +1291,7 @@ impl<IO: ReadWriteSeek, TP, OCC> Seek for File<'_, IO, TP, OCC> {
         );
         if new_offset == self.context.offset {
             // position is the same - nothing to do
-            return Ok(u64::from(self.context.offset));
+            return Ok(u64::from(past_end.unwrap_or(self.context.offset)));
         }
         let new_offset_in_clusters = self.fs.clusters_from_bytes(u64::from(new_offset));
         let old_offset_in_clusters = self.fs.clusters_from_bytes(u64::from(self.context.offset));
@@ -475,15 +1306,25 @@ impl<IO: ReadWriteSeek, TP, OCC> Seek for File<'_, IO, TP, OCC> {
             debug_assert!(new_offset_in_clusters > 0);
             let clusters_to_skip = new_offset_in_clusters - 1;
             let mut cluster = first_cluster;
-            let mut iter = self.fs.cluster_iter(first_cluster);
-            for i in 0..clusters_to_skip {
-                cluster = if let Some(r) = iter.next().await {
-                    r?
-                } else {
+            if let Some(runs) = self.forward_runs() {
+                // the chain was resolved when the forward-only mode was enabled
+                let (n, skipped) = nth_cluster_in_runs(runs, clusters_to_skip).unwrap_or((first_cluster, 0));
+                cluster = n;
+                if skipped < clusters_to_skip {
                     // cluster chain ends before the new position - seek to the end of the last cluster
-                    new_offset = self.fs.bytes_from_clusters(i + 1) as u32;
-                    break;
-                };
+                    new_offset = self.fs.bytes_from_clusters(skipped + 1) as u32;
+                }
+            } else {
+                let mut iter = self.fs.cluster_iter(first_cluster);
+                for i in 0..clusters_to_skip {
+                    cluster = if let Some(r) = iter.next().await {
+                        r?
+                    } else {
+                        // cluster chain ends before the new position - seek to the end of the last cluster
+                        new_offset = self.fs.bytes_from_clusters(i + 1) as u32;
+                        break;
+                    };
+                }
             }
             Some(cluster)
         } else {
@@ -493,6 +1334,131 @@ impl<IO: ReadWriteSeek, TP, OCC> Seek for File<'_, IO, TP, OCC> {
         };
         self.context.offset = new_offset;
         self.context.current_cluster = new_cluster;
-        Ok(u64::from(self.context.offset))
+        Ok(u64::from(past_end.unwrap_or(self.context.offset)))
+    }
+}
+
+// Returns the cluster following `cluster` in a chain of `(first cluster, cluster count)` runs.
+fn next_cluster_in_runs(runs: &[(u32, u32)], cluster: u32) -> Option<u32> {
+    let i = runs
+        .iter()
+        .position(|&(start, count)| cluster >= start && cluster - start < count)?;
+    let (start, count) = runs[i];
+    if cluster - start + 1 < count {
+        Some(cluster + 1)
+    } else {
+        runs.get(i + 1).map(|&(start, _)| start)
+    }
+}
+
+// Returns the `n`-th cluster (counting from 0) of a chain of `(first cluster, cluster count)` runs together with the
+// number of clusters skipped to reach it. The last cluster is returned if the chain is shorter.
+fn nth_cluster_in_runs(runs: &[(u32, u32)], n: u32) -> Option<(u32, u32)> {
+    let mut skipped = 0;
+    for &(start, count) in runs {
+        if n - skipped < count {
+            return Some((start + n - skipped, n));
+        }
+        skipped += count;
+    }
+    let &(start, count) = runs.last()?;
+    Some((start + count - 1, skipped - 1))
+}
+
+/// A buffered reader of a `File`.
+///
+/// Reading a file in small pieces (e.g. byte by byte or line by line) is slow, because every `File::read` call
+/// accesses the storage. `BufReader` reads up to `N` bytes at once and serves small reads from its buffer. A refill
+/// never crosses a cluster boundary, so it is a single contiguous storage access. `N` should be a multiple of the
+/// sector size.
+///
+/// Seeking discards the buffered data. Dropping the reader drops the file, so it should be flushed with
+/// `into_inner().flush()` if the filesystem updates accessed dates.
+pub struct BufReader<'a, IO: ReadWriteSeek, TP, OCC, const N: usize> {
+    file: File<'a, IO, TP, OCC>,
+    buf: [u8; N],
+    // range of `buf` not consumed yet
+    pos: usize,
+    filled: usize,
+}
+
+impl<'a, IO: ReadWriteSeek, TP, OCC, const N: usize> BufReader<'a, IO, TP, OCC, N> {
+    /// Creates a new buffered reader of `file`.
+    #[must_use]
+    pub fn new(file: File<'a, IO, TP, OCC>) -> Self {
+        Self {
+            file,
+            buf: [0; N],
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Returns a reference to the inner file.
+    #[must_use]
+    pub fn get_ref(&self) -> &File<'a, IO, TP, OCC> {
+        &self.file
+    }
+
+    /// Unwraps the inner file, discarding the buffered data.
+    ///
+    /// The position of the returned file is after the data that was buffered.
+    #[must_use]
+    pub fn into_inner(self) -> File<'a, IO, TP, OCC> {
+        self.file
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<IO: ReadWriteSeek, TP, OCC, const N: usize> IoBase for BufReader<'_, IO, TP, OCC, N> {
+    type Error = Error<IO::Error>;
+}
+
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC, const N: usize> Read for BufReader<'_, IO, TP, OCC, N> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        // bypass the buffer for large reads if it is empty
+        if self.pos == self.filled && buf.len() >= N {
+            return self.file.read(buf).await;
+        }
+        let data = self.fill_buf().await?;
+        let n = cmp::min(data.len(), buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<IO: ReadWriteSeek, TP: TimeProvider, OCC, const N: usize> BufRead for BufReader<'_, IO, TP, OCC, N> {
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        if self.pos == self.filled {
+            // `File::read` stops at the end of the current cluster
+            self.filled = self.file.read(&mut self.buf).await?;
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.filled);
+    }
+}
+
+impl<IO: ReadWriteSeek, TP, OCC, const N: usize> Seek for BufReader<'_, IO, TP, OCC, N> {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let pos = match pos {
+            // the file position is ahead of the reader position by the buffered bytes
+            SeekFrom::Current(x) => {
+                let buffered = i64::try_from(self.filled - self.pos).map_err(|_| Error::InvalidInput)?;
+                SeekFrom::Current(x.checked_sub(buffered).ok_or(Error::InvalidInput)?)
+            }
+            pos => pos,
+        };
+        let offset = self.file.seek(pos).await?;
+        self.discard_buffer();
+        Ok(offset)
     }
 }
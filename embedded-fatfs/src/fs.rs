@@ -2,23 +2,27 @@ use core::borrow::BorrowMut;
 use core::cell::{Cell, RefCell};
 use core::char;
 use core::cmp;
-use core::fmt::Debug;
+use core::fmt::{self, Debug};
 use core::marker::PhantomData;
+use core::str;
 use core::u32;
 
 #[cfg(all(not(feature = "std"), feature = "alloc", feature = "lfn"))]
 use alloc::string::String;
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{vec, vec::Vec};
 #[cfg(feature = "std")]
 use embedded_io_adapters::tokio_1::FromTokio;
 
 use crate::boot_sector::{format_boot_sector, BiosParameterBlock, BootSector};
 use crate::dir::{Dir, DirRawStream};
 use crate::dir_entry::{DirFileEntryData, FileAttributes, SFN_PADDING, SFN_SIZE};
-use crate::error::Error;
+use crate::error::{Error, ExportError};
 use crate::file::File;
 use crate::io::{self, IoBase, Read, ReadLeExt, Seek, SeekFrom, Write, WriteLeExt};
 use crate::table::{
-    alloc_cluster, count_free_clusters, format_fat, read_fat_flags, ClusterIterator, RESERVED_FAT_ENTRIES,
+    alloc_cluster, alloc_contiguous_clusters, count_free_clusters, format_fat, free_cluster, is_cluster_allocated,
+    mark_end_of_chain, read_fat_flags, write_fat_flags, CachedFat, ClusterIterator, FatCache, RESERVED_FAT_ENTRIES,
 };
 use crate::time::{DefaultTimeProvider, TimeProvider};
 
@@ -40,6 +44,16 @@ pub enum FatType {
     Fat32,
 }
 
+impl fmt::Display for FatType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FatType::Fat12 => "FAT12",
+            FatType::Fat16 => "FAT16",
+            FatType::Fat32 => "FAT32",
+        })
+    }
+}
+
 impl FatType {
     const FAT16_MIN_CLUSTERS: u32 = 4085;
     const FAT32_MIN_CLUSTERS: u32 = 65525;
@@ -55,6 +69,39 @@ impl FatType {
         }
     }
 
+    /// Returns the smallest FAT entry value marking the end of a cluster chain.
+    ///
+    /// All values from this one up to the largest value of a FAT entry (e.g. `0xFFF` on FAT12) mark the end of a
+    /// chain. Only the lower 28 bits of FAT32 entries are used, the upper 4 bits have to be masked out before
+    /// comparing.
+    #[must_use]
+    pub fn end_of_chain_marker(self) -> u32 {
+        match self {
+            FatType::Fat12 => 0xFF8,
+            FatType::Fat16 => 0xFFF8,
+            FatType::Fat32 => 0x0FFF_FFF8,
+        }
+    }
+
+    /// Returns the FAT entry value marking a bad cluster.
+    #[must_use]
+    pub fn bad_cluster_marker(self) -> u32 {
+        match self {
+            FatType::Fat12 => 0xFF7,
+            FatType::Fat16 => 0xFFF7,
+            FatType::Fat32 => 0x0FFF_FFF7,
+        }
+    }
+
+    /// Returns the largest cluster number a volume of this type can have.
+    ///
+    /// Data clusters are numbered from 2, so FAT entry values between 2 and this number refer to the next cluster of
+    /// a chain. Larger values are reserved or markers.
+    #[must_use]
+    pub fn max_valid_cluster(self) -> u32 {
+        self.max_clusters() + RESERVED_FAT_ENTRIES - 1
+    }
+
     pub(crate) fn bits_per_fat_entry(self) -> u32 {
         match self {
             FatType::Fat12 => 12,
@@ -244,8 +291,15 @@ impl FsInfoSector {
 /// Options are specified as an argument for `FileSystem::new` method.
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Copy, Clone, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct FsOptions<TP, OCC> {
     pub(crate) update_accessed_date: bool,
+    pub(crate) read_only: bool,
+    // stored inverted so the derived `Default` matches `FsOptions::new`
+    pub(crate) case_sensitive: bool,
+    pub(crate) fat_cache_sectors: usize,
+    #[cfg(feature = "alloc")]
+    pub(crate) lock_open_files: bool,
     pub(crate) oem_cp_converter: OCC,
     pub(crate) time_provider: TP,
 }
@@ -256,6 +310,11 @@ impl FsOptions<DefaultTimeProvider, LossyOemCpConverter> {
     pub fn new() -> Self {
         Self {
             update_accessed_date: false,
+            read_only: false,
+            case_sensitive: false,
+            fat_cache_sectors: 0,
+            #[cfg(feature = "alloc")]
+            lock_open_files: false,
             oem_cp_converter: LossyOemCpConverter::new(),
             time_provider: DefaultTimeProvider::new(),
         }
@@ -263,26 +322,98 @@ impl FsOptions<DefaultTimeProvider, LossyOemCpConverter> {
 }
 
 impl<TP: TimeProvider, OCC: OemCpConverter> FsOptions<TP, OCC> {
-    /// If enabled accessed date field in directory entry is updated when reading or writing a file.
+    /// If enabled accessed date field in directory entry is updated when reading a file.
+    ///
+    /// Disabled by default. When disabled, reading files and directories never writes to the storage device: the
+    /// volume is not marked dirty and flushing a file that was only read does not write its directory entry. This
+    /// avoids extra writes (and wear) on flash media.
+    ///
+    /// When enabled, the directory entry of a file is written on the next flush after a read if the stored date
+    /// differs from the current date. The accessed date has a granularity of one day, so a file read repeatedly on
+    /// the same day causes at most one directory entry write.
     #[must_use]
     pub fn update_accessed_date(mut self, enabled: bool) -> Self {
         self.update_accessed_date = enabled;
         self
     }
 
+    /// If enabled the filesystem never writes to the storage device.
+    ///
+    /// All mutating operations (e.g. creating, removing, renaming or writing files) return `Error::ReadOnly`.
+    /// Neither the volume dirty flag nor the FS Information Sector is updated and accessed dates are not changed.
+    /// Useful for inspecting possibly corrupted or write-protected media.
+    #[must_use]
+    pub fn read_only(mut self, enabled: bool) -> Self {
+        self.read_only = enabled;
+        self
+    }
+
+    /// If enabled (the default) file names are compared ignoring case when looking up directory entries.
+    ///
+    /// This matches the behaviour of Windows. When disabled long file names must match exactly. Short (8.3) names
+    /// are stored in upper case, so they match either in upper case or in the lower case variant indicated by the
+    /// entry's case flags.
+    #[must_use]
+    pub fn case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_sensitive = !enabled;
+        self
+    }
+
+    /// Sets the number of FAT sectors kept in memory to speed up cluster chain traversal.
+    ///
+    /// The cache holds FAT data in 512 byte blocks (one sector on most media) and evicts the least recently used
+    /// block when full. It is updated on every FAT write, so it never returns stale data. Each cached sector uses
+    /// 512 bytes of memory. Without the `alloc` feature at most 1 sector can be cached.
+    ///
+    /// The default is 0, which disables the cache.
+    #[must_use]
+    pub fn fat_cache_sectors(mut self, sectors: usize) -> Self {
+        self.fat_cache_sectors = sectors;
+        self
+    }
+
+    /// If enabled a file can only be opened once at a time.
+    ///
+    /// Handles do not share their cached state, so writing through two handles of the same file can corrupt its size
+    /// and cluster chain. When enabled `Dir::open_file`, `Dir::create_file` and `OpenOptions::open` return
+    /// `Error::Busy` for a file that is still open through another handle, and so do the methods of `Dir` that remove
+    /// or rename it. Files are tracked until the last handle
+    /// (including clones) is dropped. Handles created from a `DirEntry` or a `FileContext` are not tracked.
+    ///
+    /// Disabled by default. Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn lock_open_files(mut self, enabled: bool) -> Self {
+        self.lock_open_files = enabled;
+        self
+    }
+
     /// Changes default OEM code page encoder-decoder.
     pub fn oem_cp_converter<OCC2: OemCpConverter>(self, oem_cp_converter: OCC2) -> FsOptions<TP, OCC2> {
         FsOptions::<TP, OCC2> {
             update_accessed_date: self.update_accessed_date,
+            read_only: self.read_only,
+            case_sensitive: self.case_sensitive,
+            fat_cache_sectors: self.fat_cache_sectors,
+            #[cfg(feature = "alloc")]
+            lock_open_files: self.lock_open_files,
             oem_cp_converter,
             time_provider: self.time_provider,
         }
     }
 
     /// Changes default time provider.
+    ///
+    /// The default is `DefaultTimeProvider`. Use `NullTimeProvider` on targets without a real-time clock.
+    #[must_use]
     pub fn time_provider<TP2: TimeProvider>(self, time_provider: TP2) -> FsOptions<TP2, OCC> {
         FsOptions::<TP2, OCC> {
             update_accessed_date: self.update_accessed_date,
+            read_only: self.read_only,
+            case_sensitive: self.case_sensitive,
+            fat_cache_sectors: self.fat_cache_sectors,
+            #[cfg(feature = "alloc")]
+            lock_open_files: self.lock_open_files,
             oem_cp_converter: self.oem_cp_converter,
             time_provider,
         }
@@ -318,6 +449,143 @@ impl FileSystemStats {
     }
 }
 
+/// A summary of the FAT volume layout.
+///
+/// Returned by `FileSystem::geometry`.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Geometry {
+    fat_type: FatType,
+    bytes_per_sector: u16,
+    sectors_per_cluster: u8,
+    total_clusters: u32,
+    free_clusters: u32,
+    reserved_sectors: u32,
+    fats: u8,
+    root_dir_sectors: u32,
+}
+
+impl Geometry {
+    /// Type of File Allocation Table
+    #[must_use]
+    pub fn fat_type(&self) -> FatType {
+        self.fat_type
+    }
+
+    /// Sector size in bytes
+    #[must_use]
+    pub fn bytes_per_sector(&self) -> u16 {
+        self.bytes_per_sector
+    }
+
+    /// Number of sectors in a cluster
+    #[must_use]
+    pub fn sectors_per_cluster(&self) -> u8 {
+        self.sectors_per_cluster
+    }
+
+    /// Number of total clusters in filesystem usable for file allocation
+    #[must_use]
+    pub fn total_clusters(&self) -> u32 {
+        self.total_clusters
+    }
+
+    /// Number of free clusters
+    #[must_use]
+    pub fn free_clusters(&self) -> u32 {
+        self.free_clusters
+    }
+
+    /// Number of sectors before the first FAT, including the boot sector
+    #[must_use]
+    pub fn reserved_sectors(&self) -> u32 {
+        self.reserved_sectors
+    }
+
+    /// Number of FAT copies
+    #[must_use]
+    pub fn fats(&self) -> u8 {
+        self.fats
+    }
+
+    /// Number of sectors occupied by the fixed root directory region (always zero on FAT32)
+    #[must_use]
+    pub fn root_dir_sectors(&self) -> u32 {
+        self.root_dir_sectors
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, {} bytes/sector, {} sectors/cluster, {}/{} clusters free, {} reserved sectors, {} FATs, {} root dir sectors",
+            self.fat_type,
+            self.bytes_per_sector,
+            self.sectors_per_cluster,
+            self.free_clusters,
+            self.total_clusters,
+            self.reserved_sectors,
+            self.fats,
+            self.root_dir_sectors
+        )
+    }
+}
+
+/// A result of a filesystem consistency check.
+///
+/// Returned by `FileSystem::check`.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct CheckReport {
+    cross_linked_chains: u32,
+    invalid_chains: u32,
+    lost_clusters: u32,
+    size_mismatches: u32,
+    repaired: bool,
+}
+
+impl CheckReport {
+    /// Number of cluster chains that run into a cluster already used by another chain (or by themselves).
+    #[must_use]
+    pub fn cross_linked_chains(&self) -> u32 {
+        self.cross_linked_chains
+    }
+
+    /// Number of cluster chains that point to a cluster number outside of the volume.
+    #[must_use]
+    pub fn invalid_chains(&self) -> u32 {
+        self.invalid_chains
+    }
+
+    /// Number of clusters marked as used in the FAT that do not belong to any directory entry.
+    #[must_use]
+    pub fn lost_clusters(&self) -> u32 {
+        self.lost_clusters
+    }
+
+    /// Number of files whose size exceeds the length of their cluster chain.
+    #[must_use]
+    pub fn size_mismatches(&self) -> u32 {
+        self.size_mismatches
+    }
+
+    /// Returns true if no inconsistency was found.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.cross_linked_chains == 0
+            && self.invalid_chains == 0
+            && self.lost_clusters == 0
+            && self.size_mismatches == 0
+    }
+
+    /// Returns true if the check was run with repair enabled.
+    #[must_use]
+    pub fn repaired(&self) -> bool {
+        self.repaired
+    }
+}
+
 /// A FAT filesystem object.
 ///
 /// `FileSystem` struct is representing a state of a mounted FAT volume.
@@ -326,11 +594,18 @@ pub struct FileSystem<IO: Read + Write + Seek, TP, OCC> {
     pub(crate) options: FsOptions<TP, OCC>,
     fat_type: FatType,
     bpb: BiosParameterBlock,
+    oem_name: [u8; 8],
     first_data_sector: u32,
     root_dir_sectors: u32,
     total_clusters: u32,
     fs_info: RefCell<FsInfoSector>,
-    current_status_flags: Cell<FsStatusFlags>,
+    fat_cache: RefCell<FatCache>,
+    // flags read from BPB on mount - cleared only by a successful repair
+    mount_status_flags: Cell<FsStatusFlags>,
+    current_status_flags: CurrentStatusFlags,
+    // directory entry positions of open files and their number of handles - see `FsOptions::lock_open_files`
+    #[cfg(feature = "alloc")]
+    open_files: RefCell<Vec<(u64, usize)>>,
 }
 
 /// The underlying storage device
@@ -380,12 +655,38 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         trace!("FileSystem::new");
         debug_assert!(disk.seek(SeekFrom::Current(0)).await? == 0);
 
-        // read boot sector
-        let bpb = {
-            let boot = BootSector::deserialize(&mut disk).await?;
-            boot.validate()?;
-            boot.bpb
-        };
+        let boot = BootSector::read(&mut disk).await?;
+        let fs = Self::mount(disk, boot, options).await?;
+        trace!("FileSystem::new end");
+        Ok(fs)
+    }
+
+    /// Creates a new filesystem object instance from a boot sector that has already been read.
+    ///
+    /// Unlike `new` this does not read the boot sector from `storage`, which makes mounting faster when the boot
+    /// sector was read before, e.g. by a bootloader. `boot` must have been read from the same `storage`. It is validated
+    /// again, which does not access `storage`. The FS Information Sector of FAT32 volumes is still read from `storage`.
+    ///
+    /// Unlike `new` the position of `storage` does not matter.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::CorruptedFileSystem` will be returned if `boot` contains invalid values, e.g. a default `BootSector`.
+    /// * `Error::Io` will be returned if the provided storage object returned an I/O error.
+    pub async fn from_existing_parts<T: IntoStorage<IO>>(
+        storage: T,
+        boot: BootSector,
+        options: FsOptions<TP, OCC>,
+    ) -> Result<Self, Error<IO::Error>> {
+        trace!("FileSystem::from_existing_parts");
+        boot.validate()?;
+        Self::mount(storage.into_storage(), boot, options).await
+    }
+
+    async fn mount(mut disk: IO, boot: BootSector, options: FsOptions<TP, OCC>) -> Result<Self, Error<IO::Error>> {
+        let BootSector { bpb, oem_name, .. } = boot;
 
         let root_dir_sectors = bpb.root_dir_sectors();
         let first_data_sector = bpb.first_data_sector();
@@ -411,17 +712,22 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
 
         // return FileSystem struct
         let status_flags = bpb.status_flags();
-        trace!("FileSystem::new end");
+        let fat_cache = FatCache::new(options.fat_cache_sectors);
         Ok(Self {
             disk: RefCell::new(disk),
             options,
             fat_type,
             bpb,
+            oem_name,
             first_data_sector,
             root_dir_sectors,
             total_clusters,
             fs_info: RefCell::new(fs_info),
-            current_status_flags: Cell::new(status_flags),
+            fat_cache: RefCell::new(fat_cache),
+            mount_status_flags: Cell::new(status_flags),
+            current_status_flags: CurrentStatusFlags(Cell::new(status_flags)),
+            #[cfg(feature = "alloc")]
+            open_files: RefCell::new(Vec::new()),
         })
     }
 
@@ -430,6 +736,66 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         self.fat_type
     }
 
+    /// Returns true if this filesystem was mounted with `FsOptions::read_only` enabled.
+    pub fn is_read_only(&self) -> bool {
+        self.options.read_only
+    }
+
+    /// Registers the first handle of the file with the directory entry at `entry_pos`.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn lock_file(&self, entry_pos: u64) -> Result<(), Error<IO::Error>> {
+        let mut open_files = self.open_files.borrow_mut();
+        if open_files.iter().any(|(pos, _)| *pos == entry_pos) {
+            error!("File is already open");
+            return Err(Error::Busy);
+        }
+        open_files.push((entry_pos, 1));
+        Ok(())
+    }
+
+    /// Registers an additional handle of an open file.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn retain_file(&self, entry_pos: u64) {
+        if let Some((_, handles)) = self
+            .open_files
+            .borrow_mut()
+            .iter_mut()
+            .find(|(pos, _)| *pos == entry_pos)
+        {
+            *handles += 1;
+        }
+    }
+
+    /// Returns `Error::Busy` if the file with the directory entry at `entry_pos` is registered as open.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn check_file_closed(&self, entry_pos: u64) -> Result<(), Error<IO::Error>> {
+        if self.open_files.borrow().iter().any(|(pos, _)| *pos == entry_pos) {
+            error!("File is open");
+            return Err(Error::Busy);
+        }
+        Ok(())
+    }
+
+    /// Unregisters a handle of an open file, the file is unlocked when its last handle is gone.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn unlock_file(&self, entry_pos: u64) {
+        let mut open_files = self.open_files.borrow_mut();
+        if let Some(i) = open_files.iter().position(|(pos, _)| *pos == entry_pos) {
+            open_files[i].1 -= 1;
+            if open_files[i].1 == 0 {
+                open_files.swap_remove(i);
+            }
+        }
+    }
+
+    pub(crate) fn check_writable(&self) -> Result<(), Error<IO::Error>> {
+        if self.options.read_only {
+            error!("Filesystem is mounted read-only");
+            return Err(Error::ReadOnly);
+        }
+        Ok(())
+    }
+
     /// Returns a volume identifier read from BPB in the Boot Sector.
     pub fn volume_id(&self) -> u32 {
         self.bpb.volume_id
@@ -440,6 +806,7 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
     /// Label is encoded in the OEM codepage.
     /// Note: This function returns label stored in the BPB block. Use `read_volume_label_from_root_dir_as_bytes` to
     /// read label from the root directory.
+    #[must_use]
     pub fn volume_label_as_bytes(&self) -> &[u8] {
         let full_label_slice = &self.bpb.volume_label;
         let len = full_label_slice
@@ -449,18 +816,90 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         &full_label_slice[..len]
     }
 
+    /// Returns a volume label from BPB in the Boot Sector, without the trailing padding.
+    ///
+    /// Unlike `volume_label` this does not decode the OEM codepage and does not allocate. Like `oem_name` it is cut
+    /// off at the first byte that is not valid UTF-8.
+    /// Note: This function returns label stored in the BPB block. Use `read_volume_label_from_root_dir` to read label
+    /// from the root directory.
+    #[must_use]
+    pub fn bpb_volume_label(&self) -> &str {
+        utf8_prefix(self.volume_label_as_bytes())
+    }
+
+    /// Returns the OEM name from the Boot Sector, without the trailing padding.
+    ///
+    /// The OEM name usually identifies the tool that formatted the volume, e.g. "MSWIN4.1" or "mkfs.fat". It is cut
+    /// off at the first byte that is not valid UTF-8.
+    #[must_use]
+    pub fn oem_name(&self) -> &str {
+        let len = self
+            .oem_name
+            .iter()
+            .rposition(|b| *b != b' ' && *b != 0)
+            .map_or(0, |p| p + 1);
+        utf8_prefix(&self.oem_name[..len])
+    }
+
     fn offset_from_sector(&self, sector: u32) -> u64 {
         self.bpb.bytes_from_sectors(sector)
     }
 
-    fn sector_from_cluster(&self, cluster: u32) -> u32 {
+    pub(crate) fn sector_from_cluster(&self, cluster: u32) -> u32 {
         self.first_data_sector + self.bpb.sectors_from_clusters(cluster - RESERVED_FAT_ENTRIES)
     }
 
+    /// Returns the size of a cluster in bytes.
+    #[must_use]
     pub fn cluster_size(&self) -> u32 {
         self.bpb.cluster_size()
     }
 
+    /// Returns the byte offset of the data region relative to the start of the volume.
+    ///
+    /// The data region starts with cluster 2, so the data of cluster `n` begins at
+    /// `data_offset_bytes() + (n - 2) * cluster_size()`. Together with `cluster_size` this allows computing
+    /// physical offsets of the clusters returned by `File::cluster_chain`.
+    #[must_use]
+    pub fn data_offset_bytes(&self) -> u64 {
+        self.offset_from_sector(self.first_data_sector)
+    }
+
+    /// Returns an iterator over the FAT chain starting at cluster `start`.
+    ///
+    /// The iterator yields `start` followed by every cluster linked to it until the end-of-chain marker. Unlike
+    /// `File::cluster_chain` it does not require an open file, so it can be used to inspect arbitrary chains, e.g.
+    /// when checking a volume for errors.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned by the iterator:
+    ///
+    /// * `Error::InvalidInput` will be returned if `start` is not a valid data cluster.
+    /// * `Error::CorruptedFileSystem` will be returned if the chain links to an invalid cluster or contains a cycle.
+    ///   A few clusters of the cycle may be yielded before it is detected.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    #[must_use]
+    pub fn walk_chain(&self, start: u32) -> ChainWalk<'_, IO, TP, OCC> {
+        ChainWalk {
+            fs: self,
+            cluster: Some(start),
+            first: true,
+            tortoise: start,
+            power: 1,
+            lam: 0,
+        }
+    }
+
+    pub(crate) fn sector_size(&self) -> u32 {
+        u32::from(self.bpb.bytes_per_sector)
+    }
+
+    // maximal number of entries in the fixed-size root directory of FAT12/FAT16 volumes (0 on FAT32)
+    pub(crate) fn root_dir_entries(&self) -> u32 {
+        u32::from(self.bpb.root_entries)
+    }
+
     pub(crate) fn offset_from_cluster(&self, cluster: u32) -> u64 {
         self.offset_from_sector(self.sector_from_cluster(cluster))
     }
@@ -475,7 +914,7 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
 
     fn fat_slice(&self) -> impl ReadWriteSeek<Error = Error<IO::Error>> + '_ {
         let io = FsIoAdapter { fs: self };
-        fat_slice(io, &self.bpb)
+        CachedFat::new(fat_slice(io, &self.bpb), &self.fat_cache)
     }
 
     pub(crate) fn cluster_iter(
@@ -502,6 +941,14 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         Ok(())
     }
 
+    // writes `len` zero bytes at `offset` relative to the start of the volume
+    pub(crate) async fn write_zeros_at(&self, offset: u64, len: u64) -> Result<(), Error<IO::Error>> {
+        let mut disk = self.disk.borrow_mut();
+        disk.seek(SeekFrom::Start(offset)).await?;
+        write_zeros(&mut *disk, len).await?;
+        Ok(())
+    }
+
     pub(crate) async fn alloc_cluster(&self, prev_cluster: Option<u32>, zero: bool) -> Result<u32, Error<IO::Error>> {
         trace!("alloc_cluster");
         let hint = self.fs_info.borrow().next_free_cluster;
@@ -510,9 +957,8 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
             alloc_cluster(&mut fat, self.fat_type, prev_cluster, hint, self.total_clusters).await?
         };
         if zero {
-            let mut disk = self.disk.borrow_mut();
-            disk.seek(SeekFrom::Start(self.offset_from_cluster(cluster))).await?;
-            write_zeros(&mut *disk, u64::from(self.cluster_size())).await?;
+            self.write_zeros_at(self.offset_from_cluster(cluster), u64::from(self.cluster_size()))
+                .await?;
         }
         let mut fs_info = self.fs_info.borrow_mut();
         fs_info.set_next_free_cluster(cluster + 1);
@@ -520,13 +966,88 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         Ok(cluster)
     }
 
+    /// Allocates `count` consecutive clusters linked after `prev_cluster` and returns the first one.
+    ///
+    /// Returns `None` if there is no free run long enough.
+    pub(crate) async fn alloc_contiguous_clusters(
+        &self,
+        prev_cluster: Option<u32>,
+        count: u32,
+    ) -> Result<Option<u32>, Error<IO::Error>> {
+        trace!("alloc_contiguous_clusters {}", count);
+        let hint = self.fs_info.borrow().next_free_cluster;
+        let first_cluster = {
+            let mut fat = self.fat_slice();
+            alloc_contiguous_clusters(&mut fat, self.fat_type, prev_cluster, hint, self.total_clusters, count).await?
+        };
+        if let Some(n) = first_cluster {
+            let mut fs_info = self.fs_info.borrow_mut();
+            fs_info.set_next_free_cluster(n + count);
+            fs_info.map_free_clusters(|free| free - count);
+        }
+        Ok(first_cluster)
+    }
+
+    /// Reads the beginning of a data cluster into `buf`, bypassing files and directories.
+    ///
+    /// `cluster` is a cluster number as stored in the FAT, the first data cluster is 2. The FAT is not read, so
+    /// any cluster can be read regardless of whether it is allocated. Meant for repair and forensics tools.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if `cluster` is not a valid data cluster number or `buf` is larger
+    ///   than a cluster.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn read_cluster(&self, cluster: u32, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+        trace!("read_cluster {}", cluster);
+        let offset = self.raw_cluster_offset(cluster, buf.len())?;
+        let mut disk = self.disk.borrow_mut();
+        disk.seek(SeekFrom::Start(offset)).await?;
+        disk.read_exact(buf).await?;
+        Ok(())
+    }
+
+    /// Writes `buf` to the beginning of a data cluster, bypassing files and directories.
+    ///
+    /// `cluster` is a cluster number as stored in the FAT, the first data cluster is 2. The FAT is neither read nor
+    /// modified, so writing to a cluster that belongs to a file or directory changes its content. Meant for repair
+    /// and forensics tools.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidInput` will be returned if `cluster` is not a valid data cluster number or `buf` is larger
+    ///   than a cluster.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn write_cluster(&self, cluster: u32, buf: &[u8]) -> Result<(), Error<IO::Error>> {
+        trace!("write_cluster {}", cluster);
+        self.check_writable()?;
+        let offset = self.raw_cluster_offset(cluster, buf.len())?;
+        let mut disk = self.disk.borrow_mut();
+        disk.seek(SeekFrom::Start(offset)).await?;
+        disk.write_all(buf).await?;
+        Ok(())
+    }
+
+    fn raw_cluster_offset(&self, cluster: u32, len: usize) -> Result<u64, Error<IO::Error>> {
+        let end_cluster = self.total_clusters + RESERVED_FAT_ENTRIES;
+        if !(RESERVED_FAT_ENTRIES..end_cluster).contains(&cluster) || len > self.cluster_size() as usize {
+            return Err(Error::InvalidInput);
+        }
+        Ok(self.offset_from_cluster(cluster))
+    }
+
     /// Returns status flags for this volume.
     ///
     /// # Errors
     ///
     /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn read_status_flags(&self) -> Result<FsStatusFlags, Error<IO::Error>> {
-        let bpb_status = self.bpb.status_flags();
+        let bpb_status = self.mount_status_flags.get();
         let fat_status = read_fat_flags(&mut self.fat_slice(), self.fat_type).await?;
         Ok(FsStatusFlags {
             dirty: bpb_status.dirty || fat_status.dirty,
@@ -536,8 +1057,12 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
 
     /// Returns filesystem statistics like number of total and free clusters.
     ///
-    /// For FAT32 volumes number of free clusters from the FS Information Sector is returned (may be incorrect).
-    /// For other FAT variants number is computed on the first call to this method and cached for later use.
+    /// For FAT32 volumes number of free clusters from the FS Information Sector is returned (may be incorrect) so no
+    /// scan of the FAT is needed. The FAT is scanned instead if the FS Information Sector does not contain a valid
+    /// number or the volume was not cleanly unmounted. For other FAT variants number is computed on the first call to
+    /// this method and cached for later use.
+    /// The cached number is updated whenever clusters are allocated or freed, so only the first call scans the FAT.
+    /// Use `recompute_stats` if the cached number is not trusted.
     ///
     /// # Errors
     ///
@@ -556,6 +1081,45 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         })
     }
 
+    /// Returns a summary of the volume layout read from the boot sector.
+    ///
+    /// The number of free clusters is obtained the same way as in `stats`.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn geometry(&self) -> Result<Geometry, Error<IO::Error>> {
+        let stats = self.stats().await?;
+        Ok(Geometry {
+            fat_type: self.fat_type,
+            bytes_per_sector: self.bpb.bytes_per_sector,
+            sectors_per_cluster: self.bpb.sectors_per_cluster,
+            total_clusters: stats.total_clusters,
+            free_clusters: stats.free_clusters,
+            reserved_sectors: self.bpb.reserved_sectors(),
+            fats: self.bpb.fats,
+            root_dir_sectors: self.bpb.root_dir_sectors(),
+        })
+    }
+
+    /// Returns filesystem statistics after counting free clusters in the FAT.
+    ///
+    /// Unlike `stats` this always scans the whole FAT, which takes time proportional to the volume size. The
+    /// result replaces the cached number of free clusters (and the value stored in the FS Information Sector on the
+    /// next flush).
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn recompute_stats(&self) -> Result<FileSystemStats, Error<IO::Error>> {
+        let free_clusters = self.recalc_free_clusters().await?;
+        Ok(FileSystemStats {
+            cluster_size: self.cluster_size(),
+            total_clusters: self.total_clusters,
+            free_clusters,
+        })
+    }
+
     /// Forces free clusters recalculation.
     async fn recalc_free_clusters(&self) -> Result<u32, Error<IO::Error>> {
         let mut fat = self.fat_slice();
@@ -575,13 +1139,118 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         self.flush().await
     }
 
+    /// Unmounts the filesystem and returns the underlying storage.
+    ///
+    /// Like `unmount` this updates the FS Information Sector if needed, clears the dirty flag and flushes the
+    /// storage before returning it, so the storage can be reused (e.g. to mount another partition) or powered down.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn unmount_into_inner(self) -> Result<IO, Error<IO::Error>> {
+        self.flush().await?;
+        let Self { disk, .. } = self;
+        Ok(disk.into_inner())
+    }
+
     /// Flushes any in memory state to the filesystem
     ///
-    /// Updates the FS Information Sector if needed and clears
-    /// the dirty flag.
+    /// Updates the FS Information Sector if needed, clears the dirty flag and flushes the underlying storage.
+    /// Unlike `unmount` the filesystem stays usable, so this can be used to periodically checkpoint the volume.
+    /// Calling it when nothing has changed only flushes the underlying storage.
+    ///
+    /// Note: open files must be flushed separately for their directory entries to be updated.
+    /// For a read-only filesystem only the underlying storage is flushed.
+    ///
+    /// # Errors
+    ///
+    /// `Error::Io` will be returned if the underlying storage object returned an I/O error.
     pub async fn flush(&self) -> Result<(), Error<IO::Error>> {
-        self.flush_fs_info().await?;
-        self.set_dirty_flag(false).await?;
+        if !self.options.read_only {
+            self.flush_fs_info().await?;
+            self.set_dirty_flag(false).await?;
+        }
+        self.disk.borrow_mut().flush().await?;
+        Ok(())
+    }
+
+    /// Copies the active FAT to all other FAT copies.
+    ///
+    /// While FAT mirroring is enabled (the default) every FAT modification is written to all copies, so they only
+    /// differ if the volume was modified by a tool that does not keep them in sync. If mirroring is disabled in the
+    /// FAT32 extended BPB flags only the active FAT is updated and this function brings the other copies up to date.
+    /// Sectors that already match are not rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::CorruptedFileSystem` will be returned if the active FAT number is not a valid FAT.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn sync_fats(&self) -> Result<(), Error<IO::Error>> {
+        trace!("FileSystem::sync_fats");
+        self.check_writable()?;
+        let active_fat = u64::from(self.bpb.active_fat());
+        let fats = u64::from(self.bpb.fats);
+        if active_fat >= fats {
+            error!("Active FAT {} does not exist, the volume has {} FATs", active_fat, fats);
+            return Err(Error::CorruptedFileSystem);
+        }
+        let fats_begin = self.bpb.bytes_from_sectors(self.bpb.reserved_sectors());
+        let fat_size = self.bpb.bytes_from_sectors(self.bpb.sectors_per_fat());
+        let mut disk = FsIoAdapter { fs: self };
+        let mut active = [0_u8; 512];
+        let mut mirror = [0_u8; 512];
+        let mut offset = 0;
+        while offset < fat_size {
+            // safe cast: the length is limited by the buffer size
+            #[allow(clippy::cast_possible_truncation)]
+            let len = cmp::min(active.len() as u64, fat_size - offset) as usize;
+            disk.seek(SeekFrom::Start(fats_begin + active_fat * fat_size + offset))
+                .await?;
+            disk.read_exact(&mut active[..len]).await?;
+            for i in (0..fats).filter(|i| *i != active_fat) {
+                let pos = fats_begin + i * fat_size + offset;
+                disk.seek(SeekFrom::Start(pos)).await?;
+                disk.read_exact(&mut mirror[..len]).await?;
+                if active[..len] != mirror[..len] {
+                    disk.seek(SeekFrom::Start(pos)).await?;
+                    disk.write_all(&active[..len]).await?;
+                }
+            }
+            offset += len as u64;
+        }
+        disk.flush().await?;
+        Ok(())
+    }
+
+    /// Writes a copy of the whole volume to `out`.
+    ///
+    /// Every sector from the boot sector up to the last data sector is copied, like `dd` would do. The filesystem
+    /// is flushed first, so the copy contains all modifications made so far and can be mounted on its own.
+    ///
+    /// # Errors
+    ///
+    /// `ExportError::Source` will be returned if flushing or reading the filesystem failed and `ExportError::Sink`
+    /// will be returned if `out` returned an error.
+    pub async fn export_image<W: Write>(&self, out: &mut W) -> Result<(), ExportError<IO::Error, W::Error>> {
+        trace!("FileSystem::export_image");
+        self.flush().await?;
+        let total_size = self.bpb.bytes_from_sectors(self.bpb.total_sectors());
+        let mut disk = FsIoAdapter { fs: self };
+        disk.seek(SeekFrom::Start(0)).await.map_err(Error::Io)?;
+        let mut buf = [0_u8; 512];
+        let mut offset = 0;
+        while offset < total_size {
+            // safe cast: the length is limited by the buffer size
+            #[allow(clippy::cast_possible_truncation)]
+            let len = cmp::min(buf.len() as u64, total_size - offset) as usize;
+            disk.read_exact(&mut buf[..len]).await.map_err(Error::from)?;
+            out.write_all(&buf[..len]).await.map_err(ExportError::Sink)?;
+            offset += len as u64;
+        }
+        out.flush().await.map_err(ExportError::Sink)?;
         Ok(())
     }
 
@@ -599,10 +1268,10 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
 
     pub(crate) async fn set_dirty_flag(&self, dirty: bool) -> Result<(), IO::Error> {
         // Do not overwrite flags read from BPB on mount
-        let mut flags = self.bpb.status_flags();
+        let mut flags = self.mount_status_flags.get();
         flags.dirty |= dirty;
         // Check if flags has changed
-        let current_flags = self.current_status_flags.get();
+        let current_flags = self.current_status_flags.0.get();
         if flags == current_flags {
             // Nothing to do
             return Ok(());
@@ -619,7 +1288,7 @@ impl<IO: ReadWriteSeek, TP, OCC> FileSystem<IO, TP, OCC> {
         disk.seek(io::SeekFrom::Start(offset)).await?;
         disk.write_u8(encoded).await?;
         disk.flush().await?;
-        self.current_status_flags.set(flags);
+        self.current_status_flags.0.set(flags);
         Ok(())
     }
 
@@ -651,7 +1320,8 @@ impl<IO: ReadWriteSeek, TP, OCC: OemCpConverter> FileSystem<IO, TP, OCC> {
     #[cfg(feature = "alloc")]
     pub fn volume_label(&self) -> String {
         // Decode volume label from OEM codepage
-        let volume_label_iter = self.volume_label_as_bytes().iter().copied();
+        let volume_label = self.volume_label_as_bytes();
+        let volume_label_iter = volume_label.iter().copied();
         let char_iter = volume_label_iter.map(|c| self.options.oem_cp_converter.decode(c));
         // Build string from character iterator
         char_iter.collect()
@@ -698,19 +1368,277 @@ impl<IO: ReadWriteSeek, TP: TimeProvider, OCC: OemCpConverter> FileSystem<IO, TP
         let entry_opt = self.root_dir().find_volume_entry().await?;
         Ok(entry_opt.map(|e| *e.raw_short_name()))
     }
+
+    /// Changes the volume label.
+    ///
+    /// The label is stored in the volume label entry of the root directory, which is created if it does not exist,
+    /// and in the BPB of the Boot Sector (and its backup on FAT32). It is encoded in the OEM codepage and ASCII
+    /// lowercase letters are converted to uppercase. An empty label removes the root directory entry and sets the
+    /// BPB label to `NO NAME`.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::InvalidFileNameLength` will be returned if the encoded label is longer than 11 bytes.
+    /// * `Error::UnsupportedFileNameCharacter` will be returned if the label contains a character that is not
+    ///   allowed in a volume label or cannot be encoded in the OEM codepage.
+    /// * `Error::NotEnoughSpace` will be returned if there is no room for a new entry in the root directory.
+    /// * `Error::ReadOnly` will be returned if the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    pub async fn set_volume_label(&mut self, label: &str) -> Result<(), Error<IO::Error>> {
+        trace!("FileSystem::set_volume_label");
+        self.check_writable()?;
+        let mut encoded = [SFN_PADDING; SFN_SIZE];
+        let mut len = 0;
+        for c in label.chars() {
+            if len == SFN_SIZE {
+                return Err(Error::InvalidFileNameLength);
+            }
+            encoded[len] = encode_volume_label_char(c.to_ascii_uppercase(), &self.options.oem_cp_converter)?;
+            len += 1;
+        }
+        self.root_dir().write_volume_entry(encoded).await?;
+
+        let bpb_label = if len == 0 { *b"NO NAME    " } else { encoded };
+        if self.bpb.ext_sig == 0x29 {
+            // Note: only the label field is written to avoid rewriting entire boot-sector which could be dangerous
+            let offset = if self.fat_type() == FatType::Fat32 {
+                0x047
+            } else {
+                0x02B
+            };
+            // set the dirty flag upfront so the adapter does not move the disk position between writes
+            self.set_dirty_flag(true).await?;
+            let mut disk = FsIoAdapter { fs: self };
+            disk.seek(io::SeekFrom::Start(offset)).await?;
+            disk.write_all(&bpb_label).await?;
+            if self.fat_type() == FatType::Fat32 && self.bpb.backup_boot_sector != 0 {
+                let backup_offset = self.offset_from_sector(u32::from(self.bpb.backup_boot_sector));
+                disk.seek(io::SeekFrom::Start(backup_offset + offset)).await?;
+                disk.write_all(&bpb_label).await?;
+            }
+            disk.flush().await?;
+        }
+        self.bpb.volume_label = bpb_label;
+        Ok(())
+    }
+
+    /// Checks the consistency of the filesystem and optionally repairs it.
+    ///
+    /// The cluster chain of every directory entry is walked and compared against the allocation table. The check
+    /// reports chains that are cross-linked (share clusters with another chain or loop), chains pointing outside of
+    /// the volume, clusters marked as used that do not belong to any entry (lost clusters) and files whose size
+    /// exceeds the length of their cluster chain. Like other fsck tools, the check accepts cluster chains that are
+    /// longer than the file size, e.g. clusters preallocated by `File::allocate_contiguous`.
+    ///
+    /// If `repair` is true, lost clusters are freed, cross-linked and invalid chains are truncated before the first
+    /// offending cluster (files starting with such a cluster become empty) and the volume dirty flag is cleared.
+    /// Size mismatches are only reported. Make sure there are no open files when repairing the filesystem.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::ReadOnly` will be returned if `repair` is true and the filesystem is mounted read-only.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    #[cfg(feature = "alloc")]
+    pub async fn check(&self, repair: bool) -> Result<CheckReport, Error<IO::Error>> {
+        trace!("FileSystem::check");
+        if repair {
+            self.check_writable()?;
+        }
+        let mut report = CheckReport {
+            repaired: repair,
+            ..CheckReport::default()
+        };
+        let end_cluster = self.total_clusters + RESERVED_FAT_ENTRIES;
+        let mut used = vec![0_u32; end_cluster.div_ceil(32) as usize];
+
+        if self.fat_type == FatType::Fat32 {
+            self.check_chain(self.bpb.root_dir_first_cluster, &mut used, &mut report, repair)
+                .await?;
+        }
+        let mut dirs = Vec::new();
+        dirs.push(self.root_dir());
+        while let Some(dir) = dirs.pop() {
+            let mut iter = dir.iter();
+            while let Some(r) = iter.next().await {
+                let e = r?;
+                let name = e.short_file_name_as_bytes();
+                // ignore special entries "." and ".."
+                if name == b"." || name == b".." {
+                    continue;
+                }
+                let chain_len = match e.first_cluster() {
+                    Some(n) => self.check_chain(n, &mut used, &mut report, repair).await?,
+                    None => 0,
+                };
+                if e.first_cluster().is_some() && chain_len == 0 {
+                    // the first cluster is already in use or invalid - the chain cannot be followed
+                    if repair && e.is_file() {
+                        let mut editor = e.editor();
+                        editor.set_first_cluster(None, self.fat_type);
+                        editor.set_size(0);
+                        editor.flush(self).await?;
+                    }
+                } else if e.is_dir() {
+                    if chain_len > 0 {
+                        dirs.push(e.to_dir());
+                    }
+                } else if self.clusters_from_bytes(e.len()) > chain_len {
+                    // a chain longer than the size is accepted, clusters can be allocated up front - see
+                    // `File::allocate_contiguous`
+                    warn!("File size exceeds its cluster chain: {:?}", e);
+                    report.size_mismatches += 1;
+                }
+            }
+        }
+
+        let mut fat = self.fat_slice();
+        for cluster in RESERVED_FAT_ENTRIES..end_cluster {
+            if used[(cluster / 32) as usize] & (1 << (cluster % 32)) != 0 {
+                continue;
+            }
+            if is_cluster_allocated(&mut fat, self.fat_type, cluster).await? {
+                trace!("lost cluster {}", cluster);
+                report.lost_clusters += 1;
+                if repair {
+                    free_cluster(&mut fat, self.fat_type, cluster).await?;
+                }
+            }
+        }
+
+        if repair {
+            self.recalc_free_clusters().await?;
+            let flags = FsStatusFlags {
+                dirty: false,
+                io_error: self.mount_status_flags.get().io_error,
+            };
+            write_fat_flags(&mut fat, self.fat_type, flags).await?;
+            self.mount_status_flags.set(flags);
+            self.flush().await?;
+        }
+        Ok(report)
+    }
+
+    /// Marks the cluster chain starting at `first_cluster` as used and returns the number of valid clusters in it.
+    #[cfg(feature = "alloc")]
+    async fn check_chain(
+        &self,
+        first_cluster: u32,
+        used: &mut [u32],
+        report: &mut CheckReport,
+        repair: bool,
+    ) -> Result<u32, Error<IO::Error>> {
+        let end_cluster = self.total_clusters + RESERVED_FAT_ENTRIES;
+        let mut prev_cluster = None;
+        let mut cluster = first_cluster;
+        let mut len = 0;
+        loop {
+            let valid = (RESERVED_FAT_ENTRIES..end_cluster).contains(&cluster);
+            if valid && used[(cluster / 32) as usize] & (1 << (cluster % 32)) == 0 {
+                used[(cluster / 32) as usize] |= 1 << (cluster % 32);
+                len += 1;
+                match self.cluster_iter(cluster).next().await {
+                    Some(r) => {
+                        prev_cluster = Some(cluster);
+                        cluster = r?;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+            if valid {
+                warn!("Cross-linked cluster {}", cluster);
+                report.cross_linked_chains += 1;
+            } else {
+                warn!("Invalid cluster {} in chain", cluster);
+                report.invalid_chains += 1;
+            }
+            if repair {
+                if let Some(n) = prev_cluster {
+                    mark_end_of_chain(&mut self.fat_slice(), self.fat_type, n).await?;
+                }
+            }
+            break;
+        }
+        Ok(len)
+    }
+}
+
+/// An iterator over the clusters of a FAT chain.
+///
+/// This struct is created by the `walk_chain` method on `FileSystem`.
+pub struct ChainWalk<'a, IO: ReadWriteSeek, TP, OCC> {
+    fs: &'a FileSystem<IO, TP, OCC>,
+    // cluster returned by the next call to `next`
+    cluster: Option<u32>,
+    first: bool,
+    // state of Brent's cycle detection - `tortoise` is compared against every returned cluster and moved forward
+    // whenever `lam` reaches `power`
+    tortoise: u32,
+    power: u32,
+    lam: u32,
+}
+
+impl<IO: ReadWriteSeek, TP, OCC> ChainWalk<'_, IO, TP, OCC> {
+    pub async fn next(&mut self) -> Option<Result<u32, Error<IO::Error>>> {
+        let cluster = self.cluster.take()?;
+        let end_cluster = self.fs.total_clusters + RESERVED_FAT_ENTRIES;
+        if !(RESERVED_FAT_ENTRIES..end_cluster).contains(&cluster) {
+            if self.first {
+                return Some(Err(Error::InvalidInput));
+            }
+            error!("Invalid cluster {} in chain", cluster);
+            return Some(Err(Error::CorruptedFileSystem));
+        }
+        if self.first {
+            self.first = false;
+        } else {
+            if cluster == self.tortoise {
+                error!("Cycle detected in cluster chain at cluster {}", cluster);
+                return Some(Err(Error::CorruptedFileSystem));
+            }
+            self.lam += 1;
+            if self.lam == self.power {
+                self.tortoise = cluster;
+                self.power = self.power.saturating_mul(2);
+                self.lam = 0;
+            }
+        }
+        match self.fs.cluster_iter(cluster).next().await.transpose() {
+            Ok(next) => self.cluster = next,
+            Err(err) => return Some(Err(err)),
+        }
+        Some(Ok(cluster))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub async fn collect(&mut self) -> Vec<Result<u32, Error<IO::Error>>> {
+        let mut v = Vec::new();
+        while let Some(i) = self.next().await {
+            v.push(i);
+        }
+        v
+    }
 }
 
-/// `Drop` implementation tries to unmount the filesystem when dropping.
-impl<IO: Read + Write + Seek, TP, OCC> Drop for FileSystem<IO, TP, OCC> {
+// Status flags currently stored on the volume - kept in a separate type so the filesystem itself has no `Drop`
+// implementation and can be taken apart in `FileSystem::unmount_into_inner`
+struct CurrentStatusFlags(Cell<FsStatusFlags>);
+
+/// `Drop` implementation warns if the filesystem is dropped without unmounting.
+impl Drop for CurrentStatusFlags {
     fn drop(&mut self) {
-        if self.current_status_flags.get().dirty {
+        if self.0.get().dirty {
             warn!("Dropping FileSytem without unmount");
         }
     }
 }
 
 pub(crate) struct FsIoAdapter<'a, IO: ReadWriteSeek, TP, OCC> {
-    fs: &'a FileSystem<IO, TP, OCC>,
+    pub(crate) fs: &'a FileSystem<IO, TP, OCC>,
 }
 
 impl<IO: ReadWriteSeek, TP, OCC> IoBase for FsIoAdapter<'_, IO, TP, OCC> {
@@ -881,6 +1809,23 @@ impl<B, S: IoBase> Seek for DiskSlice<B, S> {
     }
 }
 
+/// Returns the longest prefix of `bytes` that is valid UTF-8.
+fn utf8_prefix(bytes: &[u8]) -> &str {
+    match str::from_utf8(bytes) {
+        Ok(s) => s,
+        // `valid_up_to` guarantees the prefix is valid
+        Err(e) => str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+    }
+}
+
+fn encode_volume_label_char<E, OCC: OemCpConverter>(c: char, oem_cp_converter: &OCC) -> Result<u8, Error<E>> {
+    // characters not allowed in short names are not allowed in volume labels either (space is allowed)
+    if c < ' ' || "\"*+,./:;<=>?[\\]|\u{7F}".contains(c) {
+        return Err(Error::UnsupportedFileNameCharacter);
+    }
+    oem_cp_converter.encode(c).ok_or(Error::UnsupportedFileNameCharacter)
+}
+
 /// An OEM code page encoder/decoder.
 ///
 /// Provides a custom implementation for a short name encoding/decoding.
@@ -931,7 +1876,7 @@ impl OemCpConverter for LossyOemCpConverter {
     }
 }
 
-async fn write_zeros<IO: ReadWriteSeek>(disk: &mut IO, mut len: u64) -> Result<(), IO::Error> {
+pub(crate) async fn write_zeros<IO: ReadWriteSeek>(disk: &mut IO, mut len: u64) -> Result<(), IO::Error> {
     const ZEROS: [u8; 512] = [0_u8; 512];
     while len > 0 {
         let write_size = cmp::min(len, ZEROS.len() as u64) as usize;
@@ -963,8 +1908,10 @@ pub struct FormatVolumeOptions {
     pub(crate) bytes_per_sector: Option<u16>,
     pub(crate) total_sectors: Option<u32>,
     pub(crate) bytes_per_cluster: Option<u32>,
+    pub(crate) sectors_per_cluster: Option<u8>,
+    pub(crate) reserved_sectors: Option<u16>,
     pub(crate) fat_type: Option<FatType>,
-    pub(crate) max_root_dir_entries: Option<u16>,
+    pub(crate) root_dir_entries: Option<u16>,
     pub(crate) fats: Option<u8>,
     pub(crate) media: Option<u8>,
     pub(crate) sectors_per_track: Option<u16>,
@@ -972,6 +1919,7 @@ pub struct FormatVolumeOptions {
     pub(crate) drive_num: Option<u8>,
     pub(crate) volume_id: Option<u32>,
     pub(crate) volume_label: Option<[u8; SFN_SIZE]>,
+    pub(crate) oem_name: Option<[u8; 8]>,
 }
 
 impl FormatVolumeOptions {
@@ -1003,12 +1951,39 @@ impl FormatVolumeOptions {
         self
     }
 
+    /// Set size of cluster in sectors
+    ///
+    /// Alternative to `bytes_per_cluster`. The value must be a power of two. If both options are specified they must
+    /// describe the same cluster size.
+    /// If option is not specified optimal cluster size is selected based on partition size.
+    /// `format_volume` returns `Error::InvalidInput` if the value is invalid or the volume cannot be divided into a
+    /// proper number of clusters.
+    #[must_use]
+    pub fn sectors_per_cluster(mut self, sectors_per_cluster: u8) -> Self {
+        self.sectors_per_cluster = Some(sectors_per_cluster);
+        self
+    }
+
+    /// Set number of reserved sectors preceding the first File Allocation Table
+    ///
+    /// Reserved sectors hold the boot sector and on FAT32 volumes the FS Information Sector and the backup boot sector
+    /// (sector 6), so FAT32 needs at least 7 of them. Some tools use a bigger value to align the FAT and data region.
+    /// Default is `1` for FAT12/FAT16 and `8` for FAT32.
+    /// `format_volume` returns `Error::InvalidInput` if the value is too small for the selected FAT type.
+    #[must_use]
+    pub fn reserved_sectors(mut self, reserved_sectors: u16) -> Self {
+        self.reserved_sectors = Some(reserved_sectors);
+        self
+    }
+
     /// Set File Allocation Table type
     ///
-    /// Option allows to override File Allocation Table (FAT) entry size.
+    /// Option allows to override File Allocation Table (FAT) entry size. If it is set the volume is formatted with
+    /// the selected FAT type regardless of its size, otherwise the type is selected automatically.
     /// It is unrecommended to set this option unless you know what you are doing.
-    /// Note: FAT type is determined from total number of clusters. Changing this option can cause formatting to fail
-    /// if the volume cannot be divided into proper number of clusters for selected FAT type.
+    /// Note: FAT type is determined from total number of clusters when mounting. `format_volume` returns
+    /// `Error::InvalidInput` if the volume cannot be divided into proper number of clusters for selected FAT type,
+    /// e.g. FAT32 requires at least 65525 clusters so it needs at least about 33 MB with 512 byte clusters.
     #[must_use]
     pub fn fat_type(mut self, fat_type: FatType) -> Self {
         self.fat_type = Some(fat_type);
@@ -1044,13 +2019,22 @@ impl FormatVolumeOptions {
 
     /// Set maximal numer of entries in root directory for FAT12/FAT16 volumes
     ///
-    /// Total root directory size should be dividable by sectors size so keep it a multiple of 16 (for default sector
-    /// size).
-    /// Note: this limit is not used on FAT32 volumes.
+    /// This is the same as `root_dir_entries`.
+    #[must_use]
+    pub fn max_root_dir_entries(self, max_root_dir_entries: u16) -> Self {
+        self.root_dir_entries(max_root_dir_entries)
+    }
+
+    /// Set number of entries in the fixed-size root directory of FAT12/FAT16 volumes
+    ///
+    /// The root directory must fill whole sectors, so the value has to be a multiple of the number of directory
+    /// entries per sector (16 for 512 byte sectors), otherwise formatting fails with `Error::InvalidInput`. Some
+    /// boot loaders expect a specific value, e.g. `224` for 1.44 MB floppy images.
+    /// Note: the root directory of FAT32 volumes is a cluster chain, so this option is ignored with a warning.
     /// Default is `512`.
     #[must_use]
-    pub fn max_root_dir_entries(mut self, max_root_dir_entries: u16) -> Self {
-        self.max_root_dir_entries = Some(max_root_dir_entries);
+    pub fn root_dir_entries(mut self, root_dir_entries: u16) -> Self {
+        self.root_dir_entries = Some(root_dir_entries);
         self
     }
 
@@ -1122,6 +2106,16 @@ impl FormatVolumeOptions {
         self.volume_label = Some(volume_label);
         self
     }
+
+    /// Set OEM name stored in the Boot Sector
+    ///
+    /// Names used by other filesystems (`EXFAT   `, `NTFS    `) are rejected by `format_volume`.
+    /// Default is `MSWIN4.1`.
+    #[must_use]
+    pub fn oem_name(mut self, oem_name: [u8; 8]) -> Self {
+        self.oem_name = Some(oem_name);
+        self
+    }
 }
 
 /// Create FAT filesystem on a disk or partition (format a volume)
@@ -1180,10 +2174,11 @@ pub async fn format_volume<S: ReadWriteSeek>(
 
     let bpb = &boot.bpb;
     if bpb.is_fat32() {
-        // FSInfo sector
+        // FSInfo sector - all clusters except the first root directory cluster are free so the free cluster count
+        // is known up front and mounting the new volume does not require a scan of the FAT
         let fs_info_sector = FsInfoSector {
-            free_cluster_count: None,
-            next_free_cluster: None,
+            free_cluster_count: Some(bpb.total_clusters() - 1),
+            next_free_cluster: Some(bpb.root_dir_first_cluster + 1),
             dirty: false,
         };
         storage
@@ -65,6 +65,8 @@ extern crate alloc;
 // MUST be the first module listed
 mod fmt;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod boot_sector;
 mod dir;
 mod dir_entry;
@@ -72,12 +74,17 @@ mod error;
 mod file;
 mod fs;
 mod io;
+mod oem;
+#[cfg(feature = "partitions")]
+mod partition;
 mod table;
 mod time;
 
+pub use crate::boot_sector::BootSector;
 pub use crate::dir::*;
 pub use crate::dir_entry::*;
 pub use crate::error::*;
 pub use crate::file::*;
 pub use crate::fs::*;
+pub use crate::oem::*;
 pub use crate::time::*;
@@ -0,0 +1,206 @@
+//! OEM code page converters for short file names.
+
+use crate::fs::OemCpConverter;
+
+const REPLACEMENT_CHAR: char = '\u{FFFD}';
+
+fn decode_with_table(table: &[char; 128], oem_char: u8) -> char {
+    if oem_char <= 0x7F {
+        char::from(oem_char)
+    } else {
+        table[usize::from(oem_char - 0x80)]
+    }
+}
+
+fn encode_with_table(table: &[char; 128], uni_char: char) -> Option<u8> {
+    if uni_char <= '\x7F' {
+        Some(uni_char as u8) // safe cast: value is in range [0, 0x7F]
+    } else if uni_char == REPLACEMENT_CHAR {
+        // undefined bytes are decoded as the replacement character - it cannot be encoded
+        None
+    } else {
+        // safe cast: table has 128 entries
+        #[allow(clippy::cast_possible_truncation)]
+        table.iter().position(|&c| c == uni_char).map(|i| 0x80 + i as u8)
+    }
+}
+
+// Characters for bytes 0x80-0xFF
+const CP437_TABLE: [char; 128] = [
+    '\u{00C7}', '\u{00FC}', '\u{00E9}', '\u{00E2}', '\u{00E4}', '\u{00E0}', '\u{00E5}', '\u{00E7}', '\u{00EA}',
+    '\u{00EB}', '\u{00E8}', '\u{00EF}', '\u{00EE}', '\u{00EC}', '\u{00C4}', '\u{00C5}', '\u{00C9}', '\u{00E6}',
+    '\u{00C6}', '\u{00F4}', '\u{00F6}', '\u{00F2}', '\u{00FB}', '\u{00F9}', '\u{00FF}', '\u{00D6}', '\u{00DC}',
+    '\u{00A2}', '\u{00A3}', '\u{00A5}', '\u{20A7}', '\u{0192}', '\u{00E1}', '\u{00ED}', '\u{00F3}', '\u{00FA}',
+    '\u{00F1}', '\u{00D1}', '\u{00AA}', '\u{00BA}', '\u{00BF}', '\u{2310}', '\u{00AC}', '\u{00BD}', '\u{00BC}',
+    '\u{00A1}', '\u{00AB}', '\u{00BB}', '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}',
+    '\u{2562}', '\u{2556}', '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{255C}', '\u{255B}',
+    '\u{2510}', '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{255E}', '\u{255F}',
+    '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{2567}', '\u{2568}',
+    '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256B}', '\u{256A}', '\u{2518}',
+    '\u{250C}', '\u{2588}', '\u{2584}', '\u{258C}', '\u{2590}', '\u{2580}', '\u{03B1}', '\u{00DF}', '\u{0393}',
+    '\u{03C0}', '\u{03A3}', '\u{03C3}', '\u{00B5}', '\u{03C4}', '\u{03A6}', '\u{0398}', '\u{03A9}', '\u{03B4}',
+    '\u{221E}', '\u{03C6}', '\u{03B5}', '\u{2229}', '\u{2261}', '\u{00B1}', '\u{2265}', '\u{2264}', '\u{2320}',
+    '\u{2321}', '\u{00F7}', '\u{2248}', '\u{00B0}', '\u{2219}', '\u{00B7}', '\u{221A}', '\u{207F}', '\u{00B2}',
+    '\u{25A0}', '\u{00A0}',
+];
+
+/// `OemCpConverter` implementation for IBM PC code page 437 (OEM United States).
+///
+/// Select it with `FsOptions::oem_cp_converter` to decode and encode non-ASCII characters in short names.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cp437OemCpConverter {
+    _dummy: (),
+}
+
+impl Cp437OemCpConverter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { _dummy: () }
+    }
+}
+
+impl OemCpConverter for Cp437OemCpConverter {
+    fn decode(&self, oem_char: u8) -> char {
+        decode_with_table(&CP437_TABLE, oem_char)
+    }
+
+    fn encode(&self, uni_char: char) -> Option<u8> {
+        encode_with_table(&CP437_TABLE, uni_char)
+    }
+}
+
+// Characters for bytes 0x80-0xFF
+const CP850_TABLE: [char; 128] = [
+    '\u{00C7}', '\u{00FC}', '\u{00E9}', '\u{00E2}', '\u{00E4}', '\u{00E0}', '\u{00E5}', '\u{00E7}', '\u{00EA}',
+    '\u{00EB}', '\u{00E8}', '\u{00EF}', '\u{00EE}', '\u{00EC}', '\u{00C4}', '\u{00C5}', '\u{00C9}', '\u{00E6}',
+    '\u{00C6}', '\u{00F4}', '\u{00F6}', '\u{00F2}', '\u{00FB}', '\u{00F9}', '\u{00FF}', '\u{00D6}', '\u{00DC}',
+    '\u{00F8}', '\u{00A3}', '\u{00D8}', '\u{00D7}', '\u{0192}', '\u{00E1}', '\u{00ED}', '\u{00F3}', '\u{00FA}',
+    '\u{00F1}', '\u{00D1}', '\u{00AA}', '\u{00BA}', '\u{00BF}', '\u{00AE}', '\u{00AC}', '\u{00BD}', '\u{00BC}',
+    '\u{00A1}', '\u{00AB}', '\u{00BB}', '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{00C1}',
+    '\u{00C2}', '\u{00C0}', '\u{00A9}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{00A2}', '\u{00A5}',
+    '\u{2510}', '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{00E3}', '\u{00C3}',
+    '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{00A4}', '\u{00F0}',
+    '\u{00D0}', '\u{00CA}', '\u{00CB}', '\u{00C8}', '\u{0131}', '\u{00CD}', '\u{00CE}', '\u{00CF}', '\u{2518}',
+    '\u{250C}', '\u{2588}', '\u{2584}', '\u{00A6}', '\u{00CC}', '\u{2580}', '\u{00D3}', '\u{00DF}', '\u{00D4}',
+    '\u{00D2}', '\u{00F5}', '\u{00D5}', '\u{00B5}', '\u{00FE}', '\u{00DE}', '\u{00DA}', '\u{00DB}', '\u{00D9}',
+    '\u{00FD}', '\u{00DD}', '\u{00AF}', '\u{00B4}', '\u{00AD}', '\u{00B1}', '\u{2017}', '\u{00BE}', '\u{00B6}',
+    '\u{00A7}', '\u{00F7}', '\u{00B8}', '\u{00B0}', '\u{00A8}', '\u{00B7}', '\u{00B9}', '\u{00B3}', '\u{00B2}',
+    '\u{25A0}', '\u{00A0}',
+];
+
+/// `OemCpConverter` implementation for IBM PC code page 850 (OEM Multilingual Latin 1).
+///
+/// Select it with `FsOptions::oem_cp_converter` to decode and encode non-ASCII characters in short names.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cp850OemCpConverter {
+    _dummy: (),
+}
+
+impl Cp850OemCpConverter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { _dummy: () }
+    }
+}
+
+impl OemCpConverter for Cp850OemCpConverter {
+    fn decode(&self, oem_char: u8) -> char {
+        decode_with_table(&CP850_TABLE, oem_char)
+    }
+
+    fn encode(&self, uni_char: char) -> Option<u8> {
+        encode_with_table(&CP850_TABLE, uni_char)
+    }
+}
+
+// Characters for bytes 0x80-0xFF
+const CP1252_TABLE: [char; 128] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}', '\u{02C6}',
+    '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{017D}', '\u{FFFD}', '\u{FFFD}', '\u{2018}',
+    '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}', '\u{02DC}', '\u{2122}', '\u{0161}',
+    '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}', '\u{00A0}', '\u{00A1}', '\u{00A2}', '\u{00A3}',
+    '\u{00A4}', '\u{00A5}', '\u{00A6}', '\u{00A7}', '\u{00A8}', '\u{00A9}', '\u{00AA}', '\u{00AB}', '\u{00AC}',
+    '\u{00AD}', '\u{00AE}', '\u{00AF}', '\u{00B0}', '\u{00B1}', '\u{00B2}', '\u{00B3}', '\u{00B4}', '\u{00B5}',
+    '\u{00B6}', '\u{00B7}', '\u{00B8}', '\u{00B9}', '\u{00BA}', '\u{00BB}', '\u{00BC}', '\u{00BD}', '\u{00BE}',
+    '\u{00BF}', '\u{00C0}', '\u{00C1}', '\u{00C2}', '\u{00C3}', '\u{00C4}', '\u{00C5}', '\u{00C6}', '\u{00C7}',
+    '\u{00C8}', '\u{00C9}', '\u{00CA}', '\u{00CB}', '\u{00CC}', '\u{00CD}', '\u{00CE}', '\u{00CF}', '\u{00D0}',
+    '\u{00D1}', '\u{00D2}', '\u{00D3}', '\u{00D4}', '\u{00D5}', '\u{00D6}', '\u{00D7}', '\u{00D8}', '\u{00D9}',
+    '\u{00DA}', '\u{00DB}', '\u{00DC}', '\u{00DD}', '\u{00DE}', '\u{00DF}', '\u{00E0}', '\u{00E1}', '\u{00E2}',
+    '\u{00E3}', '\u{00E4}', '\u{00E5}', '\u{00E6}', '\u{00E7}', '\u{00E8}', '\u{00E9}', '\u{00EA}', '\u{00EB}',
+    '\u{00EC}', '\u{00ED}', '\u{00EE}', '\u{00EF}', '\u{00F0}', '\u{00F1}', '\u{00F2}', '\u{00F3}', '\u{00F4}',
+    '\u{00F5}', '\u{00F6}', '\u{00F7}', '\u{00F8}', '\u{00F9}', '\u{00FA}', '\u{00FB}', '\u{00FC}', '\u{00FD}',
+    '\u{00FE}', '\u{00FF}',
+];
+
+/// `OemCpConverter` implementation for Windows code page 1252 (Western European).
+///
+/// Select it with `FsOptions::oem_cp_converter` to decode and encode non-ASCII characters in short names.
+///
+/// Bytes that are not defined in this code page (0x81, 0x8D, 0x8F, 0x90 and 0x9D) are decoded as the replacement
+/// character (U+FFFD).
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cp1252OemCpConverter {
+    _dummy: (),
+}
+
+impl Cp1252OemCpConverter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { _dummy: () }
+    }
+}
+
+impl OemCpConverter for Cp1252OemCpConverter {
+    fn decode(&self, oem_char: u8) -> char {
+        decode_with_table(&CP1252_TABLE, oem_char)
+    }
+
+    fn encode(&self, uni_char: char) -> Option<u8> {
+        encode_with_table(&CP1252_TABLE, uni_char)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip<OCC: OemCpConverter>(oem_cp_converter: &OCC) {
+        for b in 0..=0xFF_u8 {
+            let c = oem_cp_converter.decode(b);
+            if c != REPLACEMENT_CHAR {
+                assert_eq!(oem_cp_converter.encode(c), Some(b), "byte {:#X}", b);
+            }
+        }
+    }
+
+    #[test]
+    fn cp437_round_trip() {
+        let conv = Cp437OemCpConverter::new();
+        assert_eq!(conv.decode(0x82), '\u{E9}');
+        assert_eq!(conv.decode(0xE1), '\u{DF}');
+        assert_round_trip(&conv);
+    }
+
+    #[test]
+    fn cp850_round_trip() {
+        let conv = Cp850OemCpConverter::new();
+        assert_eq!(conv.decode(0x90), '\u{C9}');
+        assert_eq!(conv.decode(0xD5), '\u{131}');
+        assert_round_trip(&conv);
+    }
+
+    #[test]
+    fn cp1252_round_trip() {
+        let conv = Cp1252OemCpConverter::new();
+        assert_eq!(conv.decode(0xE9), '\u{E9}');
+        assert_eq!(conv.encode('\u{E9}'), Some(0xE9));
+        assert_eq!(conv.decode(0x80), '\u{20AC}');
+        assert_eq!(conv.decode(0x81), REPLACEMENT_CHAR);
+        assert_eq!(conv.encode(REPLACEMENT_CHAR), None);
+        assert_eq!(conv.encode('\u{3042}'), None);
+        assert_round_trip(&conv);
+    }
+}
@@ -0,0 +1,87 @@
+//! Mounting volumes stored in a partition of a MBR or GPT partitioned device.
+//!
+//! Enabled by the `partitions` feature.
+
+use block_device_adapters::gpt::{self, GptError};
+use block_device_adapters::mbr::{self, MbrError};
+use block_device_adapters::{StreamSlice, StreamSliceError};
+
+use crate::error::Error;
+use crate::fs::{FileSystem, FsOptions, ReadWriteSeek};
+
+impl<T: ReadWriteSeek, TP, OCC> FileSystem<StreamSlice<T>, TP, OCC> {
+    /// Mounts the first FAT partition of a partitioned device.
+    ///
+    /// The Master Boot Record of `device` is read first. If it is a protective MBR the GUID Partition Table is
+    /// searched for the first basic data partition, otherwise the first primary partition with a FAT partition type
+    /// (see `block_device_adapters::mbr::FAT_PARTITION_TYPES`) is used. The partition is wrapped in a `StreamSlice`
+    /// and mounted like in `FileSystem::new`.
+    ///
+    /// # Errors
+    ///
+    /// Errors that can be returned:
+    ///
+    /// * `Error::NotFound` will be returned if the device contains no FAT partition.
+    /// * `Error::CorruptedFileSystem` will be returned if the partition table is invalid or the partition does not
+    ///   contain a valid FAT volume.
+    /// * `Error::UnexpectedEof` will be returned if the device ends before the partition table.
+    /// * `Error::Io` will be returned if the underlying storage object returned an I/O error.
+    /// * All errors of `FileSystem::new`.
+    pub async fn mount_first_fat_partition(
+        mut device: T,
+        options: FsOptions<TP, OCC>,
+    ) -> Result<Self, Error<StreamSliceError<T::Error>>> {
+        let entries = mbr::read_partitions(&mut device).await.map_err(mbr_error)?;
+        let range = if entries.iter().flatten().any(mbr::PartitionEntry::is_protective) {
+            let header = gpt::read_header(&mut device).await.map_err(gpt_error)?;
+            let mut partitions = gpt::partitions(&mut device, &header);
+            let mut range = None;
+            while let Some(r) = partitions.next().await {
+                let (index, partition) = r.map_err(gpt_error)?;
+                if partition.is_basic_data() {
+                    trace!("mounting GPT partition {}", index);
+                    range = Some((partition.start_offset(), partition.end_offset()));
+                    break;
+                }
+            }
+            range
+        } else {
+            entries
+                .iter()
+                .enumerate()
+                .find_map(|(index, e)| e.filter(mbr::PartitionEntry::is_fat).map(|e| (index, e)))
+                .map(|(index, e)| {
+                    trace!("mounting MBR partition {}", index);
+                    (e.start_offset(), e.end_offset())
+                })
+        };
+        let Some((start, end)) = range else {
+            error!("No FAT partition found");
+            return Err(Error::NotFound);
+        };
+        let slice = StreamSlice::new(device, start, end).await?;
+        Self::new(slice, options).await
+    }
+}
+
+fn mbr_error<E: core::fmt::Debug>(err: MbrError<E>) -> Error<StreamSliceError<E>> {
+    match err {
+        MbrError::Io(e) => Error::Io(StreamSliceError::Other(e)),
+        MbrError::UnexpectedEof => Error::UnexpectedEof,
+        _ => {
+            error!("Invalid Master Boot Record");
+            Error::CorruptedFileSystem
+        }
+    }
+}
+
+fn gpt_error<E: core::fmt::Debug>(err: GptError<E>) -> Error<StreamSliceError<E>> {
+    match err {
+        GptError::Io(e) => Error::Io(StreamSliceError::Other(e)),
+        GptError::UnexpectedEof => Error::UnexpectedEof,
+        _ => {
+            error!("Invalid GUID Partition Table");
+            Error::CorruptedFileSystem
+        }
+    }
+}
@@ -1,4 +1,7 @@
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
 use core::borrow::BorrowMut;
+use core::cell::RefCell;
 use core::cmp;
 use core::marker::PhantomData;
 
@@ -153,6 +156,75 @@ where
     Ok(new_cluster)
 }
 
+async fn find_free_run<S, E>(
+    fat: &mut S,
+    fat_type: FatType,
+    start_cluster: u32,
+    end_cluster: u32,
+    count: u32,
+) -> Result<Option<u32>, Error<E>>
+where
+    S: Read + Seek,
+    E: IoError,
+    Error<E>: From<S::Error> + From<ReadExactError<S::Error>>,
+{
+    let mut run_start = start_cluster;
+    let mut run_len = 0;
+    for cluster in start_cluster..end_cluster {
+        if read_fat(fat, fat_type, cluster).await? == FatValue::Free {
+            if run_len == 0 {
+                run_start = cluster;
+            }
+            run_len += 1;
+            if run_len == count {
+                return Ok(Some(run_start));
+            }
+        } else {
+            run_len = 0;
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) async fn alloc_contiguous_clusters<S, E>(
+    fat: &mut S,
+    fat_type: FatType,
+    prev_cluster: Option<u32>,
+    hint: Option<u32>,
+    total_clusters: u32,
+    count: u32,
+) -> Result<Option<u32>, Error<E>>
+where
+    S: Read + Write + Seek,
+    E: IoError,
+    Error<E>: From<S::Error> + From<ReadExactError<S::Error>>,
+{
+    let end_cluster = total_clusters + RESERVED_FAT_ENTRIES;
+    let start_cluster = match hint {
+        Some(n) if n < end_cluster => n,
+        _ => RESERVED_FAT_ENTRIES,
+    };
+    let mut run = find_free_run(fat, fat_type, start_cluster, end_cluster, count).await?;
+    if run.is_none() && start_cluster > RESERVED_FAT_ENTRIES {
+        // a run can cross the hint so scan the whole FAT
+        run = find_free_run(fat, fat_type, RESERVED_FAT_ENTRIES, end_cluster, count).await?;
+    }
+    let Some(first_cluster) = run else {
+        return Ok(None);
+    };
+    // write the chain backwards so it is never linked to clusters that are still marked as free
+    let last_cluster = first_cluster + count - 1;
+    write_fat(fat, fat_type, last_cluster, FatValue::EndOfChain).await?;
+    for cluster in (first_cluster..last_cluster).rev() {
+        write_fat(fat, fat_type, cluster, FatValue::Data(cluster + 1)).await?;
+    }
+    if let Some(n) = prev_cluster {
+        write_fat(fat, fat_type, n, FatValue::Data(first_cluster)).await?;
+    }
+    trace!("allocated clusters {}-{}", first_cluster, last_cluster);
+    Ok(Some(first_cluster))
+}
+
 pub(crate) async fn read_fat_flags<S, E>(fat: &mut S, fat_type: FatType) -> Result<FsStatusFlags, Error<E>>
 where
     S: Read + Seek,
@@ -178,6 +250,64 @@ where
     Ok(FsStatusFlags { dirty, io_error })
 }
 
+pub(crate) async fn write_fat_flags<S, E>(fat: &mut S, fat_type: FatType, flags: FsStatusFlags) -> Result<(), Error<E>>
+where
+    S: Read + Write + Seek,
+    E: IoError,
+    Error<E>: From<S::Error> + From<ReadExactError<S::Error>>,
+{
+    // the flags are stored inverted in the MSB of the second FAT entry (except in FAT12)
+    let (dirty_bit, io_error_bit) = match fat_type {
+        FatType::Fat12 => return Ok(()),
+        FatType::Fat16 => (1 << 15, 1 << 14),
+        FatType::Fat32 => (1 << 27, 1 << 26),
+    };
+    let mut val = match fat_type {
+        FatType::Fat16 => Fat16::get_raw(fat, 1).await?,
+        _ => Fat32::get_raw(fat, 1).await?,
+    };
+    val |= dirty_bit | io_error_bit;
+    if flags.dirty {
+        val &= !dirty_bit;
+    }
+    if flags.io_error {
+        val &= !io_error_bit;
+    }
+    match fat_type {
+        FatType::Fat16 => Fat16::set_raw(fat, 1, val).await,
+        _ => Fat32::set_raw(fat, 1, val).await,
+    }
+}
+
+/// Returns true if `cluster` is marked as used (data or end of chain) in the FAT.
+pub(crate) async fn is_cluster_allocated<S, E>(fat: &mut S, fat_type: FatType, cluster: u32) -> Result<bool, Error<E>>
+where
+    S: Read + Seek,
+    E: IoError,
+    Error<E>: From<S::Error> + From<ReadExactError<S::Error>>,
+{
+    let val = read_fat(fat, fat_type, cluster).await?;
+    Ok(matches!(val, FatValue::Data(_) | FatValue::EndOfChain))
+}
+
+pub(crate) async fn free_cluster<S, E>(fat: &mut S, fat_type: FatType, cluster: u32) -> Result<(), Error<E>>
+where
+    S: Read + Write + Seek,
+    E: IoError,
+    Error<E>: From<S::Error> + From<ReadExactError<S::Error>>,
+{
+    write_fat(fat, fat_type, cluster, FatValue::Free).await
+}
+
+pub(crate) async fn mark_end_of_chain<S, E>(fat: &mut S, fat_type: FatType, cluster: u32) -> Result<(), Error<E>>
+where
+    S: Read + Write + Seek,
+    E: IoError,
+    Error<E>: From<S::Error> + From<ReadExactError<S::Error>>,
+{
+    write_fat(fat, fat_type, cluster, FatValue::EndOfChain).await
+}
+
 pub(crate) async fn count_free_clusters<S, E>(
     fat: &mut S,
     fat_type: FatType,
@@ -586,6 +716,200 @@ impl FatTrait for Fat32 {
     }
 }
 
+// Size of a block of FAT data held by `FatCache`
+const FAT_CACHE_BLOCK_SIZE: usize = 512;
+
+// Number of blocks that can be cached when `alloc` feature is disabled
+#[cfg(not(feature = "alloc"))]
+pub(crate) const MAX_FAT_CACHE_BLOCKS: usize = 1;
+
+#[derive(Clone)]
+struct FatCacheEntry {
+    block: u32,
+    last_used: u32,
+    data: [u8; FAT_CACHE_BLOCK_SIZE],
+}
+
+impl FatCacheEntry {
+    const EMPTY: Self = Self {
+        block: u32::MAX,
+        last_used: 0,
+        data: [0; FAT_CACHE_BLOCK_SIZE],
+    };
+}
+
+/// A cache of recently read FAT blocks.
+///
+/// Blocks are evicted in least recently used order. Writes to the FAT update cached blocks so the cache never
+/// contains stale data.
+pub(crate) struct FatCache {
+    #[cfg(feature = "alloc")]
+    entries: Vec<FatCacheEntry>,
+    #[cfg(not(feature = "alloc"))]
+    entries: [FatCacheEntry; MAX_FAT_CACHE_BLOCKS],
+    capacity: usize,
+    clock: u32,
+}
+
+impl FatCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        #[cfg(feature = "alloc")]
+        let entries = Vec::with_capacity(capacity);
+        #[cfg(not(feature = "alloc"))]
+        let entries = [FatCacheEntry::EMPTY; MAX_FAT_CACHE_BLOCKS];
+        #[cfg(not(feature = "alloc"))]
+        let capacity = cmp::min(capacity, MAX_FAT_CACHE_BLOCKS);
+        Self {
+            entries,
+            capacity,
+            clock: 0,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Copies cached data starting at `offset` into `buf` and returns number of copied bytes.
+    fn read(&mut self, offset: u64, buf: &mut [u8]) -> Option<usize> {
+        let block = offset / FAT_CACHE_BLOCK_SIZE as u64;
+        let offset_in_block = (offset % FAT_CACHE_BLOCK_SIZE as u64) as usize;
+        self.clock = self.clock.wrapping_add(1);
+        let clock = self.clock;
+        let entry = self.entries.iter_mut().find(|e| u64::from(e.block) == block)?;
+        entry.last_used = clock;
+        let len = cmp::min(buf.len(), FAT_CACHE_BLOCK_SIZE - offset_in_block);
+        buf[..len].copy_from_slice(&entry.data[offset_in_block..offset_in_block + len]);
+        Some(len)
+    }
+
+    fn insert(&mut self, block: u32, data: &[u8; FAT_CACHE_BLOCK_SIZE]) {
+        #[cfg(feature = "alloc")]
+        if self.entries.len() < self.capacity {
+            self.entries.push(FatCacheEntry::EMPTY);
+        }
+        let len = cmp::min(self.entries.len(), self.capacity);
+        let entries = &mut self.entries[..len];
+        // replace least recently used entry - empty entries are never used
+        let clock = self.clock;
+        let Some(entry) = entries.iter_mut().max_by_key(|e| {
+            if e.block == u32::MAX {
+                u32::MAX
+            } else {
+                clock.wrapping_sub(e.last_used)
+            }
+        }) else {
+            return;
+        };
+        entry.block = block;
+        entry.last_used = clock;
+        entry.data.copy_from_slice(data);
+    }
+
+    /// Updates cached blocks overlapping the written range.
+    fn write(&mut self, offset: u64, buf: &[u8]) {
+        let end = offset + buf.len() as u64;
+        for entry in &mut self.entries {
+            if entry.block == u32::MAX {
+                continue;
+            }
+            let block_start = u64::from(entry.block) * FAT_CACHE_BLOCK_SIZE as u64;
+            let block_end = block_start + FAT_CACHE_BLOCK_SIZE as u64;
+            let start = cmp::max(offset, block_start);
+            let stop = cmp::min(end, block_end);
+            if start < stop {
+                let src = &buf[(start - offset) as usize..(stop - offset) as usize];
+                entry.data[(start - block_start) as usize..(stop - block_start) as usize].copy_from_slice(src);
+            }
+        }
+    }
+}
+
+/// A FAT stream reading through a shared `FatCache`.
+pub(crate) struct CachedFat<'c, S> {
+    inner: S,
+    cache: &'c RefCell<FatCache>,
+    offset: u64,
+}
+
+impl<'c, S> CachedFat<'c, S> {
+    pub(crate) fn new(inner: S, cache: &'c RefCell<FatCache>) -> Self {
+        Self {
+            inner,
+            cache,
+            offset: 0,
+        }
+    }
+}
+
+impl<S: IoBase> IoBase for CachedFat<'_, S> {
+    type Error = S::Error;
+}
+
+impl<E: IoError, S: Read + Seek + IoBase<Error = Error<E>>> Read for CachedFat<'_, S> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if !self.cache.borrow().is_enabled() {
+            self.inner.seek(io::SeekFrom::Start(self.offset)).await?;
+            let n = self.inner.read(buf).await?;
+            self.offset += n as u64;
+            return Ok(n);
+        }
+        if let Some(n) = self.cache.borrow_mut().read(self.offset, buf) {
+            self.offset += n as u64;
+            return Ok(n);
+        }
+        // cache miss - read the whole block
+        let block = self.offset / FAT_CACHE_BLOCK_SIZE as u64;
+        let mut data = [0_u8; FAT_CACHE_BLOCK_SIZE];
+        self.inner
+            .seek(io::SeekFrom::Start(block * FAT_CACHE_BLOCK_SIZE as u64))
+            .await?;
+        let mut filled = 0;
+        while filled < data.len() {
+            match self.inner.read(&mut data[filled..]).await? {
+                0 => return Err(Error::UnexpectedEof),
+                n => filled += n,
+            }
+        }
+        let mut cache = self.cache.borrow_mut();
+        // safe cast: FAT size is limited to 32 bits
+        #[allow(clippy::cast_possible_truncation)]
+        cache.insert(block as u32, &data);
+        let n = cache.read(self.offset, buf).unwrap_or(0);
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: Write + Seek> Write for CachedFat<'_, S> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.seek(io::SeekFrom::Start(self.offset)).await?;
+        let n = self.inner.write(buf).await?;
+        self.cache.borrow_mut().write(self.offset, &buf[..n]);
+        self.offset += n as u64;
+        Ok(n)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
+}
+
+impl<S: Seek> Seek for CachedFat<'_, S> {
+    async fn seek(&mut self, pos: io::SeekFrom) -> Result<u64, Self::Error> {
+        // let the inner stream validate the new position
+        let pos = match pos {
+            io::SeekFrom::Current(x) => {
+                self.inner.seek(io::SeekFrom::Start(self.offset)).await?;
+                io::SeekFrom::Current(x)
+            }
+            pos => pos,
+        };
+        self.offset = self.inner.seek(pos).await?;
+        Ok(self.offset)
+    }
+}
+
 pub(crate) struct ClusterIterator<B, E, S = B> {
     fat: B,
     fat_type: FatType,
@@ -750,6 +1074,37 @@ mod tests {
         assert_eq!(read_fat(&mut cur, fat_type, 0x16).await.ok(), Some(FatValue::Free));
     }
 
+    #[tokio::test]
+    async fn test_fat_type_markers() {
+        assert_eq!(FatType::Fat12.end_of_chain_marker(), 0xFF8);
+        assert_eq!(FatType::Fat16.end_of_chain_marker(), 0xFFF8);
+        assert_eq!(FatType::Fat32.end_of_chain_marker(), 0x0FFF_FFF8);
+        assert_eq!(FatType::Fat12.bad_cluster_marker(), 0xFF7);
+        assert_eq!(FatType::Fat16.bad_cluster_marker(), 0xFFF7);
+        assert_eq!(FatType::Fat32.bad_cluster_marker(), 0x0FFF_FFF7);
+        assert_eq!(FatType::Fat12.max_valid_cluster(), 0xFF5);
+        assert_eq!(FatType::Fat16.max_valid_cluster(), 0xFFF5);
+        assert_eq!(FatType::Fat32.max_valid_cluster(), 0x0FFF_FFF5);
+
+        // the markers are interpreted the same way when read from a FAT
+        for fat_type in [FatType::Fat12, FatType::Fat16, FatType::Fat32] {
+            let mut cur = FromTokio::new(Cursor::<Vec<u8>>::new(vec![0; 32]));
+            let values = [
+                (fat_type.end_of_chain_marker(), FatValue::EndOfChain),
+                (fat_type.end_of_chain_marker() - 1, FatValue::Bad),
+                (fat_type.bad_cluster_marker(), FatValue::Bad),
+                (
+                    fat_type.max_valid_cluster(),
+                    FatValue::Data(fat_type.max_valid_cluster()),
+                ),
+            ];
+            for (raw, expected) in values {
+                write_fat(&mut cur, fat_type, 2, FatValue::Data(raw)).await.unwrap();
+                assert_eq!(read_fat(&mut cur, fat_type, 2).await.ok(), Some(expected));
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_fat12() {
         let fat: Vec<u8> = vec![
@@ -1,5 +1,5 @@
 #![allow(deprecated)]
-use core::fmt::Debug;
+use core::fmt::{self, Debug};
 
 #[cfg(feature = "chrono")]
 use chrono::{Datelike, Local, TimeZone, Timelike};
@@ -44,8 +44,26 @@ impl Date {
         Self { year, month, day }
     }
 
+    // returns the closest date in the range supported by FAT
+    pub(crate) fn clamped(self) -> Self {
+        if self.year < MIN_YEAR {
+            return Self::new(MIN_YEAR, MIN_MONTH, MIN_DAY);
+        }
+        if self.year > MAX_YEAR {
+            return Self::new(MAX_YEAR, MAX_MONTH, MAX_DAY);
+        }
+        Self {
+            year: self.year,
+            month: self.month.clamp(MIN_MONTH, MAX_MONTH),
+            day: self.day.clamp(MIN_DAY, MAX_DAY),
+        }
+    }
+
     pub(crate) fn decode(dos_date: u16) -> Self {
         let (year, month, day) = ((dos_date >> 9) + MIN_YEAR, (dos_date >> 5) & 0xF, dos_date & 0x1F);
+        // unset (zero) or corrupted fields are decoded as the closest valid value
+        let month = month.clamp(MIN_MONTH, MAX_MONTH);
+        let day = day.max(MIN_DAY);
         Self { year, month, day }
     }
 
@@ -91,6 +109,16 @@ impl Time {
         Self { hour, min, sec, millis }
     }
 
+    // returns the closest valid time
+    pub(crate) fn clamped(self) -> Self {
+        Self {
+            hour: self.hour.min(23),
+            min: self.min.min(59),
+            sec: self.sec.min(59),
+            millis: self.millis.min(999),
+        }
+    }
+
     pub(crate) fn decode(dos_time: u16, dos_time_hi_res: u8) -> Self {
         let hour = dos_time >> 11;
         let min = (dos_time >> 5) & 0x3F;
@@ -127,6 +155,18 @@ impl DateTime {
         Self { date, time }
     }
 
+    // returns the closest date and time in the range supported by FAT
+    pub(crate) fn clamped(self) -> Self {
+        let time = if self.date.year < MIN_YEAR {
+            Time::new(0, 0, 0, 0)
+        } else if self.date.year > MAX_YEAR {
+            Time::new(23, 59, 59, 999)
+        } else {
+            self.time.clamped()
+        };
+        Self::new(self.date.clamped(), time)
+    }
+
     pub(crate) fn decode(dos_date: u16, dos_time: u16, dos_time_hi_res: u8) -> Self {
         Self::new(Date::decode(dos_date), Time::decode(dos_time, dos_time_hi_res))
     }
@@ -228,6 +268,9 @@ impl TimeProvider for ChronoTimeProvider {
 }
 
 /// `TimeProvider` implementation that always returns DOS minimal date-time (1980-01-01 00:00:00).
+///
+/// Useful for targets without a real-time clock. It is a zero-sized type that does not depend on `chrono` or
+/// `std`, so it adds no code size or runtime cost to `no_std` builds. Select it with `FsOptions::time_provider`.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct NullTimeProvider {
     _dummy: (),
@@ -250,6 +293,39 @@ impl TimeProvider for NullTimeProvider {
     }
 }
 
+/// `TimeProvider` implementation that gets the current date and time from a user-supplied function.
+///
+/// Useful for targets with a real-time clock peripheral but without `chrono` and `std`, the function typically reads
+/// the RTC. If it returns `None` (e.g. the RTC could not be read or was never set) the DOS minimal date-time
+/// (1980-01-01 00:00:00) is used, like with `NullTimeProvider`. Select it with `FsOptions::time_provider`.
+#[derive(Clone, Copy)]
+pub struct FnTimeProvider<F> {
+    get_date_time: F,
+}
+
+impl<F: Fn() -> Option<DateTime>> FnTimeProvider<F> {
+    #[must_use]
+    pub fn new(get_date_time: F) -> Self {
+        Self { get_date_time }
+    }
+}
+
+impl<F> Debug for FnTimeProvider<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnTimeProvider").finish_non_exhaustive()
+    }
+}
+
+impl<F: Fn() -> Option<DateTime>> TimeProvider for FnTimeProvider<F> {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        (self.get_date_time)().unwrap_or_else(|| DateTime::decode(0, 0, 0))
+    }
+}
+
 /// Default time provider implementation.
 ///
 /// Defined as `ChronoTimeProvider` if `chrono` feature is enabled. Otherwise defined as `NullTimeProvider`.
@@ -292,6 +368,15 @@ mod tests {
         assert_eq!(d, Date::decode(x));
     }
 
+    #[test]
+    fn date_decode_unset() {
+        assert_eq!(Date::decode(0), Date::new(1980, 1, 1));
+        assert_eq!(
+            DateTime::decode(0, 0, 0),
+            DateTime::new(Date::new(1980, 1, 1), Time::new(0, 0, 0, 0))
+        );
+    }
+
     #[test]
     fn time_encode_decode() {
         let t1 = Time::new(15, 3, 29, 990);
@@ -0,0 +1,143 @@
+#![cfg(feature = "blocking")]
+
+use core::convert::Infallible;
+
+use embedded_fatfs::blocking::{block_on, FileSystem};
+use embedded_fatfs::{format_volume, FormatVolumeOptions, FsOptions};
+use embedded_io::{Read, Seek, SeekFrom, Write};
+
+const TEST_STR: &str = "Hi there Rust programmer!\n";
+
+/// In-memory storage whose futures always complete on the first poll.
+struct RamDisk {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl RamDisk {
+    fn new(len: usize) -> Self {
+        Self {
+            data: vec![0; len],
+            pos: 0,
+        }
+    }
+}
+
+impl embedded_io_async::ErrorType for RamDisk {
+    type Error = Infallible;
+}
+
+impl embedded_io_async::Read for RamDisk {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl embedded_io_async::Write for RamDisk {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(self.data.len() - self.pos);
+        self.data[self.pos..self.pos + n].copy_from_slice(&buf[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl embedded_io_async::Seek for RamDisk {
+    async fn seek(&mut self, pos: embedded_io_async::SeekFrom) -> Result<u64, Self::Error> {
+        let new_pos = match pos {
+            embedded_io_async::SeekFrom::Start(n) => n as i64,
+            embedded_io_async::SeekFrom::End(n) => self.data.len() as i64 + n,
+            embedded_io_async::SeekFrom::Current(n) => self.pos as i64 + n,
+        };
+        self.pos = new_pos as usize;
+        Ok(self.pos as u64)
+    }
+}
+
+fn formatted_ram_disk() -> RamDisk {
+    let mut disk = RamDisk::new(4 * 1024 * 1024);
+    block_on(format_volume(&mut disk, FormatVolumeOptions::new())).expect("format");
+    disk.pos = 0;
+    disk
+}
+
+#[test]
+fn test_blocking_read_write_seek() {
+    let fs = FileSystem::new(formatted_ram_disk(), FsOptions::new()).expect("open fs");
+    let root_dir = fs.root_dir();
+    let dir = root_dir.create_dir("dir").expect("create_dir");
+    {
+        let mut file = dir.create_file("test.txt").expect("create_file");
+        file.write_all(TEST_STR.as_bytes()).expect("write_all");
+        file.flush().expect("flush");
+    }
+
+    let mut file = root_dir.open_file("dir/test.txt").expect("open_file");
+    let mut buf = [0_u8; TEST_STR.len()];
+    file.read_exact(&mut buf).expect("read_exact");
+    assert_eq!(&buf, TEST_STR.as_bytes());
+
+    assert_eq!(file.seek(SeekFrom::Start(3)).expect("seek"), 3);
+    file.truncate().expect("truncate");
+    file.flush().expect("flush");
+    file.rewind().expect("rewind");
+    let mut buf = [0_u8; 8];
+    assert_eq!(file.read(&mut buf).expect("read"), 3);
+    assert_eq!(&buf[..3], &TEST_STR.as_bytes()[..3]);
+    drop(file);
+
+    let names = dir
+        .iter()
+        .map(|r| r.expect("entry").file_name())
+        .filter(|name| name != "." && name != "..")
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["test.txt"]);
+    assert!(root_dir.exists("dir/test.txt").expect("exists"));
+    dir.remove("test.txt").expect("remove");
+    assert!(!root_dir.exists("dir/test.txt").expect("exists"));
+
+    drop(dir);
+    drop(root_dir);
+
+    let stats = fs.stats().expect("stats");
+    assert!(stats.free_clusters() < stats.total_clusters());
+    fs.unmount().expect("unmount");
+}
+
+#[test]
+#[should_panic(expected = "blocking API requires synchronous storage")]
+fn test_blocking_pending_future_panics() {
+    block_on(core::future::pending::<()>());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_blocking_std_io() {
+    let fs = FileSystem::new(formatted_ram_disk(), FsOptions::new()).expect("open fs");
+    let root_dir = fs.root_dir();
+    {
+        let mut file = root_dir.create_file("std.txt").expect("create_file");
+        std::io::Write::write_all(&mut file, TEST_STR.repeat(100).as_bytes()).expect("write_all");
+        std::io::Write::flush(&mut file).expect("flush");
+    }
+
+    let mut file = root_dir.open_file("std.txt").expect("open_file");
+    let mut content = Vec::new();
+    std::io::Read::read_to_end(&mut file, &mut content).expect("read_to_end");
+    assert_eq!(content, TEST_STR.repeat(100).as_bytes());
+
+    assert_eq!(
+        std::io::Seek::seek(&mut file, std::io::SeekFrom::End(-3)).expect("seek"),
+        content.len() as u64 - 3
+    );
+    let mut copied = Vec::new();
+    std::io::copy(&mut file, &mut copied).expect("copy");
+    assert_eq!(copied, &content[content.len() - 3..]);
+
+    // filesystem errors are converted to `std::io::Error`
+    let err = std::io::Seek::seek(&mut file, std::io::SeekFrom::Current(-10_000)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
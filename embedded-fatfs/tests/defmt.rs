@@ -0,0 +1,21 @@
+#![cfg(feature = "defmt")]
+
+use embedded_fatfs::{
+    BootSector, DirEntry, DirMetadata, FatType, FileSystemKind, FileSystemStats, Geometry, LossyOemCpConverter,
+    NullTimeProvider,
+};
+
+type Storage = embedded_io_adapters::tokio_1::FromTokio<tokio::fs::File>;
+
+fn assert_format<T: defmt::Format>() {}
+
+#[test]
+fn public_types_implement_format() {
+    assert_format::<DirEntry<'static, Storage, NullTimeProvider, LossyOemCpConverter>>();
+    assert_format::<FileSystemStats>();
+    assert_format::<FatType>();
+    assert_format::<Geometry>();
+    assert_format::<DirMetadata>();
+    assert_format::<FileSystemKind>();
+    assert_format::<BootSector>();
+}
@@ -1,7 +1,7 @@
 use std::io;
 
 use embedded_fatfs::{ChronoTimeProvider, LossyOemCpConverter};
-use embedded_io_async::Write;
+use embedded_io_async::{Read, Seek, SeekFrom, Write};
 
 const KB: u64 = 1024;
 const MB: u64 = KB * 1024;
@@ -171,6 +171,966 @@ async fn test_format_volume_label_and_id() {
     assert_eq!(fs.volume_id(), 1234);
 }
 
+#[tokio::test]
+async fn test_format_remove_dir_all() {
+    let total_bytes = MB;
+    let opts = embedded_fatfs::FormatVolumeOptions::new();
+    let fs = test_format_fs(opts, total_bytes).await;
+    let root_dir = fs.root_dir();
+    root_dir.remove("subdir1/subdir2 with long name").await.unwrap();
+    root_dir.remove("subdir1").await.unwrap();
+    root_dir.remove("new-name.txt").await.unwrap();
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+
+    let b = root_dir.create_dir_all("a/b").await.unwrap();
+    for name in ["f1.txt", "f2.txt"] {
+        let mut file = b.create_file(name).await.unwrap();
+        file.write_all(TEST_STR.repeat(100).as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+    }
+    root_dir.create_dir("a/c").await.unwrap();
+    assert!(root_dir.remove("a").await.is_err());
+
+    root_dir.remove_dir_all("a").await.expect("remove_dir_all");
+    assert_eq!(root_dir.iter().collect().await.len(), 0);
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters);
+}
+
+/// In-memory block device with 4096 byte blocks.
+struct RamBlockDevice(Vec<u8>);
+
+impl block_device_driver::BlockDevice<4096> for RamBlockDevice {
+    type Error = core::convert::Infallible;
+    type Align = aligned::A4;
+
+    async fn read(
+        &mut self,
+        block_address: u32,
+        data: &mut [aligned::Aligned<Self::Align, [u8; 4096]>],
+    ) -> Result<(), Self::Error> {
+        let start = block_address as usize * 4096;
+        for (i, block) in data.iter_mut().enumerate() {
+            block.copy_from_slice(&self.0[start + i * 4096..start + (i + 1) * 4096]);
+        }
+        Ok(())
+    }
+
+    async fn write(
+        &mut self,
+        block_address: u32,
+        data: &[aligned::Aligned<Self::Align, [u8; 4096]>],
+    ) -> Result<(), Self::Error> {
+        let start = block_address as usize * 4096;
+        for (i, block) in data.iter().enumerate() {
+            self.0[start + i * 4096..start + (i + 1) * 4096].copy_from_slice(&block[..]);
+        }
+        Ok(())
+    }
+
+    async fn size(&mut self) -> Result<u64, Self::Error> {
+        Ok(self.0.len() as u64)
+    }
+}
+
+#[tokio::test]
+async fn test_format_1gb_4096sec_block_device() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let total_bytes = 1024 * MB;
+    let device = RamBlockDevice(vec![0xD1_u8; total_bytes as usize]);
+    let mut stream = block_device_adapters::BufStream::<_, 4096>::new(device);
+    let opts = embedded_fatfs::FormatVolumeOptions::new().bytes_per_sector(4096);
+    embedded_fatfs::format_volume(&mut stream, opts)
+        .await
+        .expect("format volume");
+    stream.flush().await.unwrap();
+
+    let test_str = TEST_STR.repeat(1000);
+    {
+        let fs = embedded_fatfs::FileSystem::new(&mut stream, embedded_fatfs::FsOptions::new())
+            .await
+            .expect("open fs");
+        assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat32);
+        {
+            let root_dir = fs.root_dir();
+            let dir = root_dir.create_dir("subdir").await.unwrap();
+            let mut file = dir.create_file("test file name.txt").await.unwrap();
+            file.write_all(test_str.as_bytes()).await.unwrap();
+            file.flush().await.unwrap();
+        }
+        fs.unmount().await.unwrap();
+    }
+    let device = stream.close().await.unwrap();
+
+    // remount from the block device and read the file back
+    let stream = block_device_adapters::BufStream::<_, 4096>::new(device);
+    let fs = embedded_fatfs::FileSystem::new(stream, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("remount fs");
+    let mut file = fs.root_dir().open_file("subdir/test file name.txt").await.unwrap();
+    let content = read_to_end(&mut file).await.unwrap();
+    assert_eq!(core::str::from_utf8(&content).unwrap(), test_str);
+    let stats = fs.stats().await.unwrap();
+    // root dir, subdir and the file itself
+    assert_eq!(
+        stats.total_clusters() - stats.free_clusters(),
+        3 + (test_str.len() as u32 - 1) / fs.cluster_size()
+    );
+}
+
+#[tokio::test]
+async fn test_format_slice_block_device() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut image = vec![0_u8; (8 * MB) as usize];
+    {
+        let device = block_device_driver::SliceBlockDevice::<512>::new(&mut image).unwrap();
+        let mut stream = block_device_adapters::BufStream::<_, 512>::new(device);
+        embedded_fatfs::format_volume(&mut stream, embedded_fatfs::FormatVolumeOptions::new())
+            .await
+            .expect("format volume");
+        let fs = embedded_fatfs::FileSystem::new(&mut stream, embedded_fatfs::FsOptions::new())
+            .await
+            .expect("open fs");
+        let mut file = fs.root_dir().create_file("slice.txt").await.unwrap();
+        file.write_all(TEST_STR.as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+        fs.unmount().await.unwrap();
+        stream.flush().await.unwrap();
+    }
+    // the image is a plain byte buffer and can be mounted again from any other storage
+    assert!(image.windows(TEST_STR.len()).any(|w| w == TEST_STR.as_bytes()));
+
+    let device = block_device_driver::SliceBlockDevice::<512>::new(&mut image).unwrap();
+    let fs = embedded_fatfs::FileSystem::new(
+        block_device_adapters::BufStream::<_, 512>::new(device),
+        embedded_fatfs::FsOptions::new(),
+    )
+    .await
+    .expect("remount fs");
+    let mut file = fs.root_dir().open_file("slice.txt").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_unmount_into_inner() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let device = block_device_driver::RamDisk::<512>::new((8 * MB / 512) as usize);
+    let mut stream = block_device_adapters::BufStream::<_, 512>::new(device);
+    embedded_fatfs::format_volume(&mut stream, embedded_fatfs::FormatVolumeOptions::new())
+        .await
+        .expect("format volume");
+    let fs = embedded_fatfs::FileSystem::new(stream, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("open fs");
+    let dir = fs.root_dir().create_dir("dir").await.unwrap();
+    let mut file = dir.create_file("unmount.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    drop(dir);
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+
+    // the returned storage has all changes written back and the volume is clean
+    let stream = fs.unmount_into_inner().await.unwrap();
+    let device = stream.close().await.unwrap();
+    let stream = block_device_adapters::BufStream::<_, 512>::new(device);
+    let fs = embedded_fatfs::FileSystem::new(stream, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("remount fs");
+    assert!(!fs.read_status_flags().await.unwrap().dirty());
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters);
+    let mut file = fs.root_dir().open_file("dir/unmount.txt").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+}
+
+#[cfg(feature = "partitions")]
+fn write_mbr_entry(image: &mut [u8], index: usize, partition_type: u8, lba_start: u32, sector_count: u32) {
+    let entry = &mut image[0x1BE + index * 16..0x1BE + (index + 1) * 16];
+    entry[4] = partition_type;
+    entry[8..12].copy_from_slice(&lba_start.to_le_bytes());
+    entry[12..16].copy_from_slice(&sector_count.to_le_bytes());
+    image[510..512].copy_from_slice(&[0x55, 0xAA]);
+}
+
+#[cfg(feature = "partitions")]
+#[tokio::test]
+async fn test_mount_first_fat_partition() {
+    use block_device_adapters::StreamSlice;
+    use embedded_io_adapters::tokio_1::FromTokio;
+
+    let _ = env_logger::builder().is_test(true).try_init();
+    const SECTORS: u32 = (2 * MB / 512) as u32;
+    // a non-FAT partition followed by two FAT partitions
+    let mut image = vec![0_u8; 512 * (1 + 3 * SECTORS as usize)];
+    write_mbr_entry(&mut image, 0, 0x83, 1, SECTORS);
+    write_mbr_entry(&mut image, 1, 0x06, 1 + SECTORS, SECTORS);
+    write_mbr_entry(&mut image, 2, 0x0C, 1 + 2 * SECTORS, SECTORS);
+    let mut io = FromTokio::new(io::Cursor::new(image));
+    for (lba, label) in [(1 + SECTORS, b"FIRST      "), (1 + 2 * SECTORS, b"SECOND     ")] {
+        let start = u64::from(lba) * 512;
+        let mut slice = StreamSlice::new(&mut io, start, start + u64::from(SECTORS) * 512)
+            .await
+            .unwrap();
+        let options = embedded_fatfs::FormatVolumeOptions::new().volume_label(*label);
+        embedded_fatfs::format_volume(&mut slice, options).await.unwrap();
+    }
+
+    let fs = embedded_fatfs::FileSystem::mount_first_fat_partition(io, embedded_fatfs::FsOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(fs.volume_label(), "FIRST");
+    let mut file = fs.root_dir().create_file("part.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let slice = fs.unmount_into_inner().await.unwrap();
+    assert_eq!(slice.start_offset(), u64::from(1 + SECTORS) * 512);
+    assert_eq!(slice.len(), u64::from(SECTORS) * 512);
+    let mut image = slice.into_inner().into_inner().into_inner();
+
+    // without a FAT partition type the device cannot be mounted
+    write_mbr_entry(&mut image, 1, 0x83, 1 + SECTORS, SECTORS);
+    write_mbr_entry(&mut image, 2, 0x83, 1 + 2 * SECTORS, SECTORS);
+    let io = FromTokio::new(io::Cursor::new(image));
+    assert!(matches!(
+        embedded_fatfs::FileSystem::mount_first_fat_partition(io, embedded_fatfs::FsOptions::new()).await,
+        Err(embedded_fatfs::Error::NotFound)
+    ));
+}
+
+/// CRC-32 as used by the GUID Partition Table, computed bit by bit.
+#[cfg(feature = "partitions")]
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Writes a protective MBR and a primary GPT header with one entry per partition `(type GUID, first LBA, last LBA)`.
+#[cfg(feature = "partitions")]
+fn write_gpt(image: &mut [u8], partitions: &[([u8; 16], u64, u64)]) {
+    const ENTRIES: usize = 4;
+    let sectors = (image.len() / 512) as u32;
+    write_mbr_entry(image, 0, 0xEE, 1, sectors - 1);
+    // the partition entry array fills LBA 2
+    let entries = &mut image[2 * 512..3 * 512];
+    entries.fill(0);
+    for (entry, (type_guid, first_lba, last_lba)) in entries.chunks_exact_mut(128).zip(partitions) {
+        entry[0..16].copy_from_slice(type_guid);
+        entry[16..32].copy_from_slice(&[0x22; 16]);
+        entry[32..40].copy_from_slice(&first_lba.to_le_bytes());
+        entry[40..48].copy_from_slice(&last_lba.to_le_bytes());
+    }
+    let entries_crc = crc32(&image[2 * 512..2 * 512 + ENTRIES * 128]);
+    let header = &mut image[512..512 + 92];
+    header[0..8].copy_from_slice(b"EFI PART");
+    header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+    header[12..16].copy_from_slice(&92u32.to_le_bytes());
+    header[16..20].fill(0);
+    header[24..32].copy_from_slice(&1u64.to_le_bytes());
+    header[32..40].copy_from_slice(&u64::from(sectors - 1).to_le_bytes());
+    header[40..48].copy_from_slice(&3u64.to_le_bytes());
+    header[48..56].copy_from_slice(&u64::from(sectors - 2).to_le_bytes());
+    header[56..72].copy_from_slice(&[0x11; 16]);
+    header[72..80].copy_from_slice(&2u64.to_le_bytes());
+    header[80..84].copy_from_slice(&(ENTRIES as u32).to_le_bytes());
+    header[84..88].copy_from_slice(&128u32.to_le_bytes());
+    header[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+    let header_crc = crc32(header);
+    header[16..20].copy_from_slice(&header_crc.to_le_bytes());
+}
+
+#[cfg(feature = "partitions")]
+#[tokio::test]
+async fn test_mount_first_fat_partition_gpt() {
+    use block_device_adapters::gpt::BASIC_DATA_PARTITION_GUID;
+    use block_device_adapters::StreamSlice;
+    use embedded_io_adapters::tokio_1::FromTokio;
+
+    let _ = env_logger::builder().is_test(true).try_init();
+    const SECTORS: u64 = 2 * MB / 512;
+    const LINUX_FS_GUID: [u8; 16] = [
+        0xAF, 0x3D, 0xC6, 0x0F, 0x83, 0x84, 0x72, 0x47, 0x8E, 0x79, 0x3D, 0x69, 0xD8, 0x47, 0x7D, 0xE4,
+    ];
+    // a Linux partition followed by two basic data partitions, the first one starting at LBA 3
+    let starts = [3, 3 + SECTORS, 3 + 2 * SECTORS];
+    let mut image = vec![0_u8; 512 * (4 + 3 * SECTORS as usize)];
+    let mut partitions = vec![
+        (LINUX_FS_GUID, starts[0], starts[1] - 1),
+        (BASIC_DATA_PARTITION_GUID, starts[1], starts[2] - 1),
+        (BASIC_DATA_PARTITION_GUID, starts[2], starts[2] + SECTORS - 1),
+    ];
+    write_gpt(&mut image, &partitions);
+    let mut io = FromTokio::new(io::Cursor::new(image));
+    for (&start, label) in starts[1..].iter().zip([b"FIRST      ", b"SECOND     "]) {
+        let mut slice = StreamSlice::new(&mut io, start * 512, (start + SECTORS) * 512)
+            .await
+            .unwrap();
+        let options = embedded_fatfs::FormatVolumeOptions::new().volume_label(*label);
+        embedded_fatfs::format_volume(&mut slice, options).await.unwrap();
+    }
+
+    let fs = embedded_fatfs::FileSystem::mount_first_fat_partition(io, embedded_fatfs::FsOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(fs.volume_label(), "FIRST");
+    let mut file = fs.root_dir().create_file("gpt.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let slice = fs.unmount_into_inner().await.unwrap();
+    assert_eq!(slice.start_offset(), starts[1] * 512);
+    assert_eq!(slice.len(), SECTORS * 512);
+    let mut image = slice.into_inner().into_inner().into_inner();
+
+    // the data written through the partition ends up inside of it
+    let stream = FromTokio::new(io::Cursor::new(image.clone()));
+    let fs = embedded_fatfs::FileSystem::mount_first_fat_partition(stream, embedded_fatfs::FsOptions::new())
+        .await
+        .unwrap();
+    let mut file = fs.root_dir().open_file("gpt.txt").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+    drop(file);
+    drop(fs);
+
+    // without a basic data partition the device cannot be mounted
+    partitions[1].0 = LINUX_FS_GUID;
+    partitions[2].0 = LINUX_FS_GUID;
+    write_gpt(&mut image, &partitions);
+    let stream = FromTokio::new(io::Cursor::new(image.clone()));
+    assert!(matches!(
+        embedded_fatfs::FileSystem::mount_first_fat_partition(stream, embedded_fatfs::FsOptions::new()).await,
+        Err(embedded_fatfs::Error::NotFound)
+    ));
+
+    // a corrupt GPT header without a valid backup is rejected
+    image[512 + 16] ^= 1;
+    let stream = FromTokio::new(io::Cursor::new(image));
+    assert!(matches!(
+        embedded_fatfs::FileSystem::mount_first_fat_partition(stream, embedded_fatfs::FsOptions::new()).await,
+        Err(embedded_fatfs::Error::CorruptedFileSystem)
+    ));
+}
+
+#[tokio::test]
+async fn test_format_ram_disk() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let device = block_device_driver::RamDisk::<512>::new((8 * MB / 512) as usize);
+    let mut stream = block_device_adapters::BufStream::<_, 512>::new(device);
+    embedded_fatfs::format_volume(&mut stream, embedded_fatfs::FormatVolumeOptions::new())
+        .await
+        .expect("format volume");
+    {
+        let fs = embedded_fatfs::FileSystem::new(&mut stream, embedded_fatfs::FsOptions::new())
+            .await
+            .expect("open fs");
+        let mut file = fs.root_dir().create_file("ram.txt").await.unwrap();
+        file.write_all(TEST_STR.as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+        fs.unmount().await.unwrap();
+    }
+    let device = stream.close().await.unwrap();
+    assert_eq!(device.num_blocks(), (8 * MB / 512) as usize);
+
+    let stream = block_device_adapters::BufStream::<_, 512>::new(device);
+    let fs = embedded_fatfs::FileSystem::new(stream, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("remount fs");
+    let mut file = fs.root_dir().open_file("ram.txt").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_format_oem_name_and_media() {
+    let fs = test_format_fs(embedded_fatfs::FormatVolumeOptions::new(), MB).await;
+    assert_eq!(fs.oem_name(), "MSWIN4.1");
+
+    let opts = embedded_fatfs::FormatVolumeOptions::new()
+        .oem_name(*b"MYBOOT  ")
+        .media(0xF0);
+    let storage_vec: Vec<u8> = vec![0_u8; MB as usize];
+    let mut stream =
+        embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(storage_vec)));
+    embedded_fatfs::format_volume(&mut stream, opts)
+        .await
+        .expect("format volume");
+    // media descriptor in the BPB and in the first FAT entry
+    let mut media = [0_u8; 1];
+    stream.seek(SeekFrom::Start(21)).await.unwrap();
+    stream.read_exact(&mut media).await.unwrap();
+    assert_eq!(media[0], 0xF0);
+    stream.seek(SeekFrom::Start(512)).await.unwrap();
+    stream.read_exact(&mut media).await.unwrap();
+    assert_eq!(media[0], 0xF0);
+    stream.seek(SeekFrom::Start(0)).await.unwrap();
+
+    let fs = embedded_fatfs::FileSystem::new(stream, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("open fs");
+    assert_eq!(fs.oem_name(), "MYBOOT");
+    basic_fs_test(&fs).await;
+
+    // a name of another filesystem would make the volume unmountable
+    let opts = embedded_fatfs::FormatVolumeOptions::new().oem_name(*b"EXFAT   ");
+    let mut stream = embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(vec![
+            0_u8;
+            MB as usize
+        ])));
+    assert!(matches!(
+        embedded_fatfs::format_volume(&mut stream, opts).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+}
+
+async fn read_fats<S: Read + Seek>(storage: &mut S) -> Vec<Vec<u8>> {
+    let mut bpb = [0_u8; 40];
+    storage.seek(SeekFrom::Start(0)).await.unwrap();
+    storage.read_exact(&mut bpb).await.unwrap();
+    let bytes_per_sector = u64::from(u16::from_le_bytes([bpb[11], bpb[12]]));
+    let reserved_sectors = u64::from(u16::from_le_bytes([bpb[14], bpb[15]]));
+    let fats = bpb[16];
+    let sectors_per_fat = match u16::from_le_bytes([bpb[22], bpb[23]]) {
+        0 => u64::from(u32::from_le_bytes([bpb[36], bpb[37], bpb[38], bpb[39]])),
+        n => u64::from(n),
+    };
+    let fat_size = sectors_per_fat * bytes_per_sector;
+    let mut result = Vec::new();
+    for i in 0..u64::from(fats) {
+        let mut fat = vec![0_u8; fat_size as usize];
+        storage
+            .seek(SeekFrom::Start((reserved_sectors * bytes_per_sector) + i * fat_size))
+            .await
+            .unwrap();
+        storage.read_exact(&mut fat).await.unwrap();
+        result.push(fat);
+    }
+    storage.seek(SeekFrom::Start(0)).await.unwrap();
+    result
+}
+
+async fn write_test_file<S: embedded_io_async::Read + embedded_io_async::Write + Seek>(storage: &mut S, name: &str) {
+    storage.seek(SeekFrom::Start(0)).await.unwrap();
+    let fs = embedded_fatfs::FileSystem::new(storage, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("open fs");
+    let mut file = fs.root_dir().create_file(name).await.unwrap();
+    for _ in 0..100 {
+        file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    }
+    file.flush().await.unwrap();
+    drop(file);
+    fs.unmount().await.unwrap();
+}
+
+async fn sync_fats<S: embedded_io_async::Read + embedded_io_async::Write + Seek>(storage: &mut S) {
+    storage.seek(SeekFrom::Start(0)).await.unwrap();
+    let fs = embedded_fatfs::FileSystem::new(storage, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("open fs");
+    fs.sync_fats().await.unwrap();
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fat_mirrors() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    for (fat_type, size) in [
+        (embedded_fatfs::FatType::Fat12, MB),
+        (embedded_fatfs::FatType::Fat16, 8 * MB),
+        (embedded_fatfs::FatType::Fat32, 40 * MB),
+    ] {
+        let mut stream =
+            embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(vec![
+                0_u8;
+                size
+                    as usize
+            ])));
+        let opts = embedded_fatfs::FormatVolumeOptions::new().fat_type(fat_type);
+        embedded_fatfs::format_volume(&mut stream, opts)
+            .await
+            .expect("format volume");
+        let formatted = read_fats(&mut stream).await;
+        assert_eq!(formatted.len(), 2);
+
+        // FAT changes are written to both copies
+        write_test_file(&mut stream, "a.txt").await;
+        let fats = read_fats(&mut stream).await;
+        assert_ne!(fats[0], formatted[0]);
+        assert_eq!(fats[0], fats[1]);
+
+        // a copy that went out of sync is restored from the first FAT
+        stream.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut corrupted = fats.clone();
+        corrupted[1][10..20].fill(0xAB);
+        let fat_size = fats[0].len() as u64;
+        let second_fat_pos = {
+            let mut bpb = [0_u8; 16];
+            stream.read_exact(&mut bpb).await.unwrap();
+            u64::from(u16::from_le_bytes([bpb[11], bpb[12]])) * u64::from(u16::from_le_bytes([bpb[14], bpb[15]]))
+                + fat_size
+        };
+        stream.seek(SeekFrom::Start(second_fat_pos)).await.unwrap();
+        stream.write_all(&corrupted[1]).await.unwrap();
+        assert_ne!(read_fats(&mut stream).await[1], fats[0]);
+        sync_fats(&mut stream).await;
+        let synced = read_fats(&mut stream).await;
+        assert_eq!(synced[0], fats[0]);
+        assert_eq!(synced[1], fats[0]);
+
+        if fat_type == embedded_fatfs::FatType::Fat32 {
+            // with mirroring disabled only the active FAT is updated
+            stream.seek(SeekFrom::Start(40)).await.unwrap();
+            stream.write_all(&0x81_u16.to_le_bytes()).await.unwrap();
+            write_test_file(&mut stream, "b.txt").await;
+            let fats = read_fats(&mut stream).await;
+            assert_eq!(fats[0], synced[0]);
+            assert_ne!(fats[1], synced[1]);
+
+            sync_fats(&mut stream).await;
+            let synced = read_fats(&mut stream).await;
+            assert_eq!(synced[0], fats[1]);
+            assert_eq!(synced[1], fats[1]);
+        }
+    }
+}
+
+// an in-memory writer with its own error type, which is unrelated to the error type of the storage
+struct VecSink {
+    data: Vec<u8>,
+    capacity: usize,
+}
+
+#[derive(Debug, PartialEq)]
+struct SinkFull;
+
+impl embedded_io_async::Error for SinkFull {
+    fn kind(&self) -> embedded_io_async::ErrorKind {
+        embedded_io_async::ErrorKind::OutOfMemory
+    }
+}
+
+impl embedded_io_async::ErrorType for VecSink {
+    type Error = SinkFull;
+}
+
+impl embedded_io_async::Write for VecSink {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let len = buf.len().min(self.capacity - self.data.len());
+        if len == 0 && !buf.is_empty() {
+            return Err(SinkFull);
+        }
+        self.data.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
+
+#[tokio::test]
+async fn test_export_image() {
+    let fs = test_format_fs(embedded_fatfs::FormatVolumeOptions::new(), MB).await;
+    let mut file = fs.root_dir().create_file("exported.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+
+    let mut out = VecSink {
+        data: Vec::new(),
+        capacity: MB as usize,
+    };
+    fs.export_image(&mut out).await.unwrap();
+    let image = out.data;
+    assert_eq!(image.len() as u64, MB);
+
+    // errors of the writer are returned as they are
+    let mut out = VecSink {
+        data: Vec::new(),
+        capacity: 4096,
+    };
+    assert!(matches!(
+        fs.export_image(&mut out).await,
+        Err(embedded_fatfs::ExportError::Sink(SinkFull))
+    ));
+
+    let copy = embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(image)));
+    let copy = embedded_fatfs::FileSystem::new(copy, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("mount exported image");
+    assert!(!copy.read_status_flags().await.unwrap().dirty());
+    assert_eq!(copy.volume_id(), fs.volume_id());
+    let mut file = copy.root_dir().open_file("exported.txt").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+    let mut file = copy.root_dir().open_file("new-name.txt").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.repeat(1000).as_bytes());
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_flatten_adapter_error() {
+    use block_device_adapters::{BufStream, BufStreamError, StreamSlice, StreamSliceError};
+    use block_device_driver::{RamDisk, RamDiskError};
+
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut stream = BufStream::<_, 512>::new(RamDisk::<512>::new((8 * MB / 512) as usize));
+    embedded_fatfs::format_volume(&mut stream, embedded_fatfs::FormatVolumeOptions::new())
+        .await
+        .expect("format volume");
+    stream.seek(SeekFrom::Start(0)).await.unwrap();
+    // the slice ends long before the end of the volume, so writing a large file fails at the end of the slice
+    let slice = StreamSlice::new(stream, 0, MB).await.unwrap();
+    let fs = embedded_fatfs::FileSystem::new(slice, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("open fs");
+    let mut file = fs.root_dir().create_file("large.bin").await.unwrap();
+    let err: embedded_fatfs::Error<StreamSliceError<BufStreamError<RamDiskError>>> =
+        file.write_all(&vec![0; 2 * MB as usize]).await.unwrap_err();
+    // the directory entry is located at the start of the volume and can still be updated
+    file.flush().await.unwrap();
+    assert!(matches!(err, embedded_fatfs::Error::Io(StreamSliceError::EndOfSlice)));
+    let err: embedded_fatfs::Error<StreamSliceError<RamDiskError>> = err.map_io(StreamSliceError::flatten);
+    assert!(matches!(err, embedded_fatfs::Error::Io(StreamSliceError::EndOfSlice)));
+
+    // the error of the device stays recoverable through both layers
+    let err = embedded_fatfs::Error::Io(StreamSliceError::Other(BufStreamError::Io(RamDiskError::OutOfBounds)));
+    let err = err.map_io(StreamSliceError::flatten);
+    assert!(matches!(
+        err,
+        embedded_fatfs::Error::Io(StreamSliceError::Other(RamDiskError::OutOfBounds))
+    ));
+    assert!(matches!(
+        embedded_fatfs::Error::<u8>::NotFound.map_io(u32::from),
+        embedded_fatfs::Error::NotFound
+    ));
+}
+
+async fn read_fs_info_free_cluster_count<S: Read + Seek>(storage: &mut S) -> u32 {
+    // FSInfo is stored in sector 1 by format_volume, the free cluster count is at offset 488
+    storage.seek(SeekFrom::Start(512 + 488)).await.unwrap();
+    let mut buf = [0_u8; 4];
+    storage.read_exact(&mut buf).await.unwrap();
+    u32::from_le_bytes(buf)
+}
+
+#[tokio::test]
+async fn test_format_fat32_fs_info() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let storage_vec: Vec<u8> = vec![0xD1_u8; (40 * MB) as usize];
+    let mut stream =
+        embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(storage_vec)));
+    let opts = embedded_fatfs::FormatVolumeOptions::new().fat_type(embedded_fatfs::FatType::Fat32);
+    embedded_fatfs::format_volume(&mut stream, opts)
+        .await
+        .expect("format volume");
+
+    let fs_info_free = read_fs_info_free_cluster_count(&mut stream).await;
+    stream.seek(SeekFrom::Start(0)).await.unwrap();
+    {
+        let fs = embedded_fatfs::FileSystem::new(&mut stream, embedded_fatfs::FsOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat32);
+        let cluster_size = fs.cluster_size();
+        assert_eq!(fs.stats().await.unwrap().free_clusters(), fs_info_free);
+        assert_eq!(fs.recompute_stats().await.unwrap().free_clusters(), fs_info_free);
+
+        {
+            let mut file = fs.root_dir().create_file("test.bin").await.unwrap();
+            file.write_all(&vec![0_u8; cluster_size as usize * 10]).await.unwrap();
+            file.flush().await.unwrap();
+        }
+        fs.unmount().await.unwrap();
+    }
+
+    let fs_info_free_after = read_fs_info_free_cluster_count(&mut stream).await;
+    assert_eq!(fs_info_free_after, fs_info_free - 10);
+    stream.seek(SeekFrom::Start(0)).await.unwrap();
+    let fs = embedded_fatfs::FileSystem::new(&mut stream, embedded_fatfs::FsOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), fs_info_free_after);
+    assert_eq!(fs.recompute_stats().await.unwrap().free_clusters(), fs_info_free_after);
+    fs.unmount().await.unwrap();
+}
+
+async fn format_and_read_boot_sector(opts: embedded_fatfs::FormatVolumeOptions, total_bytes: u64) -> [u8; 512] {
+    let storage_vec: Vec<u8> = vec![0xD1_u8; total_bytes as usize];
+    let mut stream =
+        embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(storage_vec)));
+    embedded_fatfs::format_volume(&mut stream, opts)
+        .await
+        .expect("format volume");
+    stream.seek(SeekFrom::Start(0)).await.unwrap();
+    let mut boot_sector = [0_u8; 512];
+    stream.read_exact(&mut boot_sector).await.unwrap();
+    boot_sector
+}
+
+#[tokio::test]
+async fn test_format_geometry_overrides() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    // FAT16 volume smaller than the storage
+    let opts = embedded_fatfs::FormatVolumeOptions::new()
+        .total_sectors(15360)
+        .sectors_per_cluster(2)
+        .reserved_sectors(4);
+    let boot_sector = format_and_read_boot_sector(opts.clone(), 8 * MB).await;
+    assert_eq!(boot_sector[13], 2);
+    assert_eq!(u16::from_le_bytes([boot_sector[14], boot_sector[15]]), 4);
+    assert_eq!(u16::from_le_bytes([boot_sector[19], boot_sector[20]]), 15360);
+    let fs = test_format_fs(opts, 8 * MB).await;
+    assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat16);
+    assert_eq!(fs.cluster_size(), 1024);
+
+    // FAT32 volume with a bigger reserved area
+    let opts = embedded_fatfs::FormatVolumeOptions::new()
+        .sectors_per_cluster(1)
+        .reserved_sectors(32);
+    let boot_sector = format_and_read_boot_sector(opts.clone(), 40 * MB).await;
+    assert_eq!(boot_sector[13], 1);
+    assert_eq!(u16::from_le_bytes([boot_sector[14], boot_sector[15]]), 32);
+    assert_eq!(u32::from_le_bytes(boot_sector[32..36].try_into().unwrap()), 81920);
+    let fs = test_format_fs(opts, 40 * MB).await;
+    assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat32);
+    assert_eq!(fs.cluster_size(), 512);
+}
+
+#[tokio::test]
+async fn test_format_invalid_geometry() {
+    let invalid_opts = [
+        // not a power of two
+        embedded_fatfs::FormatVolumeOptions::new().sectors_per_cluster(3),
+        // no room for the boot sector
+        embedded_fatfs::FormatVolumeOptions::new().reserved_sectors(0),
+        // conflicting cluster sizes
+        embedded_fatfs::FormatVolumeOptions::new()
+            .sectors_per_cluster(2)
+            .bytes_per_cluster(4096),
+        // too many clusters for FAT16 but no room for the FAT32 backup boot sector
+        embedded_fatfs::FormatVolumeOptions::new()
+            .sectors_per_cluster(1)
+            .reserved_sectors(4),
+    ];
+    for opts in invalid_opts {
+        let mut stream =
+            embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(vec![
+                0_u8;
+                (40 * MB)
+                    as usize
+            ])));
+        assert!(matches!(
+            embedded_fatfs::format_volume(&mut stream, opts).await,
+            Err(embedded_fatfs::Error::InvalidInput)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_format_forced_fat_type() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    // automatically formatted as FAT16
+    let fs = test_format_fs(embedded_fatfs::FormatVolumeOptions::new(), 34 * MB).await;
+    assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat16);
+
+    let opts = embedded_fatfs::FormatVolumeOptions::new().fat_type(embedded_fatfs::FatType::Fat32);
+    let fs = test_format_fs(opts, 34 * MB).await;
+    assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat32);
+    {
+        let root_dir = fs.root_dir();
+        let mut file = root_dir.create_file("forced.txt").await.unwrap();
+        file.write_all(TEST_STR.as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+        file.rewind().await.unwrap();
+        assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    }
+
+    // too many 512 byte clusters for FAT16, FAT32 is not selected instead
+    let opts = embedded_fatfs::FormatVolumeOptions::new()
+        .bytes_per_cluster(512)
+        .fat_type(embedded_fatfs::FatType::Fat16);
+    let storage_vec: Vec<u8> = vec![0_u8; (40 * MB) as usize];
+    let mut stream =
+        embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(storage_vec)));
+    assert!(matches!(
+        embedded_fatfs::format_volume(&mut stream, opts).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    let opts = embedded_fatfs::FormatVolumeOptions::new().fat_type(embedded_fatfs::FatType::Fat16);
+    let fs = test_format_fs(opts, 40 * MB).await;
+    assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat16);
+
+    // 16 MB cannot hold the minimal number of FAT32 clusters
+    let opts = embedded_fatfs::FormatVolumeOptions::new().fat_type(embedded_fatfs::FatType::Fat32);
+    let storage_vec: Vec<u8> = vec![0_u8; (16 * MB) as usize];
+    let mut stream =
+        embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(storage_vec)));
+    assert!(matches!(
+        embedded_fatfs::format_volume(&mut stream, opts).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+}
+
+#[tokio::test]
+async fn test_format_root_dir_entries() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut total_clusters = Vec::new();
+    for entries in [224_u16, 512] {
+        let opts = embedded_fatfs::FormatVolumeOptions::new().root_dir_entries(entries);
+        let boot_sector = format_and_read_boot_sector(opts.clone(), 16 * MB).await;
+        assert_eq!(u16::from_le_bytes([boot_sector[17], boot_sector[18]]), entries);
+
+        let storage_vec: Vec<u8> = vec![0_u8; (16 * MB) as usize];
+        let mut stream =
+            embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(storage_vec)));
+        embedded_fatfs::format_volume(&mut stream, opts)
+            .await
+            .expect("format volume");
+        let fs = embedded_fatfs::FileSystem::new(stream, embedded_fatfs::FsOptions::new())
+            .await
+            .expect("open fs");
+        assert_eq!(fs.fat_type(), embedded_fatfs::FatType::Fat16);
+        total_clusters.push(fs.stats().await.unwrap().total_clusters());
+
+        // every file takes a long name and a short name entry of the fixed-size root directory
+        let root_dir = fs.root_dir();
+        let mut created = 0;
+        loop {
+            match root_dir.create_file(&format!("F{}.TXT", created)).await {
+                Ok(mut file) => file.flush().await.unwrap(),
+                Err(embedded_fatfs::Error::NotEnoughSpace) => break,
+                Err(err) => panic!("unexpected error: {:?}", err),
+            }
+            created += 1;
+        }
+        assert_eq!(created * 2, entries);
+    }
+    // a smaller root directory leaves more room for data
+    assert!(total_clusters[0] > total_clusters[1]);
+
+    // the root directory must fill whole sectors
+    let storage_vec: Vec<u8> = vec![0_u8; (16 * MB) as usize];
+    let mut stream =
+        embedded_io_adapters::tokio_1::FromTokio::new(tokio::io::BufStream::new(io::Cursor::new(storage_vec)));
+    assert!(matches!(
+        embedded_fatfs::format_volume(
+            &mut stream,
+            embedded_fatfs::FormatVolumeOptions::new().root_dir_entries(100)
+        )
+        .await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+
+    // the option is ignored on FAT32
+    let opts = embedded_fatfs::FormatVolumeOptions::new()
+        .fat_type(embedded_fatfs::FatType::Fat32)
+        .root_dir_entries(224);
+    let boot_sector = format_and_read_boot_sector(opts, 40 * MB).await;
+    assert_eq!(u16::from_le_bytes([boot_sector[17], boot_sector[18]]), 0);
+}
+
+struct CountingDevice {
+    inner: block_device_driver::RamDisk<512>,
+    writes: std::rc::Rc<std::cell::Cell<usize>>,
+}
+
+impl block_device_driver::BlockDevice<512> for CountingDevice {
+    type Error = block_device_driver::RamDiskError;
+    type Align = aligned::A4;
+
+    async fn read(
+        &mut self,
+        block_address: u32,
+        data: &mut [aligned::Aligned<Self::Align, [u8; 512]>],
+    ) -> Result<(), Self::Error> {
+        self.inner.read(block_address, data).await
+    }
+
+    async fn write(
+        &mut self,
+        block_address: u32,
+        data: &[aligned::Aligned<Self::Align, [u8; 512]>],
+    ) -> Result<(), Self::Error> {
+        self.writes.set(self.writes.get() + 1);
+        self.inner.write(block_address, data).await
+    }
+
+    async fn size(&mut self) -> Result<u64, Self::Error> {
+        self.inner.size().await
+    }
+}
+
+#[tokio::test]
+async fn test_create_files_batches_writes() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let writes = std::rc::Rc::new(std::cell::Cell::new(0));
+    let device = CountingDevice {
+        inner: block_device_driver::RamDisk::<512>::new((8 * MB / 512) as usize),
+        writes: writes.clone(),
+    };
+    let mut stream = block_device_adapters::BufStream::<_, 512>::new(device);
+    embedded_fatfs::format_volume(&mut stream, embedded_fatfs::FormatVolumeOptions::new())
+        .await
+        .expect("format volume");
+    let fs = embedded_fatfs::FileSystem::new(stream, embedded_fatfs::FsOptions::new())
+        .await
+        .expect("open fs");
+    let root_dir = fs.root_dir();
+    let dir_a = root_dir.create_dir("a").await.unwrap();
+    let dir_b = root_dir.create_dir("b").await.unwrap();
+    let names = (0..16).map(|i| format!("file number {}.txt", i)).collect::<Vec<_>>();
+    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let start = writes.get();
+    for name in &names {
+        dir_a.create_file(name).await.unwrap().flush().await.unwrap();
+    }
+    let individual_writes = writes.get() - start;
+
+    let start = writes.get();
+    dir_b.create_files(&names).await.unwrap();
+    let batched_writes = writes.get() - start;
+    assert!(
+        batched_writes * 2 <= individual_writes,
+        "batched: {}, individual: {}",
+        batched_writes,
+        individual_writes
+    );
+
+    // both directories contain the same entries
+    for dir in [&dir_a, &dir_b] {
+        let filenames = dir
+            .iter()
+            .collect()
+            .await
+            .iter()
+            .map(|r| r.as_ref().unwrap().file_name())
+            .collect::<Vec<String>>();
+        assert_eq!(&filenames[2..], &names[..]);
+    }
+
+    // existing files are opened and an error stops the batch
+    dir_b.create_files(&["file number 0.txt"]).await.unwrap();
+    assert!(matches!(
+        root_dir.create_files(&["new.txt", "a"]).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    assert!(root_dir.file_exists("new.txt").await.unwrap());
+}
+
 async fn read_to_end<IO: embedded_io_async::Read>(io: &mut IO) -> Result<Vec<u8>, IO::Error> {
     let mut buf = Vec::new();
     loop {
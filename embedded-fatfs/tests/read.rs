@@ -1,7 +1,11 @@
+use std::cell::Cell;
+use std::rc::Rc;
 use std::str;
 
-use embedded_fatfs::{ChronoTimeProvider, FatType, FsOptions, LossyOemCpConverter};
-use embedded_io_async::{Read, Seek, SeekFrom};
+use embedded_fatfs::{
+    BootSector, ChronoTimeProvider, FatType, FileAttributes, FileSystemKind, FsOptions, LossyOemCpConverter,
+};
+use embedded_io_async::{BufRead, Read, Seek, SeekFrom, Write};
 
 const TEST_TEXT: &str = "Rust is cool!\n";
 const FAT12_IMG: &str = "resources/fat12.img";
@@ -15,9 +19,13 @@ type FileSystem = embedded_fatfs::FileSystem<
 >;
 
 async fn create_fs(name: &str) -> FileSystem {
+    create_fs_with_options(name, FsOptions::new()).await
+}
+
+async fn create_fs_with_options(name: &str, options: FsOptions<ChronoTimeProvider, LossyOemCpConverter>) -> FileSystem {
     let _ = env_logger::builder().is_test(true).try_init();
     let file = tokio::fs::File::open(name).await.unwrap();
-    embedded_fatfs::FileSystem::new(file, FsOptions::new()).await.unwrap()
+    embedded_fatfs::FileSystem::new(file, options).await.unwrap()
 }
 
 async fn test_root_dir(fs: FileSystem) {
@@ -65,10 +73,7 @@ async fn test_read_seek_short_file(fs: FileSystem) {
     short_file.read_exact(&mut buf2).await.unwrap();
     assert_eq!(str::from_utf8(&buf2).unwrap(), &TEST_TEXT[5..10]);
 
-    assert_eq!(
-        short_file.seek(SeekFrom::Start(1000)).await.unwrap(),
-        TEST_TEXT.len() as u64
-    );
+    assert_eq!(short_file.seek(SeekFrom::Start(1000)).await.unwrap(), 1000);
     let mut buf2 = [0; 5];
     assert_eq!(short_file.read(&mut buf2).await.unwrap(), 0);
 }
@@ -287,9 +292,639 @@ async fn test_exists_fat32() {
     test_exists(create_fs(FAT32_IMG).await).await
 }
 
+async fn test_extents(fs: FileSystem, img: &str) {
+    // the test images use 512 byte sectors
+    const SECTOR_SIZE: usize = 512;
+    let image = std::fs::read(img).unwrap();
+    let mut file = fs.root_dir().open_file("long.txt").await.unwrap();
+    let expected = read_to_end(&mut file).await.unwrap();
+
+    let mut data = Vec::new();
+    let mut extents = file.extents();
+    while let Some(r) = extents.next().await {
+        let (sector, count) = r.unwrap();
+        let start = sector as usize * SECTOR_SIZE;
+        data.extend_from_slice(&image[start..start + count as usize * SECTOR_SIZE]);
+    }
+    // the last sector is padded
+    assert_eq!(data.len(), expected.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE);
+    assert_eq!(&data[..expected.len()], &expected[..]);
+}
+
+#[tokio::test]
+async fn test_extents_fat12() {
+    test_extents(create_fs(FAT12_IMG).await, FAT12_IMG).await
+}
+
+#[tokio::test]
+async fn test_extents_fat16() {
+    test_extents(create_fs(FAT16_IMG).await, FAT16_IMG).await
+}
+
+#[tokio::test]
+async fn test_extents_fat32() {
+    test_extents(create_fs(FAT32_IMG).await, FAT32_IMG).await
+}
+
+/// Storage wrapper counting the read and write calls reaching the storage.
+struct CountingStorage {
+    inner: embedded_io_adapters::tokio_1::FromTokio<tokio::fs::File>,
+    reads: Rc<Cell<usize>>,
+    writes: Rc<Cell<usize>>,
+}
+
+impl CountingStorage {
+    async fn open(name: &str) -> Self {
+        Self {
+            inner: embedded_io_adapters::tokio_1::FromTokio::new(tokio::fs::File::open(name).await.unwrap()),
+            reads: Rc::new(Cell::new(0)),
+            writes: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+impl embedded_io_async::ErrorType for CountingStorage {
+    type Error = std::io::Error;
+}
+
+impl Read for CountingStorage {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.read(buf).await
+    }
+}
+
+impl Write for CountingStorage {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.writes.set(self.writes.get() + 1);
+        self.inner.write(buf).await
+    }
+}
+
+impl Seek for CountingStorage {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        self.inner.seek(pos).await
+    }
+}
+
+async fn test_buf_reader(name: &str) {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let storage = CountingStorage::open(name).await;
+    let reads = storage.reads.clone();
+    let fs = embedded_fatfs::FileSystem::new(storage, FsOptions::new())
+        .await
+        .unwrap();
+    let root_dir = fs.root_dir();
+    let expected = TEST_TEXT.repeat(1000);
+
+    let mut file = root_dir.open_file("long.txt").await.unwrap();
+    reads.set(0);
+    let mut unbuffered = Vec::new();
+    let mut byte = [0_u8];
+    while file.read(&mut byte).await.unwrap() == 1 {
+        unbuffered.push(byte[0]);
+    }
+    let unbuffered_reads = reads.get();
+    assert_eq!(str::from_utf8(&unbuffered).unwrap(), expected);
+
+    let file = root_dir.open_file("long.txt").await.unwrap();
+    let mut reader = embedded_fatfs::BufReader::<_, _, _, 512>::new(file);
+    reads.set(0);
+    let mut buffered = Vec::new();
+    while reader.read(&mut byte).await.unwrap() == 1 {
+        buffered.push(byte[0]);
+    }
+    let buffered_reads = reads.get();
+    assert_eq!(buffered, unbuffered);
+    assert!(
+        buffered_reads * 100 < unbuffered_reads,
+        "{} buffered reads, {} unbuffered reads",
+        buffered_reads,
+        unbuffered_reads
+    );
+
+    // seeking discards the buffered data
+    assert_eq!(reader.seek(SeekFrom::Start(2017)).await.unwrap(), 2017);
+    reader.read_exact(&mut byte).await.unwrap();
+    assert_eq!(byte[0], expected.as_bytes()[2017]);
+    assert_eq!(reader.seek(SeekFrom::Current(-2)).await.unwrap(), 2016);
+    let data = reader.fill_buf().await.unwrap();
+    assert_eq!(data[..10], expected.as_bytes()[2016..2026]);
+    reader.consume(10);
+    let mut buf = [0_u8; 4];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, expected.as_bytes()[2026..2030]);
+}
+
+#[tokio::test]
+async fn test_buf_reader_fat12() {
+    test_buf_reader(FAT12_IMG).await
+}
+
+#[tokio::test]
+async fn test_buf_reader_fat16() {
+    test_buf_reader(FAT16_IMG).await
+}
+
+#[tokio::test]
+async fn test_buf_reader_fat32() {
+    test_buf_reader(FAT32_IMG).await
+}
+
+async fn count_writes_of_read_workload(name: &str) -> usize {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let storage = CountingStorage::open(name).await;
+    let writes = storage.writes.clone();
+    let fs = embedded_fatfs::FileSystem::new(storage, FsOptions::new())
+        .await
+        .unwrap();
+    {
+        let root_dir = fs.root_dir();
+        root_dir.iter().collect().await;
+        for path in ["long.txt", "short.txt", "very/long/path/test.txt"] {
+            let mut file = root_dir.open_file(path).await.unwrap();
+            read_to_end(&mut file).await.unwrap();
+            file.flush().await.unwrap();
+        }
+        fs.stats().await.unwrap();
+    }
+    fs.unmount().await.unwrap();
+    writes.get()
+}
+
+#[tokio::test]
+async fn test_read_only_workload_does_not_write() {
+    for name in [FAT12_IMG, FAT16_IMG, FAT32_IMG] {
+        // accessed dates are not updated by default
+        assert_eq!(count_writes_of_read_workload(name).await, 0);
+    }
+}
+
+async fn test_geometry(fs: FileSystem, expected: &str) {
+    let geometry = fs.geometry().await.unwrap();
+    let stats = fs.stats().await.unwrap();
+    assert_eq!(geometry.fat_type(), fs.fat_type());
+    assert_eq!(geometry.total_clusters(), stats.total_clusters());
+    assert_eq!(geometry.free_clusters(), stats.free_clusters());
+    assert_eq!(
+        u32::from(geometry.bytes_per_sector()) * u32::from(geometry.sectors_per_cluster()),
+        stats.cluster_size()
+    );
+    assert_eq!(geometry.to_string(), expected);
+}
+
+#[tokio::test]
+async fn test_geometry_fat12() {
+    test_geometry(
+        create_fs(FAT12_IMG).await,
+        "FAT12, 512 bytes/sector, 1 sectors/cluster, 1920/1955 clusters free, 1 reserved sectors, 2 FATs, 32 root dir sectors",
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_geometry_fat16() {
+    test_geometry(
+        create_fs(FAT16_IMG).await,
+        "FAT16, 512 bytes/sector, 1 sectors/cluster, 4892/4927 clusters free, 1 reserved sectors, 2 FATs, 32 root dir sectors",
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_geometry_fat32() {
+    test_geometry(
+        create_fs(FAT32_IMG).await,
+        "FAT32, 512 bytes/sector, 1 sectors/cluster, 66886/66922 clusters free, 32 reserved sectors, 2 FATs, 0 root dir sectors",
+    )
+    .await
+}
+
+async fn test_dir_metadata(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let meta = root_dir.metadata();
+    assert!(meta.is_root());
+    assert_eq!(meta.is_fixed_root(), fs.fat_type() != FatType::Fat32);
+    assert_eq!(meta.first_cluster().is_none(), meta.is_fixed_root());
+    assert_eq!(meta.attributes(), FileAttributes::DIRECTORY);
+    assert_eq!(root_dir.content_len().await.unwrap(), 14000 + 14);
+
+    let mut dirs = 0;
+    let entries = root_dir.iter().collect().await;
+    for e in entries.iter().map(|r| r.as_ref().unwrap()).filter(|e| e.is_dir()) {
+        let meta = e.to_dir().metadata();
+        assert!(!meta.is_root());
+        assert!(!meta.is_fixed_root());
+        assert!(meta.first_cluster().is_some());
+        assert_eq!(meta.first_cluster(), e.first_cluster());
+        assert_eq!(meta.attributes(), e.attributes());
+        dirs += 1;
+    }
+    assert_eq!(dirs, 2);
+
+    let dir = root_dir.open_dir("very/long/path").await.unwrap();
+    assert_eq!(
+        dir.metadata().first_cluster(),
+        root_dir.open_meta("very/long/path").await.unwrap().first_cluster()
+    );
+    assert_eq!(dir.content_len().await.unwrap(), 14);
+}
+
+#[tokio::test]
+async fn test_dir_metadata_fat12() {
+    test_dir_metadata(create_fs(FAT12_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_dir_metadata_fat16() {
+    test_dir_metadata(create_fs(FAT16_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_dir_metadata_fat32() {
+    test_dir_metadata(create_fs(FAT32_IMG).await).await
+}
+
+fn foreign_boot_sector(bootjmp: [u8; 3], fs_name: &[u8; 8]) -> Vec<u8> {
+    let mut sector = vec![0_u8; 4096];
+    sector[0..3].copy_from_slice(&bootjmp);
+    sector[3..11].copy_from_slice(fs_name);
+    sector[510..512].copy_from_slice(&[0x55, 0xAA]);
+    sector
+}
+
+async fn mount_foreign_volume(sector: Vec<u8>) -> Result<(), embedded_fatfs::Error<std::io::Error>> {
+    let storage = embedded_io_adapters::tokio_1::FromTokio::new(std::io::Cursor::new(sector));
+    embedded_fatfs::FileSystem::new(storage, FsOptions::new())
+        .await
+        .map(|_| ())
+}
+
+#[tokio::test]
+async fn test_unsupported_file_system() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    // exFAT has no BPB - the FAT BPB region is zeroed
+    let mut exfat = foreign_boot_sector([0xEB, 0x76, 0x90], b"EXFAT   ");
+    exfat[108] = 9; // BytesPerSectorShift
+    exfat[109] = 3; // SectorsPerClusterShift
+    let r = mount_foreign_volume(exfat).await;
+    assert!(matches!(
+        r,
+        Err(embedded_fatfs::Error::UnsupportedFileSystem(FileSystemKind::ExFat))
+    ));
+
+    // NTFS has a BPB similar to FAT but with zeroed fields FAT requires
+    let mut ntfs = foreign_boot_sector([0xEB, 0x52, 0x90], b"NTFS    ");
+    ntfs[11..13].copy_from_slice(&512_u16.to_le_bytes());
+    ntfs[13] = 8;
+    ntfs[21] = 0xF8;
+    let r = mount_foreign_volume(ntfs).await;
+    assert!(matches!(
+        r,
+        Err(embedded_fatfs::Error::UnsupportedFileSystem(FileSystemKind::Ntfs))
+    ));
+    assert_eq!(
+        embedded_fatfs::Error::<std::io::Error>::UnsupportedFileSystem(FileSystemKind::Ntfs).to_string(),
+        "Unsupported file system: NTFS"
+    );
+
+    // any other name in the OEM field is still treated as FAT
+    let r = mount_foreign_volume(foreign_boot_sector([0xEB, 0x3C, 0x90], b"MSWIN4.1")).await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::CorruptedFileSystem)));
+}
+
+async fn test_from_existing_parts(name: &str) {
+    let mut file = embedded_io_adapters::tokio_1::FromTokio::new(tokio::fs::File::open(name).await.unwrap());
+    let boot = BootSector::read(&mut file).await.unwrap();
+    // the boot sector is not read again, so the position of the storage does not matter
+    let fs: FileSystem = embedded_fatfs::FileSystem::from_existing_parts(file, boot.clone(), FsOptions::new())
+        .await
+        .unwrap();
+    let reference = create_fs(name).await;
+
+    assert_eq!(fs.fat_type(), reference.fat_type());
+    assert_eq!(fs.volume_id(), reference.volume_id());
+    assert_eq!(fs.volume_label_as_bytes(), reference.volume_label_as_bytes());
+    assert_eq!(fs.oem_name(), reference.oem_name());
+    assert_eq!(fs.geometry().await.unwrap(), reference.geometry().await.unwrap());
+    let names = |entries: Vec<Result<embedded_fatfs::DirEntry<_, _, _>, _>>| {
+        entries
+            .iter()
+            .map(|e| e.as_ref().unwrap().file_name())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(fs.root_dir().iter().collect().await),
+        names(reference.root_dir().iter().collect().await)
+    );
+    let mut file = fs.root_dir().open_file("short.txt").await.unwrap();
+    let buf = read_to_end(&mut file).await.unwrap();
+    assert_eq!(str::from_utf8(&buf).unwrap(), TEST_TEXT);
+
+    // a boot sector read from the wrong location is rejected
+    let mut file = embedded_io_adapters::tokio_1::FromTokio::new(tokio::fs::File::open(name).await.unwrap());
+    file.seek(SeekFrom::Start(512)).await.unwrap();
+    assert!(matches!(
+        BootSector::read(&mut file).await,
+        Err(embedded_fatfs::Error::CorruptedFileSystem)
+    ));
+
+    // an invalid boot sector is rejected in optimized builds too, instead of dividing by zero
+    let r: Result<FileSystem, _> =
+        embedded_fatfs::FileSystem::from_existing_parts(file, BootSector::default(), FsOptions::new()).await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::CorruptedFileSystem)));
+}
+
+#[tokio::test]
+async fn test_from_existing_parts_fat12() {
+    test_from_existing_parts(FAT12_IMG).await
+}
+
+#[tokio::test]
+async fn test_from_existing_parts_fat16() {
+    test_from_existing_parts(FAT16_IMG).await
+}
+
+#[tokio::test]
+async fn test_from_existing_parts_fat32() {
+    test_from_existing_parts(FAT32_IMG).await
+}
+
+async fn test_read_vectored(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.open_file("long.txt").await.unwrap();
+    let expected = read_to_end(&mut file).await.unwrap();
+
+    // buffers spanning cluster boundaries
+    let cluster_size = fs.cluster_size() as usize;
+    let (mut a, mut b, mut c) = (vec![0; 100], vec![0; cluster_size], vec![0; 3000]);
+    file.seek(SeekFrom::Start(7)).await.unwrap();
+    let n = file.read_vectored(&mut [&mut a, &mut b, &mut c]).await.unwrap();
+    assert_eq!(n, a.len() + b.len() + c.len());
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 7 + n as u64);
+
+    let (mut a2, mut b2, mut c2) = (vec![0; a.len()], vec![0; b.len()], vec![0; c.len()]);
+    file.seek(SeekFrom::Start(7)).await.unwrap();
+    file.read_exact(&mut a2).await.unwrap();
+    file.read_exact(&mut b2).await.unwrap();
+    file.read_exact(&mut c2).await.unwrap();
+    assert_eq!((a, b, c), (a2, b2, c2));
+
+    // several buffers, some of them empty, served from a single cluster
+    let (mut d, mut e, mut f, mut g) = (vec![0; 3], Vec::new(), vec![0; 5], vec![0; 11]);
+    file.seek(SeekFrom::Start(1)).await.unwrap();
+    let n = file.read_vectored(&mut [&mut d, &mut e, &mut f, &mut g]).await.unwrap();
+    assert_eq!(n, 19);
+    assert_eq!([d, f, g].concat(), &expected[1..20]);
+
+    // reading stops at the end of the file
+    let mut tail = vec![0; 20];
+    let mut empty = vec![0; 5];
+    file.seek(SeekFrom::End(-10)).await.unwrap();
+    assert_eq!(file.read_vectored(&mut [&mut tail, &mut empty]).await.unwrap(), 10);
+    assert_eq!(&tail[..10], &expected[expected.len() - 10..]);
+}
+
+#[tokio::test]
+async fn test_read_vectored_fat12() {
+    test_read_vectored(create_fs(FAT12_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_read_vectored_fat16() {
+    test_read_vectored(create_fs(FAT16_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_read_vectored_fat32() {
+    test_read_vectored(create_fs(FAT32_IMG).await).await
+}
+
+async fn test_exists_entry(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let e = root_dir.exists_entry("very/long/path").await.unwrap().unwrap();
+    assert!(e.is_dir());
+    assert_eq!(e.file_name(), "path");
+
+    let e = root_dir.exists_entry("very/long/path/test.txt").await.unwrap().unwrap();
+    assert!(e.is_file());
+    assert_eq!(e.len(), TEST_TEXT.len() as u64);
+    let meta = root_dir.open_meta("very/long/path/test.txt").await.unwrap();
+    assert_eq!(e.modified(), meta.modified());
+    assert_eq!(e.first_cluster(), meta.first_cluster());
+
+    assert!(root_dir
+        .exists_entry("very/long/path/missing.txt")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(root_dir.exists_entry("missing").await.unwrap().is_none());
+    // a missing directory in the path is still an error
+    assert!(matches!(
+        root_dir.exists_entry("very/missing/path/test.txt").await,
+        Err(embedded_fatfs::Error::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn test_exists_entry_fat12() {
+    test_exists_entry(create_fs(FAT12_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_exists_entry_fat16() {
+    test_exists_entry(create_fs(FAT16_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_exists_entry_fat32() {
+    test_exists_entry(create_fs(FAT32_IMG).await).await
+}
+
+async fn test_data_region_layout(fs: FileSystem, name: &str) {
+    let boot = std::fs::read(name).unwrap();
+    let u16_at = |pos: usize| u64::from(u16::from_le_bytes([boot[pos], boot[pos + 1]]));
+    let bytes_per_sector = u16_at(11);
+    let sectors_per_cluster = u64::from(boot[13]);
+    let reserved_sectors = u16_at(14);
+    let fats = u64::from(boot[16]);
+    let root_dir_sectors = (u16_at(17) * 32).div_ceil(bytes_per_sector);
+    let sectors_per_fat = match u16_at(22) {
+        0 => u64::from(u32::from_le_bytes(boot[36..40].try_into().unwrap())),
+        n => n,
+    };
+    let data_offset = (reserved_sectors + fats * sectors_per_fat + root_dir_sectors) * bytes_per_sector;
+    assert_eq!(u64::from(fs.cluster_size()), sectors_per_cluster * bytes_per_sector);
+    assert_eq!(fs.data_offset_bytes(), data_offset);
+
+    // the file data is found at the offset computed from its first cluster
+    let file = fs.root_dir().open_file("short.txt").await.unwrap();
+    let (cluster, _) = file.cluster_chain().next().await.unwrap().unwrap();
+    let offset = (fs.data_offset_bytes() + u64::from(cluster - 2) * u64::from(fs.cluster_size())) as usize;
+    assert_eq!(&boot[offset..offset + TEST_TEXT.len()], TEST_TEXT.as_bytes());
+}
+
+#[tokio::test]
+async fn test_data_region_layout_fat12() {
+    test_data_region_layout(create_fs(FAT12_IMG).await, FAT12_IMG).await
+}
+
+#[tokio::test]
+async fn test_data_region_layout_fat16() {
+    test_data_region_layout(create_fs(FAT16_IMG).await, FAT16_IMG).await
+}
+
+#[tokio::test]
+async fn test_data_region_layout_fat32() {
+    test_data_region_layout(create_fs(FAT32_IMG).await, FAT32_IMG).await
+}
+
+// A storage that fails backward seeks once `forward_only` is set
+struct ForwardOnlyStorage {
+    inner: embedded_io_adapters::tokio_1::FromTokio<tokio::fs::File>,
+    pos: u64,
+    forward_only: Rc<Cell<bool>>,
+}
+
+impl embedded_io_async::ErrorType for ForwardOnlyStorage {
+    type Error = std::io::Error;
+}
+
+impl Read for ForwardOnlyStorage {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf).await?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for ForwardOnlyStorage {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.write(buf).await?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
+}
+
+impl Seek for ForwardOnlyStorage {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+            SeekFrom::End(_) => None,
+        };
+        if self.forward_only.get() && target.map_or(true, |n| n < self.pos) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "backward seek"));
+        }
+        self.pos = self.inner.seek(pos).await?;
+        Ok(self.pos)
+    }
+}
+
+async fn test_forward_only_read(name: &str) {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let forward_only = Rc::new(Cell::new(false));
+    let storage = ForwardOnlyStorage {
+        inner: embedded_io_adapters::tokio_1::FromTokio::new(tokio::fs::File::open(name).await.unwrap()),
+        pos: 0,
+        forward_only: forward_only.clone(),
+    };
+    let fs = embedded_fatfs::FileSystem::new(storage, FsOptions::new())
+        .await
+        .unwrap();
+    let mut file = fs.root_dir().open_file("long.txt").await.unwrap();
+    file.set_forward_only(true).await.unwrap();
+
+    // from now on the storage cannot seek backward - the whole file can still be read sequentially
+    forward_only.set(true);
+    let expected = TEST_TEXT.repeat(1000);
+    let mut buf = [0; 100];
+    file.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf[..], &expected.as_bytes()[..100]);
+    assert_eq!(file.seek(SeekFrom::Start(1500)).await.unwrap(), 1500);
+    assert!(matches!(
+        file.seek(SeekFrom::Start(10)).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    assert!(matches!(file.write(b"x").await, Err(embedded_fatfs::Error::ReadOnly)));
+    let content = read_to_end(&mut file).await.unwrap();
+    assert_eq!(content, &expected.as_bytes()[1500..]);
+
+    // without the mode the data is read from the beginning again, which requires seeking backward
+    file.set_forward_only(false).await.unwrap();
+    file.seek(SeekFrom::Start(0)).await.unwrap();
+    assert!(matches!(
+        read_to_end(&mut file).await,
+        Err(embedded_fatfs::Error::Io(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_forward_only_read_fat12() {
+    test_forward_only_read(FAT12_IMG).await
+}
+
+#[tokio::test]
+async fn test_forward_only_read_fat16() {
+    test_forward_only_read(FAT16_IMG).await
+}
+
+#[tokio::test]
+async fn test_forward_only_read_fat32() {
+    test_forward_only_read(FAT32_IMG).await
+}
+
+async fn test_raw_bytes(fs: FileSystem, name: &str) {
+    let image = std::fs::read(name).unwrap();
+    let root_dir_start = match fs.fat_type() {
+        FatType::Fat32 => {
+            let root_cluster = u32::from_le_bytes(image[44..48].try_into().unwrap());
+            fs.data_offset_bytes() + u64::from(root_cluster - 2) * u64::from(fs.cluster_size())
+        }
+        _ => {
+            let root_entries = u16::from_le_bytes([image[17], image[18]]);
+            fs.data_offset_bytes() - u64::from(root_entries) * 32
+        }
+    };
+    // the root directories of the test images fit into a single cluster
+    let on_disk = image[root_dir_start as usize..]
+        .chunks_exact(32)
+        .take_while(|slot| slot[0] != 0)
+        .collect::<Vec<_>>();
+
+    let slots = fs.root_dir().raw_bytes().collect().await;
+    let slots = slots.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(slots.len(), on_disk.len());
+    assert!(slots.iter().zip(&on_disk).all(|(a, b)| a[..] == b[..]));
+    // the volume label, long name parts and short names are all returned
+    assert!(slots.len() > fs.root_dir().iter().collect().await.len());
+}
+
+#[tokio::test]
+async fn test_raw_bytes_fat12() {
+    test_raw_bytes(create_fs(FAT12_IMG).await, FAT12_IMG).await
+}
+
+#[tokio::test]
+async fn test_raw_bytes_fat16() {
+    test_raw_bytes(create_fs(FAT16_IMG).await, FAT16_IMG).await
+}
+
+#[tokio::test]
+async fn test_raw_bytes_fat32() {
+    test_raw_bytes(create_fs(FAT32_IMG).await, FAT32_IMG).await
+}
+
 async fn test_volume_metadata(fs: FileSystem, fat_type: FatType) {
     assert_eq!(fs.volume_id(), 0x1234_5678);
     assert_eq!(fs.volume_label(), "Test!");
+    assert_eq!(fs.bpb_volume_label(), "Test!");
+    assert_eq!(fs.oem_name(), "mkfs.fat");
     assert_eq!(&fs.read_volume_label_from_root_dir().await.unwrap().unwrap(), "Test!");
     assert_eq!(fs.fat_type(), fat_type);
 }
@@ -377,6 +1012,122 @@ async fn test_multi_thread() {
     }
 }
 
+async fn test_case_insensitive_lookup(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    for name in ["short.txt", "SHORT.TXT", "Short.Txt"] {
+        let mut file = root_dir.open_file(name).await.unwrap();
+        let buf = read_to_end(&mut file).await.unwrap();
+        assert_eq!(str::from_utf8(&buf).unwrap(), TEST_TEXT);
+    }
+    for name in ["very-long-dir-name", "VERY-LONG-DIR-NAME", "Very-Long-Dir-Name"] {
+        root_dir.open_dir(name).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_case_insensitive_lookup_fat12() {
+    test_case_insensitive_lookup(create_fs(FAT12_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_case_insensitive_lookup_fat16() {
+    test_case_insensitive_lookup(create_fs(FAT16_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_case_insensitive_lookup_fat32() {
+    test_case_insensitive_lookup(create_fs(FAT32_IMG).await).await
+}
+
+async fn test_case_sensitive_lookup(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    // short names match as displayed or as stored on disk (upper case)
+    for name in ["short.txt", "SHORT.TXT"] {
+        let mut file = root_dir.open_file(name).await.unwrap();
+        let buf = read_to_end(&mut file).await.unwrap();
+        assert_eq!(str::from_utf8(&buf).unwrap(), TEST_TEXT);
+    }
+    assert!(matches!(
+        root_dir.open_file("Short.Txt").await,
+        Err(embedded_fatfs::Error::NotFound)
+    ));
+    // long names must match exactly, the short name alias still matches
+    root_dir.open_dir("very-long-dir-name").await.unwrap();
+    root_dir.open_dir("VERY-L~1").await.unwrap();
+    assert!(matches!(
+        root_dir.open_dir("Very-Long-Dir-Name").await,
+        Err(embedded_fatfs::Error::NotFound)
+    ));
+    assert!(matches!(
+        root_dir.open_dir("Very").await,
+        Err(embedded_fatfs::Error::NotFound)
+    ));
+    assert!(root_dir.exists("very/long").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_case_sensitive_lookup_fat12() {
+    test_case_sensitive_lookup(create_fs_with_options(FAT12_IMG, FsOptions::new().case_insensitive(false)).await).await
+}
+
+#[tokio::test]
+async fn test_case_sensitive_lookup_fat16() {
+    test_case_sensitive_lookup(create_fs_with_options(FAT16_IMG, FsOptions::new().case_insensitive(false)).await).await
+}
+
+#[tokio::test]
+async fn test_case_sensitive_lookup_fat32() {
+    test_case_sensitive_lookup(create_fs_with_options(FAT32_IMG, FsOptions::new().case_insensitive(false)).await).await
+}
+
+async fn test_walk(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let paths = root_dir
+        .walk()
+        .collect()
+        .await
+        .into_iter()
+        .map(|r| r.unwrap().0)
+        .collect::<Vec<String>>();
+    assert_eq!(
+        paths,
+        [
+            "long.txt",
+            "short.txt",
+            "very",
+            "very/long",
+            "very/long/path",
+            "very/long/path/test.txt",
+            "very-long-dir-name",
+            "very-long-dir-name/very-long-file-name.txt",
+        ]
+    );
+
+    // paths are relative to the directory the walk starts in
+    let dir = root_dir.open_dir("very").await.unwrap();
+    let entries = dir.walk().collect().await;
+    let entries = entries.iter().map(|r| r.as_ref().unwrap()).collect::<Vec<_>>();
+    let paths = entries.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>();
+    assert_eq!(paths, ["long", "long/path", "long/path/test.txt"]);
+    let is_dir = entries.iter().map(|(_, e)| e.is_dir()).collect::<Vec<_>>();
+    assert_eq!(is_dir, [true, true, false]);
+}
+
+#[tokio::test]
+async fn test_walk_fat12() {
+    test_walk(create_fs(FAT12_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_walk_fat16() {
+    test_walk(create_fs(FAT16_IMG).await).await
+}
+
+#[tokio::test]
+async fn test_walk_fat32() {
+    test_walk(create_fs(FAT32_IMG).await).await
+}
+
 async fn read_to_end<IO: embedded_io_async::Read>(io: &mut IO) -> Result<Vec<u8>, IO::Error> {
     let mut buf = Vec::new();
     loop {
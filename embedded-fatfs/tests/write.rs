@@ -2,8 +2,11 @@ use std::future::Future;
 use std::str;
 use tokio::fs;
 
-use embedded_fatfs::{ChronoTimeProvider, FsOptions, LossyOemCpConverter};
-use embedded_io_async::{Seek, SeekFrom, Write};
+use embedded_fatfs::{
+    ChronoTimeProvider, Date, DateTime, FileAttributes, FnTimeProvider, FsOptions, LossyOemCpConverter,
+    NullTimeProvider, Time,
+};
+use embedded_io_async::{Read, Seek, SeekFrom, Write};
 
 const FAT12_IMG: &str = "fat12.img";
 const FAT16_IMG: &str = "fat16.img";
@@ -519,6 +522,2257 @@ async fn test_multiple_files_in_directory_fat32() {
     call_with_fs(&test_multiple_files_in_directory, FAT32_IMG, 8).await
 }
 
+async fn test_set_len(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.create_file("prealloc.bin").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    let free_before = fs.stats().await.unwrap().free_clusters();
+    let cluster_size = fs.cluster_size() as u64;
+    let new_len = cluster_size * 3 + 10;
+    file.set_len(new_len).await.unwrap();
+    // position is preserved
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), TEST_STR.len() as u64);
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_before - 3);
+    file.seek(SeekFrom::Start(0)).await.unwrap();
+    let buf = read_to_end(&mut file).await.unwrap();
+    assert_eq!(buf.len() as u64, new_len);
+    assert_eq!(&buf[..TEST_STR.len()], TEST_STR.as_bytes());
+    assert!(buf[TEST_STR.len()..].iter().all(|b| *b == 0));
+    // shrink back into the first cluster
+    file.set_len(5).await.unwrap();
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 5);
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_before);
+    file.seek(SeekFrom::Start(0)).await.unwrap();
+    let buf = read_to_end(&mut file).await.unwrap();
+    assert_eq!(&buf, &TEST_STR.as_bytes()[..5]);
+    // grow again - bytes past the old end must read back as zeros
+    file.set_len(20).await.unwrap();
+    file.seek(SeekFrom::Start(0)).await.unwrap();
+    let buf = read_to_end(&mut file).await.unwrap();
+    assert_eq!(&buf[..5], &TEST_STR.as_bytes()[..5]);
+    assert_eq!(&buf[5..], &[0; 15]);
+    file.set_len(0).await.unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(
+        root_dir
+            .open_file("prealloc.bin")
+            .await
+            .unwrap()
+            .seek(SeekFrom::End(0))
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_set_len_fat12() {
+    call_with_fs(&test_set_len, FAT12_IMG, 9).await
+}
+
+#[tokio::test]
+async fn test_set_len_fat16() {
+    call_with_fs(&test_set_len, FAT16_IMG, 9).await
+}
+
+#[tokio::test]
+async fn test_set_len_fat32() {
+    call_with_fs(&test_set_len, FAT32_IMG, 9).await
+}
+
+async fn test_create_dir_all(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let d = root_dir.create_dir_all("a/b/c/d").await.unwrap();
+    assert!(d.is_empty().await.unwrap());
+    assert!(root_dir.dir_exists("a").await.unwrap());
+    assert!(root_dir.dir_exists("a/b").await.unwrap());
+    assert!(root_dir.dir_exists("a/b/c").await.unwrap());
+    assert!(root_dir.dir_exists("a/b/c/d").await.unwrap());
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+    // calling it again just opens the existing directories
+    let mut file = root_dir
+        .create_dir_all("/a/b/c/d/")
+        .await
+        .unwrap()
+        .create_file("f.txt")
+        .await
+        .unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters);
+    assert!(root_dir.file_exists("a/b/c/d/f.txt").await.unwrap());
+    // special entries are followed, not created
+    let e = root_dir.create_dir_all("a/b/../b/./e").await.unwrap();
+    assert!(e.is_empty().await.unwrap());
+    assert!(root_dir.dir_exists("a/b/e").await.unwrap());
+    // a file in the middle of the path is an error
+    assert!(root_dir.create_dir_all("a/b/c/d/f.txt/g").await.is_err());
+    assert!(!root_dir.exists("a/b/c/d/f.txt/g").await.unwrap_or(false));
+}
+
+#[tokio::test]
+async fn test_create_dir_all_fat12() {
+    call_with_fs(&test_create_dir_all, FAT12_IMG, 10).await
+}
+
+#[tokio::test]
+async fn test_create_dir_all_fat16() {
+    call_with_fs(&test_create_dir_all, FAT16_IMG, 10).await
+}
+
+#[tokio::test]
+async fn test_create_dir_all_fat32() {
+    call_with_fs(&test_create_dir_all, FAT32_IMG, 10).await
+}
+
+async fn test_sync(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.create_file("sync.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.sync_data().await.unwrap();
+    // size is visible through a new handle without a full flush
+    let mut other = root_dir.open_file("sync.txt").await.unwrap();
+    let buf = read_to_end(&mut other).await.unwrap();
+    other.sync_all().await.unwrap();
+    assert_eq!(TEST_STR, str::from_utf8(&buf).unwrap());
+    file.write_all(TEST_STR2.as_bytes()).await.unwrap();
+    file.sync_all().await.unwrap();
+    let mut other = root_dir.open_file("sync.txt").await.unwrap();
+    let buf = read_to_end(&mut other).await.unwrap();
+    other.sync_all().await.unwrap();
+    assert_eq!(format!("{}{}", TEST_STR, TEST_STR2), str::from_utf8(&buf).unwrap());
+}
+
+#[tokio::test]
+async fn test_sync_fat12() {
+    call_with_fs(&test_sync, FAT12_IMG, 11).await
+}
+
+#[tokio::test]
+async fn test_sync_fat16() {
+    call_with_fs(&test_sync, FAT16_IMG, 11).await
+}
+
+#[tokio::test]
+async fn test_sync_fat32() {
+    call_with_fs(&test_sync, FAT32_IMG, 11).await
+}
+
+async fn test_set_attributes(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.create_file("attrs.txt").await.unwrap();
+    file.set_attributes(FileAttributes::READ_ONLY | FileAttributes::HIDDEN | FileAttributes::DIRECTORY);
+    file.flush().await.unwrap();
+    let attrs = root_dir.open_meta("attrs.txt").await.unwrap().attributes();
+    assert_eq!(attrs, FileAttributes::READ_ONLY | FileAttributes::HIDDEN);
+
+    let mut dir = root_dir.create_dir("attrs").await.unwrap();
+    dir.set_attributes(FileAttributes::ARCHIVE | FileAttributes::SYSTEM)
+        .await
+        .unwrap();
+    let attrs = root_dir.open_meta("attrs").await.unwrap().attributes();
+    assert_eq!(
+        attrs,
+        FileAttributes::DIRECTORY | FileAttributes::ARCHIVE | FileAttributes::SYSTEM
+    );
+    // clearing the archive bit keeps the entry a directory
+    dir.set_attributes(FileAttributes::SYSTEM).await.unwrap();
+    let e = root_dir.open_meta("attrs").await.unwrap();
+    assert!(e.is_dir());
+    assert_eq!(e.attributes(), FileAttributes::DIRECTORY | FileAttributes::SYSTEM);
+
+    assert!(fs.root_dir().set_attributes(FileAttributes::HIDDEN).await.is_err());
+}
+
+#[tokio::test]
+async fn test_set_attributes_fat12() {
+    call_with_fs(&test_set_attributes, FAT12_IMG, 12).await
+}
+
+#[tokio::test]
+async fn test_set_attributes_fat16() {
+    call_with_fs(&test_set_attributes, FAT16_IMG, 12).await
+}
+
+#[tokio::test]
+async fn test_set_attributes_fat32() {
+    call_with_fs(&test_set_attributes, FAT32_IMG, 12).await
+}
+
+async fn test_attribute_predicates(tmp_path: String) {
+    // (short name, attribute byte, read-only, hidden, system, archive)
+    let cases = [
+        ("ATTR0", 0x00, false, false, false, false),
+        ("ATTR1", 0x01, true, false, false, false),
+        ("ATTR2", 0x02, false, true, false, false),
+        ("ATTR3", 0x04, false, false, true, false),
+        ("ATTR4", 0x20, false, false, false, true),
+        ("ATTR5", 0x27, true, true, true, true),
+    ];
+    let fs = open_filesystem_rw(tmp_path.clone()).await;
+    for (name, ..) in cases {
+        fs.root_dir().create_file(&format!("{}.TXT", name)).await.unwrap();
+    }
+    fs.unmount().await.unwrap();
+
+    let mut image = fs::read(&tmp_path).await.unwrap();
+    for (name, attrs, ..) in cases {
+        let short_name = format!("{:<8}TXT", name);
+        let pos = image.windows(11).position(|w| w == short_name.as_bytes()).unwrap();
+        image[pos + 11] = attrs;
+    }
+    fs::write(&tmp_path, &image).await.unwrap();
+
+    let fs = open_filesystem_rw(tmp_path).await;
+    for (name, attrs, read_only, hidden, system, archive) in cases {
+        let e = fs.root_dir().open_meta(&format!("{}.TXT", name)).await.unwrap();
+        assert_eq!(e.attributes().bits(), attrs);
+        assert_eq!(e.is_read_only(), read_only, "{}", name);
+        assert_eq!(e.is_hidden(), hidden, "{}", name);
+        assert_eq!(e.is_system(), system, "{}", name);
+        assert_eq!(e.is_archive(), archive, "{}", name);
+        assert!(e.is_file());
+        assert!(!e.is_dir());
+    }
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_attribute_predicates_fat12() {
+    call_with_tmp_img(&test_attribute_predicates, FAT12_IMG, 43).await
+}
+
+#[tokio::test]
+async fn test_attribute_predicates_fat16() {
+    call_with_tmp_img(&test_attribute_predicates, FAT16_IMG, 43).await
+}
+
+#[tokio::test]
+async fn test_attribute_predicates_fat32() {
+    call_with_tmp_img(&test_attribute_predicates, FAT32_IMG, 43).await
+}
+
+async fn test_walk_directory_loop(tmp_path: String) {
+    let fs = open_filesystem_rw(tmp_path.clone()).await;
+    let cluster = {
+        let dir = fs.root_dir().create_dir("LOOPA").await.unwrap();
+        dir.create_dir("LOOPB").await.unwrap();
+        dir.create_file("FILE.TXT").await.unwrap();
+        let entry = fs.root_dir().open_meta("LOOPA").await.unwrap();
+        entry.first_cluster().unwrap()
+    };
+    fs.unmount().await.unwrap();
+
+    // point the subdirectory at its parent
+    let mut image = fs::read(&tmp_path).await.unwrap();
+    let pos = image.windows(11).position(|w| w == b"LOOPB      ").unwrap();
+    image[pos + 20..pos + 22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    image[pos + 26..pos + 28].copy_from_slice(&(cluster as u16).to_le_bytes());
+    fs::write(&tmp_path, &image).await.unwrap();
+
+    let fs = open_filesystem_rw(tmp_path).await;
+    let entries = fs.root_dir().walk().collect().await;
+    let results = entries
+        .iter()
+        .map(|r| r.as_ref().map(|(path, _)| path.as_str()))
+        // skip the entries already present in the image
+        .filter(|r| !matches!(r, Ok(path) if !path.starts_with("LOOPA")))
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 3, "{:?}", results);
+    assert!(matches!(results[0], Ok("LOOPA")));
+    assert!(matches!(results[1], Err(embedded_fatfs::Error::CorruptedFileSystem)));
+    assert!(matches!(results[2], Ok("LOOPA/FILE.TXT")));
+    // removing the tree terminates too
+    assert!(matches!(
+        fs.root_dir().remove_dir_all("LOOPA").await,
+        Err(embedded_fatfs::Error::CorruptedFileSystem)
+    ));
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_walk_directory_loop_fat12() {
+    call_with_tmp_img(&test_walk_directory_loop, FAT12_IMG, 44).await
+}
+
+#[tokio::test]
+async fn test_walk_directory_loop_fat16() {
+    call_with_tmp_img(&test_walk_directory_loop, FAT16_IMG, 44).await
+}
+
+#[tokio::test]
+async fn test_walk_directory_loop_fat32() {
+    call_with_tmp_img(&test_walk_directory_loop, FAT32_IMG, 44).await
+}
+
+fn cluster_offset(image: &[u8], cluster: u32) -> usize {
+    let u16_at = |i: usize| u16::from_le_bytes([image[i], image[i + 1]]) as usize;
+    let bytes_per_sector = u16_at(11);
+    let sectors_per_cluster = image[13] as usize;
+    let reserved_sectors = u16_at(14);
+    let fats = image[16] as usize;
+    let root_dir_sectors = (u16_at(17) * 32).div_ceil(bytes_per_sector);
+    let sectors_per_fat = match u16_at(22) {
+        0 => u32::from_le_bytes(image[36..40].try_into().unwrap()) as usize,
+        n => n,
+    };
+    let first_data_sector = reserved_sectors + fats * sectors_per_fat + root_dir_sectors;
+    (first_data_sector + (cluster as usize - 2) * sectors_per_cluster) * bytes_per_sector
+}
+
+async fn test_cluster_chain(tmp_path: String) {
+    let fs = open_filesystem_rw(tmp_path.clone()).await;
+    let cluster_size = fs.cluster_size() as usize;
+    let data: Vec<u8> = (0..cluster_size * 6 + 100).map(|i| (i % 253) as u8).collect();
+    let (runs, read) = {
+        let root_dir = fs.root_dir();
+        let mut file = root_dir.create_file("chain.bin").await.unwrap();
+        let mut other = root_dir.create_file("other.bin").await.unwrap();
+        // interleave writes with another file to fragment the chain
+        for (i, chunk) in data.chunks(cluster_size * 2).enumerate() {
+            file.write_all(chunk).await.unwrap();
+            if i % 2 == 0 {
+                other.write_all(&data[..cluster_size]).await.unwrap();
+            }
+        }
+        file.flush().await.unwrap();
+        other.flush().await.unwrap();
+
+        let runs = file
+            .cluster_chain()
+            .collect()
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        file.seek(SeekFrom::Start(0)).await.unwrap();
+        let read = read_to_end(&mut file).await.unwrap();
+        file.flush().await.unwrap();
+        (runs, read)
+    };
+    fs.unmount().await.unwrap();
+    assert!(runs.len() > 1, "chain is not fragmented: {:?}", runs);
+    assert_eq!(runs.iter().map(|r| r.1).sum::<u32>(), 7);
+
+    let image = fs::read(&tmp_path).await.unwrap();
+    let mut raw = Vec::new();
+    for (start, count) in runs {
+        let offset = cluster_offset(&image, start);
+        raw.extend_from_slice(&image[offset..offset + count as usize * cluster_size]);
+    }
+    raw.truncate(data.len());
+    assert_eq!(raw, read);
+    assert_eq!(raw, data);
+}
+
+#[tokio::test]
+async fn test_cluster_chain_fat12() {
+    call_with_tmp_img(&test_cluster_chain, FAT12_IMG, 13).await
+}
+
+#[tokio::test]
+async fn test_cluster_chain_fat16() {
+    call_with_tmp_img(&test_cluster_chain, FAT16_IMG, 13).await
+}
+
+#[tokio::test]
+async fn test_cluster_chain_fat32() {
+    call_with_tmp_img(&test_cluster_chain, FAT32_IMG, 13).await
+}
+
+fn set_fat_entry(image: &mut [u8], fat_type: embedded_fatfs::FatType, cluster: u32, value: u32) {
+    let u16_at = |i: usize| u16::from_le_bytes([image[i], image[i + 1]]) as usize;
+    let bytes_per_sector = u16_at(11);
+    let reserved_sectors = u16_at(14);
+    let fats = image[16] as usize;
+    let sectors_per_fat = match u16_at(22) {
+        0 => u32::from_le_bytes(image[36..40].try_into().unwrap()) as usize,
+        n => n,
+    };
+    for i in 0..fats {
+        let fat = &mut image[(reserved_sectors + i * sectors_per_fat) * bytes_per_sector..];
+        match fat_type {
+            embedded_fatfs::FatType::Fat12 => {
+                let idx = cluster as usize * 3 / 2;
+                if cluster % 2 == 1 {
+                    fat[idx] = (fat[idx] & 0x0F) | ((value << 4) as u8);
+                    fat[idx + 1] = (value >> 4) as u8;
+                } else {
+                    fat[idx] = value as u8;
+                    fat[idx + 1] = (fat[idx + 1] & 0xF0) | ((value >> 8) as u8 & 0x0F);
+                }
+            }
+            embedded_fatfs::FatType::Fat16 => {
+                let idx = cluster as usize * 2;
+                fat[idx..idx + 2].copy_from_slice(&(value as u16).to_le_bytes());
+            }
+            embedded_fatfs::FatType::Fat32 => {
+                let idx = cluster as usize * 4;
+                let old = u32::from_le_bytes(fat[idx..idx + 4].try_into().unwrap());
+                fat[idx..idx + 4].copy_from_slice(&((old & 0xF000_0000) | value).to_le_bytes());
+            }
+        }
+    }
+}
+
+async fn test_walk_chain(tmp_path: String) {
+    let fs = open_filesystem_rw(tmp_path.clone()).await;
+    let fat_type = fs.fat_type();
+    let cluster_size = fs.cluster_size() as usize;
+    let expected = {
+        let root_dir = fs.root_dir();
+        let mut file = root_dir.create_file("walk.bin").await.unwrap();
+        let mut other = root_dir.create_file("other.bin").await.unwrap();
+        // interleave writes with another file to fragment the chain
+        for _ in 0..3 {
+            file.write_all(&vec![0xAB; cluster_size]).await.unwrap();
+            other.write_all(&vec![0xCD; cluster_size]).await.unwrap();
+        }
+        file.flush().await.unwrap();
+        other.flush().await.unwrap();
+        file.cluster_chain()
+            .collect()
+            .await
+            .into_iter()
+            .flat_map(|r| {
+                let (start, count) = r.unwrap();
+                start..start + count
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(expected.len(), 3);
+    let walked = fs
+        .walk_chain(expected[0])
+        .collect()
+        .await
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(walked, expected);
+    assert!(matches!(
+        fs.walk_chain(0).next().await,
+        Some(Err(embedded_fatfs::Error::InvalidInput))
+    ));
+    fs.unmount().await.unwrap();
+
+    // link the last cluster back to the first one
+    let mut image = fs::read(&tmp_path).await.unwrap();
+    set_fat_entry(&mut image, fat_type, expected[2], expected[0]);
+    fs::write(&tmp_path, &image).await.unwrap();
+
+    let fs = open_filesystem_rw(tmp_path).await;
+    let walked = fs.walk_chain(expected[0]).collect().await;
+    assert!(
+        walked.len() <= expected.len() * 3,
+        "cycle detected too late: {:?}",
+        walked
+    );
+    let (last, clusters) = walked.split_last().unwrap();
+    assert!(matches!(last, Err(embedded_fatfs::Error::CorruptedFileSystem)));
+    for (i, r) in clusters.iter().enumerate() {
+        assert_eq!(*r.as_ref().unwrap(), expected[i % expected.len()]);
+    }
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_walk_chain_fat12() {
+    call_with_tmp_img(&test_walk_chain, FAT12_IMG, 42).await
+}
+
+#[tokio::test]
+async fn test_walk_chain_fat16() {
+    call_with_tmp_img(&test_walk_chain, FAT16_IMG, 42).await
+}
+
+#[tokio::test]
+async fn test_walk_chain_fat32() {
+    call_with_tmp_img(&test_walk_chain, FAT32_IMG, 42).await
+}
+
+async fn test_null_time_provider(tmp_path: String) {
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&tmp_path)
+        .await
+        .unwrap();
+    let options = FsOptions::new().time_provider(NullTimeProvider::new());
+    let fs = embedded_fatfs::FileSystem::new(file, options).await.unwrap();
+    {
+        let root_dir = fs.root_dir();
+        let mut file = root_dir.create_file("null-tp.txt").await.unwrap();
+        file.write_all(TEST_STR.as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+        let e = root_dir.open_meta("null-tp.txt").await.unwrap();
+        let epoch = DateTime::new(Date::new(1980, 1, 1), Time::new(0, 0, 0, 0));
+        assert_eq!(e.created(), epoch);
+        assert_eq!(e.modified(), epoch);
+        assert_eq!(e.accessed(), epoch.date);
+    }
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_null_time_provider_fat12() {
+    call_with_tmp_img(&test_null_time_provider, FAT12_IMG, 14).await
+}
+
+#[tokio::test]
+async fn test_null_time_provider_fat16() {
+    call_with_tmp_img(&test_null_time_provider, FAT16_IMG, 14).await
+}
+
+#[tokio::test]
+async fn test_null_time_provider_fat32() {
+    call_with_tmp_img(&test_null_time_provider, FAT32_IMG, 14).await
+}
+
+async fn test_fs_flush(tmp_path: String) {
+    let fs = open_filesystem_rw(tmp_path.clone()).await;
+    {
+        let mut file = fs.root_dir().create_file("flush.txt").await.unwrap();
+        file.write_all(TEST_STR.repeat(500).as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+    }
+    fs.flush().await.unwrap();
+    // flushing again is a no-op
+    fs.flush().await.unwrap();
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+    // simulate power loss after the checkpoint
+    core::mem::forget(fs);
+
+    let fs = open_filesystem_rw(tmp_path).await;
+    assert!(!fs.read_status_flags().await.unwrap().dirty());
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters);
+    let mut file = fs.root_dir().open_file("flush.txt").await.unwrap();
+    let buf = read_to_end(&mut file).await.unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(TEST_STR.repeat(500), str::from_utf8(&buf).unwrap());
+}
+
+#[tokio::test]
+async fn test_fs_flush_fat12() {
+    call_with_tmp_img(&test_fs_flush, FAT12_IMG, 15).await
+}
+
+#[tokio::test]
+async fn test_fs_flush_fat16() {
+    call_with_tmp_img(&test_fs_flush, FAT16_IMG, 15).await
+}
+
+#[tokio::test]
+async fn test_fs_flush_fat32() {
+    call_with_tmp_img(&test_fs_flush, FAT32_IMG, 15).await
+}
+
+async fn test_move_path(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let stats = fs.stats().await.unwrap();
+
+    // move a file up the tree
+    root_dir
+        .move_path("very/long/path/test.txt", "moved.txt")
+        .await
+        .unwrap();
+    assert!(!root_dir.exists("very/long/path/test.txt").await.unwrap());
+    let mut file = root_dir.open_file("moved.txt").await.unwrap();
+    let buf = read_to_end(&mut file).await.unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(str::from_utf8(&buf).unwrap(), TEST_STR2);
+
+    // move a directory into another directory
+    root_dir
+        .move_path("very/long", "very-long-dir-name/long")
+        .await
+        .unwrap();
+    assert!(!root_dir.exists("very/long").await.unwrap());
+    assert!(root_dir.dir_exists("very-long-dir-name/long/path").await.unwrap());
+    let parent_dir = root_dir.open_dir("very-long-dir-name/long/..").await.unwrap();
+    let names = parent_dir
+        .iter()
+        .collect()
+        .await
+        .into_iter()
+        .map(|r| r.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert!(names.iter().any(|n| n == "long"));
+
+    // move a directory back to the root
+    root_dir.move_path("very-long-dir-name/long", "long").await.unwrap();
+    let parent_dir = root_dir.open_dir("long/path/../..").await.unwrap();
+    let names = parent_dir
+        .iter()
+        .collect()
+        .await
+        .into_iter()
+        .map(|r| r.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert!(names.iter().any(|n| n == "moved.txt"));
+
+    // a directory cannot be moved into itself or one of its descendants
+    assert!(matches!(
+        root_dir.move_path("long", "long/path/long").await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    assert!(matches!(
+        root_dir.move_path("long", "long/long").await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    assert!(root_dir.dir_exists("long/path").await.unwrap());
+
+    assert!(matches!(
+        root_dir.move_path("short.txt", "moved.txt").await,
+        Err(embedded_fatfs::Error::AlreadyExists)
+    ));
+
+    let new_stats = fs.stats().await.unwrap();
+    assert_eq!(new_stats.free_clusters(), stats.free_clusters());
+}
+
+#[tokio::test]
+async fn test_move_path_fat12() {
+    call_with_fs(test_move_path, FAT12_IMG, 16).await
+}
+
+#[tokio::test]
+async fn test_move_path_fat16() {
+    call_with_fs(test_move_path, FAT16_IMG, 16).await
+}
+
+#[tokio::test]
+async fn test_move_path_fat32() {
+    call_with_fs(test_move_path, FAT32_IMG, 16).await
+}
+
+/// Storage wrapper that fails the test on any write attempt.
+struct WriteBlocker<T>(T);
+
+impl<T: embedded_io_async::ErrorType> embedded_io_async::ErrorType for WriteBlocker<T> {
+    type Error = T::Error;
+}
+
+impl<T: embedded_io_async::Read> embedded_io_async::Read for WriteBlocker<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.0.read(buf).await
+    }
+}
+
+impl<T: embedded_io_async::ErrorType> Write for WriteBlocker<T> {
+    async fn write(&mut self, _buf: &[u8]) -> Result<usize, Self::Error> {
+        panic!("write to a read-only filesystem");
+    }
+}
+
+impl<T: Seek> Seek for WriteBlocker<T> {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        self.0.seek(pos).await
+    }
+}
+
+async fn test_read_only(tmp_path: String) {
+    let file = fs::File::open(&tmp_path).await.unwrap();
+    let storage = WriteBlocker(embedded_io_adapters::tokio_1::FromTokio::new(file));
+    let options = FsOptions::new().update_accessed_date(true).read_only(true);
+    let fs = embedded_fatfs::FileSystem::new(storage, options).await.unwrap();
+    assert!(fs.is_read_only());
+    {
+        let root_dir = fs.root_dir();
+
+        let mut file = root_dir.open_file("short.txt").await.unwrap();
+        let buf = read_to_end(&mut file).await.unwrap();
+        assert_eq!(str::from_utf8(&buf).unwrap(), TEST_STR2);
+        file.flush().await.unwrap();
+        let err = file.write(b"x").await.unwrap_err();
+        assert!(matches!(err, embedded_fatfs::Error::ReadOnly));
+        assert_eq!(
+            embedded_io_async::Error::kind(&err),
+            embedded_io_async::ErrorKind::PermissionDenied
+        );
+        assert_eq!(err.to_string(), "Read-only file system");
+        assert!(matches!(file.truncate().await, Err(embedded_fatfs::Error::ReadOnly)));
+        assert!(matches!(file.set_len(0).await, Err(embedded_fatfs::Error::ReadOnly)));
+        file.set_attributes(FileAttributes::HIDDEN);
+        assert!(matches!(file.flush().await, Err(embedded_fatfs::Error::ReadOnly)));
+        core::mem::forget(file);
+
+        assert!(matches!(
+            root_dir.create_file("new.txt").await,
+            Err(embedded_fatfs::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            root_dir.create_dir("new").await,
+            Err(embedded_fatfs::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            root_dir.create_dir_all("new/sub").await,
+            Err(embedded_fatfs::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            root_dir.remove("short.txt").await,
+            Err(embedded_fatfs::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            root_dir.rename("short.txt", &root_dir, "renamed.txt").await,
+            Err(embedded_fatfs::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            root_dir.move_path("short.txt", "very/short.txt").await,
+            Err(embedded_fatfs::Error::ReadOnly)
+        ));
+        assert!(root_dir.file_exists("short.txt").await.unwrap());
+    }
+
+    // free cluster count is calculated but never written back
+    fs.stats().await.unwrap();
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_read_only_fat12() {
+    call_with_tmp_img(&test_read_only, FAT12_IMG, 17).await
+}
+
+#[tokio::test]
+async fn test_read_only_fat16() {
+    call_with_tmp_img(&test_read_only, FAT16_IMG, 17).await
+}
+
+#[tokio::test]
+async fn test_read_only_fat32() {
+    call_with_tmp_img(&test_read_only, FAT32_IMG, 17).await
+}
+
+async fn test_check(tmp_path: String) {
+    let fs = open_filesystem_rw(tmp_path.clone()).await;
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+
+    // allocate clusters for a file but never write its directory entry - they become lost
+    {
+        let mut file = fs.root_dir().create_file("lost.txt").await.unwrap();
+        file.write_all(&[0xAB; 5000]).await.unwrap();
+        core::mem::forget(file);
+    }
+    core::mem::forget(fs);
+
+    let fs = open_filesystem_rw(tmp_path.clone()).await;
+    assert!(fs.read_status_flags().await.unwrap().dirty());
+    let report = fs.check(false).await.unwrap();
+    assert!(!report.is_clean());
+    assert!(report.lost_clusters() > 0);
+    assert_eq!(report.cross_linked_chains(), 0);
+    assert_eq!(report.size_mismatches(), 0);
+
+    let repair_report = fs.check(true).await.unwrap();
+    assert!(repair_report.repaired());
+    assert_eq!(repair_report.lost_clusters(), report.lost_clusters());
+    assert!(!fs.read_status_flags().await.unwrap().dirty());
+    assert!(fs.check(false).await.unwrap().is_clean());
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters);
+    fs.unmount().await.unwrap();
+
+    let fs = open_filesystem_rw(tmp_path).await;
+    assert!(!fs.read_status_flags().await.unwrap().dirty());
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_check_fat12() {
+    call_with_tmp_img(&test_check, FAT12_IMG, 18).await
+}
+
+#[tokio::test]
+async fn test_check_fat16() {
+    call_with_tmp_img(&test_check, FAT16_IMG, 18).await
+}
+
+#[tokio::test]
+async fn test_check_fat32() {
+    call_with_tmp_img(&test_check, FAT32_IMG, 18).await
+}
+
+async fn test_seek_past_end(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.create_file("sparse.txt").await.unwrap();
+    file.write_all(b"Hello").await.unwrap();
+    assert_eq!(file.seek(SeekFrom::Start(4096)).await.unwrap(), 4096);
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 4096);
+    let mut buf = [0; 1];
+    assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+    file.write_all(b"World").await.unwrap();
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 4101);
+    file.flush().await.unwrap();
+
+    let mut file = root_dir.open_file("sparse.txt").await.unwrap();
+    let buf = read_to_end(&mut file).await.unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(buf.len(), 4101);
+    assert_eq!(&buf[..5], b"Hello");
+    assert!(buf[5..4096].iter().all(|&b| b == 0));
+    assert_eq!(&buf[4096..], b"World");
+}
+
+#[tokio::test]
+async fn test_seek_past_end_fat12() {
+    call_with_fs(test_seek_past_end, FAT12_IMG, 19).await
+}
+
+#[tokio::test]
+async fn test_seek_past_end_fat16() {
+    call_with_fs(test_seek_past_end, FAT16_IMG, 19).await
+}
+
+#[tokio::test]
+async fn test_seek_past_end_fat32() {
+    call_with_fs(test_seek_past_end, FAT32_IMG, 19).await
+}
+
+async fn test_copy_file(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let dir = root_dir.open_dir("very/long/path").await.unwrap();
+    root_dir.copy_file("long.txt", &dir, "long copy.txt").await.unwrap();
+
+    let mut src = root_dir.open_file("long.txt").await.unwrap();
+    let src_data = read_to_end(&mut src).await.unwrap();
+    src.flush().await.unwrap();
+    let mut dst = dir.open_file("long copy.txt").await.unwrap();
+    let dst_data = read_to_end(&mut dst).await.unwrap();
+    dst.flush().await.unwrap();
+    assert_eq!(src_data, dst_data);
+
+    let src_entry = root_dir.open_meta("long.txt").await.unwrap();
+    let dst_entry = root_dir.open_meta("very/long/path/long copy.txt").await.unwrap();
+    assert_eq!(dst_entry.len(), src_entry.len());
+    assert_eq!(dst_entry.attributes(), src_entry.attributes());
+    assert_eq!(dst_entry.modified(), src_entry.modified());
+
+    assert!(matches!(
+        root_dir.copy_file("long.txt", &root_dir, "short.txt").await,
+        Err(embedded_fatfs::Error::AlreadyExists)
+    ));
+
+    // leave less free space than the copy needs, a failed copy frees the clusters allocated for it
+    let cluster_size = u64::from(fs.cluster_size());
+    let needed = src_entry.len().div_ceil(cluster_size);
+    assert!(needed > 1);
+    let free_clusters = u64::from(fs.stats().await.unwrap().free_clusters());
+    let mut filler = root_dir.create_file("filler.bin").await.unwrap();
+    filler
+        .allocate_contiguous((free_clusters - needed + 1) * cluster_size)
+        .await
+        .unwrap();
+    filler.flush().await.unwrap();
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+    assert!(matches!(
+        root_dir.copy_file("long.txt", &root_dir, "no space.txt").await,
+        Err(embedded_fatfs::Error::NotEnoughSpace)
+    ));
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters);
+    assert!(!root_dir.file_exists("no space.txt").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_copy_file_fat12() {
+    call_with_fs(test_copy_file, FAT12_IMG, 20).await
+}
+
+#[tokio::test]
+async fn test_copy_file_fat16() {
+    call_with_fs(test_copy_file, FAT16_IMG, 20).await
+}
+
+#[tokio::test]
+async fn test_copy_file_fat32() {
+    call_with_fs(test_copy_file, FAT32_IMG, 20).await
+}
+
+async fn test_copy_file_not_enough_space(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    // fill the volume so only a single cluster is left - the copy must fail and free its clusters
+    let cluster_size = u64::from(fs.cluster_size());
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+    let mut filler = root_dir.create_file("filler.bin").await.unwrap();
+    filler
+        .set_len(u64::from(free_clusters - 1) * cluster_size)
+        .await
+        .unwrap();
+    filler.flush().await.unwrap();
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), 1);
+    assert!(matches!(
+        root_dir.copy_file("long.txt", &root_dir, "second copy.txt").await,
+        Err(embedded_fatfs::Error::NotEnoughSpace)
+    ));
+    assert!(!root_dir.exists("second copy.txt").await.unwrap());
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), 1);
+}
+
+// Note: FAT32 image is skipped because filling it takes too long
+#[tokio::test]
+async fn test_copy_file_not_enough_space_fat12() {
+    call_with_fs(test_copy_file_not_enough_space, FAT12_IMG, 21).await
+}
+
+#[tokio::test]
+async fn test_copy_file_not_enough_space_fat16() {
+    call_with_fs(test_copy_file_not_enough_space, FAT16_IMG, 21).await
+}
+
+/// Storage wrapper counting read calls.
+struct ReadCounter<T>(T, std::rc::Rc<std::cell::Cell<usize>>);
+
+impl<T: embedded_io_async::ErrorType> embedded_io_async::ErrorType for ReadCounter<T> {
+    type Error = T::Error;
+}
+
+impl<T: embedded_io_async::Read> embedded_io_async::Read for ReadCounter<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.1.set(self.1.get() + 1);
+        self.0.read(buf).await
+    }
+}
+
+impl<T: Write> Write for ReadCounter<T> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.write(buf).await
+    }
+}
+
+impl<T: Seek> Seek for ReadCounter<T> {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        self.0.seek(pos).await
+    }
+}
+
+async fn count_reads_of_file(tmp_path: &str, fat_cache_sectors: usize, name: &str) -> (usize, Vec<u8>) {
+    let file = fs::File::open(tmp_path).await.unwrap();
+    let reads = std::rc::Rc::new(std::cell::Cell::new(0));
+    let storage = ReadCounter(embedded_io_adapters::tokio_1::FromTokio::new(file), reads.clone());
+    let options = FsOptions::new().read_only(true).fat_cache_sectors(fat_cache_sectors);
+    let fs = embedded_fatfs::FileSystem::new(storage, options).await.unwrap();
+    let mut file = fs.root_dir().open_file(name).await.unwrap();
+    reads.set(0);
+    let data = read_to_end(&mut file).await.unwrap();
+    (reads.get(), data)
+}
+
+async fn test_fat_cache(tmp_path: String) {
+    // interleave writes to two files so their cluster chains are fragmented
+    {
+        let fs = open_filesystem_rw(tmp_path.clone()).await;
+        let root_dir = fs.root_dir();
+        let cluster = vec![0xAB_u8; fs.cluster_size() as usize];
+        let mut file1 = root_dir.create_file("fragmented1.bin").await.unwrap();
+        let mut file2 = root_dir.create_file("fragmented2.bin").await.unwrap();
+        for _ in 0..16 {
+            file1.write_all(&cluster).await.unwrap();
+            file2.write_all(&cluster).await.unwrap();
+        }
+        file1.flush().await.unwrap();
+        file2.flush().await.unwrap();
+    }
+
+    let (uncached_reads, uncached_data) = count_reads_of_file(&tmp_path, 0, "fragmented1.bin").await;
+    let (cached_reads, cached_data) = count_reads_of_file(&tmp_path, 4, "fragmented1.bin").await;
+    assert_eq!(uncached_data, cached_data);
+    assert!(cached_data.iter().all(|&b| b == 0xAB));
+    // every FAT lookup hits the device without the cache, with the cache only the first one does
+    assert!(
+        cached_reads < uncached_reads,
+        "cached {} uncached {}",
+        cached_reads,
+        uncached_reads
+    );
+
+    // FAT writes are visible through the cache
+    let fs = embedded_fatfs::FileSystem::new(
+        fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&tmp_path)
+            .await
+            .unwrap(),
+        FsOptions::new().fat_cache_sectors(1),
+    )
+    .await
+    .unwrap();
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.open_file("fragmented2.bin").await.unwrap();
+    assert_eq!(
+        read_to_end(&mut file).await.unwrap().len(),
+        16 * fs.cluster_size() as usize
+    );
+    file.seek(SeekFrom::Start(u64::from(fs.cluster_size()))).await.unwrap();
+    file.truncate().await.unwrap();
+    file.flush().await.unwrap();
+    let mut file = root_dir.open_file("fragmented2.bin").await.unwrap();
+    file.seek(SeekFrom::Start(0)).await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap().len(), fs.cluster_size() as usize);
+    let mut other = root_dir.create_file("new.bin").await.unwrap();
+    other
+        .write_all(&vec![0xCD_u8; 4 * fs.cluster_size() as usize])
+        .await
+        .unwrap();
+    other.flush().await.unwrap();
+    let mut file = root_dir.open_file("fragmented1.bin").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), cached_data);
+}
+
+#[tokio::test]
+async fn test_fat_cache_fat12() {
+    call_with_tmp_img(&test_fat_cache, FAT12_IMG, 22).await
+}
+
+#[tokio::test]
+async fn test_fat_cache_fat16() {
+    call_with_tmp_img(&test_fat_cache, FAT16_IMG, 22).await
+}
+
+#[tokio::test]
+async fn test_fat_cache_fat32() {
+    call_with_tmp_img(&test_fat_cache, FAT32_IMG, 22).await
+}
+
+async fn test_allocate_contiguous(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let cluster_size = fs.cluster_size() as usize;
+    // leave single cluster holes in the free space
+    {
+        let mut holes = root_dir.create_file("holes.bin").await.unwrap();
+        let mut other = root_dir.create_file("other.bin").await.unwrap();
+        for _ in 0..8 {
+            holes.write_all(&vec![1_u8; cluster_size]).await.unwrap();
+            other.write_all(&vec![2_u8; cluster_size]).await.unwrap();
+        }
+        holes.flush().await.unwrap();
+        other.flush().await.unwrap();
+    }
+    root_dir.remove("holes.bin").await.unwrap();
+
+    let data: Vec<u8> = (0..cluster_size * 6 + 1).map(|i| (i % 251) as u8).collect();
+    let mut file = root_dir.create_file("contiguous.bin").await.unwrap();
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+    assert!(file.allocate_contiguous(data.len() as u64).await.unwrap());
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters - 7);
+    let runs = file
+        .cluster_chain()
+        .collect()
+        .await
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(runs.len(), 1, "chain is fragmented: {:?}", runs);
+    assert_eq!(runs[0].1, 7);
+    // allocation does not change the size and is not repeated
+    file.flush().await.unwrap();
+    assert_eq!(root_dir.open_meta("contiguous.bin").await.unwrap().len(), 0);
+    assert!(file.allocate_contiguous(data.len() as u64).await.unwrap());
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters - 7);
+    // a chain longer than the file size is not an inconsistency
+    assert!(fs.check(false).await.unwrap().is_clean());
+
+    // writes use the allocated clusters
+    file.write_all(&data).await.unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters - 7);
+    let new_runs = file
+        .cluster_chain()
+        .collect()
+        .await
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(new_runs, runs);
+    let mut file = root_dir.open_file("contiguous.bin").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), data);
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_allocate_contiguous_fat12() {
+    call_with_fs(test_allocate_contiguous, FAT12_IMG, 23).await
+}
+
+#[tokio::test]
+async fn test_allocate_contiguous_fat16() {
+    call_with_fs(test_allocate_contiguous, FAT16_IMG, 23).await
+}
+
+#[tokio::test]
+async fn test_allocate_contiguous_fat32() {
+    call_with_fs(test_allocate_contiguous, FAT32_IMG, 23).await
+}
+
+async fn test_stats_cache(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let cluster_size = fs.cluster_size() as usize;
+    let initial = fs.recompute_stats().await.unwrap();
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), initial.free_clusters());
+
+    let mut file = root_dir.create_file("stats.bin").await.unwrap();
+    file.write_all(&vec![0_u8; cluster_size * 5]).await.unwrap();
+    file.flush().await.unwrap();
+    let dir = root_dir.create_dir("stats-dir").await.unwrap();
+    let mut file2 = dir.create_file("inner.bin").await.unwrap();
+    file2.write_all(&vec![0_u8; cluster_size * 3 + 1]).await.unwrap();
+    file2.flush().await.unwrap();
+    let cached = fs.stats().await.unwrap().free_clusters();
+    assert!(cached < initial.free_clusters());
+    assert_eq!(fs.recompute_stats().await.unwrap().free_clusters(), cached);
+
+    file.seek(SeekFrom::Start(cluster_size as u64)).await.unwrap();
+    file.truncate().await.unwrap();
+    file.flush().await.unwrap();
+    root_dir.remove_dir_all("stats-dir").await.unwrap();
+    let cached = fs.stats().await.unwrap().free_clusters();
+    assert_eq!(fs.recompute_stats().await.unwrap().free_clusters(), cached);
+
+    root_dir.remove("stats.bin").await.unwrap();
+    let cached = fs.stats().await.unwrap().free_clusters();
+    assert_eq!(fs.recompute_stats().await.unwrap().free_clusters(), cached);
+    assert_eq!(cached, initial.free_clusters());
+}
+
+#[tokio::test]
+async fn test_stats_cache_fat12() {
+    call_with_fs(test_stats_cache, FAT12_IMG, 24).await
+}
+
+#[tokio::test]
+async fn test_stats_cache_fat16() {
+    call_with_fs(test_stats_cache, FAT16_IMG, 24).await
+}
+
+#[tokio::test]
+async fn test_stats_cache_fat32() {
+    call_with_fs(test_stats_cache, FAT32_IMG, 24).await
+}
+
+async fn test_positional_io(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.create_file("positional.bin").await.unwrap();
+    file.write_all(b"0123456789").await.unwrap();
+    file.seek(SeekFrom::Start(3)).await.unwrap();
+
+    let mut buf = [0_u8; 4];
+    assert_eq!(file.read_at(5, &mut buf).await.unwrap(), 4);
+    assert_eq!(&buf, b"5678");
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 3);
+
+    // short read at the end of the file
+    assert_eq!(file.read_at(8, &mut buf).await.unwrap(), 2);
+    assert_eq!(&buf[..2], b"89");
+    assert_eq!(file.read_at(20, &mut buf).await.unwrap(), 0);
+    assert!(matches!(
+        file.read_exact_at(8, &mut buf).await,
+        Err(embedded_fatfs::Error::UnexpectedEof)
+    ));
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 3);
+
+    file.write_all_at(1, b"ab").await.unwrap();
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 3);
+
+    // write past the end grows the file and zero-fills the gap
+    file.write_all_at(12, b"xy").await.unwrap();
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 3);
+    let mut rest = [0_u8; 11];
+    file.read_exact(&mut rest).await.unwrap();
+    assert_eq!(&rest, b"3456789\0\0xy");
+
+    let mut all = [0_u8; 14];
+    file.read_exact_at(0, &mut all).await.unwrap();
+    assert_eq!(&all, b"0ab3456789\0\0xy");
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 14);
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_positional_io_fat12() {
+    call_with_fs(test_positional_io, FAT12_IMG, 25).await
+}
+
+#[tokio::test]
+async fn test_positional_io_fat16() {
+    call_with_fs(test_positional_io, FAT16_IMG, 25).await
+}
+
+#[tokio::test]
+async fn test_positional_io_fat32() {
+    call_with_fs(test_positional_io, FAT32_IMG, 25).await
+}
+
+async fn test_root_dir_full(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    // each name is 249 characters long so it needs 20 LFN entries and a short name entry
+    let long_name = |i: u32| format!("{:06}-{}.txt", i, "x".repeat(238));
+    let mut i = 0;
+    let err = loop {
+        match root_dir.create_file(&long_name(i)).await {
+            Ok(mut file) => file.flush().await.unwrap(),
+            Err(err) => break err,
+        }
+        i += 1;
+    };
+    assert!(matches!(err, embedded_fatfs::Error::NotEnoughSpace));
+    // fill the remaining slots with short names
+    let mut j = 0;
+    let err = loop {
+        match root_dir.create_file(&format!("S{}.TXT", j)).await {
+            Ok(mut file) => file.flush().await.unwrap(),
+            Err(err) => break err,
+        }
+        j += 1;
+    };
+    assert!(matches!(err, embedded_fatfs::Error::NotEnoughSpace));
+    assert!(j < 21);
+
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+    assert!(matches!(
+        root_dir.create_dir("DIR").await,
+        Err(embedded_fatfs::Error::NotEnoughSpace)
+    ));
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters);
+
+    // removing a long name entry frees enough slots for another one
+    root_dir.remove(&long_name(0)).await.unwrap();
+    let mut file = root_dir.create_file(&long_name(i)).await.unwrap();
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_root_dir_full_fat12() {
+    call_with_fs(test_root_dir_full, FAT12_IMG, 26).await
+}
+
+#[tokio::test]
+async fn test_root_dir_full_fat16() {
+    call_with_fs(test_root_dir_full, FAT16_IMG, 26).await
+}
+
+// FAT32 root directory is a regular cluster chain that grows as needed
+
+async fn test_set_volume_label(tmp_path: String) {
+    {
+        let mut fs = open_filesystem_rw(tmp_path.clone()).await;
+        assert!(matches!(
+            fs.set_volume_label("LABEL TOO LONG").await,
+            Err(embedded_fatfs::Error::InvalidFileNameLength)
+        ));
+        assert!(matches!(
+            fs.set_volume_label("BAD*LABEL").await,
+            Err(embedded_fatfs::Error::UnsupportedFileNameCharacter)
+        ));
+        fs.set_volume_label("New Label").await.unwrap();
+        assert_eq!(fs.volume_label(), "NEW LABEL");
+        assert_eq!(
+            fs.read_volume_label_from_root_dir().await.unwrap().as_deref(),
+            Some("NEW LABEL")
+        );
+        fs.unmount().await.unwrap();
+    }
+    {
+        let mut fs = open_filesystem_rw(tmp_path.clone()).await;
+        assert_eq!(fs.volume_label(), "NEW LABEL");
+        assert_eq!(
+            fs.read_volume_label_from_root_dir().await.unwrap().as_deref(),
+            Some("NEW LABEL")
+        );
+        // removing the label deletes the root directory entry
+        fs.set_volume_label("").await.unwrap();
+        fs.unmount().await.unwrap();
+    }
+    {
+        let mut fs = open_filesystem_rw(tmp_path.clone()).await;
+        assert_eq!(fs.volume_label(), "NO NAME");
+        assert_eq!(fs.read_volume_label_from_root_dir().await.unwrap(), None);
+        // a new entry is created when the root directory has none
+        fs.set_volume_label("AGAIN").await.unwrap();
+        fs.unmount().await.unwrap();
+    }
+    let fs = open_filesystem_rw(tmp_path).await;
+    assert_eq!(fs.volume_label(), "AGAIN");
+    assert_eq!(
+        fs.read_volume_label_from_root_dir().await.unwrap().as_deref(),
+        Some("AGAIN")
+    );
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_set_volume_label_fat12() {
+    call_with_tmp_img(&test_set_volume_label, FAT12_IMG, 27).await
+}
+
+#[tokio::test]
+async fn test_set_volume_label_fat16() {
+    call_with_tmp_img(&test_set_volume_label, FAT16_IMG, 27).await
+}
+
+#[tokio::test]
+async fn test_set_volume_label_fat32() {
+    call_with_tmp_img(&test_set_volume_label, FAT32_IMG, 27).await
+}
+
+async fn test_dir_entry_count(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let root_entries = root_dir.iter().collect().await.len();
+    assert_eq!(root_dir.entry_count().await.unwrap(), root_entries);
+    assert!(!root_dir.is_empty().await.unwrap());
+
+    // a new directory only holds "." and ".."
+    let dir = root_dir.create_dir("counted").await.unwrap();
+    assert!(dir.is_empty().await.unwrap());
+    assert_eq!(dir.entry_count().await.unwrap(), 0);
+
+    for name in ["a.txt", "long file name.txt", "c"] {
+        dir.create_file(name).await.unwrap().flush().await.unwrap();
+    }
+    dir.create_dir("subdir").await.unwrap();
+    assert!(!dir.is_empty().await.unwrap());
+    assert_eq!(dir.entry_count().await.unwrap(), 4);
+    assert_eq!(root_dir.entry_count().await.unwrap(), root_entries + 1);
+
+    // deleted entries are not counted
+    dir.remove("long file name.txt").await.unwrap();
+    assert_eq!(dir.entry_count().await.unwrap(), 3);
+    for name in ["a.txt", "c", "subdir"] {
+        dir.remove(name).await.unwrap();
+    }
+    assert!(dir.is_empty().await.unwrap());
+    assert_eq!(dir.entry_count().await.unwrap(), 0);
+    root_dir.remove("counted").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dir_entry_count_fat12() {
+    call_with_fs(&test_dir_entry_count, FAT12_IMG, 28).await
+}
+
+#[tokio::test]
+async fn test_dir_entry_count_fat16() {
+    call_with_fs(&test_dir_entry_count, FAT16_IMG, 28).await
+}
+
+#[tokio::test]
+async fn test_dir_entry_count_fat32() {
+    call_with_fs(&test_dir_entry_count, FAT32_IMG, 28).await
+}
+
+async fn test_raw_cluster_io(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let cluster_size = fs.cluster_size() as usize;
+    let mut kept = root_dir.create_file("kept.bin").await.unwrap();
+    kept.write_all(&vec![0x11; cluster_size]).await.unwrap();
+    kept.flush().await.unwrap();
+    let kept_cluster = kept.cluster_chain().next().await.unwrap().unwrap().0;
+    drop(kept);
+
+    let mut freed = root_dir.create_file("freed.bin").await.unwrap();
+    freed.write_all(&vec![0x22; cluster_size]).await.unwrap();
+    freed.flush().await.unwrap();
+    let free_cluster = freed.cluster_chain().next().await.unwrap().unwrap().0;
+    drop(freed);
+    root_dir.remove("freed.bin").await.unwrap();
+
+    let free_clusters = fs.recompute_stats().await.unwrap().free_clusters();
+    let pattern = (0..cluster_size).map(|i| i as u8).collect::<Vec<_>>();
+    fs.write_cluster(free_cluster, &pattern).await.unwrap();
+    let mut buf = vec![0; cluster_size];
+    fs.read_cluster(free_cluster, &mut buf).await.unwrap();
+    assert_eq!(buf, pattern);
+    // a shorter buffer reads the beginning of the cluster
+    let mut head = [0; 16];
+    fs.read_cluster(free_cluster, &mut head).await.unwrap();
+    assert_eq!(head[..], pattern[..16]);
+
+    // neither the FAT nor other clusters are modified
+    assert_eq!(fs.recompute_stats().await.unwrap().free_clusters(), free_clusters);
+    fs.read_cluster(kept_cluster, &mut buf).await.unwrap();
+    assert!(buf.iter().all(|b| *b == 0x11));
+    let mut kept = root_dir.open_file("kept.bin").await.unwrap();
+    assert_eq!(read_to_end(&mut kept).await.unwrap(), vec![0x11; cluster_size]);
+
+    let end_cluster = fs.stats().await.unwrap().total_clusters() + 2;
+    for cluster in [0, 1, end_cluster] {
+        assert!(matches!(
+            fs.read_cluster(cluster, &mut head).await,
+            Err(embedded_fatfs::Error::InvalidInput)
+        ));
+        assert!(matches!(
+            fs.write_cluster(cluster, &head).await,
+            Err(embedded_fatfs::Error::InvalidInput)
+        ));
+    }
+    let mut too_long = vec![0; cluster_size + 1];
+    assert!(matches!(
+        fs.read_cluster(free_cluster, &mut too_long).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    assert!(matches!(
+        fs.write_cluster(free_cluster, &too_long).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+}
+
+#[tokio::test]
+async fn test_raw_cluster_io_fat12() {
+    call_with_fs(&test_raw_cluster_io, FAT12_IMG, 29).await
+}
+
+#[tokio::test]
+async fn test_raw_cluster_io_fat16() {
+    call_with_fs(&test_raw_cluster_io, FAT16_IMG, 29).await
+}
+
+#[tokio::test]
+async fn test_raw_cluster_io_fat32() {
+    call_with_fs(&test_raw_cluster_io, FAT32_IMG, 29).await
+}
+
+async fn test_fragment_count(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let cluster_size = fs.cluster_size() as usize;
+
+    let empty = root_dir.create_file("empty.bin").await.unwrap();
+    assert_eq!(empty.fragment_count().await.unwrap(), 0);
+    assert!(empty.is_contiguous().await.unwrap());
+    drop(empty);
+
+    // free space of the test images is fragmented, so allocate the clusters up front
+    let mut contiguous = root_dir.create_file("contiguous.bin").await.unwrap();
+    assert!(contiguous.allocate_contiguous(cluster_size as u64 * 4).await.unwrap());
+    contiguous.write_all(&vec![1_u8; cluster_size * 4]).await.unwrap();
+    contiguous.flush().await.unwrap();
+    assert_eq!(contiguous.fragment_count().await.unwrap(), 1);
+    assert!(contiguous.is_contiguous().await.unwrap());
+
+    // interleave allocations of two files cluster by cluster
+    let mut a = root_dir.create_file("a.bin").await.unwrap();
+    let mut b = root_dir.create_file("b.bin").await.unwrap();
+    for _ in 0..3 {
+        a.write_all(&vec![2_u8; cluster_size]).await.unwrap();
+        a.flush().await.unwrap();
+        b.write_all(&vec![3_u8; cluster_size]).await.unwrap();
+        b.flush().await.unwrap();
+    }
+    assert_eq!(a.fragment_count().await.unwrap(), 3);
+    assert!(!a.is_contiguous().await.unwrap());
+    assert_eq!(b.fragment_count().await.unwrap(), 3);
+    assert!(!b.is_contiguous().await.unwrap());
+    // the data is not affected
+    a.rewind().await.unwrap();
+    assert_eq!(read_to_end(&mut a).await.unwrap(), vec![2_u8; cluster_size * 3]);
+    a.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fragment_count_fat12() {
+    call_with_fs(&test_fragment_count, FAT12_IMG, 30).await
+}
+
+#[tokio::test]
+async fn test_fragment_count_fat16() {
+    call_with_fs(&test_fragment_count, FAT16_IMG, 30).await
+}
+
+#[tokio::test]
+async fn test_fragment_count_fat32() {
+    call_with_fs(&test_fragment_count, FAT32_IMG, 30).await
+}
+
+async fn test_open_file_with_options(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+
+    // invalid combinations of options
+    let r = root_dir.open_file_with_options().open("short.txt").await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::InvalidInput)));
+    let r = root_dir
+        .open_file_with_options()
+        .read(true)
+        .truncate(true)
+        .open("short.txt")
+        .await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::InvalidInput)));
+    let r = root_dir
+        .open_file_with_options()
+        .read(true)
+        .create(true)
+        .open("new.txt")
+        .await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::InvalidInput)));
+
+    // without create the file must exist
+    let r = root_dir.open_file_with_options().write(true).open("new.txt").await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::NotFound)));
+
+    // read-only access
+    let mut file = root_dir
+        .open_file_with_options()
+        .read(true)
+        .open("short.txt")
+        .await
+        .unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR2.as_bytes());
+    assert!(matches!(file.write(b"x").await, Err(embedded_fatfs::Error::ReadOnly)));
+    assert!(matches!(file.truncate().await, Err(embedded_fatfs::Error::ReadOnly)));
+    file.flush().await.unwrap();
+    drop(file);
+
+    // write-only access keeps the content
+    let mut file = root_dir
+        .open_file_with_options()
+        .write(true)
+        .open("short.txt")
+        .await
+        .unwrap();
+    let mut buf = [0_u8; 4];
+    assert!(matches!(
+        file.read(&mut buf).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    file.write_all(b"Yo").await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let mut file = root_dir.open_file("short.txt").await.unwrap();
+    let expected = format!("Yo{}", &TEST_STR2[2..]);
+    assert_eq!(read_to_end(&mut file).await.unwrap(), expected.as_bytes());
+    file.flush().await.unwrap();
+    drop(file);
+
+    // create_new fails if the file exists and creates it otherwise
+    let r = root_dir
+        .open_file_with_options()
+        .write(true)
+        .create_new(true)
+        .open("short.txt")
+        .await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::AlreadyExists)));
+    let mut file = root_dir
+        .open_file_with_options()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open("new.txt")
+        .await
+        .unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.rewind().await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+    drop(file);
+
+    // create opens an existing file without truncating it
+    let mut file = root_dir
+        .open_file_with_options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open("new.txt")
+        .await
+        .unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+    drop(file);
+
+    // truncate discards the content
+    let mut file = root_dir
+        .open_file_with_options()
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("new.txt")
+        .await
+        .unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), b"");
+    file.flush().await.unwrap();
+    drop(file);
+    assert_eq!(root_dir.open_meta("new.txt").await.unwrap().len(), 0);
+
+    // append writes at the end of the file even after an intervening seek
+    let mut file = root_dir
+        .open_file_with_options()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open("append.txt")
+        .await
+        .unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.seek(SeekFrom::Start(3)).await.unwrap();
+    file.write_all(TEST_STR2.as_bytes()).await.unwrap();
+    file.seek(SeekFrom::Start(1000)).await.unwrap();
+    file.write_all(b"!").await.unwrap();
+    file.rewind().await.unwrap();
+    let expected = format!("{}{}!", TEST_STR, TEST_STR2);
+    assert_eq!(read_to_end(&mut file).await.unwrap(), expected.as_bytes());
+    file.flush().await.unwrap();
+    drop(file);
+
+    // append to an existing file
+    let mut file = root_dir
+        .open_file_with_options()
+        .append(true)
+        .open("append.txt")
+        .await
+        .unwrap();
+    file.write_all(b"?").await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let mut file = root_dir.open_file("append.txt").await.unwrap();
+    let expected = format!("{}{}!?", TEST_STR, TEST_STR2);
+    assert_eq!(read_to_end(&mut file).await.unwrap(), expected.as_bytes());
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_open_file_with_options_fat12() {
+    call_with_fs(&test_open_file_with_options, FAT12_IMG, 31).await
+}
+
+#[tokio::test]
+async fn test_open_file_with_options_fat16() {
+    call_with_fs(&test_open_file_with_options, FAT16_IMG, 31).await
+}
+
+#[tokio::test]
+async fn test_open_file_with_options_fat32() {
+    call_with_fs(&test_open_file_with_options, FAT32_IMG, 31).await
+}
+
+async fn test_set_file_times(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let created = DateTime::new(Date::new(1999, 12, 31), Time::new(23, 59, 58, 0));
+    let modified = DateTime::new(Date::new(2001, 2, 3), Time::new(4, 5, 6, 0));
+    let accessed = Date::new(2002, 3, 4);
+
+    let mut file = root_dir.create_file("restored.txt").await.unwrap();
+    file.set_created(created);
+    file.set_modified(modified);
+    file.set_accessed(accessed);
+    // writing and reading afterwards keeps the explicit timestamps
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.rewind().await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+
+    file.flush().await.unwrap();
+    drop(file);
+
+    let entry = root_dir.open_meta("restored.txt").await.unwrap();
+    assert_eq!(entry.created(), created);
+    assert_eq!(entry.modified(), modified);
+    assert_eq!(entry.accessed(), accessed);
+
+    // dates out of the FAT range are clamped, the setters work on files opened read-only too
+    let mut file = root_dir
+        .open_file_with_options()
+        .read(true)
+        .open("restored.txt")
+        .await
+        .unwrap();
+    let mut before_epoch = Date::new(1980, 1, 1);
+    before_epoch.year = 1979;
+    let mut after_max = Date::new(2107, 12, 31);
+    after_max.year = 2108;
+    file.set_created(DateTime::new(before_epoch, Time::new(12, 0, 0, 0)));
+    file.set_modified(DateTime::new(after_max, Time::new(12, 0, 0, 0)));
+    file.set_accessed(before_epoch);
+    file.flush().await.unwrap();
+    drop(file);
+    let entry = root_dir.open_meta("restored.txt").await.unwrap();
+    assert_eq!(
+        entry.created(),
+        DateTime::new(Date::new(1980, 1, 1), Time::new(0, 0, 0, 0))
+    );
+    assert_eq!(
+        entry.modified(),
+        DateTime::new(Date::new(2107, 12, 31), Time::new(23, 59, 58, 0))
+    );
+    assert_eq!(entry.accessed(), Date::new(1980, 1, 1));
+}
+
+#[tokio::test]
+async fn test_set_file_times_fat12() {
+    call_with_fs(&test_set_file_times, FAT12_IMG, 32).await
+}
+
+#[tokio::test]
+async fn test_set_file_times_fat16() {
+    call_with_fs(&test_set_file_times, FAT16_IMG, 32).await
+}
+
+#[tokio::test]
+async fn test_set_file_times_fat32() {
+    call_with_fs(&test_set_file_times, FAT32_IMG, 32).await
+}
+
+async fn test_open_by_short_name(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let dir = root_dir.create_dir("aliases").await.unwrap();
+    for name in ["long-file-name-1.txt", "long-file-name-2.txt"] {
+        let mut file = dir.create_file(name).await.unwrap();
+        file.write_all(name.as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+    }
+
+    let entries = dir.iter().collect().await;
+    let short_names = entries
+        .iter()
+        .map(|r| r.as_ref().unwrap())
+        .filter(|e| e.is_file())
+        .map(|e| (e.file_name(), e.short_file_name()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        short_names,
+        [
+            ("long-file-name-1.txt".to_string(), "LONG-F~1.TXT".to_string()),
+            ("long-file-name-2.txt".to_string(), "LONG-F~2.TXT".to_string()),
+        ]
+    );
+
+    for (long_name, short_name) in &short_names {
+        // short name lookup ignores case like long name lookup
+        for path in [
+            short_name.clone(),
+            short_name.to_lowercase(),
+            format!("aliases/{}", short_name),
+        ] {
+            let base = if path.starts_with("aliases/") { &root_dir } else { &dir };
+            let mut file = base.open_file(&path).await.unwrap();
+            let content = read_to_end(&mut file).await.unwrap();
+            assert_eq!(content, long_name.as_bytes());
+        }
+        assert!(dir.exists(short_name).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_open_by_short_name_fat12() {
+    call_with_fs(&test_open_by_short_name, FAT12_IMG, 33).await
+}
+
+#[tokio::test]
+async fn test_open_by_short_name_fat16() {
+    call_with_fs(&test_open_by_short_name, FAT16_IMG, 33).await
+}
+
+#[tokio::test]
+async fn test_open_by_short_name_fat32() {
+    call_with_fs(&test_open_by_short_name, FAT32_IMG, 33).await
+}
+
+async fn test_truncate_to(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let cluster_size = u64::from(fs.stats().await.unwrap().cluster_size());
+    let data = (0..3 * cluster_size + 100).map(|i| i as u8).collect::<Vec<_>>();
+    let mut file = root_dir.create_file("truncate_to.bin").await.unwrap();
+    file.write_all(&data).await.unwrap();
+    file.flush().await.unwrap();
+    let free_clusters = fs.stats().await.unwrap().free_clusters();
+
+    // growing is rejected
+    let r = file.truncate_to(data.len() as u64 + 1).await;
+    assert!(matches!(r, Err(embedded_fatfs::Error::InvalidInput)));
+
+    // to a cluster boundary: the position past the end is moved to the new end
+    file.truncate_to(2 * cluster_size).await.unwrap();
+    assert_eq!(file.stream_position().await.unwrap(), 2 * cluster_size);
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters + 2);
+
+    // to a mid-cluster offset: the position before the new end is kept and no cluster is freed
+    file.seek(SeekFrom::Start(10)).await.unwrap();
+    file.truncate_to(cluster_size + cluster_size / 2).await.unwrap();
+    assert_eq!(file.stream_position().await.unwrap(), 10);
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters + 2);
+    file.flush().await.unwrap();
+    drop(file);
+    let mut file = root_dir.open_file("truncate_to.bin").await.unwrap();
+    assert_eq!(
+        read_to_end(&mut file).await.unwrap(),
+        &data[..(cluster_size + cluster_size / 2) as usize]
+    );
+
+    // to zero: all clusters are freed
+    file.truncate_to(0).await.unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(fs.stats().await.unwrap().free_clusters(), free_clusters + 4);
+    assert_eq!(root_dir.open_meta("truncate_to.bin").await.unwrap().len(), 0);
+    assert_eq!(read_to_end(&mut file).await.unwrap(), b"");
+}
+
+#[tokio::test]
+async fn test_truncate_to_fat12() {
+    call_with_fs(&test_truncate_to, FAT12_IMG, 34).await
+}
+
+#[tokio::test]
+async fn test_truncate_to_fat16() {
+    call_with_fs(&test_truncate_to, FAT16_IMG, 34).await
+}
+
+#[tokio::test]
+async fn test_truncate_to_fat32() {
+    call_with_fs(&test_truncate_to, FAT32_IMG, 34).await
+}
+
+async fn test_iter_raw(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let dir = root_dir.create_dir("raw").await.unwrap();
+    let mut file = dir.create_file("deleted file.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let entry = dir.open_meta("deleted file.txt").await.unwrap();
+    let first_cluster = entry.first_cluster().unwrap();
+    let short_name = entry.short_file_name_as_bytes().to_vec();
+
+    // the file uses two LFN slots followed by the short name slot
+    let slots = dir.iter_raw().collect().await;
+    let slots = slots.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+    let live_slots = slots.len();
+    assert!(slots.iter().all(|e| !e.is_deleted()));
+    let entry_slots = &slots[live_slots - 3..];
+    assert!(entry_slots[0].is_lfn() && entry_slots[1].is_lfn());
+    assert_eq!(entry_slots[2].first_cluster(), Some(first_cluster));
+    assert_eq!(entry_slots[2].size(), TEST_STR.len() as u32);
+    assert!(entry_slots[1].entry_pos() < entry_slots[2].entry_pos());
+
+    dir.remove("deleted file.txt").await.unwrap();
+    assert_eq!(dir.iter().collect().await.len(), 2);
+    let slots = dir.iter_raw().collect().await;
+    let slots = slots.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(slots.len(), live_slots);
+    assert_eq!(slots.iter().filter(|e| e.is_deleted()).count(), 3);
+    let entry_slots = &slots[live_slots - 3..];
+    assert!(entry_slots.iter().all(|e| e.is_deleted()));
+    assert!(entry_slots[0].is_lfn() && entry_slots[1].is_lfn());
+    let deleted = &entry_slots[2];
+    assert!(!deleted.is_lfn());
+    assert_eq!(deleted.short_name_as_bytes()[0], 0xE5);
+    assert_eq!(&deleted.short_name_as_bytes()[1..], &b"ELETE~1TXT"[..]);
+    assert_eq!(short_name, b"DELETE~1.TXT");
+    assert_eq!(deleted.first_cluster(), Some(first_cluster));
+    assert_eq!(deleted.size(), TEST_STR.len() as u32);
+    assert_eq!(deleted.as_bytes()[11], deleted.attributes().bits());
+}
+
+#[tokio::test]
+async fn test_iter_raw_fat12() {
+    call_with_fs(&test_iter_raw, FAT12_IMG, 35).await
+}
+
+#[tokio::test]
+async fn test_iter_raw_fat16() {
+    call_with_fs(&test_iter_raw, FAT16_IMG, 35).await
+}
+
+#[tokio::test]
+async fn test_iter_raw_fat32() {
+    call_with_fs(&test_iter_raw, FAT32_IMG, 35).await
+}
+
+async fn test_len_on_disk(fs: FileSystem) {
+    let cluster_size = u64::from(fs.cluster_size());
+    let root_dir = fs.root_dir();
+    let dir = root_dir.create_dir("on-disk").await.unwrap();
+
+    let mut file = dir.create_file("one-byte.txt").await.unwrap();
+    file.write_all(b"x").await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let mut file = dir.create_file("two-clusters.bin").await.unwrap();
+    file.write_all(&vec![0xAB; cluster_size as usize + 1]).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let mut file = dir.create_file("empty.txt").await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+
+    let len_on_disk = |name: &'static str| {
+        let dir = dir.clone();
+        async move { dir.open_meta(name).await.unwrap().len_on_disk().await.unwrap() }
+    };
+    assert_eq!(len_on_disk("one-byte.txt").await, cluster_size);
+    assert_eq!(len_on_disk("two-clusters.bin").await, 2 * cluster_size);
+    assert_eq!(len_on_disk("empty.txt").await, 0);
+
+    // the directory entry of a directory has no size, its clusters are counted instead
+    let entry = root_dir.open_meta("on-disk").await.unwrap();
+    assert_eq!(entry.len(), 0);
+    assert_eq!(entry.len_on_disk().await.unwrap(), cluster_size);
+    // a cluster worth of additional entries makes the directory grow to a second cluster
+    for i in 0..cluster_size / 32 {
+        dir.create_file(&format!("F{}.TXT", i))
+            .await
+            .unwrap()
+            .flush()
+            .await
+            .unwrap();
+    }
+    let slots = dir.iter_raw().collect().await.len() as u64;
+    let entry = root_dir.open_meta("on-disk").await.unwrap();
+    assert!(slots * 32 > cluster_size);
+    assert_eq!(
+        entry.len_on_disk().await.unwrap(),
+        (slots * 32).div_ceil(cluster_size) * cluster_size
+    );
+}
+
+#[tokio::test]
+async fn test_len_on_disk_fat12() {
+    call_with_fs(&test_len_on_disk, FAT12_IMG, 36).await
+}
+
+#[tokio::test]
+async fn test_len_on_disk_fat16() {
+    call_with_fs(&test_len_on_disk, FAT16_IMG, 36).await
+}
+
+#[tokio::test]
+async fn test_len_on_disk_fat32() {
+    call_with_fs(&test_len_on_disk, FAT32_IMG, 36).await
+}
+
+async fn test_rename_in_place(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let dir = root_dir.create_dir("in-place").await.unwrap();
+    let mut file = dir.create_file("first name.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let before = dir.open_meta("first name.txt").await.unwrap();
+    let slots = dir.iter_raw().collect().await.len();
+
+    // same number of LFN entries: the entries are overwritten, no deleted entries are left behind
+    dir.rename_in_place("first name.txt", "other name.txt").await.unwrap();
+    let raw = dir.iter_raw().collect().await;
+    assert_eq!(raw.len(), slots);
+    assert!(raw.iter().all(|e| !e.as_ref().unwrap().is_deleted()));
+    assert!(matches!(
+        dir.open_meta("first name.txt").await,
+        Err(embedded_fatfs::Error::NotFound)
+    ));
+    let after = dir.open_meta("other name.txt").await.unwrap();
+    assert_eq!(after.first_cluster(), before.first_cluster());
+    assert_eq!(after.created(), before.created());
+    assert_eq!(after.modified(), before.modified());
+    assert_eq!(after.attributes(), before.attributes());
+    assert_eq!(after.len(), TEST_STR.len() as u64);
+
+    // a longer name needs more entries and is written to free entries of the same directory
+    root_dir
+        .rename_in_place("in-place/other name.txt", "a considerably longer name.txt")
+        .await
+        .unwrap();
+    let after = dir.open_meta("a considerably longer name.txt").await.unwrap();
+    assert_eq!(after.first_cluster(), before.first_cluster());
+    assert_eq!(after.created(), before.created());
+    assert_eq!(after.modified(), before.modified());
+    let mut file = after.to_file();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), TEST_STR.as_bytes());
+    file.flush().await.unwrap();
+    drop(file);
+    let names = dir
+        .iter()
+        .collect()
+        .await
+        .into_iter()
+        .map(|e| e.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, [".", "..", "a considerably longer name.txt"]);
+
+    dir.create_file("taken.txt").await.unwrap().flush().await.unwrap();
+    assert!(matches!(
+        dir.rename_in_place("a considerably longer name.txt", "taken.txt").await,
+        Err(embedded_fatfs::Error::AlreadyExists)
+    ));
+    // an invalid name is rejected before the old entry is removed
+    assert!(matches!(
+        dir.rename_in_place("a considerably longer name.txt", "inva|id").await,
+        Err(embedded_fatfs::Error::UnsupportedFileNameCharacter)
+    ));
+    assert!(dir.open_meta("a considerably longer name.txt").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_rename_in_place_fat12() {
+    call_with_fs(&test_rename_in_place, FAT12_IMG, 37).await
+}
+
+#[tokio::test]
+async fn test_rename_in_place_fat16() {
+    call_with_fs(&test_rename_in_place, FAT16_IMG, 37).await
+}
+
+#[tokio::test]
+async fn test_rename_in_place_fat32() {
+    call_with_fs(&test_rename_in_place, FAT32_IMG, 37).await
+}
+
+async fn test_lock_open_files(tmp_path: String) {
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&tmp_path)
+        .await
+        .unwrap();
+    let fs = FileSystem::new(file, FsOptions::new().lock_open_files(true))
+        .await
+        .unwrap();
+    let root_dir = fs.root_dir();
+
+    let file = root_dir.open_file("short.txt").await.unwrap();
+    assert!(matches!(
+        root_dir.open_file("short.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    // the same entry is detected through its short name and with a different case
+    assert!(matches!(
+        root_dir.open_file("SHORT.TXT").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    assert!(matches!(
+        root_dir.create_file("short.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    assert!(matches!(
+        root_dir.open_file_with_options().read(true).open("short.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    // other files are not affected
+    let mut other = root_dir.open_file("long.txt").await.unwrap();
+    other.flush().await.unwrap();
+    drop(other);
+
+    // the file stays locked until the last clone is dropped
+    let clone = file.clone();
+    drop(file);
+    assert!(matches!(
+        root_dir.open_file("short.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    drop(clone);
+    let file = root_dir.open_file("short.txt").await.unwrap();
+    // closing a file releases it
+    file.close().await.unwrap();
+    let mut file = root_dir.open_file("short.txt").await.unwrap();
+    file.write_all(b"locked").await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+
+    // a newly created file is locked as well
+    let mut created = root_dir.create_file("created.txt").await.unwrap();
+    assert!(matches!(
+        root_dir.open_file("created.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    created.flush().await.unwrap();
+    drop(created);
+    root_dir.open_file("created.txt").await.unwrap();
+
+    // an open file cannot be removed or renamed
+    let file = root_dir.open_file("short.txt").await.unwrap();
+    assert!(matches!(
+        root_dir.remove("short.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    assert!(matches!(
+        root_dir.rename("short.txt", &root_dir, "moved.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    assert!(matches!(
+        root_dir.rename_in_place("short.txt", "moved.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    assert!(matches!(
+        root_dir.move_path("short.txt", "very/moved.txt").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    // other files are not affected
+    root_dir.remove("created.txt").await.unwrap();
+    drop(file);
+    root_dir.rename("short.txt", &root_dir, "moved.txt").await.unwrap();
+    let file = root_dir.open_file("moved.txt").await.unwrap();
+    drop(file);
+    root_dir.remove("moved.txt").await.unwrap();
+
+    // nor can a directory tree containing an open file
+    let file = root_dir.open_file("very/long/path/test.txt").await.unwrap();
+    assert!(matches!(
+        root_dir.remove_dir_all("very").await,
+        Err(embedded_fatfs::Error::Busy)
+    ));
+    drop(file);
+    root_dir.remove_dir_all("very").await.unwrap();
+
+    // without the option a file can be opened more than once
+    drop(root_dir);
+    fs.unmount().await.unwrap();
+    let fs = open_filesystem_rw(tmp_path).await;
+    let root_dir = fs.root_dir();
+    let first = root_dir.open_file("long.txt").await.unwrap();
+    let second = root_dir.open_file("long.txt").await.unwrap();
+    drop((first, second));
+}
+
+#[tokio::test]
+async fn test_lock_open_files_fat12() {
+    call_with_tmp_img(&test_lock_open_files, FAT12_IMG, 38).await
+}
+
+#[tokio::test]
+async fn test_lock_open_files_fat16() {
+    call_with_tmp_img(&test_lock_open_files, FAT16_IMG, 38).await
+}
+
+#[tokio::test]
+async fn test_lock_open_files_fat32() {
+    call_with_tmp_img(&test_lock_open_files, FAT32_IMG, 38).await
+}
+
+async fn test_zero_range(fs: FileSystem) {
+    let cluster_size = fs.cluster_size() as usize;
+    let data = (0..cluster_size * 4 + 100)
+        .map(|i| (i % 251) as u8 + 1)
+        .collect::<Vec<_>>();
+    let mut file = fs.root_dir().create_file("zero-range.bin").await.unwrap();
+    file.write_all(&data).await.unwrap();
+    file.seek(SeekFrom::Start(7)).await.unwrap();
+
+    // partial head cluster, two full clusters and a partial tail cluster
+    let start = cluster_size / 2;
+    let len = cluster_size * 3;
+    file.zero_range(start as u64, len as u64).await.unwrap();
+    assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 7);
+    assert_eq!(file.seek(SeekFrom::End(0)).await.unwrap(), data.len() as u64);
+
+    file.seek(SeekFrom::Start(0)).await.unwrap();
+    let mut expected = data.clone();
+    expected[start..start + len].fill(0);
+    assert_eq!(read_to_end(&mut file).await.unwrap(), expected);
+
+    assert!(matches!(
+        file.zero_range(data.len() as u64 - 10, 11).await,
+        Err(embedded_fatfs::Error::InvalidInput)
+    ));
+    file.zero_range(data.len() as u64, 0).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+    let mut file = fs.root_dir().open_file("zero-range.bin").await.unwrap();
+    assert_eq!(read_to_end(&mut file).await.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_zero_range_fat12() {
+    call_with_fs(&test_zero_range, FAT12_IMG, 39).await
+}
+
+#[tokio::test]
+async fn test_zero_range_fat16() {
+    call_with_fs(&test_zero_range, FAT16_IMG, 39).await
+}
+
+#[tokio::test]
+async fn test_zero_range_fat32() {
+    call_with_fs(&test_zero_range, FAT32_IMG, 39).await
+}
+
+async fn test_fn_time_provider(tmp_path: String) {
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&tmp_path)
+        .await
+        .unwrap();
+    // a stub RTC holding a fixed time, reading it fails once it is cleared
+    let rtc = core::cell::Cell::new(Some(DateTime::new(Date::new(2024, 5, 17), Time::new(13, 45, 10, 0))));
+    let options = FsOptions::new().time_provider(FnTimeProvider::new(|| rtc.get()));
+    let fs = embedded_fatfs::FileSystem::new(file, options).await.unwrap();
+    {
+        let root_dir = fs.root_dir();
+        let mut file = root_dir.create_file("rtc.txt").await.unwrap();
+        file.write_all(TEST_STR.as_bytes()).await.unwrap();
+        file.flush().await.unwrap();
+        let e = root_dir.open_meta("rtc.txt").await.unwrap();
+        let now = rtc.get().unwrap();
+        assert_eq!(e.created(), now);
+        assert_eq!(e.modified(), now);
+        assert_eq!(e.accessed(), now.date);
+
+        // creating files in a batch updates the modification time of their directory
+        root_dir.create_dir("batch").await.unwrap();
+        let later = DateTime::new(Date::new(2024, 5, 18), Time::new(9, 0, 0, 0));
+        rtc.set(Some(later));
+        root_dir.create_files(&["batch/a.txt", "batch/b.txt"]).await.unwrap();
+        assert_eq!(root_dir.open_meta("batch").await.unwrap().modified(), later);
+
+        rtc.set(None);
+        let mut file = root_dir.create_file("no-rtc.txt").await.unwrap();
+        file.flush().await.unwrap();
+        let e = root_dir.open_meta("no-rtc.txt").await.unwrap();
+        let epoch = DateTime::new(Date::new(1980, 1, 1), Time::new(0, 0, 0, 0));
+        assert_eq!(e.created(), epoch);
+        assert_eq!(e.modified(), epoch);
+    }
+    fs.unmount().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fn_time_provider_fat12() {
+    call_with_tmp_img(&test_fn_time_provider, FAT12_IMG, 40).await
+}
+
+#[tokio::test]
+async fn test_fn_time_provider_fat16() {
+    call_with_tmp_img(&test_fn_time_provider, FAT16_IMG, 40).await
+}
+
+#[tokio::test]
+async fn test_fn_time_provider_fat32() {
+    call_with_tmp_img(&test_fn_time_provider, FAT32_IMG, 40).await
+}
+
+async fn test_seek_relative(fs: FileSystem) {
+    let root_dir = fs.root_dir();
+    let mut file = root_dir.create_file("seek_relative.txt").await.unwrap();
+    file.write_all(TEST_STR.as_bytes()).await.unwrap();
+    let len = TEST_STR.len() as u64;
+    assert_eq!(file.position(), len);
+
+    // clamped at the start of the file
+    assert_eq!(file.seek_relative(-5).await.unwrap(), len - 5);
+    assert_eq!(file.seek_relative(-1000).await.unwrap(), 0);
+    assert_eq!(file.seek_relative(i64::MIN).await.unwrap(), 0);
+    assert_eq!(file.position(), 0);
+
+    // clamped at the end of the file
+    assert_eq!(file.seek_relative(3).await.unwrap(), 3);
+    let mut buf = [0; 2];
+    file.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, &TEST_STR.as_bytes()[3..5]);
+    assert_eq!(file.seek_relative(1000).await.unwrap(), len);
+    assert_eq!(file.seek_relative(i64::MAX).await.unwrap(), len);
+
+    // a position beyond the end set by `seek` is reported and clamped by the next relative seek
+    assert_eq!(file.seek(SeekFrom::Start(len + 10)).await.unwrap(), len + 10);
+    assert_eq!(file.position(), len + 10);
+    assert_eq!(file.seek_relative(0).await.unwrap(), len);
+    assert_eq!(file.position(), len);
+    file.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_seek_relative_fat12() {
+    call_with_fs(&test_seek_relative, FAT12_IMG, 41).await
+}
+
+#[tokio::test]
+async fn test_seek_relative_fat16() {
+    call_with_fs(&test_seek_relative, FAT16_IMG, 41).await
+}
+
+#[tokio::test]
+async fn test_seek_relative_fat32() {
+    call_with_fs(&test_seek_relative, FAT32_IMG, 41).await
+}
+
 async fn read_to_end<IO: embedded_io_async::Read>(io: &mut IO) -> Result<Vec<u8>, IO::Error> {
     let mut buf = Vec::new();
     loop {
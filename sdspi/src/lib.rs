@@ -1,13 +1,15 @@
 //! A crate for interfacing with SD cards over SPI.
 
 #![no_std]
+#![allow(async_fn_in_trait)]
 
 use aligned::Aligned;
 use core::fmt::Debug;
 use core::future::Future;
 use core::marker::PhantomData;
 use embassy_futures::select::{select, Either};
-use sdio_host::sd::{CardCapacity, CID, CSD, OCR, SD};
+use embassy_futures::yield_now;
+use sdio_host::sd::{CardCapacity, SDStatus, CID, CSD, OCR, SCR, SD};
 use sdio_host::{common_cmd::*, sd_cmd::*};
 
 // MUST be the first module listed
@@ -30,6 +32,15 @@ pub const DATA_RES_MASK: u8 = 0x1F;
 /// Write data accepted token
 pub const DATA_RES_ACCEPTED: u8 = 0x05;
 
+/// Block length used for data transfers
+const BLOCK_SIZE: u32 = 512;
+/// Default timeout for command responses and data tokens
+const DEFAULT_CMD_TIMEOUT_MS: u32 = 1000;
+/// Default timeout for the card to leave the busy state
+const DEFAULT_BUSY_TIMEOUT_MS: u32 = 5000;
+/// Default maximum number of blocks transferred by a single multi-block command
+const DEFAULT_MAX_BLOCKS_PER_COMMAND: usize = 64;
+
 #[derive(Clone, Copy, Debug, Default)]
 /// SD Card
 pub struct Card {
@@ -43,13 +54,33 @@ pub struct Card {
     pub cid: CID<SD>,
     /// Card Specific Data
     pub csd: CSD<SD>,
+    /// SD Status (speed class, allocation unit size, ...), `None` if the card did not return it during init
+    pub sd_status: Option<SDStatus>,
+    /// SD Configuration Register, `None` if the card did not return it during init
+    pub scr: Option<SCR>,
 }
 
 impl Card {
     /// Size in bytes
     pub fn size(&self) -> u64 {
-        // SDHC / SDXC / SDUC
-        u64::from(self.csd.block_count()) * 512
+        match self.card_type {
+            // SDSC cards may report a block length other than 512 bytes
+            CardCapacity::StandardCapacity => self.csd.card_size(),
+            // SDHC / SDXC / SDUC
+            _ => u64::from(self.csd.block_count()) * 512,
+        }
+    }
+
+    /// Converts a block address to the argument of a data transfer command.
+    ///
+    /// Standard capacity cards are byte addressed, all other cards are block addressed.
+    fn data_address(&self, block_address: u32) -> Result<u32, Error> {
+        match self.card_type {
+            CardCapacity::StandardCapacity => block_address
+                .checked_mul(BLOCK_SIZE)
+                .ok_or(Error::UnsupportedCard),
+            _ => Ok(block_address),
+        }
     }
 }
 
@@ -67,6 +98,72 @@ pub enum Error {
     CrcMismatch(u16, u16),
     NotInitialized,
     WriteError,
+    /// A multi-block write was rejected by the card. Holds the number of blocks that were written successfully,
+    /// as reported by [SdSpi::num_written_blocks].
+    PartialWrite(u32),
+    EraseError(u8),
+    /// The card rejected the number of blocks to pre-erase (ACMD23) sent before a multi-block write. Holds the R1
+    /// response. The write is not started.
+    PreEraseError(u8),
+    /// The write-protect switch reports that the card is locked.
+    ReadOnly,
+    /// Reading the card-detect or write-protect pin failed.
+    GpioError,
+}
+
+impl Error {
+    /// Returns whether retrying the initialization cannot succeed, either because the card is not supported or
+    /// because there is no card to initialize.
+    fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Error::UnsupportedCard | Error::NotInitialized | Error::GpioError
+        )
+    }
+}
+
+/// Placeholder for a card-detect or write-protect switch that is not connected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoPin;
+
+impl embedded_hal::digital::ErrorType for NoPin {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal::digital::InputPin for NoPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// Measures the timeouts of card operations.
+///
+/// Implemented for every [DelayNs](embedded_hal_async::delay::DelayNs) that is `Clone`. The source is cloned for
+/// each timeout, so implement it on a monotonic timer to avoid a delay peripheral, e.g. with `embassy-time`:
+///
+/// ```ignore
+/// #[derive(Clone, Copy)]
+/// struct EmbassyTimeout;
+///
+/// impl sdspi::TimeoutSource for EmbassyTimeout {
+///     async fn after_ms(&mut self, ms: u32) {
+///         embassy_time::Timer::after_millis(ms.into()).await
+///     }
+/// }
+/// ```
+pub trait TimeoutSource: Clone {
+    /// Completes once `ms` milliseconds have passed.
+    async fn after_ms(&mut self, ms: u32);
+}
+
+impl<D: embedded_hal_async::delay::DelayNs + Clone> TimeoutSource for D {
+    async fn after_ms(&mut self, ms: u32) {
+        self.delay_ms(ms).await
+    }
 }
 
 /// Must be called between powerup and [SdSpi::init] to ensure the sdcard is properly initialized.
@@ -82,22 +179,57 @@ where
     Ok(())
 }
 
-pub struct SdSpi<SPI, D, ALIGN>
+/// Calls [sd_init] up to `attempts` times, waiting `backoff_ms` milliseconds after each failed attempt.
+///
+/// Returns the error of the last attempt if all of them fail. At least one attempt is made, even if `attempts` is 0.
+pub async fn sd_init_with_retries<SPI, CS, BE, D>(
+    spi: &mut SPI,
+    cs: &mut CS,
+    delay: &D,
+    attempts: u32,
+    backoff_ms: u32,
+) -> Result<(), Error>
+where
+    SPI: embedded_hal_async::spi::SpiBus<Error = BE>,
+    CS: embedded_hal::digital::OutputPin,
+    D: TimeoutSource,
+{
+    let mut attempt = 1;
+    loop {
+        match sd_init(spi, cs).await {
+            Err(e) if attempt < attempts => {
+                warn!("sd_init attempt {} failed: {:?}", attempt, e);
+                delay.clone().after_ms(backoff_ms).await;
+                attempt += 1;
+            }
+            r => return r,
+        }
+    }
+}
+
+pub struct SdSpi<SPI, D, ALIGN, WP = NoPin, CD = NoPin>
 where
     SPI: embedded_hal_async::spi::SpiDevice,
-    D: embedded_hal_async::delay::DelayNs,
+    D: TimeoutSource,
     ALIGN: aligned::Alignment,
 {
     spi: SPI,
     delay: D,
     card: Option<Card>,
+    crc: bool,
+    cmd_timeout_ms: u32,
+    busy_timeout_ms: u32,
+    chunk_size: usize,
+    max_blocks_per_command: usize,
+    write_protect: Option<WP>,
+    card_detect: Option<CD>,
     _align: PhantomData<ALIGN>,
 }
 
 impl<SPI, D, ALIGN> SdSpi<SPI, D, ALIGN>
 where
     SPI: embedded_hal_async::spi::SpiDevice,
-    D: embedded_hal_async::delay::DelayNs + Clone,
+    D: TimeoutSource,
     ALIGN: aligned::Alignment,
 {
     pub fn new(spi: SPI, delay: D) -> Self {
@@ -105,30 +237,180 @@ where
             spi,
             delay,
             card: None,
+            crc: true,
+            cmd_timeout_ms: DEFAULT_CMD_TIMEOUT_MS,
+            busy_timeout_ms: DEFAULT_BUSY_TIMEOUT_MS,
+            chunk_size: BLOCK_SIZE as usize,
+            max_blocks_per_command: DEFAULT_MAX_BLOCKS_PER_COMMAND,
+            write_protect: None,
+            card_detect: None,
             _align: PhantomData,
         }
     }
+}
+
+impl<SPI, D, ALIGN, WP, CD> SdSpi<SPI, D, ALIGN, WP, CD>
+where
+    SPI: embedded_hal_async::spi::SpiDevice,
+    D: TimeoutSource,
+    ALIGN: aligned::Alignment,
+    WP: embedded_hal::digital::InputPin,
+    CD: embedded_hal::digital::InputPin,
+{
+    /// Uses `pin` to read the write-protect switch of the card socket.
+    ///
+    /// The switch is typically wired active-low: it connects the pin to ground while the card's lock tab is in the
+    /// unlocked position and an external or internal pull-up drives it high when the card is locked. [SdSpi::write]
+    /// and [SdSpi::erase] return [Error::ReadOnly] while the pin reads high. Invert the pin if the socket is wired
+    /// the other way around.
+    pub fn with_write_protect<P: embedded_hal::digital::InputPin>(
+        self,
+        pin: P,
+    ) -> SdSpi<SPI, D, ALIGN, P, CD> {
+        SdSpi {
+            spi: self.spi,
+            delay: self.delay,
+            card: self.card,
+            crc: self.crc,
+            cmd_timeout_ms: self.cmd_timeout_ms,
+            busy_timeout_ms: self.busy_timeout_ms,
+            chunk_size: self.chunk_size,
+            max_blocks_per_command: self.max_blocks_per_command,
+            write_protect: Some(pin),
+            card_detect: self.card_detect,
+            _align: PhantomData,
+        }
+    }
+
+    /// Uses `pin` to read the card-detect switch of the card socket.
+    ///
+    /// The switch is typically wired active-low: it connects the pin to ground while a card is inserted and a
+    /// pull-up drives it high when the socket is empty. While the pin reads high, [SdSpi::init] and all data
+    /// transfers return [Error::NotInitialized] and the previously initialized card is forgotten, so it has to be
+    /// initialized again after being reinserted.
+    pub fn with_card_detect<P: embedded_hal::digital::InputPin>(
+        self,
+        pin: P,
+    ) -> SdSpi<SPI, D, ALIGN, WP, P> {
+        SdSpi {
+            spi: self.spi,
+            delay: self.delay,
+            card: self.card,
+            crc: self.crc,
+            cmd_timeout_ms: self.cmd_timeout_ms,
+            busy_timeout_ms: self.busy_timeout_ms,
+            chunk_size: self.chunk_size,
+            max_blocks_per_command: self.max_blocks_per_command,
+            write_protect: self.write_protect,
+            card_detect: Some(pin),
+            _align: PhantomData,
+        }
+    }
+
+    /// Returns whether a card is inserted according to the card-detect switch.
+    ///
+    /// Always returns `true` if no card-detect pin was configured with [SdSpi::with_card_detect].
+    pub fn is_card_present(&mut self) -> Result<bool, Error> {
+        match &mut self.card_detect {
+            Some(pin) => pin.is_low().map_err(|_| Error::GpioError),
+            None => Ok(true),
+        }
+    }
+
+    /// Returns whether the card is locked according to the write-protect switch.
+    ///
+    /// Always returns `false` if no write-protect pin was configured with [SdSpi::with_write_protect].
+    pub fn is_write_protected(&mut self) -> Result<bool, Error> {
+        match &mut self.write_protect {
+            Some(pin) => pin.is_high().map_err(|_| Error::GpioError),
+            None => Ok(false),
+        }
+    }
+
+    fn check_card_present(&mut self) -> Result<(), Error> {
+        if !self.is_card_present()? {
+            warn!("No card inserted");
+            self.card = None;
+            return Err(Error::NotInitialized);
+        }
+        Ok(())
+    }
+
+    fn check_writable(&mut self) -> Result<(), Error> {
+        if self.is_write_protected()? {
+            warn!("Card is write-protected");
+            return Err(Error::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Sets whether the card should check the CRC of commands and written data blocks.
+    ///
+    /// Takes effect on the next call to [SdSpi::init]. Enabled by default.
+    pub fn with_crc(mut self, enabled: bool) -> Self {
+        self.crc = enabled;
+        self
+    }
+
+    /// Sets the timeouts used when talking to the card.
+    ///
+    /// `cmd_ms` limits how long to wait for a command response or the start of a data block, and how long each
+    /// initialization step of [SdSpi::init] may take. `busy_ms` limits how long the card may stay busy (e.g. while
+    /// programming written data). Defaults to 1000 ms and 5000 ms.
+    pub fn with_timeouts(mut self, cmd_ms: u32, busy_ms: u32) -> Self {
+        self.cmd_timeout_ms = cmd_ms;
+        self.busy_timeout_ms = busy_ms;
+        self
+    }
+
+    /// Sets the maximum number of bytes transferred by a single SPI transfer when reading or writing data blocks.
+    ///
+    /// Data blocks are split into transfers of `chunk_size` bytes, which is useful if the SPI driver uses DMA with a
+    /// buffer smaller than a block. The CRC is still computed over the whole block. Defaults to 512 bytes (one
+    /// block), a `chunk_size` of 0 is treated as 1.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of blocks transferred by a single multi-block read or write command.
+    ///
+    /// [SdSpi::read] and [SdSpi::write] split larger transfers into several commands, each one stopped before the
+    /// next one is started, so a platform that has to transfer a whole command atomically (e.g. with DMA) does not
+    /// need a buffer for all blocks. Defaults to 64 blocks, a value of 0 is treated as 1.
+    pub fn with_max_blocks_per_command(mut self, max_blocks: usize) -> Self {
+        self.max_blocks_per_command = max_blocks.max(1);
+        self
+    }
 
     /// To comply with the SD card spec, [sd_init] must be called between powerup and calling this function.
     pub async fn init(&mut self) -> Result<(), Error> {
+        // forget the previous card, it is only restored once initialization succeeds
+        self.card = None;
+        self.check_card_present()?;
         let r = async {
-            with_timeout(self.delay.clone(), 1000, async {
+            with_timeout(self.delay.clone(), self.cmd_timeout_ms, async {
                 loop {
                     let r = self.cmd(idle()).await?;
                     if r == R1_IDLE_STATE {
                         return Ok(());
                     }
+                    yield_now().await;
                 }
             })
             .await??;
 
             // "The SPI interface is initialized in the CRC OFF mode in default"
             // -- SD Part 1 Physical Layer Specification v9.00, Section 7.2.2 Bus Transfer Protection
-            if self.cmd(cmd::<R1>(0x3B, 1)).await? != R1_IDLE_STATE {
+            let r = self.cmd(cmd::<R1>(0x3B, u32::from(self.crc))).await?;
+            if r != R1_IDLE_STATE {
+                if r & R1_ILLEGAL_COMMAND != 0 {
+                    warn!("Card rejected CRC_ON_OFF");
+                }
                 return Err(Error::Cmd59Error);
             }
 
-            with_timeout(self.delay.clone(), 1000, async {
+            with_timeout(self.delay.clone(), self.cmd_timeout_ms, async {
                 loop {
                     let r = self.cmd(send_if_cond(0x1, 0xAA)).await?;
                     if r == (R1_ILLEGAL_COMMAND | R1_IDLE_STATE) {
@@ -142,6 +424,7 @@ where
                     if buffer[3] == 0xAA {
                         return Ok(());
                     }
+                    yield_now().await;
                 }
             })
             .await??;
@@ -152,18 +435,19 @@ where
             let mut card = Card::default();
 
             // send ACMD41
-            with_timeout(self.delay.clone(), 1000, async {
+            with_timeout(self.delay.clone(), self.cmd_timeout_ms, async {
                 loop {
                     let r = self.acmd(sd_send_op_cond(true, false, true, 0x20)).await?;
                     if r == R1_READY_STATE {
                         return Ok(());
                     }
+                    yield_now().await;
                 }
             })
             .await??;
 
             trace!("send_ocr");
-            card.ocr = with_timeout(self.delay.clone(), 1000, async {
+            card.ocr = with_timeout(self.delay.clone(), self.cmd_timeout_ms, async {
                 loop {
                     let r = self.cmd(cmd::<R3>(0x3A, 0)).await?;
                     if r != R1_READY_STATE {
@@ -178,10 +462,25 @@ where
                     if !ocr.is_busy() {
                         return Ok(ocr);
                     }
+                    yield_now().await;
                 }
             })
             .await??;
 
+            card.card_type = if card.ocr.high_capacity() {
+                CardCapacity::HighCapacity
+            } else {
+                CardCapacity::StandardCapacity
+            };
+
+            if matches!(card.card_type, CardCapacity::StandardCapacity) {
+                // SDSC cards may default to a different block length
+                trace!("set_block_length");
+                if self.cmd(set_block_length(BLOCK_SIZE)).await? != R1_READY_STATE {
+                    return Err(Error::UnsupportedCard);
+                }
+            }
+
             trace!("send_csd");
             let r = self.cmd(send_csd(card.rca as u16)).await?;
             if r != R1_READY_STATE {
@@ -200,6 +499,25 @@ where
             self.read_data(&mut cid).await?;
             card.cid = u128::from_be_bytes(cid).into();
 
+            // the SD status is informational only, failing to read it must not fail the initialization
+            card.sd_status = match self.sd_status().await {
+                Ok(status) => Some(status),
+                Err(e) => {
+                    warn!("failed to read the SD status: {:?}", e);
+                    None
+                }
+            };
+
+            // the SCR only tells which optional commands are supported, failing to read it must not fail the
+            // initialization
+            card.scr = match self.scr().await {
+                Ok(scr) => Some(scr),
+                Err(e) => {
+                    warn!("failed to read the SCR: {:?}", e);
+                    None
+                }
+            };
+
             trace!("Card initialized: {:?}", card);
             debug!("Found card with size: {}bytes", card.size());
 
@@ -212,28 +530,146 @@ where
         r
     }
 
+    /// Calls [SdSpi::init] up to `attempts` times, waiting `backoff_ms` milliseconds after each failed attempt.
+    ///
+    /// Cards may need several attempts while they ramp up after powerup, so timeouts and communication errors are
+    /// retried. [Error::UnsupportedCard], [Error::GpioError] and [Error::NotInitialized] (no card inserted) are
+    /// returned immediately as retrying cannot fix them. Returns the error of the last attempt if all of them fail. At least one
+    /// attempt is made, even if `attempts` is 0.
+    pub async fn init_with_retries(&mut self, attempts: u32, backoff_ms: u32) -> Result<(), Error> {
+        let mut attempt = 1;
+        loop {
+            match self.init().await {
+                Err(e) if attempt < attempts && !e.is_fatal() => {
+                    warn!("init attempt {} failed: {:?}", attempt, e);
+                    self.delay.clone().after_ms(backoff_ms).await;
+                    attempt += 1;
+                }
+                r => return r,
+            }
+        }
+    }
+
+    /// Switches the card to high-speed mode (up to 50 MHz) using the switch function command (CMD6).
+    ///
+    /// Returns `Ok(true)` if the card confirmed the switch, and `Ok(false)` if the card does not support CMD6 or
+    /// high-speed mode, in which case it stays in default-speed mode (up to 25 MHz). Must be called after
+    /// [SdSpi::init].
+    ///
+    /// This only changes the mode of the card: the caller still owns the SPI bus and has to raise its clock
+    /// frequency after this returns `Ok(true)`.
+    pub async fn try_switch_high_speed(&mut self) -> Result<bool, Error> {
+        self.check_card_present()?;
+        self.card.ok_or(Error::NotInitialized)?;
+        // query function group 1 without switching, keeping all other groups unchanged
+        let Some(status) = self.switch_function(0x00FF_FFF1).await? else {
+            return Ok(false);
+        };
+        // bits 415:400 hold the functions supported by group 1, function 1 is high-speed
+        if status[13] & 0x02 == 0 {
+            debug!("Card does not support high-speed mode");
+            return Ok(false);
+        }
+        let Some(status) = self.switch_function(0x80FF_FFF1).await? else {
+            return Ok(false);
+        };
+        // bits 379:376 hold the function selected in group 1, 0xF if the switch failed
+        Ok(status[16] & 0x0F == 0x01)
+    }
+
+    /// Reads the 512-bit SD status register (ACMD13).
+    ///
+    /// The status holds the speed class and the allocation unit size of the card, which are useful for choosing
+    /// write sizes. It is also read during [SdSpi::init] and stored in [Card::sd_status].
+    pub async fn read_sd_status(&mut self) -> Result<SDStatus, Error> {
+        self.check_card_present()?;
+        self.card.ok_or(Error::NotInitialized)?;
+        let status = self.sd_status().await?;
+        if let Some(card) = self.card.as_mut() {
+            card.sd_status = Some(status);
+        }
+        Ok(status)
+    }
+
+    async fn sd_status(&mut self) -> Result<SDStatus, Error> {
+        trace!("sd_status");
+        let r = self.acmd(sd_status()).await?;
+        // in SPI mode ACMD13 responds with R2, the second byte holds further status bits
+        let r2 = self.read_byte().await?;
+        if r != R1_READY_STATE {
+            return Err(Error::RegisterError(r));
+        }
+        if r2 != 0 {
+            return Err(Error::RegisterError(r2));
+        }
+        let mut buf = [0xFFu8; 64];
+        self.read_data(&mut buf).await?;
+        // the register is sent MSB first, `SDStatus` expects little endian words
+        let mut words = [0u32; 16];
+        for (word, bytes) in words.iter_mut().rev().zip(buf.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        Ok(words.into())
+    }
+
+    async fn scr(&mut self) -> Result<SCR, Error> {
+        trace!("send_scr");
+        let r = self.acmd(send_scr()).await?;
+        if r != R1_READY_STATE {
+            return Err(Error::RegisterError(r));
+        }
+        let mut buf = [0xFFu8; 8];
+        self.read_data(&mut buf).await?;
+        Ok(SCR(u64::from_be_bytes(buf)))
+    }
+
+    /// Issues CMD6 and reads the 64 byte switch function status, or returns `None` if the card rejects the command.
+    async fn switch_function(&mut self, arg: u32) -> Result<Option<[u8; 64]>, Error> {
+        trace!("switch_function");
+        let r = self.cmd(cmd::<R1>(0x06, arg)).await?;
+        if r & R1_ILLEGAL_COMMAND != 0 {
+            return Ok(None);
+        }
+        if r != R1_READY_STATE {
+            return Err(Error::RegisterError(r));
+        }
+        let mut status = [0xFFu8; 64];
+        self.read_data(&mut status).await?;
+        Ok(Some(status))
+    }
+
     pub async fn read<const SIZE: usize>(
         &mut self,
         block_address: u32,
         data: &mut [Aligned<ALIGN, [u8; SIZE]>],
     ) -> Result<(), Error> {
-        let r = async {
-            if data.len() == 1 {
-                self.cmd(read_single_block(block_address)).await?;
-                self.read_data(&mut data[0][..]).await?;
-            } else {
-                self.cmd(read_multiple_blocks(block_address)).await?;
-                for block in data {
-                    self.read_data(&mut block[..]).await?;
-                }
-                self.cmd(stop_transmission()).await?;
-            }
-            Ok(())
+        self.check_card_present()?;
+        let card = self.card.ok_or(Error::NotInitialized)?;
+        check_block_range(block_address, data.len())?;
+        let max_blocks = self.max_blocks_per_command;
+        for (i, run) in data.chunks_mut(max_blocks).enumerate() {
+            // cannot overflow, the whole range was checked above
+            let address = card.data_address(block_address + (i * max_blocks) as u32)?;
+            self.read_run(address, run).await?;
         }
-        .await;
-
-        r?;
+        Ok(())
+    }
 
+    async fn read_run<const SIZE: usize>(
+        &mut self,
+        address: u32,
+        data: &mut [Aligned<ALIGN, [u8; SIZE]>],
+    ) -> Result<(), Error> {
+        if data.len() == 1 {
+            self.cmd(read_single_block(address)).await?;
+            self.read_data(&mut data[0][..]).await?;
+        } else {
+            self.cmd(read_multiple_blocks(address)).await?;
+            for block in data {
+                self.read_data(&mut block[..]).await?;
+            }
+            self.cmd(stop_transmission()).await?;
+        }
         Ok(())
     }
 
@@ -241,10 +677,33 @@ where
         &mut self,
         block_address: u32,
         data: &[Aligned<ALIGN, [u8; SIZE]>],
+    ) -> Result<(), Error> {
+        self.check_card_present()?;
+        self.check_writable()?;
+        let card = self.card.ok_or(Error::NotInitialized)?;
+        check_block_range(block_address, data.len())?;
+        let max_blocks = self.max_blocks_per_command;
+        for (i, run) in data.chunks(max_blocks).enumerate() {
+            let written = (i * max_blocks) as u32;
+            // cannot overflow, the whole range was checked above
+            let address = card.data_address(block_address + written)?;
+            match self.write_run(address, run).await {
+                // report the blocks written by the previous runs too
+                Err(Error::PartialWrite(n)) => return Err(Error::PartialWrite(written + n)),
+                r => r?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn write_run<const SIZE: usize>(
+        &mut self,
+        address: u32,
+        data: &[Aligned<ALIGN, [u8; SIZE]>],
     ) -> Result<(), Error> {
         let r = async {
             if data.len() == 1 {
-                self.cmd(write_single_block(block_address)).await?;
+                self.cmd(write_single_block(address)).await?;
                 self.write_data(DATA_START_BLOCK, &data[0][..]).await?;
                 self.wait_idle().await?;
                 // check status, in SD SPI mode, the status is two bytes
@@ -255,17 +714,27 @@ where
                     return Err(Error::WriteError);
                 }
             } else {
-                // Try sending ACMD23 _before_ write.
-                // This will pre-erase blocks to improve write performance.
-                // We ignore the return value, because whether its accepted
-                // or not doesn't matter we will still proceed with the write
-                self.acmd(cmd::<R1>(0x17, data.len() as u32)).await?;
+                // Send ACMD23 _before_ write to pre-erase the blocks, which improves write performance. The
+                // command is mandatory for SD cards, so an illegal command only means the blocks are not
+                // pre-erased, any other error would leave the card in an unknown state for the write.
+                let r = self.acmd(cmd::<R1>(0x17, data.len() as u32)).await?;
+                if r & !R1_ILLEGAL_COMMAND != R1_READY_STATE {
+                    return Err(Error::PreEraseError(r));
+                }
+                if r != R1_READY_STATE {
+                    warn!("Card rejected ACMD23, writing without pre-erase");
+                }
                 self.wait_idle().await?;
 
-                self.cmd(write_multiple_blocks(block_address)).await?;
+                self.cmd(write_multiple_blocks(address)).await?;
                 for block in data {
                     self.wait_idle().await?;
-                    self.write_data(WRITE_MULTIPLE_TOKEN, &block[..]).await?;
+                    match self.write_data(WRITE_MULTIPLE_TOKEN, &block[..]).await {
+                        Err(Error::WriteError) => {
+                            return Err(self.recover_multi_block_write().await)
+                        }
+                        r => r?,
+                    }
                 }
                 // stop the write
                 self.wait_idle().await?;
@@ -283,17 +752,92 @@ where
         Ok(())
     }
 
+    /// Returns the number of blocks written without errors by the last multi-block write (ACMD22).
+    ///
+    /// Useful to find the point from which a failed multi-block write has to be resumed.
+    pub async fn num_written_blocks(&mut self) -> Result<u32, Error> {
+        self.check_card_present()?;
+        self.card.ok_or(Error::NotInitialized)?;
+        let r = self.acmd(cmd::<R1>(0x16, 0)).await?;
+        if r != R1_READY_STATE {
+            return Err(Error::RegisterError(r));
+        }
+        let mut buf = [0; 4];
+        self.read_data(&mut buf).await?;
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    /// Terminates a multi-block write the card rejected and asks it how many blocks were committed.
+    async fn recover_multi_block_write(&mut self) -> Error {
+        let r = async {
+            self.wait_idle().await?;
+            self.spi
+                .write(&[STOP_TRAN_TOKEN])
+                .await
+                .map_err(|_| Error::SpiError)?;
+            self.num_written_blocks().await
+        }
+        .await;
+        match r {
+            Ok(written) => Error::PartialWrite(written),
+            Err(e) => {
+                warn!("failed to query the number of written blocks: {:?}", e);
+                Error::WriteError
+            }
+        }
+    }
+
+    /// Returns the size of the card in bytes, or [Error::NotInitialized] if [SdSpi::init] has not succeeded yet.
     pub async fn size(&mut self) -> Result<u64, Error> {
         Ok(self.card.ok_or(Error::NotInitialized)?.size())
     }
 
+    /// Erases the blocks from `start_block` to `end_block` (inclusive).
+    ///
+    /// The contents of erased blocks are undefined until they are written again.
+    pub async fn erase(&mut self, start_block: u32, end_block: u32) -> Result<(), Error> {
+        self.check_card_present()?;
+        self.check_writable()?;
+        let card = self.card.ok_or(Error::NotInitialized)?;
+        let start = card.data_address(start_block)?;
+        let end = card.data_address(end_block)?;
+        let r = async {
+            // built by hand, `erase_wr_blk_start_addr` and `erase_wr_blk_end_addr` of sdio-host 0.9 both encode
+            // CMD35 instead of CMD32 and CMD33
+            trace!("erase_wr_blk_start_addr");
+            let r = self.cmd(cmd::<R1>(32, start)).await?;
+            if r != R1_READY_STATE {
+                return Err(Error::EraseError(r));
+            }
+            trace!("erase_wr_blk_end_addr");
+            let r = self.cmd(cmd::<R1>(33, end)).await?;
+            if r != R1_READY_STATE {
+                return Err(Error::EraseError(r));
+            }
+            trace!("erase");
+            let r = self.cmd(erase()).await?;
+            if r != R1_READY_STATE {
+                return Err(Error::EraseError(r));
+            }
+            // the card holds the line low while the erase is in progress
+            self.wait_idle().await
+        }
+        .await;
+
+        r?;
+
+        Ok(())
+    }
+
     async fn read_data(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
-        let r = with_timeout(self.delay.clone(), 1000, async {
-            let mut byte = 0xFF;
-            while byte == 0xFF {
-                byte = self.read_byte().await?;
+        let r = with_timeout(self.delay.clone(), self.cmd_timeout_ms, async {
+            loop {
+                let byte = self.read_byte().await?;
+                if byte != 0xFF {
+                    return Ok(byte);
+                }
+                yield_now().await;
             }
-            Ok(byte)
         })
         .await??;
 
@@ -301,11 +845,15 @@ where
             return Err(Error::RegisterError(r));
         }
 
-        buffer.fill(0xFF);
-        self.spi
-            .transfer_in_place(buffer)
-            .await
-            .map_err(|_| Error::SpiError)?;
+        let mut calc_crc = 0;
+        for chunk in buffer.chunks_mut(self.chunk_size) {
+            chunk.fill(0xFF);
+            self.spi
+                .transfer_in_place(chunk)
+                .await
+                .map_err(|_| Error::SpiError)?;
+            calc_crc = crc16_update(calc_crc, chunk);
+        }
 
         let mut crc_bytes = [0xFF; 2];
         self.spi
@@ -313,7 +861,6 @@ where
             .await
             .map_err(|_| Error::SpiError)?;
         let crc = u16::from_be_bytes(crc_bytes);
-        let calc_crc = crc16(buffer);
         if crc != calc_crc {
             return Err(Error::CrcMismatch(crc, calc_crc));
         }
@@ -326,8 +873,12 @@ where
             .write(&[token])
             .await
             .map_err(|_| Error::SpiError)?;
-        self.spi.write(buffer).await.map_err(|_| Error::SpiError)?;
-        let crc_bytes = crc16(buffer).to_be_bytes();
+        let mut crc = 0;
+        for chunk in buffer.chunks(self.chunk_size) {
+            self.spi.write(chunk).await.map_err(|_| Error::SpiError)?;
+            crc = crc16_update(crc, chunk);
+        }
+        let crc_bytes = crc.to_be_bytes();
         self.spi
             .write(&crc_bytes)
             .await
@@ -341,6 +892,13 @@ where
         Ok(())
     }
 
+    /// Returns the registers of the initialized card, or `None` if [SdSpi::init] has not succeeded yet.
+    ///
+    /// The [Card] holds the parsed CID (manufacturer, product name, serial number), CSD (capacity) and OCR.
+    pub fn card(&self) -> Option<&Card> {
+        self.card.as_ref()
+    }
+
     pub fn spi(&mut self) -> &mut SPI {
         &mut self.spi
     }
@@ -370,17 +928,16 @@ where
                 .map_err(|_| Error::SpiError)?;
         }
 
-        let byte = with_timeout(self.delay.clone(), 1000, async {
+        with_timeout(self.delay.clone(), self.cmd_timeout_ms, async {
             loop {
                 let byte = self.read_byte().await?;
                 if byte & 0x80 == 0 {
                     return Ok(byte);
                 }
+                yield_now().await;
             }
         })
-        .await??;
-
-        Ok(byte)
+        .await?
     }
 
     async fn acmd<R: Resp>(&mut self, cmd: Cmd<R>) -> Result<u8, Error> {
@@ -390,8 +947,10 @@ where
     }
 
     async fn wait_idle(&mut self) -> Result<(), Error> {
-        with_timeout(self.delay.clone(), 5000, async {
-            while self.read_byte().await? != 0xFF {}
+        with_timeout(self.delay.clone(), self.busy_timeout_ms, async {
+            while self.read_byte().await? != 0xFF {
+                yield_now().await;
+            }
             Ok(())
         })
         .await?
@@ -408,12 +967,14 @@ where
     }
 }
 
-impl<SPI, D, ALIGN, const SIZE: usize> block_device_driver::BlockDevice<SIZE>
-    for SdSpi<SPI, D, ALIGN>
+impl<SPI, D, ALIGN, WP, CD, const SIZE: usize> block_device_driver::BlockDevice<SIZE>
+    for SdSpi<SPI, D, ALIGN, WP, CD>
 where
     SPI: embedded_hal_async::spi::SpiDevice,
-    D: embedded_hal_async::delay::DelayNs + Clone,
+    D: TimeoutSource,
     ALIGN: aligned::Alignment,
+    WP: embedded_hal::digital::InputPin,
+    CD: embedded_hal::digital::InputPin,
 {
     type Error = Error;
     type Align = ALIGN;
@@ -437,16 +998,39 @@ where
     async fn size(&mut self) -> Result<u64, Self::Error> {
         self.size().await
     }
+
+    async fn erase(
+        &mut self,
+        start_block: u32,
+        end_block: u32,
+    ) -> Result<(), block_device_driver::EraseError<Self::Error>> {
+        Ok(self.erase(start_block, end_block).await?)
+    }
+}
+
+/// Checks that all of `count` blocks starting at `block_address` are addressable, so the addresses of later runs
+/// of a split transfer cannot overflow.
+fn check_block_range(block_address: u32, count: usize) -> Result<(), Error> {
+    u32::try_from(count.saturating_sub(1))
+        .ok()
+        .and_then(|n| block_address.checked_add(n))
+        .map(|_| ())
+        .ok_or(Error::UnsupportedCard)
 }
 
-async fn with_timeout<D: embedded_hal_async::delay::DelayNs, F: Future>(
-    mut delay: D,
-    timeout: u32,
+/// Races `fut` against a timeout of `ms` milliseconds, returning [Error::Timeout] if the timeout completes first.
+///
+/// The polling loops raced here yield after each unsuccessful poll of the card, so the timeout also completes with
+/// SPI drivers that never return `Pending`. On timeout `fut` is dropped, cancelling a transfer that does not
+/// complete. The next command waits for the card to release the bus before it is sent.
+async fn with_timeout<D: TimeoutSource, F: Future>(
+    mut timer: D,
+    ms: u32,
     fut: F,
 ) -> Result<F::Output, Error> {
-    match select(fut, delay.delay_ms(timeout)).await {
+    match select(fut, timer.after_ms(ms)).await {
         Either::First(r) => Ok(r),
-        Either::Second(_) => Err(Error::Timeout),
+        Either::Second(()) => Err(Error::Timeout),
     }
 }
 
@@ -465,9 +1049,10 @@ fn crc7(data: &[u8]) -> u8 {
     (crc << 1) | 1
 }
 
-/// Perform the X25 CRC calculation, as used for data blocks.
-fn crc16(data: &[u8]) -> u16 {
-    let mut crc = 0u16;
+/// Continue the X25 CRC calculation, as used for data blocks, of the preceding data with `crc` over `data`.
+///
+/// The CRC of a whole block is computed by starting with 0.
+fn crc16_update(mut crc: u16, data: &[u8]) -> u16 {
     for &byte in data {
         crc = ((crc >> 8) & 0xFF) | (crc << 8);
         crc ^= u16::from(byte);
@@ -0,0 +1,812 @@
+use std::collections::{HashMap, VecDeque};
+
+use aligned::{Aligned, A1};
+use embassy_futures::{block_on, yield_now};
+use embedded_hal_async::spi::{ErrorType, Operation, SpiDevice};
+use sdspi::{Error, SdSpi};
+
+const BLOCK_SIZE: usize = 512;
+const NUM_BLOCKS: usize = 512;
+
+/// A command received by the mock card.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Event {
+    Cmd(u8, u32),
+    Acmd(u8, u32),
+    /// Stop token ending a multi-block write
+    StopToken,
+}
+
+#[derive(Default)]
+enum Rx {
+    #[default]
+    Idle,
+    Cmd(Vec<u8>),
+    // data block (plus CRC) written to `block`
+    Block {
+        block: usize,
+        buf: Vec<u8>,
+        multi: bool,
+    },
+}
+
+/// A card talking the SD SPI protocol, one byte in for one byte out.
+struct MockCard {
+    high_capacity: bool,
+    data: Vec<u8>,
+    // bytes sent to the host by the next transfers, 0xFF once empty
+    out: VecDeque<u8>,
+    rx: Rx,
+    app_cmd: bool,
+    // block expected by a single block write
+    single_write: Option<usize>,
+    // next block of an open multi-block write and the number of blocks written by it
+    multi_write: Option<usize>,
+    written: u32,
+    // next block streamed by an open multi-block read
+    reading: Option<usize>,
+    erase_start: u32,
+    erase_end: u32,
+    init_attempts: u32,
+    /// Number of initialization attempts during which ACMD41 reports that the card is still idle
+    idle_inits: u32,
+    /// Log of the received commands
+    events: Vec<Event>,
+    /// Sizes of all SPI operations
+    transfers: Vec<usize>,
+    /// R1 responses replacing the regular response of `(app command, command index)`
+    responses: HashMap<(bool, u8), u8>,
+    /// Rejects the data block with this index (counted over all writes)
+    reject_block: Option<u32>,
+    blocks_received: u32,
+    /// Sent in response to ACMD13
+    sd_status: [u8; 64],
+    /// Sends a wrong CRC with every SD status
+    corrupt_sd_status: bool,
+    /// Sends a wrong CRC with the next data block
+    corrupt_crc: bool,
+    /// Ignores all input and keeps the data line high
+    silent: bool,
+    /// Never completes a transaction
+    stuck: bool,
+}
+
+impl MockCard {
+    fn new(high_capacity: bool) -> Self {
+        Self {
+            high_capacity,
+            data: (0..NUM_BLOCKS * BLOCK_SIZE)
+                .map(|i| (i % 251) as u8)
+                .collect(),
+            out: VecDeque::new(),
+            rx: Rx::Idle,
+            app_cmd: false,
+            single_write: None,
+            multi_write: None,
+            written: 0,
+            reading: None,
+            erase_start: 0,
+            erase_end: 0,
+            init_attempts: 0,
+            idle_inits: 0,
+            events: Vec::new(),
+            transfers: Vec::new(),
+            responses: HashMap::new(),
+            reject_block: None,
+            blocks_received: 0,
+            sd_status: [0; 64],
+            corrupt_sd_status: false,
+            corrupt_crc: false,
+            silent: false,
+            stuck: false,
+        }
+    }
+
+    fn block(&self, n: usize) -> &[u8] {
+        &self.data[n * BLOCK_SIZE..(n + 1) * BLOCK_SIZE]
+    }
+
+    fn block_index(&self, arg: u32) -> usize {
+        if self.high_capacity {
+            arg as usize
+        } else {
+            arg as usize / BLOCK_SIZE
+        }
+    }
+
+    fn send(&mut self, bytes: &[u8]) {
+        self.out.extend(bytes);
+    }
+
+    fn send_r1(&mut self, r1: u8) {
+        // one byte of response latency
+        self.send(&[0xFF, r1]);
+    }
+
+    fn send_data(&mut self, data: &[u8]) {
+        let mut crc = crc16(data);
+        if self.corrupt_crc {
+            self.corrupt_crc = false;
+            crc ^= 1;
+        }
+        self.send(&[0xFF, 0xFE]);
+        self.send(data);
+        self.send(&crc.to_be_bytes());
+    }
+
+    fn exchange(&mut self, mosi: u8) -> u8 {
+        if self.silent {
+            return 0xFF;
+        }
+        if self.out.is_empty() {
+            if let Some(n) = self.reading {
+                let block = self.block(n).to_vec();
+                self.send_data(&block);
+                self.reading = Some(n + 1);
+            }
+        }
+        let miso = self.out.pop_front().unwrap_or(0xFF);
+        self.receive(mosi);
+        miso
+    }
+
+    fn receive(&mut self, byte: u8) {
+        match &mut self.rx {
+            Rx::Cmd(buf) => {
+                buf.push(byte);
+                if buf.len() == 6 {
+                    let buf = core::mem::take(buf);
+                    self.rx = Rx::Idle;
+                    self.command(&buf);
+                }
+            }
+            Rx::Block { buf, .. } => {
+                buf.push(byte);
+                if buf.len() == BLOCK_SIZE + 2 {
+                    let Rx::Block { block, buf, multi } = core::mem::take(&mut self.rx) else {
+                        unreachable!()
+                    };
+                    self.data_block(block, &buf, multi);
+                }
+            }
+            Rx::Idle => {
+                if let Some(block) = self.multi_write {
+                    match byte {
+                        0xFC => {
+                            self.rx = Rx::Block {
+                                block,
+                                buf: Vec::new(),
+                                multi: true,
+                            }
+                        }
+                        0xFD => {
+                            self.events.push(Event::StopToken);
+                            self.multi_write = None;
+                            // busy while programming
+                            self.send(&[0xFF, 0x00]);
+                        }
+                        _ => {}
+                    }
+                } else if let (Some(block), 0xFE) = (self.single_write, byte) {
+                    self.single_write = None;
+                    self.rx = Rx::Block {
+                        block,
+                        buf: Vec::new(),
+                        multi: false,
+                    };
+                } else if byte & 0xC0 == 0x40 {
+                    self.rx = Rx::Cmd(vec![byte]);
+                }
+            }
+        }
+    }
+
+    fn data_block(&mut self, block: usize, buf: &[u8], multi: bool) {
+        let (data, crc) = buf.split_at(BLOCK_SIZE);
+        let rejected = self.reject_block == Some(self.blocks_received);
+        self.blocks_received += 1;
+        if rejected || crc16(data).to_be_bytes() != crc {
+            // CRC error data response, the block is not written
+            self.send(&[0x0B]);
+            self.multi_write = multi.then_some(block);
+            return;
+        }
+        self.data[block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE].copy_from_slice(data);
+        // data accepted, busy while programming
+        self.send(&[0x05, 0x00]);
+        if multi {
+            self.written += 1;
+            self.multi_write = Some(block + 1);
+        }
+    }
+
+    fn command(&mut self, buf: &[u8]) {
+        let index = buf[0] & 0x3F;
+        let arg = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]);
+        let app = core::mem::take(&mut self.app_cmd);
+        if index == 12 {
+            // the command ends the data stream, a stuff byte precedes the response
+            self.out.clear();
+            self.reading = None;
+            self.events.push(Event::Cmd(index, arg));
+            self.send(&[0xFF, 0xFF, 0x00]);
+            return;
+        }
+        if index != 55 {
+            self.events.push(if app {
+                Event::Acmd(index, arg)
+            } else {
+                Event::Cmd(index, arg)
+            });
+        }
+        if let Some(&r1) = self.responses.get(&(app, index)) {
+            self.send_r1(r1);
+            return;
+        }
+        match (app, index) {
+            (_, 0) => self.send_r1(0x01),
+            (_, 59) => {
+                self.init_attempts += 1;
+                self.send_r1(0x01);
+            }
+            (_, 8) => {
+                self.send_r1(0x01);
+                self.send(&[0x00, 0x00, 0x01, 0xAA]);
+            }
+            (_, 55) => {
+                self.app_cmd = true;
+                self.send_r1(0x00);
+            }
+            (true, 41) => self.send_r1(if self.init_attempts <= self.idle_inits {
+                0x01
+            } else {
+                0x00
+            }),
+            (_, 58) => {
+                let ocr = 0x80FF_8000
+                    | if self.high_capacity {
+                        0x4000_0000u32
+                    } else {
+                        0
+                    };
+                self.send_r1(0x00);
+                self.send(&ocr.to_be_bytes());
+            }
+            (_, 16) => self.send_r1(0x00),
+            (_, 9) | (_, 10) => {
+                self.send_r1(0x00);
+                self.send_data(&[0; 16]);
+            }
+            (true, 13) => {
+                // R2
+                self.send(&[0xFF, 0x00, 0x00]);
+                let status = self.sd_status;
+                self.corrupt_crc |= self.corrupt_sd_status;
+                self.send_data(&status);
+            }
+            (false, 13) => self.send(&[0xFF, 0x00, 0x00]),
+            (true, 22) => {
+                self.send_r1(0x00);
+                let written = self.written;
+                self.send_data(&written.to_be_bytes());
+            }
+            (true, 23) => self.send_r1(0x00),
+            (_, 17) => {
+                let block = self.block(self.block_index(arg)).to_vec();
+                self.send_r1(0x00);
+                self.send_data(&block);
+            }
+            (_, 18) => {
+                self.send_r1(0x00);
+                self.reading = Some(self.block_index(arg));
+            }
+            (_, 24) => {
+                self.send_r1(0x00);
+                self.single_write = Some(self.block_index(arg));
+            }
+            (_, 25) => {
+                self.send_r1(0x00);
+                self.written = 0;
+                self.multi_write = Some(self.block_index(arg));
+            }
+            (_, 32) => {
+                self.erase_start = arg;
+                self.send_r1(0x00);
+            }
+            (_, 33) => {
+                self.erase_end = arg;
+                self.send_r1(0x00);
+            }
+            (_, 38) => {
+                let start = self.block_index(self.erase_start);
+                let end = self.block_index(self.erase_end);
+                self.data[start * BLOCK_SIZE..(end + 1) * BLOCK_SIZE].fill(0);
+                // busy while erasing
+                self.send(&[0xFF, 0x00, 0x00, 0x00, 0x00]);
+            }
+            // illegal command
+            _ => self.send_r1(0x04),
+        }
+    }
+}
+
+impl ErrorType for MockCard {
+    type Error = core::convert::Infallible;
+}
+
+impl SpiDevice for MockCard {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        if self.stuck {
+            core::future::pending::<()>().await;
+        }
+        for op in operations {
+            match op {
+                Operation::Read(buf) => {
+                    self.transfers.push(buf.len());
+                    for b in buf.iter_mut() {
+                        *b = self.exchange(0xFF);
+                    }
+                }
+                Operation::Write(buf) => {
+                    self.transfers.push(buf.len());
+                    for &b in buf.iter() {
+                        self.exchange(b);
+                    }
+                }
+                Operation::Transfer(read, write) => {
+                    self.transfers.push(read.len().max(write.len()));
+                    for i in 0..read.len().max(write.len()) {
+                        let miso = self.exchange(write.get(i).copied().unwrap_or(0xFF));
+                        if let Some(b) = read.get_mut(i) {
+                            *b = miso;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    self.transfers.push(buf.len());
+                    for b in buf.iter_mut() {
+                        *b = self.exchange(*b);
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A delay that takes a fixed number of polls regardless of the duration.
+#[derive(Clone)]
+struct MockDelay {
+    polls: u32,
+}
+
+impl embedded_hal_async::delay::DelayNs for MockDelay {
+    async fn delay_ns(&mut self, _ns: u32) {
+        for _ in 0..self.polls {
+            yield_now().await;
+        }
+    }
+
+    async fn delay_us(&mut self, _us: u32) {
+        self.delay_ns(0).await
+    }
+
+    async fn delay_ms(&mut self, _ms: u32) {
+        self.delay_ns(0).await
+    }
+}
+
+type Sd = SdSpi<MockCard, MockDelay, A1>;
+
+/// CRC-16/XMODEM, computed bit by bit as a reference for the card.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn init_card(card: MockCard) -> Sd {
+    let mut sd = Sd::new(card, MockDelay { polls: 100 });
+    block_on(sd.init()).unwrap();
+    sd.spi().events.clear();
+    sd.spi().transfers.clear();
+    sd
+}
+
+fn erase_range(high_capacity: bool) {
+    let mut sd = init_card(MockCard::new(high_capacity));
+    let before = sd.spi().data.clone();
+    block_on(sd.erase(10, 20)).unwrap();
+    // standard capacity cards are byte addressed
+    let unit = if high_capacity { 1 } else { BLOCK_SIZE as u32 };
+    assert_eq!(
+        sd.spi().events,
+        [
+            Event::Cmd(32, 10 * unit),
+            Event::Cmd(33, 20 * unit),
+            Event::Cmd(38, 0)
+        ]
+    );
+    let card = sd.spi();
+    assert!(card.data[10 * BLOCK_SIZE..21 * BLOCK_SIZE]
+        .iter()
+        .all(|&b| b == 0));
+    assert_eq!(card.data[..10 * BLOCK_SIZE], before[..10 * BLOCK_SIZE]);
+    assert_eq!(card.data[21 * BLOCK_SIZE..], before[21 * BLOCK_SIZE..]);
+}
+
+#[test]
+fn test_erase_sdsc() {
+    erase_range(false);
+}
+
+#[test]
+fn test_erase_sdhc() {
+    erase_range(true);
+}
+
+#[test]
+fn test_erase_rejected() {
+    let mut sd = init_card(MockCard::new(true));
+    // address error
+    sd.spi().responses.insert((false, 33), 0x20);
+    assert_eq!(block_on(sd.erase(10, 20)), Err(Error::EraseError(0x20)));
+    assert_eq!(sd.spi().events, [Event::Cmd(32, 10), Event::Cmd(33, 20)]);
+}
+
+#[test]
+fn test_num_written_blocks_big_endian() {
+    let mut sd = init_card(MockCard::new(true));
+    sd.spi().written = 0x0102_0304;
+    assert_eq!(block_on(sd.num_written_blocks()), Ok(0x0102_0304));
+    assert_eq!(sd.spi().events, [Event::Acmd(22, 0)]);
+}
+
+#[test]
+fn test_num_written_blocks_rejected() {
+    let mut sd = init_card(MockCard::new(true));
+    sd.spi().responses.insert((true, 22), 0x04);
+    assert_eq!(
+        block_on(sd.num_written_blocks()),
+        Err(Error::RegisterError(0x04))
+    );
+}
+
+#[test]
+fn test_partial_write_reports_written_blocks() {
+    let mut sd = init_card(MockCard::new(true));
+    sd.spi().reject_block = Some(2);
+    let blocks = [Aligned::<A1, _>([0xAAu8; BLOCK_SIZE]); 4];
+    assert_eq!(block_on(sd.write(0, &blocks)), Err(Error::PartialWrite(2)));
+    let card = sd.spi();
+    assert!(card.data[..2 * BLOCK_SIZE].iter().all(|&b| b == 0xAA));
+    assert_ne!(card.data[2 * BLOCK_SIZE], 0xAA);
+    assert_eq!(card.events.last(), Some(&Event::Acmd(22, 0)));
+}
+
+/// SD status of a 4-bit card of speed class 10 (encoded as 4) with an allocation unit of 4 MB (encoded as 9).
+fn sd_status() -> [u8; 64] {
+    let mut status = [0; 64];
+    // bits 511:510, DAT_BUS_WIDTH
+    status[0] = 0x80;
+    // bits 447:440, SPEED_CLASS
+    status[8] = 0x04;
+    // bits 439:432, PERFORMANCE_MOVE
+    status[9] = 0x0A;
+    // bits 431:428, AU_SIZE
+    status[10] = 0x90;
+    status
+}
+
+#[test]
+fn test_sd_status_read_during_init() {
+    let mut card = MockCard::new(true);
+    card.sd_status = sd_status();
+    let sd = init_card(card);
+    let status = sd.card().unwrap().sd_status.unwrap();
+    assert_eq!(status.bus_width(), sdio_host::sd::BusWidth::Four);
+    assert_eq!(status.speed_class(), 4);
+    assert_eq!(status.move_performance(), 10);
+    assert_eq!(status.allocation_unit_size(), 9);
+}
+
+#[test]
+fn test_read_sd_status() {
+    let mut sd = init_card(MockCard::new(true));
+    assert_eq!(sd.card().unwrap().sd_status.unwrap().speed_class(), 0);
+    sd.spi().sd_status = sd_status();
+    let status = block_on(sd.read_sd_status()).unwrap();
+    assert_eq!(status.speed_class(), 4);
+    assert_eq!(status.allocation_unit_size(), 9);
+    assert_eq!(sd.spi().events, [Event::Acmd(13, 0)]);
+    // the stored status is updated too
+    assert_eq!(sd.card().unwrap().sd_status.unwrap().speed_class(), 4);
+}
+
+#[test]
+fn test_sd_status_crc_mismatch() {
+    let mut card = MockCard::new(true);
+    card.sd_status = sd_status();
+    let expected = crc16(&card.sd_status);
+    card.corrupt_sd_status = true;
+    let mut sd = Sd::new(card, MockDelay { polls: 100 });
+    // a corrupted status is dropped without failing the initialization
+    block_on(sd.init()).unwrap();
+    assert!(sd.card().unwrap().sd_status.is_none());
+
+    assert_eq!(
+        block_on(sd.read_sd_status()).err(),
+        Some(Error::CrcMismatch(expected ^ 1, expected))
+    );
+    assert!(sd.card().unwrap().sd_status.is_none());
+}
+
+#[test]
+fn test_init_with_retries() {
+    let mut card = MockCard::new(true);
+    card.idle_inits = 3;
+    let mut sd = Sd::new(card, MockDelay { polls: 100 });
+    block_on(sd.init_with_retries(5, 10)).unwrap();
+    assert_eq!(sd.spi().init_attempts, 4);
+    assert!(sd.card().is_some());
+}
+
+#[test]
+fn test_init_with_retries_exhausted() {
+    let mut card = MockCard::new(true);
+    card.idle_inits = 3;
+    let mut sd = Sd::new(card, MockDelay { polls: 100 });
+    assert_eq!(block_on(sd.init_with_retries(3, 10)), Err(Error::Timeout));
+    assert_eq!(sd.spi().init_attempts, 3);
+    assert!(sd.card().is_none());
+}
+
+#[test]
+fn test_init_with_retries_unsupported_card() {
+    let mut card = MockCard::new(true);
+    // version 1 cards reject CMD8
+    card.responses.insert((false, 8), 0x05);
+    let mut sd = Sd::new(card, MockDelay { polls: 100 });
+    assert_eq!(
+        block_on(sd.init_with_retries(5, 10)),
+        Err(Error::UnsupportedCard)
+    );
+    assert_eq!(sd.spi().init_attempts, 1);
+}
+
+/// Returns how often the data of a block appears as consecutive SPI transfers of `chunks`.
+fn count_chunked_blocks(transfers: &[usize], chunks: &[usize]) -> usize {
+    transfers
+        .windows(chunks.len())
+        .filter(|w| *w == chunks)
+        .count()
+}
+
+#[test]
+fn test_chunk_size() {
+    let mut sd = init_card(MockCard::new(true)).with_chunk_size(100);
+    let chunks = [100, 100, 100, 100, 100, 12];
+
+    // the CRC of a read block is checked over all chunks
+    let mut blocks = [Aligned::<A1, _>([0u8; BLOCK_SIZE]); 2];
+    block_on(sd.read(3, &mut blocks)).unwrap();
+    assert_eq!(blocks[0][..], sd.spi().block(3)[..]);
+    assert_eq!(blocks[1][..], sd.spi().block(4)[..]);
+    assert_eq!(count_chunked_blocks(&sd.spi().transfers, &chunks), 2);
+    assert!(!sd.spi().transfers.contains(&BLOCK_SIZE));
+
+    // the card rejects blocks whose CRC does not match
+    sd.spi().transfers.clear();
+    let data: Vec<u8> = (0..BLOCK_SIZE).map(|i| (i * 7) as u8).collect();
+    let mut block = Aligned::<A1, _>([0u8; BLOCK_SIZE]);
+    block.copy_from_slice(&data);
+    block_on(sd.write(7, &[block])).unwrap();
+    block_on(sd.write(8, &[block; 3])).unwrap();
+    assert_eq!(count_chunked_blocks(&sd.spi().transfers, &chunks), 4);
+    for n in 7..11 {
+        assert_eq!(sd.spi().block(n), &data[..]);
+    }
+}
+
+#[test]
+fn test_chunk_size_crc_mismatch() {
+    let mut sd = init_card(MockCard::new(true)).with_chunk_size(64);
+    let expected = crc16(sd.spi().block(5));
+    sd.spi().corrupt_crc = true;
+    let mut blocks = [Aligned::<A1, _>([0u8; BLOCK_SIZE]); 1];
+    assert_eq!(
+        block_on(sd.read(5, &mut blocks)),
+        Err(Error::CrcMismatch(expected ^ 1, expected))
+    );
+}
+
+fn split_transfer(high_capacity: bool) {
+    let mut sd = init_card(MockCard::new(high_capacity)).with_max_blocks_per_command(64);
+    let unit = if high_capacity { 1 } else { BLOCK_SIZE as u32 };
+    // runs of 64, 64, 64 and 8 blocks
+    let starts = [10, 74, 138, 202];
+
+    let mut blocks = vec![Aligned::<A1, _>([0u8; BLOCK_SIZE]); 200];
+    block_on(sd.read(10, &mut blocks)).unwrap();
+    for (i, block) in blocks.iter().enumerate() {
+        assert_eq!(block[..], sd.spi().block(10 + i)[..], "block {}", 10 + i);
+    }
+    let expected: Vec<Event> = starts
+        .iter()
+        .flat_map(|&s| [Event::Cmd(18, s * unit), Event::Cmd(12, 0)])
+        .collect();
+    assert_eq!(sd.spi().events, expected);
+
+    sd.spi().events.clear();
+    for (i, block) in blocks.iter_mut().enumerate() {
+        block.fill(i as u8);
+    }
+    block_on(sd.write(250, &blocks)).unwrap();
+    for i in 0..200 {
+        assert!(sd.spi().block(250 + i).iter().all(|&b| b == i as u8));
+    }
+    let expected: Vec<Event> = starts
+        .iter()
+        .zip([64, 64, 64, 8])
+        .flat_map(|(&s, n)| {
+            [
+                Event::Acmd(23, n),
+                Event::Cmd(25, (s + 240) * unit),
+                Event::StopToken,
+            ]
+        })
+        .collect();
+    assert_eq!(sd.spi().events, expected);
+}
+
+#[test]
+fn test_split_transfer_sdsc() {
+    split_transfer(false);
+}
+
+#[test]
+fn test_split_transfer_sdhc() {
+    split_transfer(true);
+}
+
+#[test]
+fn test_pre_erase() {
+    let mut sd = init_card(MockCard::new(true));
+    let blocks = [Aligned::<A1, _>([0x55u8; BLOCK_SIZE]); 3];
+    block_on(sd.write(40, &blocks)).unwrap();
+    assert_eq!(
+        sd.spi().events,
+        [Event::Acmd(23, 3), Event::Cmd(25, 40), Event::StopToken]
+    );
+    // single block writes are not pre-erased
+    sd.spi().events.clear();
+    block_on(sd.write(50, &blocks[..1])).unwrap();
+    assert_eq!(sd.spi().events, [Event::Cmd(24, 50), Event::Cmd(13, 0)]);
+}
+
+#[test]
+fn test_pre_erase_unsupported() {
+    let mut sd = init_card(MockCard::new(true));
+    sd.spi().responses.insert((true, 23), 0x04);
+    let blocks = [Aligned::<A1, _>([0x55u8; BLOCK_SIZE]); 3];
+    // the write goes ahead without pre-erase
+    block_on(sd.write(40, &blocks)).unwrap();
+    assert_eq!(
+        sd.spi().events,
+        [Event::Acmd(23, 3), Event::Cmd(25, 40), Event::StopToken]
+    );
+    assert!(sd.spi().data[40 * BLOCK_SIZE..43 * BLOCK_SIZE]
+        .iter()
+        .all(|&b| b == 0x55));
+}
+
+#[test]
+fn test_pre_erase_rejected() {
+    let mut sd = init_card(MockCard::new(true));
+    let before = sd.spi().data.clone();
+    // parameter error
+    sd.spi().responses.insert((true, 23), 0x40);
+    let blocks = [Aligned::<A1, _>([0x55u8; BLOCK_SIZE]); 3];
+    assert_eq!(
+        block_on(sd.write(40, &blocks)),
+        Err(Error::PreEraseError(0x40))
+    );
+    // the write is not started
+    assert_eq!(sd.spi().events, [Event::Acmd(23, 3)]);
+    assert_eq!(sd.spi().data, before);
+}
+
+#[test]
+fn test_timeout_stuck_transfer() {
+    let mut sd = init_card(MockCard::new(true));
+    sd.spi().stuck = true;
+    let mut blocks = [Aligned::<A1, _>([0u8; BLOCK_SIZE]); 1];
+    assert_eq!(block_on(sd.read(3, &mut blocks)), Err(Error::Timeout));
+    // the dropped transfer leaves the bus usable
+    sd.spi().stuck = false;
+    block_on(sd.read(3, &mut blocks)).unwrap();
+    assert_eq!(blocks[0][..], sd.spi().block(3)[..]);
+}
+
+#[test]
+fn test_timeout_silent_card() {
+    let mut sd = init_card(MockCard::new(true));
+    sd.spi().silent = true;
+    let mut blocks = [Aligned::<A1, _>([0u8; BLOCK_SIZE]); 1];
+    assert_eq!(block_on(sd.read(3, &mut blocks)), Err(Error::Timeout));
+    assert!(sd.spi().events.is_empty());
+    sd.spi().silent = false;
+    block_on(sd.read(3, &mut blocks)).unwrap();
+    assert_eq!(blocks[0][..], sd.spi().block(3)[..]);
+}
+
+#[test]
+fn test_init_timeout_silent_card() {
+    let mut card = MockCard::new(true);
+    card.silent = true;
+    let mut sd = Sd::new(card, MockDelay { polls: 100 });
+    assert_eq!(block_on(sd.init()), Err(Error::Timeout));
+    assert!(sd.card().is_none());
+}
+
+fn block_device_loopback(high_capacity: bool) {
+    use block_device_driver::BlockDevice;
+
+    let mut sd = Sd::new(MockCard::new(high_capacity), MockDelay { polls: 100 });
+    assert_eq!(
+        block_on(BlockDevice::<BLOCK_SIZE>::size(&mut sd)),
+        Err(Error::NotInitialized)
+    );
+    block_on(sd.init()).unwrap();
+    sd.spi().events.clear();
+    let size = sd.card().unwrap().size();
+    assert_eq!(block_on(BlockDevice::<BLOCK_SIZE>::size(&mut sd)), Ok(size));
+
+    let unit = if high_capacity { 1 } else { BLOCK_SIZE as u32 };
+    let written = [Aligned::<A1, _>([0xA5u8; BLOCK_SIZE])];
+    block_on(BlockDevice::write(&mut sd, 10, &written)).unwrap();
+    let mut read = [Aligned::<A1, _>([0u8; BLOCK_SIZE])];
+    block_on(BlockDevice::read(&mut sd, 10, &mut read)).unwrap();
+    assert_eq!(read[0][..], written[0][..]);
+    assert_eq!(
+        sd.spi().events,
+        [
+            Event::Cmd(24, 10 * unit),
+            Event::Cmd(13, 0),
+            Event::Cmd(17, 10 * unit)
+        ]
+    );
+
+    let written: Vec<_> = (0..3u8)
+        .map(|i| Aligned::<A1, _>([i; BLOCK_SIZE]))
+        .collect();
+    block_on(BlockDevice::write(&mut sd, 10, &written)).unwrap();
+    let mut read = vec![Aligned::<A1, _>([0xFFu8; BLOCK_SIZE]); 3];
+    block_on(BlockDevice::read(&mut sd, 10, &mut read)).unwrap();
+    for (r, w) in read.iter().zip(&written) {
+        assert_eq!(r[..], w[..]);
+    }
+}
+
+#[test]
+fn test_block_device_loopback_sdsc() {
+    block_device_loopback(false);
+}
+
+#[test]
+fn test_block_device_loopback_sdhc() {
+    block_device_loopback(true);
+}